@@ -0,0 +1,11 @@
+#![no_main]
+
+use herb::drmecref::parse_message;
+use libfuzzer_sys::fuzz_target;
+
+// Any line from the referee or an engine must parse or fail cleanly, never panic.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(line) = std::str::from_utf8(data) {
+        let _ = parse_message(line);
+    }
+});
@@ -0,0 +1,286 @@
+//! Forced-variation ("what if") analysis.
+//!
+//! [`analyze_line`] plays a given continuation from a position, whatever Herb would have
+//! chosen, and then searches the resulting position with a fresh [`Tree`]. Forcing one move
+//! at a time answers questions like "what if Herb had played e3 here instead", and
+//! [`compare_moves`] does exactly that for several candidate moves so their outcomes can be
+//! compared side by side. [`best_responses`] walks an opening line instead, searching every
+//! position along it, and [`best_response_table`] prints Herb's preferred reply in each as a
+//! table for teaching handouts.
+//!
+//! Searches run for a fixed number of iterations, so the same analysis always gives the same
+//! answer.
+use crate::config::MctsConfig;
+use crate::mcts::{PvStep, Tree};
+use crate::openings;
+use crate::othello::{Color, Game, GameError, Move};
+use crate::record::format_move;
+
+/// The outcome of a forced line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineAnalysis {
+    /// The forced moves.
+    pub line: Vec<Move>,
+    /// The player to move before the line, whose perspective the win rate is given from.
+    pub player: Color,
+    /// The position at the end of the line.
+    pub position: Game,
+    /// The best move in the resulting position, [`Move::Pass`] if there is none.
+    pub best_move: Move,
+    /// Evaluation of the best move for the player making it, [`f64::MIN`] if there is none.
+    pub value: f64,
+    /// Fraction of the simulations from the resulting position won by `player`, draws
+    /// counting half.
+    pub win_rate: f64,
+    /// Search iterations spent on the resulting position.
+    pub iterations: u64,
+    /// The principal variation from the resulting position.
+    pub pv: Vec<PvStep>,
+}
+
+/// Play the given moves from the position and search the resulting position for `budget`
+/// iterations.
+///
+/// Returns an error if any of the moves is illegal.
+pub fn analyze_line(
+    game: Game,
+    moves: &[Move],
+    config: &MctsConfig,
+    budget: u64,
+) -> Result<LineAnalysis, GameError> {
+    let mut position = game;
+    for mv in moves {
+        position.play_next_turn(*mv)?;
+    }
+
+    let mut tree = Tree::from_config(config.clone());
+    tree.search_n(position, budget);
+    let (best_move, value) = tree.best_move_with_value(position);
+
+    let win_rate = win_rate(&tree, position, game.to_move());
+
+    Ok(LineAnalysis {
+        line: moves.to_vec(),
+        player: game.to_move(),
+        position,
+        best_move,
+        value,
+        win_rate,
+        iterations: tree.search_iterations,
+        pv: tree.principal_variation(position),
+    })
+}
+
+/// Returns the fraction of the simulations through the position's children won by the
+/// player, draws counting half, or the final result if the game is over.
+pub(crate) fn win_rate(tree: &Tree, position: Game, player: Color) -> f64 {
+    if position.is_over() {
+        match position.winner() {
+            None => 0.5,
+            Some(winner) if winner == player => 1.0,
+            _ => 0.0,
+        }
+    } else {
+        // the children hold the results from the perspective of the player to move
+        let (wins, visits) = position
+            .legal_moves()
+            .iter()
+            .filter_map(|mv| {
+                let mut child = position;
+                child.play_next_turn(*mv).unwrap();
+                tree.map.get(&child.zobrist_hash())
+            })
+            .fold((0.0, 0.0), |(wins, visits), node| {
+                (wins + node.wins, visits + node.visits)
+            });
+        let rate = if visits > 0.0 { wins / visits } else { 0.5 };
+        if position.to_move() == player {
+            rate
+        } else {
+            1.0 - rate
+        }
+    }
+}
+
+/// Analyze each of the given moves as a single forced move from the position.
+///
+/// Returns the analyses ordered from the best win rate for the player to move to the worst,
+/// or an error if any of the moves is illegal.
+pub fn compare_moves(
+    game: Game,
+    moves: &[Move],
+    config: &MctsConfig,
+    budget: u64,
+) -> Result<Vec<LineAnalysis>, GameError> {
+    let mut analyses = moves
+        .iter()
+        .map(|mv| analyze_line(game, &[*mv], config, budget))
+        .collect::<Result<Vec<_>, _>>()?;
+    analyses.sort_by(|a, b| b.win_rate.total_cmp(&a.win_rate));
+    Ok(analyses)
+}
+
+/// Herb's preferred reply in one position of a line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BestResponse {
+    /// The number of moves of the line played before the position.
+    pub ply: usize,
+    /// The move the line continues with, `None` at its end.
+    pub played: Option<Move>,
+    /// The search of the position, with the win rate from the perspective of the player to
+    /// move in it.
+    pub analysis: LineAnalysis,
+}
+
+impl BestResponse {
+    /// Returns whether the line continues with Herb's preferred reply.
+    pub fn agrees(&self) -> bool {
+        self.played == Some(self.analysis.best_move)
+    }
+}
+
+/// Search every position of the line from the position, its end included, for `budget`
+/// iterations each.
+///
+/// Returns an error if any of the moves is illegal.
+pub fn best_responses(
+    game: Game,
+    line: &[Move],
+    config: &MctsConfig,
+    budget: u64,
+) -> Result<Vec<BestResponse>, GameError> {
+    let mut position = game;
+    let mut responses = Vec::with_capacity(line.len() + 1);
+    for ply in 0..=line.len() {
+        responses.push(BestResponse {
+            ply,
+            played: line.get(ply).copied(),
+            analysis: analyze_line(position, &[], config, budget)?,
+        });
+        if let Some(mv) = line.get(ply) {
+            position.play_next_turn(*mv)?;
+        }
+    }
+    Ok(responses)
+}
+
+/// Format the responses as a Markdown table, one row per position with the opening it is, the
+/// move played and Herb's reply, evaluation, win rate and principal variation. Rows where the
+/// line leaves Herb's reply are marked with `*`.
+pub fn best_response_table(responses: &[BestResponse]) -> String {
+    let mut table = String::from(
+        "| Ply | Opening | To move | Played | Herb | Value | Wins | PV |\n\
+         |----:|---------|---------|--------|------|------:|-----:|----|\n",
+    );
+    for response in responses {
+        let analysis = &response.analysis;
+        let played = match response.played {
+            Some(mv) if response.agrees() => format_move(mv),
+            Some(mv) => format!("{} *", format_move(mv)),
+            None => String::new(),
+        };
+        let (best_move, value) = if analysis.value == f64::MIN {
+            ("-".to_string(), "-".to_string())
+        } else {
+            (
+                format_move(analysis.best_move),
+                format!("{:.2}", analysis.value),
+            )
+        };
+        let pv: Vec<String> = analysis
+            .pv
+            .iter()
+            .map(|step| format_move(step.mv))
+            .collect();
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {:.1}% | {} |\n",
+            response.ply,
+            openings::name(&analysis.position).unwrap_or(""),
+            analysis.player,
+            played,
+            best_move,
+            value,
+            analysis.win_rate * 100.0,
+            pv.join(" ")
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze_line() {
+        let config = MctsConfig::default();
+        let game = Game::new();
+        let d3 = game.legal_moves()[0];
+        let mut after = game;
+        after.play_next_turn(d3).unwrap();
+        let reply = after.legal_moves()[0];
+
+        let analysis = analyze_line(game, &[d3, reply], &config, 200).unwrap();
+        assert_eq!(analysis.line, vec![d3, reply]);
+        assert_eq!(analysis.player, Color::Black);
+        assert_eq!(analysis.position.get_turn(), 2);
+        assert_eq!(analysis.iterations, 200);
+        assert!(analysis
+            .position
+            .legal_moves()
+            .contains(&analysis.best_move));
+        assert!((0.0..=1.0).contains(&analysis.win_rate));
+        assert!(!analysis.pv.is_empty());
+
+        // the same budget gives the same answer
+        assert_eq!(
+            analyze_line(game, &[d3, reply], &config, 200).unwrap(),
+            analysis
+        );
+
+        // d3 is already taken
+        assert!(analyze_line(game, &[d3, d3], &config, 10).is_err());
+    }
+
+    #[test]
+    fn test_compare_moves() {
+        let config = MctsConfig::default();
+        let game = Game::new();
+        let analyses = compare_moves(game, &game.legal_moves(), &config, 100).unwrap();
+        assert_eq!(analyses.len(), 4);
+        assert!(analyses
+            .windows(2)
+            .all(|pair| pair[0].win_rate >= pair[1].win_rate));
+        assert!(analyses.iter().all(|analysis| analysis.line.len() == 1));
+    }
+
+    #[test]
+    fn test_best_responses() {
+        let config = MctsConfig::default();
+        let line: Vec<Move> = ["f5", "d6", "c3"]
+            .iter()
+            .map(|mv| crate::record::parse_move(mv).unwrap())
+            .collect();
+        let responses = best_responses(Game::new(), &line, &config, 100).unwrap();
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0].analysis.player, Color::Black);
+        assert_eq!(responses[1].analysis.player, Color::White);
+        assert_eq!(responses[1].played, Some(line[1]));
+        assert_eq!(responses[3].played, None);
+        assert!(!responses[3].agrees());
+        assert_eq!(responses[3].analysis.position.get_turn(), 3);
+        assert!(responses
+            .iter()
+            .all(|response| response.analysis.iterations == 100));
+
+        let table = best_response_table(&responses);
+        assert_eq!(table.lines().count(), 6);
+        assert!(table
+            .lines()
+            .nth(4)
+            .unwrap()
+            .starts_with("| 2 | Perpendicular opening | B | c3"));
+
+        assert!(best_responses(Game::new(), &[line[0], line[0]], &config, 10).is_err());
+    }
+}
@@ -0,0 +1,221 @@
+//! Crash-safe saving of the game in progress.
+//!
+//! When `autosave` is set in the [`Config`], the game against the referee is written to the
+//! autosave file after every move: the [`GameRecord`] with each mover's remaining clock, the
+//! time both sides have used, Herb's color and a digest of the configuration. Each save goes
+//! to a temporary file next to the autosave first and then replaces it, so a power loss in
+//! the middle of a write leaves the previous save intact.
+//!
+//! Started with `--resume`, `herb` reads the autosave back and continues the game from where
+//! it stopped with the clocks as they were, see
+//! [`resume_referee_game`](crate::resume_referee_game). Resuming under a different
+//! configuration is allowed but commented, the digest tells the two apart.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::config::Config;
+use crate::driver::GameResult;
+use crate::formats::{self, Header, VersionedReader};
+use crate::othello::Color::{self, Black, White};
+use crate::othello::{Game, GameError, Move};
+use crate::record::{GameRecord, RecordedMove};
+
+/// The format of autosave files.
+pub const FORMAT: Header = Header::new("autosave", 1);
+
+/// A game in progress as written to the autosave file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Autosave {
+    /// Herb's color in the game.
+    pub color: Color,
+    /// The moves so far, each annotated with the mover's remaining clock.
+    pub record: GameRecord,
+    /// Seconds black has used.
+    pub black_time: f64,
+    /// Seconds white has used.
+    pub white_time: f64,
+    /// The [`config_digest`] of the configuration the game was played with.
+    pub config_digest: String,
+}
+
+impl Autosave {
+    /// Create the autosave of a game that has not started yet, played by Herb as `color`
+    /// under the config.
+    pub fn new(config: &Config, color: Color) -> Self {
+        let mut record = GameRecord::new();
+        let (herb, referee) = ("Herb".to_string(), "Referee".to_string());
+        let (black, white) = match color {
+            Black => (herb, referee),
+            White => (referee, herb),
+        };
+        record.tags.insert("Black".to_string(), black);
+        record.tags.insert("White".to_string(), white);
+        BuildInfo::with_config(config).stamp(&mut record);
+        Autosave {
+            color,
+            record,
+            black_time: 0.0,
+            white_time: 0.0,
+            config_digest: config_digest(config),
+        }
+    }
+
+    /// Bring the autosave up to date with the game so far. Moves it does not have yet are
+    /// annotated with the mover's remaining clock under the `max_time` of the config, so
+    /// updating after every move gives every move its clock.
+    pub fn update(&mut self, config: &Config, result: &GameResult) {
+        self.black_time = result.black_time.as_secs_f64();
+        self.white_time = result.white_time.as_secs_f64();
+        for (i, mv) in result
+            .moves
+            .iter()
+            .enumerate()
+            .skip(self.record.moves.len())
+        {
+            // passes are moves too, so the turn always changes hands
+            let used = if i % 2 == 0 {
+                self.black_time
+            } else {
+                self.white_time
+            };
+            self.record.push(RecordedMove {
+                clock: Some((config.max_time - used).max(0.0)),
+                ..RecordedMove::new(*mv)
+            });
+        }
+    }
+
+    /// Returns the moves played so far.
+    pub fn moves(&self) -> Vec<Move> {
+        self.record
+            .moves
+            .iter()
+            .map(|recorded| recorded.mv)
+            .collect()
+    }
+
+    /// Returns the position the game stopped in.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn game(&self) -> Result<Game, GameError> {
+        self.record.game()
+    }
+
+    /// Returns the time black has used.
+    pub fn black_time(&self) -> Duration {
+        Duration::from_secs_f64(self.black_time.max(0.0))
+    }
+
+    /// Returns the time white has used.
+    pub fn white_time(&self) -> Duration {
+        Duration::from_secs_f64(self.white_time.max(0.0))
+    }
+
+    /// Returns the time Herb has used.
+    pub fn herb_time(&self) -> Duration {
+        match self.color {
+            Black => self.black_time(),
+            White => self.white_time(),
+        }
+    }
+
+    /// Write the autosave to the file, replacing it only once the new contents are safely on
+    /// disk.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        formats::save(path, FORMAT, self)
+    }
+
+    /// Read an autosave file. Autosaves from before the format was versioned are upgraded.
+    ///
+    /// Returns an error if it can not be read, is not an autosave of a supported version or
+    /// holds an illegal move.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        VersionedReader::new(FORMAT)
+            .migration(0, Ok)
+            .validation(|autosave: &Autosave| {
+                autosave.game().map(|_| ()).map_err(|e| e.to_string())
+            })
+            .read_file(path)
+    }
+}
+
+/// Returns a digest of the configuration, the same for equal configurations. It is a 64 bit
+/// FNV-1a hash of the configuration as json, in hex.
+pub fn config_digest(config: &Config) -> String {
+    let json = serde_json::to_string(config).expect("configurations serialize");
+    let hash = json.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+pub(crate) fn temporary_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::opponents::Opponent;
+    use crate::tournament::play_game;
+
+    #[test]
+    fn test_save_and_load() {
+        let mut result = play_game(&mut Opponent::Greedy, &mut Opponent::Greedy).unwrap();
+        result.moves.truncate(9);
+        result.black_time = Duration::from_secs(10);
+        result.white_time = Duration::from_secs(4);
+        let config = Config::default();
+        let mut autosave = Autosave::new(&config, White);
+        autosave.update(&config, &result);
+        assert_eq!(autosave.moves(), result.moves);
+        assert_eq!(autosave.herb_time(), Duration::from_secs(4));
+        assert_eq!(autosave.record.tags["White"], "Herb");
+        assert_eq!(autosave.record.moves[0].clock, Some(110.0));
+        assert_eq!(autosave.record.moves[1].clock, Some(116.0));
+        assert_eq!(autosave.game().unwrap().get_turn(), 9);
+
+        // earlier moves keep their clocks
+        result.moves = play_game(&mut Opponent::Greedy, &mut Opponent::Greedy)
+            .unwrap()
+            .moves[..10]
+            .to_vec();
+        result.white_time = Duration::from_secs(5);
+        autosave.update(&config, &result);
+        assert_eq!(autosave.moves(), result.moves);
+        assert_eq!(autosave.record.moves[1].clock, Some(116.0));
+        assert_eq!(autosave.record.moves[9].clock, Some(115.0));
+
+        let path = std::env::temp_dir().join(format!("herb-autosave-{}.json", std::process::id()));
+        autosave.save(&path).unwrap();
+        assert!(!temporary_path(&path).exists());
+        assert_eq!(Autosave::load(&path).unwrap(), autosave);
+        // autosaves from before the header are still read
+        fs::write(&path, serde_json::to_string(&autosave).unwrap()).unwrap();
+        assert_eq!(Autosave::load(&path).unwrap(), autosave);
+        fs::write(&path, "HERB lessons 1\n{}").unwrap();
+        assert!(Autosave::load(&path).is_err());
+        fs::write(&path, "{").unwrap();
+        assert!(Autosave::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_config_digest() {
+        let config = Config::default();
+        assert_eq!(config_digest(&config), config_digest(&config.clone()));
+        let changed = Config {
+            max_time: 60.0,
+            ..Config::default()
+        };
+        assert_ne!(config_digest(&config), config_digest(&changed));
+    }
+}
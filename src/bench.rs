@@ -0,0 +1,405 @@
+//! Benchmark runs and the regression gate against a stored baseline.
+//!
+//! [`run`] measures the positions of the benchmark [`corpus`](crate::corpus) a number of
+//! times each: the playouts per second of a single threaded search of every position, the
+//! flips per second of both [`bits::flips`] and [`bits::ray_flips`] on every square of it,
+//! and the seconds the [`solver`] takes on the endgame positions. A run saved as a [`Baseline`] can be
+//! [`compare`]d with a later one, e.g. before merging a change that could slow the search
+//! down.
+//!
+//! Timings are noisy, so a measurement only counts as a regression if it got worse by more
+//! than the threshold and by more than twice the standard error of the difference between the
+//! two means, estimated from the repeats of both runs. More repeats make smaller regressions
+//! stand out from the noise.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::hint::black_box;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::config::MctsConfig;
+use crate::corpus::{BenchPosition, Category};
+use crate::formats::{self, Header, VersionedReader};
+use crate::mcts::Tree;
+use crate::othello::bits;
+use crate::solver;
+
+/// The format of baseline files.
+pub const FORMAT: Header = Header::new("bench", 1);
+
+/// The smallest relative change that counts as a regression, 5%.
+pub const DEFAULT_THRESHOLD: f64 = 0.05;
+
+/// Times the flips of every square of a position are computed for a flips sample.
+const FLIP_ROUNDS: usize = 2000;
+
+/// A way of finding the discs a move flips, see [`bits::flips`].
+type Flips = fn(u64, u64, usize) -> u64;
+
+/// What a [`Measurement`] measures.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metric {
+    /// Playouts per second of the search, higher is better.
+    Playouts,
+    /// Seconds the solver takes on the position, lower is better.
+    Solve,
+    /// Flips per second of [`bits::flips`], the Kogge-Stone fill, higher is better.
+    Flips,
+    /// Flips per second of [`bits::ray_flips`], the table lookup, higher is better.
+    RayFlips,
+}
+
+impl Metric {
+    /// Returns true if a higher value is an improvement.
+    pub fn higher_is_better(self) -> bool {
+        self != Metric::Solve
+    }
+}
+
+impl Display for Metric {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let unit = match self {
+            Metric::Playouts => "playouts/s",
+            Metric::Solve => "s to solve",
+            Metric::Flips => "flips/s",
+            Metric::RayFlips => "ray flips/s",
+        };
+        write!(f, "{}", unit)
+    }
+}
+
+/// The repeated measurements of a metric on a benchmark position.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    /// The ID of the position in the corpus.
+    pub id: String,
+    pub metric: Metric,
+    pub samples: Vec<f64>,
+}
+
+impl Measurement {
+    /// Returns the mean of the samples, 0 without any.
+    pub fn mean(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().sum::<f64>() / self.samples.len() as f64
+    }
+
+    /// Returns the standard error of the mean, 0 with fewer than two samples.
+    pub fn standard_error(&self) -> f64 {
+        let n = self.samples.len() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        let variance = self
+            .samples
+            .iter()
+            .map(|sample| (sample - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        (variance / n).sqrt()
+    }
+}
+
+impl Display for Measurement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let precision = match self.metric {
+            Metric::Playouts | Metric::Flips | Metric::RayFlips => 0,
+            Metric::Solve => 4,
+        };
+        write!(
+            f,
+            "{:<12} {:>12.*} ± {:<10.*} {}",
+            self.id,
+            precision,
+            self.mean(),
+            precision,
+            self.standard_error(),
+            self.metric
+        )
+    }
+}
+
+/// The measurements of a benchmark run.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The build the run was measured with.
+    pub build: BuildInfo,
+    /// Search iterations per position.
+    pub iterations: u64,
+    pub measurements: Vec<Measurement>,
+}
+
+impl Baseline {
+    /// Read a baseline written by [`Baseline::save`].
+    pub fn load(path: &Path) -> io::Result<Self> {
+        VersionedReader::new(FORMAT).read_file(path)
+    }
+
+    /// Write the baseline as JSON, with a [`formats`] header.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        formats::save(path, FORMAT, self)
+    }
+
+    /// Returns the measurement of the metric on the position, if there is one.
+    pub fn get(&self, id: &str, metric: Metric) -> Option<&Measurement> {
+        self.measurements
+            .iter()
+            .find(|measurement| measurement.id == id && measurement.metric == metric)
+    }
+}
+
+/// Measure every position `repeats` times: a search of `iterations` iterations with a fresh
+/// tree, the flips of both players on every square with each way of finding them, and for
+/// the endgame positions a solve.
+pub fn run(positions: &[&BenchPosition], iterations: u64, repeats: usize) -> Baseline {
+    let playouts = (iterations * MctsConfig::default().playouts) as f64;
+    let mut measurements = Vec::new();
+    for position in positions {
+        let game = position.game();
+        let samples = (0..repeats)
+            .map(|_| {
+                let mut tree = Tree::new();
+                let start = Instant::now();
+                tree.search_n(game, iterations);
+                playouts / start.elapsed().as_secs_f64()
+            })
+            .collect();
+        measurements.push(Measurement {
+            id: position.id.to_string(),
+            metric: Metric::Playouts,
+            samples,
+        });
+        let board = game.get_board();
+        let (black, white) = (board.get_black(), board.get_white());
+        let ways: [(Metric, Flips); 2] = [
+            (Metric::Flips, bits::flips),
+            (Metric::RayFlips, bits::ray_flips),
+        ];
+        for (metric, flips) in ways {
+            let samples = (0..repeats)
+                .map(|_| {
+                    let start = Instant::now();
+                    for _ in 0..FLIP_ROUNDS {
+                        for square in 0..64 {
+                            black_box(flips(black_box(black), white, square));
+                            black_box(flips(black_box(white), black, square));
+                        }
+                    }
+                    (FLIP_ROUNDS * 128) as f64 / start.elapsed().as_secs_f64()
+                })
+                .collect();
+            measurements.push(Measurement {
+                id: position.id.to_string(),
+                metric,
+                samples,
+            });
+        }
+        if position.category == Category::Endgame {
+            let samples = (0..repeats)
+                .map(|_| {
+                    let start = Instant::now();
+                    solver::solve(&game);
+                    start.elapsed().as_secs_f64()
+                })
+                .collect();
+            measurements.push(Measurement {
+                id: position.id.to_string(),
+                metric: Metric::Solve,
+                samples,
+            });
+        }
+    }
+    Baseline {
+        build: BuildInfo::current(),
+        iterations,
+        measurements,
+    }
+}
+
+/// Errors from comparing benchmark runs.
+#[derive(Debug, PartialEq)]
+pub enum BenchError {
+    /// The runs searched a different number of iterations per position.
+    DifferentIterations { baseline: u64, current: u64 },
+}
+
+impl Display for BenchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            BenchError::DifferentIterations { baseline, current } => write!(
+                f,
+                "The baseline searched {} iterations per position, this run {}",
+                baseline, current
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BenchError {}
+
+/// A measurement of a run next to the same measurement of the baseline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comparison {
+    pub id: String,
+    pub metric: Metric,
+    /// The mean of the baseline.
+    pub baseline: f64,
+    /// The mean of the run.
+    pub current: f64,
+    /// The relative change of the mean, positive when the run is better whichever way the
+    /// metric goes.
+    pub change: f64,
+    /// Twice the standard error of the difference of the means, relative to the baseline.
+    pub noise: f64,
+    /// The run is worse by more than both the threshold and the noise.
+    pub regression: bool,
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{:<12} {:<10} {:>+7.1}% ± {:.1}%{}",
+            self.id,
+            self.metric,
+            self.change * 100.0,
+            self.noise * 100.0,
+            if self.regression { "  REGRESSION" } else { "" }
+        )
+    }
+}
+
+/// Compare every measurement of the run with the baseline's, skipping the ones the baseline
+/// does not have. A measurement is a regression if it is worse by more than `threshold`, a
+/// fraction of the baseline, and by more than the noise.
+///
+/// Returns an error if the runs searched a different number of iterations, their speeds are
+/// not comparable.
+pub fn compare(
+    baseline: &Baseline,
+    current: &Baseline,
+    threshold: f64,
+) -> Result<Vec<Comparison>, BenchError> {
+    if baseline.iterations != current.iterations {
+        return Err(BenchError::DifferentIterations {
+            baseline: baseline.iterations,
+            current: current.iterations,
+        });
+    }
+    Ok(current
+        .measurements
+        .iter()
+        .filter_map(|measurement| {
+            let before = baseline.get(&measurement.id, measurement.metric)?;
+            let (mean_before, mean) = (before.mean(), measurement.mean());
+            if mean_before <= 0.0 {
+                return None;
+            }
+            let sign = if measurement.metric.higher_is_better() {
+                1.0
+            } else {
+                -1.0
+            };
+            let change = sign * (mean - mean_before) / mean_before;
+            let noise =
+                2.0 * before.standard_error().hypot(measurement.standard_error()) / mean_before;
+            Some(Comparison {
+                id: measurement.id.clone(),
+                metric: measurement.metric,
+                baseline: mean_before,
+                current: mean,
+                change,
+                noise,
+                regression: -change > threshold && -change > noise,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::corpus::find;
+
+    fn baseline(measurements: &[(&str, Metric, &[f64])]) -> Baseline {
+        Baseline {
+            build: BuildInfo::current(),
+            iterations: 100,
+            measurements: measurements
+                .iter()
+                .map(|(id, metric, samples)| Measurement {
+                    id: id.to_string(),
+                    metric: *metric,
+                    samples: samples.to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let before = baseline(&[
+            ("a", Metric::Playouts, &[1000.0, 1010.0, 990.0]),
+            ("b", Metric::Playouts, &[1000.0, 1500.0, 500.0]),
+            ("c", Metric::Solve, &[1.0, 1.0, 1.0]),
+        ]);
+        let after = baseline(&[
+            // 20% slower with little noise
+            ("a", Metric::Playouts, &[800.0, 810.0, 790.0]),
+            // 20% slower but the baseline is all over the place
+            ("b", Metric::Playouts, &[800.0, 810.0, 790.0]),
+            // solving in half the time is better
+            ("c", Metric::Solve, &[0.5, 0.5, 0.5]),
+            ("d", Metric::Playouts, &[1.0]),
+        ]);
+        let comparisons = compare(&before, &after, DEFAULT_THRESHOLD).unwrap();
+        assert_eq!(comparisons.len(), 3);
+        assert!(comparisons[0].regression);
+        assert!((comparisons[0].change + 0.2).abs() < 1e-9);
+        assert!(!comparisons[1].regression);
+        assert!(comparisons[1].noise > 0.2);
+        assert!(!comparisons[2].regression);
+        assert!((comparisons[2].change - 0.5).abs() < 1e-9);
+
+        // a slowdown within the threshold is not a regression, however steady
+        let slightly = baseline(&[("a", Metric::Playouts, &[980.0, 980.0, 980.0])]);
+        let comparisons = compare(&before, &slightly, DEFAULT_THRESHOLD).unwrap();
+        assert!(!comparisons[0].regression);
+
+        let other = Baseline {
+            iterations: 200,
+            ..after
+        };
+        assert_eq!(
+            compare(&before, &other, DEFAULT_THRESHOLD),
+            Err(BenchError::DifferentIterations {
+                baseline: 100,
+                current: 200
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_and_save() {
+        let positions = [find("opening-1").unwrap()];
+        let run = run(&positions, 20, 2);
+        let metrics: Vec<Metric> = run.measurements.iter().map(|m| m.metric).collect();
+        assert_eq!(metrics, [Metric::Playouts, Metric::Flips, Metric::RayFlips]);
+        for measurement in &run.measurements {
+            assert_eq!(measurement.samples.len(), 2);
+            assert!(measurement.mean() > 0.0);
+        }
+
+        let path = std::env::temp_dir().join(format!("herb-bench-{}.json", std::process::id()));
+        run.save(&path).unwrap();
+        assert_eq!(Baseline::load(&path).unwrap(), run);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,147 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use herb::analysis::{
+    analyze_line, best_response_table, best_responses, compare_moves, LineAnalysis,
+};
+use herb::config::Config;
+use herb::othello::{Game, Move};
+use herb::record::{format_move, parse_move, GameRecord};
+
+const DEFAULT_ITERATIONS: u64 = 5000;
+
+const COMMANDS: &str = "Commands:
+  show                 print the current position
+  goto <ply>           go to the position before the given ply of the transcript
+  line <move>...       force the moves from the current position and search the result
+  whatif <move>...     compare the transcript move at this ply with the given moves
+  handout <move>...    tabulate Herb's reply in every position along the moves from here
+  quit";
+
+/// Explores forced variations of a game interactively, e.g. "what if Herb had played e3
+/// here instead". Reads commands from standard input, see `help`. With `--handout` it prints
+/// Herb's reply in every position of the transcript as a Markdown table instead and exits.
+///
+/// Usage: `herb-analyze [transcript.txt] [--iterations <n>] [--config <config.json>] [--handout]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let handout = match args.iter().position(|arg| arg == "--handout") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let iterations = match args.iter().position(|arg| arg == "--iterations") {
+        Some(i) => {
+            let iterations = args.get(i + 1).ok_or("Missing iterations")?.parse()?;
+            args.drain(i..i + 2);
+            iterations
+        }
+        None => DEFAULT_ITERATIONS,
+    };
+    let config = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let config = Config::new(args.get(i + 1).ok_or("Missing config")?);
+            args.drain(i..i + 2);
+            config
+        }
+        None => Config::default(),
+    };
+    let played: Vec<Move> = match args.first() {
+        Some(path) => GameRecord::from_transcript(&fs::read_to_string(path)?)?
+            .moves
+            .iter()
+            .map(|recorded| recorded.mv)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    if handout {
+        let responses = best_responses(Game::new(), &played, &config.mcts_config, iterations)?;
+        print!("{}", best_response_table(&responses));
+        return Ok(());
+    }
+
+    let mut ply = 0;
+    let mut game = Game::new();
+    println!("{}", COMMANDS);
+    prompt(ply)?;
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next();
+        let moves: Result<Vec<Move>, _> = tokens.clone().map(parse_move).collect();
+        match (command, moves) {
+            (None, _) => {}
+            (Some("quit"), _) => break,
+            (Some("show"), _) => {
+                print!("{}", game.get_board());
+                println!("{} to move", game.to_move());
+                if let Some(mv) = played.get(ply) {
+                    println!("Played: {}", format_move(*mv));
+                }
+            }
+            (Some("goto"), _) => match tokens.next().map(str::parse::<usize>) {
+                Some(Ok(target)) if target <= played.len() => {
+                    game = Game::new();
+                    for mv in &played[..target] {
+                        game.play_next_turn(*mv)?;
+                    }
+                    ply = target;
+                }
+                _ => println!("The transcript has {} plies", played.len()),
+            },
+            (Some("line"), Ok(moves)) => {
+                match analyze_line(game, &moves, &config.mcts_config, iterations) {
+                    Ok(analysis) => print_analysis(&analysis),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("whatif"), Ok(mut moves)) => {
+                if let Some(mv) = played.get(ply) {
+                    moves.insert(0, *mv);
+                }
+                match compare_moves(game, &moves, &config.mcts_config, iterations) {
+                    Ok(analyses) => analyses.iter().for_each(print_analysis),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("handout"), Ok(moves)) => {
+                match best_responses(game, &moves, &config.mcts_config, iterations) {
+                    Ok(responses) => print!("{}", best_response_table(&responses)),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("line" | "whatif" | "handout"), Err(e)) => println!("{}", e),
+            _ => println!("{}", COMMANDS),
+        }
+        prompt(ply)?;
+    }
+    Ok(())
+}
+
+fn prompt(ply: usize) -> io::Result<()> {
+    print!("ply {}> ", ply);
+    io::stdout().flush()
+}
+
+fn print_analysis(analysis: &LineAnalysis) {
+    let line: Vec<String> = analysis.line.iter().map(|mv| format_move(*mv)).collect();
+    let pv: Vec<String> = analysis
+        .pv
+        .iter()
+        .map(|step| format_move(step.mv))
+        .collect();
+    println!(
+        "{:<24} {} wins {:>5.1}%  then {} ({:.2})  pv {}",
+        line.join(" "),
+        analysis.player,
+        analysis.win_rate * 100.0,
+        format_move(analysis.best_move),
+        analysis.value,
+        pv.join(" ")
+    );
+}
@@ -0,0 +1,76 @@
+use std::env;
+use std::error::Error;
+
+use herb::config::Config;
+use herb::evaluation::WeightTable;
+use herb::opponents::Opponent;
+use herb::tournament::{play_match, MatchScore};
+use herb::Herb;
+
+const DEFAULT_GAMES: u32 = 4;
+const DEFAULT_GAME_TIME: f64 = 10.0;
+// the players of game `n` are seeded with the stream of this seed for `n`
+const SEED: u64 = 0;
+
+/// Plays Herb against every scripted reference opponent and reports its score against each.
+/// A `weight_table` in the config other than the classic one is played as an extra opponent.
+///
+/// Usage: `herb-arena [config.json] [games per opponent] [seconds per game]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut config = match args.first() {
+        Some(path) => Config::new(path),
+        None => Config::default(),
+    };
+    let games = args.get(1).map_or(Ok(DEFAULT_GAMES), |s| s.parse())?;
+    config.max_time = args.get(2).map_or(Ok(DEFAULT_GAME_TIME), |s| s.parse())?;
+    config.log = false;
+
+    let table = config.mcts_config.weight_table;
+    let mut total = MatchScore::default();
+    for opponent in Opponent::LADDER {
+        let score = play_match(
+            games,
+            |game| Herb::new(config.clone()).with_stream(SEED, game.into(), 0),
+            |game| opponent.with_rng(config.rng.stream(SEED, game.into(), 0)),
+        )?;
+        println!(
+            "{:<12} {:.3} (+{} -{} ={})",
+            opponent.to_string(),
+            score.score(),
+            score.wins,
+            score.losses,
+            score.draws
+        );
+        total.wins += score.wins;
+        total.losses += score.losses;
+        total.draws += score.draws;
+    }
+    if table != WeightTable::CLASSIC {
+        let score = play_match(
+            games,
+            |game| Herb::new(config.clone()).with_stream(SEED, game.into(), 0),
+            |_| table,
+        )?;
+        println!(
+            "{:<12} {:.3} (+{} -{} ={})",
+            "weight table",
+            score.score(),
+            score.wins,
+            score.losses,
+            score.draws
+        );
+        total.wins += score.wins;
+        total.losses += score.losses;
+        total.draws += score.draws;
+    }
+    println!(
+        "{:<12} {:.3} (+{} -{} ={})",
+        "total",
+        total.score(),
+        total.wins,
+        total.losses,
+        total.draws
+    );
+    Ok(())
+}
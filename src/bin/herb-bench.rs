@@ -0,0 +1,137 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use herb::bench::{compare, run, Baseline, DEFAULT_THRESHOLD};
+use herb::config::MctsConfig;
+use herb::corpus::{find, BenchPosition, CORPUS};
+use herb::scaling;
+
+const DEFAULT_ITERATIONS: u64 = 2000;
+const DEFAULT_REPEATS: usize = 1;
+
+/// Measures single threaded search speed and the speed of both ways of finding flips on the
+/// positions of the benchmark corpus, and the solver on its endgame positions. `--save` writes the run as a baseline, `--compare` compares
+/// it with a saved one and exits with status 1 if anything got significantly slower, see
+/// [`herb::bench`]. Comparisons need a few `--repeats` to tell a regression from noise.
+///
+/// `--scaling` instead searches each position for the given seconds on 1, 2, 4, ... up to
+/// `--threads` threads, every cpu by default, and reports the speedup and the root visits of
+/// each parallel search strategy, see [`herb::scaling`]. The iterations are ignored.
+///
+/// Usage: `herb-bench [iterations] [position id]... [--repeats <n>] [--save <baseline.json>]
+/// [--compare <baseline.json>] [--threshold <fraction>] [--scaling <seconds>]
+/// [--threads <n>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let repeats = match args.iter().position(|arg| arg == "--repeats") {
+        Some(i) => {
+            let repeats = args.get(i + 1).ok_or("Missing repeats")?.parse()?;
+            args.drain(i..i + 2);
+            repeats
+        }
+        None => DEFAULT_REPEATS,
+    };
+    let save = match args.iter().position(|arg| arg == "--save") {
+        Some(i) => {
+            let path = args.get(i + 1).cloned().ok_or("Missing baseline")?;
+            args.drain(i..i + 2);
+            Some(path)
+        }
+        None => None,
+    };
+    let baseline = match args.iter().position(|arg| arg == "--compare") {
+        Some(i) => {
+            let path = args.get(i + 1).ok_or("Missing baseline")?;
+            let baseline = Baseline::load(Path::new(path))?;
+            args.drain(i..i + 2);
+            Some(baseline)
+        }
+        None => None,
+    };
+    let threshold = match args.iter().position(|arg| arg == "--threshold") {
+        Some(i) => {
+            let threshold = args.get(i + 1).ok_or("Missing threshold")?.parse()?;
+            args.drain(i..i + 2);
+            threshold
+        }
+        None => DEFAULT_THRESHOLD,
+    };
+    let scaling: Option<f64> = match args.iter().position(|arg| arg == "--scaling") {
+        Some(i) => {
+            let seconds = args.get(i + 1).ok_or("Missing seconds")?.parse()?;
+            args.drain(i..i + 2);
+            Some(seconds)
+        }
+        None => None,
+    };
+    let threads = match args.iter().position(|arg| arg == "--threads") {
+        Some(i) => {
+            let threads = args.get(i + 1).ok_or("Missing threads")?.parse()?;
+            args.drain(i..i + 2);
+            threads
+        }
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    };
+    if args.iter().any(|arg| arg.starts_with("--")) {
+        eprintln!(
+            "Usage: herb-bench [iterations] [position id]... [--repeats <n>] \
+             [--save <baseline.json>] [--compare <baseline.json>] [--threshold <fraction>] \
+             [--scaling <seconds>] [--threads <n>]"
+        );
+        std::process::exit(2);
+    }
+
+    let iterations = args.first().map_or(Ok(DEFAULT_ITERATIONS), |s| s.parse())?;
+    let positions: Vec<&BenchPosition> = if args.len() > 1 {
+        args[1..]
+            .iter()
+            .map(|id| find(id).ok_or(format!("Unknown position: {}", id)))
+            .collect::<Result<_, _>>()?
+    } else {
+        CORPUS.iter().collect()
+    };
+
+    if let Some(seconds) = scaling {
+        let config = MctsConfig::default();
+        for position in positions {
+            println!("{}", position.id);
+            let rows = scaling::report(
+                position.game(),
+                &config,
+                threads,
+                Duration::from_secs_f64(seconds),
+            );
+            for row in rows {
+                println!("  {}", row);
+            }
+        }
+        return Ok(());
+    }
+
+    let current = run(&positions, iterations, repeats);
+    for measurement in &current.measurements {
+        println!("{}", measurement);
+    }
+    if let Some(path) = save {
+        current.save(Path::new(&path))?;
+    }
+    if let Some(baseline) = baseline {
+        println!();
+        let comparisons = compare(&baseline, &current, threshold)?;
+        for comparison in &comparisons {
+            println!("{}", comparison);
+        }
+        let regressions = comparisons.iter().filter(|c| c.regression).count();
+        if regressions > 0 {
+            eprintln!(
+                "{} regression(s) against the baseline of {}",
+                regressions, baseline.build
+            );
+            std::process::exit(1);
+        }
+    }
+    Ok(())
+}
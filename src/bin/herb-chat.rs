@@ -0,0 +1,54 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use herb::chat::{ChatBot, ChatConfig};
+
+/// Runs the chat bot on standard input, for trying it out or relaying a chat service to it.
+///
+/// Every line is a channel name followed by a message written in it, e.g. `general !herb d3`.
+/// Replies are printed as the channel name followed by the reply. With `--images <dir>`,
+/// which needs the `render` feature, the diagram of each reply is written to the directory as
+/// `<channel>-<n>.svg` and its path printed after the reply.
+///
+/// Usage: `herb-chat [config.json] [--images <dir>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let images = match args.iter().position(|arg| arg == "--images") {
+        Some(i) => {
+            let dir = PathBuf::from(args.get(i + 1).ok_or("Missing image directory")?);
+            args.drain(i..i + 2);
+            fs::create_dir_all(&dir)?;
+            Some(dir)
+        }
+        None => None,
+    };
+    let config: ChatConfig = match args.first() {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => ChatConfig::default(),
+    };
+
+    let mut bot = ChatBot::new(config);
+    let mut replies = 0;
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let Some((channel, message)) = line.trim().split_once(' ') else {
+            continue;
+        };
+        let Some(reply) = bot.handle(channel, message) else {
+            continue;
+        };
+        writeln!(stdout, "{} {}", channel, reply.text)?;
+        if let (Some(dir), Some(image)) = (&images, &reply.image) {
+            replies += 1;
+            let path = dir.join(format!("{}-{}.svg", channel, replies));
+            fs::write(&path, image)?;
+            writeln!(stdout, "{} {}", channel, path.display())?;
+        }
+        stdout.flush()?;
+    }
+    Ok(())
+}
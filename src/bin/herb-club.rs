@@ -0,0 +1,223 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use herb::analysis::analyze_line;
+use herb::club::{Ending, Scorekeeper};
+use herb::config::Config;
+use herb::othello::Color::{self, Black, White};
+use herb::record::{format_move, parse_move};
+
+const DEFAULT_TIME: u64 = 600;
+
+const COMMANDS: &str = "Commands:
+  <move>               play the move of the player to move, e.g. d3
+  takeback <B|W>       ask to take back the last move
+  agree <B|W>          agree to the opponent's takeback
+  decline <B|W>        turn down the opponent's takeback
+  resign <B|W>         resign the game
+  board                print the position and the clocks
+  save <file>          write the transcript of the game
+  quit";
+
+/// Keeps score of a game between two people at the board: only legal moves are accepted,
+/// forced passes are played for whoever has to pass, each side's clock runs while it is to
+/// move and takebacks need the opponent's agreement. Reads commands from standard input, see
+/// `help`. With `--commentary <iterations>` Herb searches every position for that many
+/// iterations and shows its verdict beside the board.
+///
+/// Usage: `herb-club [--black <name>] [--white <name>] [--time <seconds>]
+/// [--commentary <iterations>] [--config <config.json>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let black = option(&mut args, "--black")?.unwrap_or_else(|| "Black".to_string());
+    let white = option(&mut args, "--white")?.unwrap_or_else(|| "White".to_string());
+    let time = match option(&mut args, "--time")? {
+        Some(time) => time.parse()?,
+        None => DEFAULT_TIME,
+    };
+    let commentary: Option<u64> = option(&mut args, "--commentary")?
+        .map(|iterations| iterations.parse())
+        .transpose()?;
+    let config = match option(&mut args, "--config")? {
+        Some(path) => Config::new(&path),
+        None => Config::default(),
+    };
+
+    let mut keeper = Scorekeeper::new(&black, &white, Duration::from_secs(time));
+    println!("{}", COMMANDS);
+    show(&keeper, &config, commentary);
+    let mut started = Instant::now();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let mut tokens = line.split_whitespace();
+        let command = tokens.next();
+        let color = tokens.clone().next().and_then(parse_color);
+        let elapsed = started.elapsed();
+        started = Instant::now();
+        match (command, color) {
+            (None, _) => {}
+            (Some("quit"), _) => break,
+            (Some("board"), _) => {
+                keeper.tick(elapsed);
+                show(&keeper, &config, commentary);
+            }
+            (Some("save"), _) => {
+                keeper.tick(elapsed);
+                match tokens.next() {
+                    Some(path) => match keeper.finished_record().to_transcript() {
+                        Ok(transcript) => fs::write(path, transcript)?,
+                        Err(e) => println!("{}", e),
+                    },
+                    None => println!("Missing file"),
+                }
+            }
+            (Some("takeback"), Some(color)) => {
+                keeper.tick(elapsed);
+                match keeper.request_takeback(color) {
+                    Ok(()) => println!("{} asks to take back the last move", color),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("agree"), Some(color)) => {
+                keeper.tick(elapsed);
+                match keeper.agree_takeback(color) {
+                    Ok(mv) => {
+                        println!("{} is taken back", format_move(mv));
+                        show(&keeper, &config, commentary);
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("decline"), Some(color)) => {
+                keeper.tick(elapsed);
+                match keeper.decline_takeback(color) {
+                    Ok(()) => println!("The takeback is declined"),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            (Some("resign"), Some(color)) => {
+                if let Err(e) = keeper.resign(color) {
+                    println!("{}", e);
+                }
+            }
+            (Some(token), _) => match parse_move(token) {
+                Ok(mv) => match keeper.play(mv, elapsed) {
+                    Ok(()) => show(&keeper, &config, commentary),
+                    Err(e) => println!("{}", e),
+                },
+                Err(_) => println!("{}", COMMANDS),
+            },
+        }
+        if let Some(ending) = keeper.ending() {
+            announce(&keeper, ending);
+            break;
+        }
+        prompt(&keeper)?;
+    }
+    Ok(())
+}
+
+/// Remove `name <value>` from the arguments and return the value.
+fn option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|arg| arg == name) {
+        Some(i) => {
+            let value = args
+                .get(i + 1)
+                .cloned()
+                .ok_or(format!("Missing value for {}", name))?;
+            args.drain(i..i + 2);
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+fn parse_color(token: &str) -> Option<Color> {
+    match token.to_lowercase().as_str() {
+        "b" | "black" => Some(Black),
+        "w" | "white" => Some(White),
+        _ => None,
+    }
+}
+
+fn prompt(keeper: &Scorekeeper) -> io::Result<()> {
+    print!("{} to move> ", keeper.game().to_move());
+    io::stdout().flush()
+}
+
+/// Print the board with the clocks and, with commentary, Herb's verdict beside it.
+fn show(keeper: &Scorekeeper, config: &Config, commentary: Option<u64>) {
+    let game = keeper.game();
+    let (black, white) = game.disc_counts();
+    let record = keeper.record();
+    let mut sidebar = vec![
+        format!(
+            "B {:<12} {:>3}  {}",
+            record.tags["Black"],
+            black,
+            clock(keeper.remaining(Black))
+        ),
+        format!(
+            "W {:<12} {:>3}  {}",
+            record.tags["White"],
+            white,
+            clock(keeper.remaining(White))
+        ),
+        match record.moves.last() {
+            Some(last) => format!("Last move: {}", format_move(last.mv)),
+            None => String::new(),
+        },
+    ];
+    if let (Some(iterations), false) = (commentary, game.is_over()) {
+        if let Ok(analysis) = analyze_line(game, &[], &config.mcts_config, iterations) {
+            let pv: Vec<String> = analysis
+                .pv
+                .iter()
+                .take(6)
+                .map(|step| format_move(step.mv))
+                .collect();
+            sidebar.push(String::new());
+            sidebar.push(format!(
+                "Herb: {} ({:.2})",
+                format_move(analysis.best_move),
+                analysis.value
+            ));
+            sidebar.push(format!(
+                "{} wins {:.1}%",
+                analysis.player,
+                analysis.win_rate * 100.0
+            ));
+            sidebar.push(format!("pv {}", pv.join(" ")));
+        }
+    }
+    let board = game.get_board().to_string();
+    for (i, row) in board.lines().enumerate() {
+        println!(
+            "{} {}   {}",
+            i + 1,
+            row,
+            sidebar.get(i).map(String::as_str).unwrap_or("")
+        );
+    }
+    println!("  a b c d e f g h");
+}
+
+fn announce(keeper: &Scorekeeper, ending: Ending) {
+    let (black, white) = keeper.game().disc_counts();
+    match ending {
+        Ending::Board => match keeper.winner() {
+            Some(winner) => println!("{} wins {}-{}", winner, black, white),
+            None => println!("Draw {}-{}", black, white),
+        },
+        Ending::Time(color) => println!("{} ran out of time, {} wins", color, color.opponent()),
+        Ending::Resignation(color) => println!("{} resigns, {} wins", color, color.opponent()),
+    }
+}
+
+fn clock(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
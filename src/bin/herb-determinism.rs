@@ -0,0 +1,123 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::corpus::{find, CORPUS};
+use herb::determinism::{audit, scan};
+use herb::othello::Game;
+use herb::record::parse_move;
+
+const DEFAULT_ITERATIONS: u64 = 2000;
+const DEFAULT_PLIES: usize = 4;
+
+/// Checks that the fixed-iteration search is deterministic. From each position, a benchmark
+/// corpus ID or moves from the start, e.g. `"d3 c3 c4"`, and every corpus position if none is
+/// given, a line of `--plies` decisions is searched twice and every decision the searches
+/// disagree on is printed. The search modules are then scanned for sources of nondeterminism,
+/// printing the ones nobody has reviewed, or all of them when the searches disagreed. Exits
+/// with status 1 if anything was found.
+///
+/// Usage: `herb-determinism [<position>...] [--iterations <n>] [--plies <n>]
+/// [--config <config.json>] [--src <crate directory>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let iterations = match args.iter().position(|arg| arg == "--iterations") {
+        Some(i) => {
+            let iterations = args.get(i + 1).ok_or("Missing iterations")?.parse()?;
+            args.drain(i..i + 2);
+            iterations
+        }
+        None => DEFAULT_ITERATIONS,
+    };
+    let plies = match args.iter().position(|arg| arg == "--plies") {
+        Some(i) => {
+            let plies = args.get(i + 1).ok_or("Missing plies")?.parse()?;
+            args.drain(i..i + 2);
+            plies
+        }
+        None => DEFAULT_PLIES,
+    };
+    let config = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let config = Config::new(args.get(i + 1).ok_or("Missing config")?);
+            args.drain(i..i + 2);
+            config
+        }
+        None => Config::default(),
+    };
+    let src = match args.iter().position(|arg| arg == "--src") {
+        Some(i) => {
+            let src = args.get(i + 1).cloned().ok_or("Missing crate directory")?;
+            args.drain(i..i + 2);
+            src
+        }
+        None => env!("CARGO_MANIFEST_DIR").to_string(),
+    };
+    if args.iter().any(|arg| arg.starts_with("--")) {
+        eprintln!(
+            "Usage: herb-determinism [<position>...] [--iterations <n>] [--plies <n>] \
+             [--config <config.json>] [--src <crate directory>]"
+        );
+        std::process::exit(2);
+    }
+
+    let mut positions = Vec::new();
+    if args.is_empty() {
+        for position in &CORPUS {
+            positions.push((position.id.to_string(), position.game()));
+        }
+    }
+    for arg in &args {
+        let game = match find(arg) {
+            Some(position) => position.game(),
+            None => {
+                let mut game = Game::new();
+                for token in arg.split_whitespace() {
+                    game.play_next_turn(parse_move(token)?)?;
+                }
+                game
+            }
+        };
+        positions.push((arg.clone(), game));
+    }
+
+    let mut divergent = false;
+    for (name, game) in positions {
+        let audit = audit(game, &config.mcts_config, iterations, plies);
+        if audit.divergences.is_empty() {
+            println!("{}: {} decisions identical", name, audit.decisions);
+        } else {
+            println!(
+                "{}: {} of {} decisions differ",
+                name,
+                audit.divergences.len(),
+                audit.decisions
+            );
+            for divergence in &audit.divergences {
+                println!("  {}", divergence);
+            }
+            divergent = true;
+        }
+    }
+
+    let sources = scan(Path::new(&src))?;
+    let flagged = sources
+        .iter()
+        .filter(|source| source.reason.is_none())
+        .count();
+    println!(
+        "{} sources of nondeterminism in the search, {} not reviewed",
+        sources.len(),
+        flagged
+    );
+    for source in &sources {
+        if divergent || source.reason.is_none() {
+            println!("  {}", source);
+        }
+    }
+    if divergent || flagged > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use herb::othello::Game;
+use herb::record::GameRecord;
+use herb::render::{render_svg, RenderOptions};
+
+/// Exports an SVG diagram of every position in a game transcript, highlighting the move
+/// played from each position.
+///
+/// Usage: `herb-diagram <transcript.txt> <output directory>`
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!("Usage: herb-diagram <transcript.txt> <output directory>");
+        std::process::exit(2);
+    }
+    let record = GameRecord::from_transcript(&fs::read_to_string(&args[0])?)?;
+    let out_dir = Path::new(&args[1]);
+    fs::create_dir_all(out_dir)?;
+
+    let mut game = Game::new();
+    for (i, recorded) in record.moves.iter().enumerate() {
+        let options = RenderOptions {
+            chosen: Some(recorded.mv),
+            ..RenderOptions::default()
+        };
+        fs::write(
+            out_dir.join(format!("{:02}.svg", i)),
+            render_svg(&game, &options),
+        )?;
+        game.play_next_turn(recorded.mv)?;
+    }
+    fs::write(
+        out_dir.join(format!("{:02}.svg", record.moves.len())),
+        render_svg(&game, &RenderOptions::default()),
+    )?;
+    println!("Wrote {} diagrams", record.moves.len() + 1);
+    Ok(())
+}
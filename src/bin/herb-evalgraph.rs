@@ -0,0 +1,76 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::evalgraph::EvalSeries;
+use herb::record::GameRecord;
+
+/// Exports the move-by-move evaluation of finished games for plotting evaluation graphs. Each
+/// transcript is written to the output directory under its own name as CSV, or as json with
+/// `--json`, holding the evaluations recorded during the game. With `--reanalyze <n>` every
+/// position is also searched for `n` iterations and black's win rate added.
+///
+/// Usage: `herb-evalgraph <output directory> <transcript.txt>... [--reanalyze <iterations>]
+/// [--config <config.json>] [--json]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let json = match args.iter().position(|arg| arg == "--json") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let reanalyze: Option<u64> = match args.iter().position(|arg| arg == "--reanalyze") {
+        Some(i) => {
+            let iterations = args.get(i + 1).ok_or("Missing iterations")?.parse()?;
+            args.drain(i..i + 2);
+            Some(iterations)
+        }
+        None => None,
+    };
+    let config = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let config = Config::new(args.get(i + 1).ok_or("Missing config")?);
+            args.drain(i..i + 2);
+            config
+        }
+        None => Config::default(),
+    };
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: herb-evalgraph <output directory> <transcript.txt>... \
+             [--reanalyze <iterations>] [--config <config.json>] [--json]"
+        );
+        std::process::exit(2);
+    }
+    let out_dir = Path::new(&args[0]);
+    fs::create_dir_all(out_dir)?;
+
+    for path in &args[1..] {
+        let record = GameRecord::from_transcript(&fs::read_to_string(path)?)?;
+        let mut series = EvalSeries::from_record(&record)?;
+        if let Some(iterations) = reanalyze {
+            series.reanalyze(&config.mcts_config, iterations);
+        }
+        let name = Path::new(path).file_stem().ok_or("Missing file name")?;
+        let out = if json {
+            let out = out_dir.join(name).with_extension("json");
+            fs::write(&out, serde_json::to_string_pretty(&series)?)?;
+            out
+        } else {
+            let out = out_dir.join(name).with_extension("csv");
+            fs::write(&out, series.to_csv())?;
+            out
+        };
+        println!(
+            "{}: {} plies to {}",
+            path,
+            series.points.len(),
+            out.display()
+        );
+    }
+    Ok(())
+}
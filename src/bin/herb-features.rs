@@ -0,0 +1,52 @@
+use std::env;
+use std::error::Error;
+
+use herb::config::MctsConfig;
+use herb::evaluation::{corpus_positions, feature_importance, Evaluation};
+use herb::golden::herb_move;
+
+const DEFAULT_ITERATIONS: u64 = 5000;
+const DEFAULT_PERTURBATION: f64 = 0.5;
+
+/// Reports how much each feature of the default [`Evaluation`] matters for agreeing with
+/// Herb's deep search on the positions along the benchmark corpus lines.
+///
+/// Usage: `herb-features [iterations per reference move] [perturbation]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let iterations = args.first().map_or(Ok(DEFAULT_ITERATIONS), |s| s.parse())?;
+    let perturbation = args
+        .get(1)
+        .map_or(Ok(DEFAULT_PERTURBATION), |s| s.parse())?;
+
+    let config = MctsConfig::default();
+    let references: Vec<_> = corpus_positions()
+        .into_iter()
+        .map(|game| (game, herb_move(game, &config, iterations).0))
+        .collect();
+
+    let evaluation = Evaluation::default();
+    let (baseline, importance) = feature_importance(&evaluation, &references, perturbation);
+    println!(
+        "Agreement with {} reference moves at {} iterations: {:.3}",
+        references.len(),
+        iterations,
+        baseline
+    );
+    println!(
+        "{:<10} {:>7} {:>8} {:>8} {:>8} {:>7}",
+        "feature", "weight", "dropped", "lowered", "raised", "impact"
+    );
+    for feature in importance {
+        println!(
+            "{:<10} {:>7} {:>8.3} {:>8.3} {:>8.3} {:>7.3}",
+            feature.feature.to_string(),
+            feature.weight,
+            feature.dropped,
+            feature.lowered,
+            feature.raised,
+            feature.impact(baseline)
+        );
+    }
+    Ok(())
+}
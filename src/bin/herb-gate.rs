@@ -0,0 +1,63 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::gate::{promote, should_promote, DEFAULT_THRESHOLD};
+use herb::tournament::play_match;
+use herb::Herb;
+
+const DEFAULT_GAMES: u32 = 10;
+const DEFAULT_GAME_TIME: f64 = 20.0;
+// the engines of game `n` are seeded with the stream of this seed for `n`
+const SEED: u64 = 0;
+
+/// Plays a candidate configuration against the current best and promotes the candidate
+/// if it scores above the threshold.
+///
+/// Usage: `herb-gate <candidate.json> <best.json> [games] [threshold] [seconds per game]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: herb-gate <candidate.json> <best.json> [games] [threshold] [seconds per game]"
+        );
+        std::process::exit(2);
+    }
+    let candidate_path = Path::new(&args[0]);
+    let best_path = Path::new(&args[1]);
+    let games = args.get(2).map_or(Ok(DEFAULT_GAMES), |s| s.parse())?;
+    let threshold = args.get(3).map_or(Ok(DEFAULT_THRESHOLD), |s| s.parse())?;
+    let game_time = args.get(4).map_or(Ok(DEFAULT_GAME_TIME), |s| s.parse())?;
+
+    // both sides play with the same settings, only the search parameters differ
+    let fixed = |mut config: Config| {
+        config.max_time = game_time;
+        config.log = false;
+        config
+    };
+    let candidate = fixed(Config::new(&args[0]));
+    let best = fixed(Config::new(&args[1]));
+
+    let score = play_match(
+        games,
+        |game| Herb::new(candidate.clone()).with_stream(SEED, game.into(), 0),
+        |game| Herb::new(best.clone()).with_stream(SEED, game.into(), 0),
+    )?;
+    println!(
+        "Candidate scored {:.3} (+{} -{} ={}) against the best, threshold {:.3}",
+        score.score(),
+        score.wins,
+        score.losses,
+        score.draws,
+        threshold
+    );
+
+    if should_promote(&score, threshold) {
+        let metadata = promote(candidate_path, best_path, &score)?;
+        println!("Promoted candidate to version {}", metadata.version);
+    } else {
+        println!("Candidate rejected");
+    }
+    Ok(())
+}
@@ -0,0 +1,66 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+
+use herb::config::Config;
+use herb::golden::{check, record, DEFAULT_TOLERANCE};
+use herb::opponents::Opponent;
+use herb::othello::Color;
+use herb::record::GameRecord;
+
+const DEFAULT_ITERATIONS: u64 = 50;
+
+const USAGE: &str = "Usage: herb-golden record <golden.txt> [iterations] [B|W] [config.json]
+       herb-golden check <golden.txt>...";
+
+/// Records golden games of Herb against the greedy reference opponent, or checks that Herb
+/// still plays the recorded moves.
+///
+/// `check` exits with a failure if any game has drifted.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("record") if args.len() >= 2 => {
+            let iterations = args.get(2).map_or(Ok(DEFAULT_ITERATIONS), |s| s.parse())?;
+            let color = match args.get(3).map(String::as_str) {
+                None | Some("B") => Color::Black,
+                Some("W") => Color::White,
+                Some(other) => return Err(format!("Bad color: {}", other).into()),
+            };
+            let config = match args.get(4) {
+                Some(path) => Config::new(path),
+                None => Config::default(),
+            };
+            let golden = record(
+                &mut Opponent::Greedy,
+                color,
+                &config.mcts_config,
+                iterations,
+            );
+            fs::write(&args[1], golden.to_transcript()?)?;
+            println!("Recorded {} moves to {}", golden.moves.len(), args[1]);
+        }
+        Some("check") if args.len() >= 2 => {
+            let mut drifted = false;
+            for path in &args[1..] {
+                let golden = GameRecord::from_transcript(&fs::read_to_string(path)?)?;
+                let drifts = check(&golden, DEFAULT_TOLERANCE)?;
+                if drifts.is_empty() {
+                    println!("{}: ok", path);
+                }
+                for drift in drifts {
+                    println!("{}: {}", path, drift);
+                    drifted = true;
+                }
+            }
+            if drifted {
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
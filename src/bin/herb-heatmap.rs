@@ -0,0 +1,69 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use herb::heatmap::{Heatmap, Phase, SquareStats};
+use herb::record::{dedup, Equivalence, GameRecord};
+
+/// Collects per-square statistics from game transcripts and writes them as heatmaps: how
+/// often the winner ends up owning each square, and how often each square is played in the
+/// opening, midgame and endgame. Heatmaps are written as CSV, and also as SVG when built with
+/// the `render` feature.
+///
+/// With `--player <name>` only the moves of the player with that name in the `Black` or
+/// `White` tag are counted. Games that repeat an earlier one, on the same board or a rotated
+/// or reflected one, are only counted once.
+///
+/// Usage: `herb-heatmap <output directory> <transcript.txt>... [--player <name>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let player = match args.iter().position(|arg| arg == "--player") {
+        Some(i) => {
+            let name = args.get(i + 1).cloned();
+            args.drain(i..(i + 2).min(args.len()));
+            name
+        }
+        None => None,
+    };
+    if args.len() < 2 {
+        eprintln!("Usage: herb-heatmap <output directory> <transcript.txt>... [--player <name>]");
+        std::process::exit(2);
+    }
+    let out_dir = Path::new(&args[0]);
+    fs::create_dir_all(out_dir)?;
+
+    let mut records = Vec::new();
+    for path in &args[1..] {
+        records.push(GameRecord::from_transcript(&fs::read_to_string(path)?)?);
+    }
+    let (records, dropped) = dedup(records, Equivalence::Symmetric);
+    if dropped > 0 {
+        println!("Dropped {} duplicate games", dropped);
+    }
+    let mut stats = SquareStats::default();
+    for record in &records {
+        stats.add(record, player.as_deref())?;
+    }
+
+    write_heatmap(out_dir, "ownership", &stats.winner_ownership())?;
+    for phase in Phase::ALL {
+        write_heatmap(out_dir, phase.name(), &stats.move_preference(phase))?;
+    }
+    println!(
+        "Wrote heatmaps of {} games to {}",
+        records.len(),
+        out_dir.display()
+    );
+    Ok(())
+}
+
+fn write_heatmap(out_dir: &Path, name: &str, heatmap: &Heatmap) -> Result<(), Box<dyn Error>> {
+    fs::write(out_dir.join(format!("{}.csv", name)), heatmap.to_csv())?;
+    #[cfg(feature = "render")]
+    fs::write(
+        out_dir.join(format!("{}.svg", name)),
+        herb::render::render_heatmap(heatmap, name),
+    )?;
+    Ok(())
+}
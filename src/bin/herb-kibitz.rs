@@ -0,0 +1,177 @@
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, TryRecvError};
+use std::thread;
+
+use herb::config::Config;
+use herb::kibitz::{parse_feed_line, Commentary, FeedItem, Kibitzer};
+use herb::othello::Move;
+use herb::record::format_move;
+
+const DEFAULT_ITERATIONS: u64 = 5000;
+const DEFAULT_MAX_ITERATIONS: u64 = 200_000;
+
+/// Comments on a game fed in on standard input without playing in it: referee protocol lines,
+/// a session recording or the events of a spectator feed, e.g.
+/// `curl -N http://localhost:8080/ | herb-kibitz`. Every move is commented on once Herb has
+/// searched the new position for `--iterations`, after which it keeps searching until the
+/// next move or `--max-iterations`, saying so whenever it changes its mind. With `--json`
+/// every comment is printed as json for overlays, and `--game` picks the game to follow from
+/// a spectator feed that carries several.
+///
+/// Usage: `herb-kibitz [config.json] [--iterations <n>] [--max-iterations <n>] [--game <id>]
+/// [--json]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let json = match args.iter().position(|arg| arg == "--json") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let iterations = option(&mut args, "--iterations")?.unwrap_or(DEFAULT_ITERATIONS);
+    let max_iterations = option(&mut args, "--max-iterations")?.unwrap_or(DEFAULT_MAX_ITERATIONS);
+    let game_id = option(&mut args, "--game")?.unwrap_or(0) as usize;
+    let config = match args.first() {
+        Some(path) => Config::new(path),
+        None => Config::default(),
+    };
+
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut kibitzer = Kibitzer::new(config.mcts_config);
+    // whether the latest move still has to be commented on
+    let mut pending = true;
+    let mut announced: Option<Move> = None;
+    loop {
+        let searching = kibitzer.iterations() < max_iterations && !kibitzer.game().is_over();
+        let line = if pending || searching {
+            match lines.try_recv() {
+                Ok(line) => Some(line?),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match lines.recv() {
+                Ok(line) => Some(line?),
+                Err(_) => break,
+            }
+        };
+
+        let Some(line) = line else {
+            kibitzer.think(iterations);
+            let commentary = kibitzer.commentary();
+            if pending {
+                print_commentary(&commentary, json)?;
+            } else if announced != Some(commentary.best_move) {
+                print_change(&commentary, json)?;
+            }
+            pending = false;
+            announced = Some(commentary.best_move);
+            continue;
+        };
+
+        let item = parse_feed_line(&line);
+        if pending && item.is_some() {
+            // every move gets its comment, however fast the feed
+            if kibitzer.iterations() < iterations {
+                kibitzer.think(iterations - kibitzer.iterations());
+            }
+            print_commentary(&kibitzer.commentary(), json)?;
+            pending = false;
+        }
+        let changed = match item {
+            Some(FeedItem::NewGame) => {
+                let started = kibitzer.game().get_turn() > 0;
+                if started {
+                    kibitzer.new_game();
+                }
+                started
+            }
+            Some(FeedItem::Move(mv)) => match kibitzer.play(mv) {
+                Ok(warning) => {
+                    if let Some(warning) = warning {
+                        println!("{}", warning);
+                    }
+                    true
+                }
+                Err(e) => {
+                    println!("{}: {}", format_move(mv), e);
+                    false
+                }
+            },
+            Some(FeedItem::Position(event)) if event.game_id == game_id => {
+                match kibitzer.follow(&event) {
+                    Ok(changed) => changed,
+                    Err(_) => {
+                        println!("Lost track of the game at turn {}", event.turn);
+                        false
+                    }
+                }
+            }
+            Some(FeedItem::End) => {
+                let (black, white) = kibitzer.game().disc_counts();
+                println!("Game over {}-{}", black, white);
+                false
+            }
+            _ => false,
+        };
+        if changed {
+            pending = true;
+            announced = None;
+        }
+    }
+    if pending {
+        kibitzer.think(iterations.saturating_sub(kibitzer.iterations()));
+        print_commentary(&kibitzer.commentary(), json)?;
+    }
+    Ok(())
+}
+
+/// Remove `name <value>` from the arguments and parse the value.
+fn option(args: &mut Vec<String>, name: &str) -> Result<Option<u64>, Box<dyn Error>> {
+    match args.iter().position(|arg| arg == name) {
+        Some(i) => {
+            let value = args
+                .get(i + 1)
+                .ok_or(format!("Missing value for {}", name))?
+                .parse()?;
+            args.drain(i..i + 2);
+            Ok(Some(value))
+        }
+        None => Ok(None),
+    }
+}
+
+fn print_commentary(commentary: &Commentary, json: bool) -> serde_json::Result<()> {
+    if json {
+        println!("{}", serde_json::to_string(commentary)?);
+    } else {
+        println!("{}", commentary);
+    }
+    Ok(())
+}
+
+/// Announce that a longer search changed Herb's mind about the position.
+fn print_change(commentary: &Commentary, json: bool) -> serde_json::Result<()> {
+    if json {
+        return print_commentary(commentary, json);
+    }
+    println!(
+        "  after {} iterations Herb prefers {}, {} wins {:.0}%",
+        commentary.iterations,
+        format_move(commentary.best_move),
+        commentary.to_move,
+        commentary.win_rate * 100.0
+    );
+    Ok(())
+}
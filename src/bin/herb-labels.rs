@@ -0,0 +1,60 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::time::Instant;
+
+use herb::evaluation::Evaluation;
+use herb::labels::{generate, parse_labels, score};
+use herb::opponents::Opponent;
+
+const DEFAULT_EMPTIES: u64 = 14;
+const DEFAULT_SEED: u64 = 0;
+
+/// Generates endgame positions labeled with the exact score of every move, and scores the
+/// default [`Evaluation`] and the scripted opponents against a labels file.
+///
+/// Usage:
+/// - `herb-labels generate <count> [empties] [seed] > labels.txt`
+/// - `herb-labels score <labels.txt>`
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("generate") if args.len() >= 2 => {
+            let count = args[1].parse()?;
+            let empties = args.get(2).map_or(Ok(DEFAULT_EMPTIES), |s| s.parse())?;
+            let seed = args.get(3).map_or(Ok(DEFAULT_SEED), |s| s.parse())?;
+            let start = Instant::now();
+            let labels = generate(count, empties, seed);
+            println!("# {} positions, {} empties, seed {}", count, empties, seed);
+            for label in &labels {
+                println!("{}", label);
+            }
+            eprintln!(
+                "Labeled {} positions in {:?}",
+                labels.len(),
+                start.elapsed()
+            );
+        }
+        Some("score") if args.len() == 2 => {
+            let labels = parse_labels(&fs::read_to_string(&args[1])?)?;
+            println!(
+                "{:<12} {}",
+                "evaluation",
+                score(&mut Evaluation::default(), &labels)
+            );
+            for mut opponent in Opponent::LADDER {
+                println!(
+                    "{:<12} {}",
+                    opponent.to_string(),
+                    score(&mut opponent, &labels)
+                );
+            }
+        }
+        _ => {
+            eprintln!("Usage: herb-labels generate <count> [empties] [seed]");
+            eprintln!("       herb-labels score <labels.txt>");
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
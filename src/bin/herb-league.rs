@@ -0,0 +1,103 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::league::{round_robin, League, PlayerFactory};
+use herb::{Herb, Player};
+
+const OPENING_PLIES: usize = 4;
+// the openings and the engines of every game are drawn with this seed
+const SEED: u64 = 0;
+
+/// Plays a round-robin or Swiss event between any number of configurations and prints the
+/// standings as a crosstable.
+///
+/// A round-robin plays `rounds` cycles in which everybody meets everybody, with colors swapped
+/// in every other cycle. A Swiss event plays `rounds` rounds of pairings between players on
+/// similar scores.
+///
+/// With `--crosstable <file.csv>` the crosstable is also written as CSV.
+///
+/// Usage: `herb-league <round-robin|swiss> <rounds> <config.json>... [--crosstable <file.csv>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let csv = match args.iter().position(|arg| arg == "--crosstable") {
+        Some(i) => {
+            let path = args.get(i + 1).cloned();
+            args.drain(i..(i + 2).min(args.len()));
+            path
+        }
+        None => None,
+    };
+    if args.len() < 4 || !["round-robin", "swiss"].contains(&args[0].as_str()) {
+        eprintln!(
+            "Usage: herb-league <round-robin|swiss> <rounds> <config.json>... [--crosstable <file.csv>]"
+        );
+        std::process::exit(2);
+    }
+    let rounds: usize = args[1].parse()?;
+    let paths = &args[2..];
+
+    let names = paths
+        .iter()
+        .map(|path| {
+            Path::new(path)
+                .file_stem()
+                .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned())
+        })
+        .collect();
+    let configs: Vec<_> = paths
+        .iter()
+        .map(|path| {
+            let mut config = Config::new(path);
+            config.log = false;
+            config
+        })
+        .collect();
+    let factories: Vec<_> = configs
+        .iter()
+        .map(|config| {
+            move |game| {
+                Box::new(Herb::new(config.clone()).with_stream(SEED, game, 0)) as Box<dyn Player>
+            }
+        })
+        .collect();
+    let players: Vec<&PlayerFactory<'_>> = factories.iter().map(|f| f as _).collect();
+
+    let mut league = League::new(names);
+    if args[0] == "round-robin" {
+        let schedule = round_robin(players.len());
+        for cycle in 0..rounds {
+            for (i, pairings) in schedule.iter().enumerate() {
+                let pairings: Vec<_> = if cycle % 2 == 0 {
+                    pairings.clone()
+                } else {
+                    pairings
+                        .iter()
+                        .map(|&(black, white)| (white, black))
+                        .collect()
+                };
+                let round = cycle * schedule.len() + i;
+                league.play_round(round, &pairings, &players, OPENING_PLIES, SEED);
+                println!("Finished round {}", round + 1);
+            }
+        }
+    } else {
+        for round in 0..rounds {
+            let (pairings, bye) = league.swiss_pairings();
+            if let Some(bye) = bye {
+                league.record_bye(bye);
+            }
+            league.play_round(round, &pairings, &players, OPENING_PLIES, SEED);
+            println!("Finished round {}", round + 1);
+        }
+    }
+
+    print!("{}", league.crosstable());
+    if let Some(csv) = csv {
+        fs::write(csv, league.crosstable_csv())?;
+    }
+    Ok(())
+}
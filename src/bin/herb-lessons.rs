@@ -0,0 +1,73 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::lessons::{post_mortem, Lessons, DEFAULT_THRESHOLD};
+use herb::othello::Color;
+use herb::record::GameRecord;
+
+const DEFAULT_ITERATIONS: u64 = 2000;
+
+const USAGE: &str = "Usage: herb-lessons learn <lessons.json> <B|W> <game.txt>... [--iterations N] [--config config.json]
+       herb-lessons list <lessons.json>";
+
+/// Searches the transcripts of games Herb played as the given color for blunders and adds
+/// them to a lessons file, or lists the lessons in one.
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut iterations = DEFAULT_ITERATIONS;
+    let mut config = Config::default();
+    while let Some(index) = args.iter().position(|arg| arg.starts_with("--")) {
+        let (Some(flag), Some(value)) = (args.get(index).cloned(), args.get(index + 1).cloned())
+        else {
+            break;
+        };
+        match flag.as_str() {
+            "--iterations" => iterations = value.parse()?,
+            "--config" => config = Config::new(&value),
+            _ => break,
+        }
+        args.drain(index..index + 2);
+    }
+
+    match args.first().map(String::as_str) {
+        Some("learn") if args.len() >= 4 => {
+            let path = Path::new(&args[1]);
+            let color = match args[2].as_str() {
+                "B" => Color::Black,
+                "W" => Color::White,
+                other => return Err(format!("Bad color: {}", other).into()),
+            };
+            let mut lessons = Lessons::load(path)?;
+            for transcript in &args[3..] {
+                let record = GameRecord::from_transcript(&fs::read_to_string(transcript)?)?;
+                let found = post_mortem(
+                    &record,
+                    color,
+                    &config.mcts_config,
+                    iterations,
+                    DEFAULT_THRESHOLD,
+                )?;
+                for lesson in found {
+                    if lessons.learn(lesson) {
+                        println!("{}: {}", transcript, lesson);
+                    }
+                }
+            }
+            lessons.save(path)?;
+            println!("{} lessons in {}", lessons.lessons.len(), path.display());
+        }
+        Some("list") if args.len() == 2 => {
+            for lesson in Lessons::load(Path::new(&args[1]))?.lessons {
+                println!("{}", lesson);
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
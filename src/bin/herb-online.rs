@@ -0,0 +1,65 @@
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+
+use herb::config::Config;
+use herb::online::{play_online, GgsClient, OnlineClient, OnlineSettings};
+use herb::Herb;
+
+const DEFAULT_GAMES: usize = 1;
+const DEFAULT_CLOCK: u64 = 300;
+
+/// Plays Herb on an online Othello server in the style of the Generic Game Server, unattended.
+/// It offers a game, accepts the first challenge with at least its time and at most twice as
+/// much and plays until the given number of games is done. The password is read from the
+/// `HERB_ONLINE_PASSWORD` environment variable so it does not show up in the process list.
+/// Herb's `max_time` is set to the offered time, so it never plans for more time than it has.
+///
+/// Usage: `herb-online <host:port> <name> [config.json] [--games <n>] [--clock <seconds>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let games = match args.iter().position(|arg| arg == "--games") {
+        Some(i) => {
+            let games = args.get(i + 1).ok_or("Missing games")?.parse()?;
+            args.drain(i..i + 2);
+            games
+        }
+        None => DEFAULT_GAMES,
+    };
+    let clock = match args.iter().position(|arg| arg == "--clock") {
+        Some(i) => {
+            let clock = args.get(i + 1).ok_or("Missing clock")?.parse()?;
+            args.drain(i..i + 2);
+            clock
+        }
+        None => DEFAULT_CLOCK,
+    };
+    if args.len() < 2 {
+        eprintln!(
+            "Usage: herb-online <host:port> <name> [config.json] [--games <n>] [--clock <seconds>]"
+        );
+        std::process::exit(2);
+    }
+    let password = env::var("HERB_ONLINE_PASSWORD").map_err(|_| "Set HERB_ONLINE_PASSWORD")?;
+    let mut config = match args.get(2) {
+        Some(path) => Config::new(path),
+        None => Config::default(),
+    };
+    config.log = false;
+    config.max_time = clock as f64;
+
+    let mut client = GgsClient::connect(&args[0])?;
+    client.login(&args[1], &password)?;
+    let settings = OnlineSettings {
+        clock: Duration::from_secs(clock),
+        min_clock: Duration::from_secs(clock),
+        max_clock: Duration::from_secs(clock * 2),
+        games,
+    };
+    let mut herb = Herb::new(config);
+    for result in play_online(&mut client, &mut herb, &settings)? {
+        let (black, white) = result.game.disc_counts();
+        println!("{} moves, {}-{}", result.moves.len(), black, white);
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::env;
+use std::error::Error;
+use std::io::BufReader;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use herb::session::{self, replay};
+
+/// Replays the referee's side of a recorded session against a fresh `herb` process and
+/// reports every reply that differs from the recording, to reproduce a disputed game.
+///
+/// Usage: `herb-replay <session.jsonl> [config.json]`
+///
+/// The config is passed on to `herb`, it should be the one the session was recorded with,
+/// minus the `session` option so the recording is not overwritten. Herb's search is not
+/// deterministic, so once it plays a different move the rest of the referee's lines may no
+/// longer apply to the game.
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(path) = args.first() else {
+        eprintln!("Usage: herb-replay <session.jsonl> [config.json]");
+        std::process::exit(2);
+    };
+    let entries = session::load(Path::new(path))?;
+
+    let herb = env::current_exe()?.with_file_name("herb");
+    let mut child = Command::new(herb)
+        .args(&args[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdin = child.stdin.take().ok_or("Failed to open herb's input")?;
+    let stdout = child.stdout.take().ok_or("Failed to open herb's output")?;
+    let divergences = replay(&entries, stdin, BufReader::new(stdout))?;
+    let _ = child.kill();
+    let _ = child.wait();
+
+    for divergence in &divergences {
+        println!(
+            "line {}: recorded '{}', replayed {}",
+            divergence.index + 1,
+            divergence.expected,
+            divergence
+                .actual
+                .as_ref()
+                .map_or("nothing".to_string(), |actual| format!("'{}'", actual))
+        );
+    }
+    if divergences.is_empty() {
+        println!("Replayed {} lines without differences", entries.len());
+    } else {
+        std::process::exit(1);
+    }
+    Ok(())
+}
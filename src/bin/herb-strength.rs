@@ -0,0 +1,73 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::gate::GateMetadata;
+use herb::strength::{append_log, elo_margin, StrengthEstimate};
+use herb::tournament::play_match;
+use herb::Herb;
+
+const DEFAULT_GAMES: u32 = 10;
+const DEFAULT_GAME_TIME: f64 = 5.0;
+// the engines of game `n` are seeded with the stream of this seed for `n`
+const SEED: u64 = 0;
+
+/// Plays a quick match between a configuration and each of the reference snapshots and
+/// appends the estimated Elo differences to a CSV log. Meant to be run every so often during
+/// a training run.
+///
+/// The configuration is labelled with its gating version, see [`GateMetadata`].
+///
+/// Usage: `herb-strength <config.json> <log.csv> <reference.json>... [--games <n>] [--seconds <per game>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut option = |name: &str| match args.iter().position(|arg| arg == name) {
+        Some(i) => {
+            let value = args.get(i + 1).cloned();
+            args.drain(i..(i + 2).min(args.len()));
+            value
+        }
+        None => None,
+    };
+    let games = option("--games").map_or(Ok(DEFAULT_GAMES), |s| s.parse())?;
+    let game_time = option("--seconds").map_or(Ok(DEFAULT_GAME_TIME), |s| s.parse())?;
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: herb-strength <config.json> <log.csv> <reference.json>... [--games <n>] [--seconds <per game>]"
+        );
+        std::process::exit(2);
+    }
+
+    let fixed = |mut config: Config| {
+        config.max_time = game_time;
+        config.log = false;
+        config
+    };
+    let config = fixed(Config::new(&args[0]));
+    let label = format!("v{}", GateMetadata::load(Path::new(&args[0])).version);
+
+    let mut estimates = Vec::new();
+    for path in &args[2..] {
+        let reference = fixed(Config::new(path));
+        let score = play_match(
+            games,
+            |game| Herb::new(config.clone()).with_stream(SEED, game.into(), 0),
+            |game| Herb::new(reference.clone()).with_stream(SEED, game.into(), 0),
+        )?;
+        let name = Path::new(path)
+            .file_stem()
+            .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+        let estimate = StrengthEstimate::new(&label, &name, score);
+        println!(
+            "{} against {}: {:+.0} ± {:.0} Elo",
+            label,
+            name,
+            estimate.elo(),
+            elo_margin(&score)
+        );
+        estimates.push(estimate);
+    }
+    append_log(Path::new(&args[1]), &estimates)?;
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::strength::elo_margin;
+use herb::sweep::{write_csv, Sweep};
+
+/// Plays each version against the reference at every time budget of a grid, both sides with
+/// the same seconds per move, and writes the strength-vs-time curve of every version as CSV,
+/// see [`herb::sweep`]. The versions are labelled with their file names.
+///
+/// Usage: `herb-sweep <reference.json> <version.json>... [--csv <curves.csv>]
+/// [--budgets <seconds,...>] [--games <n>] [--plies <n>] [--seed <n>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let mut option = |name: &str| match args.iter().position(|arg| arg == name) {
+        Some(i) => {
+            let value = args.get(i + 1).cloned();
+            args.drain(i..(i + 2).min(args.len()));
+            value
+        }
+        None => None,
+    };
+    let mut sweep = Sweep::default();
+    let csv = option("--csv");
+    if let Some(budgets) = option("--budgets") {
+        sweep.budgets = budgets
+            .split(',')
+            .map(|budget| budget.trim().parse())
+            .collect::<Result<_, _>>()?;
+    }
+    if let Some(games) = option("--games") {
+        sweep.games = games.parse()?;
+    }
+    if let Some(plies) = option("--plies") {
+        sweep.opening_plies = plies.parse()?;
+    }
+    if let Some(seed) = option("--seed") {
+        sweep.seed = seed.parse()?;
+    }
+    if args.len() < 2 || args.iter().any(|arg| arg.starts_with("--")) {
+        eprintln!(
+            "Usage: herb-sweep <reference.json> <version.json>... [--csv <curves.csv>] \
+             [--budgets <seconds,...>] [--games <n>] [--plies <n>] [--seed <n>]"
+        );
+        std::process::exit(2);
+    }
+
+    let reference = Config::new(&args[0]);
+    let versions: Vec<(String, Config)> = args[1..]
+        .iter()
+        .map(|path| {
+            let label = Path::new(path)
+                .file_stem()
+                .map_or(path.clone(), |stem| stem.to_string_lossy().into_owned());
+            (label, Config::new(path))
+        })
+        .collect();
+    let points = sweep.run(&reference, &versions, |point| {
+        println!(
+            "{} at {}s per move: {:+.0} ± {:.0} Elo",
+            point.label,
+            point.budget,
+            point.elo(),
+            elo_margin(&point.score)
+        );
+    })?;
+    if let Some(path) = csv {
+        write_csv(File::create(path)?, &points)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use herb::config::Config;
+use herb::engine::{EngineProcess, FailurePolicy, ResourceLimits, Supervision};
+use herb::othello::Color::{Black, White};
+use herb::othello::Game;
+use herb::spectator::{SpectatorEvent, SpectatorFeed};
+use herb::tournament::{ColorScheme, TimeControl, Tournament};
+use herb::Herb;
+
+const DEFAULT_GAMES: u32 = 100;
+const OPENING_PLIES: usize = 4;
+const CHECKPOINT_INTERVAL: usize = 1;
+// address space limit for isolated engines
+const ENGINE_MEMORY_BYTES: u64 = 4 << 30;
+// restarts an isolated engine gets per game with `--on-failure restart` or `resync`
+const MAX_RESTARTS: u32 = 2;
+
+/// Plays a long match between two configurations, checkpointing after every game.
+/// Running it again with the same checkpoint file resumes where the last run stopped.
+///
+/// The engines of every game are seeded with the `seed` and the game's index, so a game plays
+/// the same whichever worker plays it. Give the same seed again when resuming.
+///
+/// With `--isolate` every game runs a pair of `herb` processes, limited in CPU time and
+/// memory, instead of playing in this process. An engine that crashes, or gives no answer within
+/// what is left of its clock, loses the game, unless `--on-failure <adjudicate|restart|resync>`
+/// says to restart it, see [`FailurePolicy`].
+///
+/// With `--spectate <address>` the games can be watched live as server-sent events.
+///
+/// With `--time-odds <first>:<second>` the players get the given number of seconds per game,
+/// overriding `max_time` in their configurations, and lose a game on time if they use more.
+///
+/// With `--colors <alternate|random|loser-black|loser-white>` colors are assigned by the given
+/// [`ColorScheme`] instead of alternating.
+///
+/// Usage: `herb-tournament <first.json> <second.json> <checkpoint.json> [games] [seed] [workers] [--isolate] [--on-failure <policy>] [--spectate <address>] [--time-odds <first>:<second>] [--colors <scheme>]`
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let isolate = args.iter().any(|arg| arg == "--isolate");
+    args.retain(|arg| arg != "--isolate");
+    let on_failure = match args.iter().position(|arg| arg == "--on-failure") {
+        Some(i) => {
+            let policy = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            match policy.as_str() {
+                "adjudicate" => FailurePolicy::Adjudicate,
+                "restart" => FailurePolicy::Restart {
+                    max_restarts: MAX_RESTARTS,
+                },
+                "resync" => FailurePolicy::Resync {
+                    max_restarts: MAX_RESTARTS,
+                },
+                _ => return Err(format!("Unknown failure policy: {}", policy).into()),
+            }
+        }
+        None => FailurePolicy::Adjudicate,
+    };
+    let feed = match args.iter().position(|arg| arg == "--spectate") {
+        Some(i) => {
+            let address = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            let feed = SpectatorFeed::bind(address)?;
+            println!("Spectator feed at http://{}/", feed.local_addr());
+            Some(feed)
+        }
+        None => None,
+    };
+    let time_odds = match args.iter().position(|arg| arg == "--time-odds") {
+        Some(i) => {
+            let odds = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            let (first, second) = odds
+                .split_once(':')
+                .ok_or("Expected --time-odds <first>:<second>")?;
+            Some(TimeControl {
+                first: first.parse()?,
+                second: second.parse()?,
+            })
+        }
+        None => None,
+    };
+    let colors = match args.iter().position(|arg| arg == "--colors") {
+        Some(i) => {
+            let scheme = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            match scheme.as_str() {
+                "alternate" => ColorScheme::Alternate,
+                "random" => ColorScheme::Random,
+                "loser-black" => ColorScheme::LoserPicks(Black),
+                "loser-white" => ColorScheme::LoserPicks(White),
+                _ => return Err(format!("Unknown color scheme: {}", scheme).into()),
+            }
+        }
+        None => ColorScheme::Alternate,
+    };
+    // the last opening each game passed through, named on every later event too
+    let openings: Mutex<HashMap<usize, String>> = Mutex::new(HashMap::new());
+    let observer = |index: usize, game: &Game| {
+        if let Some(feed) = &feed {
+            let mut event = SpectatorEvent::new(index, game);
+            let mut openings = openings.lock().unwrap();
+            match &event.opening {
+                Some(opening) => {
+                    openings.insert(index, opening.clone());
+                }
+                None => event.opening = openings.get(&index).cloned(),
+            }
+            feed.broadcast(&event);
+        }
+    };
+    if args.len() < 3 {
+        eprintln!(
+            "Usage: herb-tournament <first.json> <second.json> <checkpoint.json> [games] [seed] [workers] [--isolate] [--on-failure <policy>] [--spectate <address>] [--time-odds <first>:<second>] [--colors <scheme>]"
+        );
+        std::process::exit(2);
+    }
+    let checkpoint = Path::new(&args[2]);
+    let games = args.get(3).map_or(Ok(DEFAULT_GAMES), |s| s.parse())?;
+    let seed = args.get(4).map_or(Ok(0), |s| s.parse())?;
+    let workers = args.get(5).map_or(Ok(1), |s| s.parse())?;
+
+    let mut first = Config::new(&args[0]);
+    first.log = false;
+    let mut second = Config::new(&args[1]);
+    second.log = false;
+
+    let mut tournament = if checkpoint.exists() {
+        let tournament = Tournament::resume(checkpoint)?;
+        println!(
+            "Resuming after {} of {} games",
+            tournament.results().len(),
+            tournament.schedule().len()
+        );
+        tournament
+    } else {
+        // engine processes always start from the initial position
+        let opening_plies = if isolate { 0 } else { OPENING_PLIES };
+        let tournament = Tournament::new(games, opening_plies, seed).with_color_scheme(colors);
+        match time_odds {
+            Some(time_odds) => tournament.with_time_control(time_odds),
+            None => tournament,
+        }
+    };
+
+    // a resumed tournament keeps the time odds it was started with
+    let mut config_paths = [args[0].clone(), args[1].clone()];
+    let _config_files = if let Some(time_control) = tournament.time_control() {
+        first.max_time = time_control.first;
+        second.max_time = time_control.second;
+        println!(
+            "Time odds: first {}s, second {}s",
+            time_control.first, time_control.second
+        );
+        // isolated engines read their configuration from a file
+        if isolate {
+            let first_file = write_config(&first)?;
+            let second_file = write_config(&second)?;
+            config_paths[0] = first_file.path().to_string_lossy().into_owned();
+            config_paths[1] = second_file.path().to_string_lossy().into_owned();
+            Some((first_file, second_file))
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    let score = if isolate {
+        let herb = env::current_exe()?.with_file_name("herb");
+        let herb = herb.to_string_lossy().into_owned();
+        let limits = |config: &Config| ResourceLimits {
+            // every thread may search for the whole game, plus some slack
+            cpu_seconds: Some(
+                (config.max_time * 2.0) as u64 * rayon::current_num_threads() as u64 + 10,
+            ),
+            memory_bytes: Some(ENGINE_MEMORY_BYTES),
+        };
+        let spawn = |path: &str, config: &Config| {
+            EngineProcess::spawn(&herb, [path], limits(config))
+                .expect("Failed to start engine")
+                .with_supervision(Supervision {
+                    clock: Some(Duration::from_secs_f64(config.max_time.max(0.0))),
+                    on_failure,
+                })
+        };
+        tournament.run_observed(
+            workers,
+            |_| spawn(&config_paths[0], &first),
+            |_| spawn(&config_paths[1], &second),
+            Some((checkpoint, CHECKPOINT_INTERVAL)),
+            &observer,
+        )?
+    } else {
+        tournament.run_observed(
+            workers,
+            |index| Herb::new(first.clone()).with_stream(seed, index as u64, 0),
+            |index| Herb::new(second.clone()).with_stream(seed, index as u64, 0),
+            Some((checkpoint, CHECKPOINT_INTERVAL)),
+            &observer,
+        )?
+    };
+    println!(
+        "First scored {:.3} (+{} -{} ={})",
+        score.score(),
+        score.wins,
+        score.losses,
+        score.draws
+    );
+    Ok(())
+}
+
+/// Write a configuration to a temporary file that is deleted when the file is dropped.
+fn write_config(config: &Config) -> Result<tempfile::NamedTempFile, Box<dyn Error>> {
+    let mut file = tempfile::NamedTempFile::new()?;
+    serde_json::to_writer(&mut file, config)?;
+    Ok(file)
+}
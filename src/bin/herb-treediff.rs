@@ -0,0 +1,69 @@
+use std::env;
+use std::error::Error;
+use std::path::Path;
+
+use herb::config::Config;
+use herb::corpus::find;
+use herb::mcts::Tree;
+use herb::othello::Game;
+use herb::record::parse_move;
+use herb::snapshot::{SnapshotDiff, TreeSnapshot};
+
+const USAGE: &str =
+    "Usage: herb-treediff capture <position> <iterations> <snapshot.json> [config.json] [--label <name>]
+       herb-treediff diff <before.json> <after.json>";
+
+/// Captures the root statistics of a search and diffs two captures of the same position, to
+/// find out why a change or another version of Herb plays a different move.
+///
+/// The position is a benchmark corpus ID or moves from the start, e.g. `"d3 c3 c4"`. Capture
+/// the same position and number of iterations with both versions, then diff the snapshots.
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let label = match args.iter().position(|arg| arg == "--label") {
+        Some(i) => {
+            let label = args.get(i + 1).cloned().ok_or("Missing label")?;
+            args.drain(i..i + 2);
+            Some(label)
+        }
+        None => None,
+    };
+    match args.first().map(String::as_str) {
+        Some("capture") if args.len() >= 4 => {
+            let game = match find(&args[1]) {
+                Some(position) => position.game(),
+                None => {
+                    let mut game = Game::new();
+                    for token in args[1].split_whitespace() {
+                        game.play_next_turn(parse_move(token)?)?;
+                    }
+                    game
+                }
+            };
+            let iterations: u64 = args[2].parse()?;
+            let config = match args.get(4) {
+                Some(path) => Config::new(path),
+                None => Config::default(),
+            };
+            let mut tree = Tree::from_config(config.mcts_config);
+            tree.search_n(game, iterations);
+            let label = label.unwrap_or_else(|| format!("herb {}", env!("CARGO_PKG_VERSION")));
+            let snapshot = TreeSnapshot::capture(&tree, game, &label);
+            snapshot.save(Path::new(&args[3]))?;
+            println!(
+                "{} plays {} after {} iterations",
+                label, snapshot.best, iterations
+            );
+        }
+        Some("diff") if args.len() == 3 => {
+            let before = TreeSnapshot::load(Path::new(&args[1]))?;
+            let after = TreeSnapshot::load(Path::new(&args[2]))?;
+            print!("{}", SnapshotDiff::new(&before, &after)?);
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
@@ -1,9 +1,8 @@
 use herb::config::Config;
-use herb::othello::Color::{Black, White};
+use herb::driver::{Match, Seat};
+use herb::opponents::Opponent;
 use herb::othello::Game;
-use herb::othello::Move::Pass;
-use herb::{Herb, Player};
-use rand::random;
+use herb::Herb;
 use std::env;
 use std::time::Instant;
 
@@ -14,29 +13,22 @@ pub fn main() -> Result<(), Box<dyn std::error::Error>> {
     } else {
         Config::new(&args[0])
     };
-    let mut game = Game::new();
+    let game = Game::new();
+    println!("\nTurn {}: \n{}", game.get_turn(), game.get_board());
 
     let mut black = Herb::new(config);
+    let mut white = Opponent::Random;
 
     let start_time = Instant::now();
-    while !game.is_over() {
-        println!("\nTurn {}: \n{}", game.get_turn(), game.get_board());
-        let next_move = match game.to_move() {
-            White => {
-                let legal_moves = game.legal_moves();
-                if !legal_moves.is_empty() {
-                    legal_moves[random::<usize>() % legal_moves.len()]
-                } else {
-                    Pass
-                }
-            }
-            Black => black.get_next_move(game),
-        };
-        game.play_next_turn(next_move)?;
-    }
+    let result = Match::new(Seat::Local(&mut black), Seat::Local(&mut white))
+        .with_observer(|result| {
+            let game = result.game;
+            println!("\nTurn {}: \n{}", game.get_turn(), game.get_board())
+        })
+        .play()?;
     let end_time = Instant::now();
-    println!("Game Over after {} turns.", game.get_turn());
+    println!("Game Over after {} turns.", result.game.get_turn());
     println!("Duration: {}ms", (end_time - start_time).as_millis());
-    println!("Score: {}", game.score());
+    println!("Score: {}", result.game.score());
     Ok(())
 }
@@ -1,37 +1,29 @@
 use std::error::Error;
 
-use herb::drmecref::DrMecRef;
-use herb::othello::Game;
-use herb::othello::Move::Pass;
-use herb::{GameInterface, Player};
+use herb::config::Verbosity;
+use herb::driver::{Match, MatchError, Seat};
+use herb::drmecref::{official_result, DrMecRef};
+use herb::opponents::Opponent;
+use herb::othello::Color::{Black, White};
 
 /// Plays through a game of Othello interfacing with Dr. Cameron's referee.
 /// Makes random moves from the list of legal moves on each turn.
 fn main() -> Result<(), Box<dyn Error>> {
-    // setup the game
-    let mut game: Game = Game::new();
-    let mut opponent = DrMecRef::new();
-    let herb_color = opponent.init()?;
+    let referee = DrMecRef::new();
+    let color = referee.init()?;
     // Let the ref know we are ready
-    opponent.ready(herb_color);
+    referee.ready(color);
 
-    // Game loop
-    loop {
-        // println!("Board:\n{}\n", game.get_board()); // debug, violates the referee
-
-        let legal_moves = game.legal_moves();
-        if game.to_move() == herb_color {
-            let mv = if !legal_moves.is_empty() {
-                legal_moves[rand::random::<usize>() % legal_moves.len()]
-            } else {
-                Pass
-            };
-            opponent.send_move(mv, herb_color)?;
-            game.play_next_turn(mv)?;
-        } else {
-            // it the opponents turn, get their next move and update the game
-            let opponents_move = opponent.get_next_move(game);
-            game.play_next_turn(opponents_move)?;
-        }
-    } // end game loop
+    let mut random = Opponent::Random;
+    let (black, white) = match color {
+        Black => (Seat::Local(&mut random), Seat::Remote(&referee)),
+        White => (Seat::Remote(&referee), Seat::Local(&mut random)),
+    };
+    match Match::new(black, white)
+        .with_log("Random", Verbosity::Moves)
+        .play()
+    {
+        Err(MatchError::Interface(e)) if official_result(&e).is_some() => Ok(()),
+        result => result.map(|_| ()).map_err(Into::into),
+    }
 }
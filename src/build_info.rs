@@ -0,0 +1,135 @@
+//! The build a result was produced by.
+//!
+//! Experiments run for weeks and their outputs pile up, so everything Herb exports is
+//! stamped with a [`BuildInfo`]: the crate version, the git commit it was built from, the
+//! build profile, the enabled features and, where a configuration was involved, its
+//! [`config_digest`]. Game records carry it in their `Build` and `Config` tags, tournament
+//! checkpoints with every completed game, training data in a `.build.json` file next to the
+//! samples and the telemetry in a comment at the start of every game.
+//!
+//! The commit is found by `build.rs` when the crate is compiled, `unknown` outside of a git
+//! checkout.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::autosave::config_digest;
+use crate::config::Config;
+use crate::record::GameRecord;
+
+/// The version of Herb a result was produced by.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// The crate version.
+    pub version: String,
+    /// The abbreviated git commit hash.
+    pub git_hash: String,
+    /// `debug` or `release`.
+    pub profile: String,
+    /// The enabled cargo features.
+    pub features: Vec<String>,
+    /// The [`config_digest`] of the configuration used, if any.
+    #[serde(default)]
+    pub config_digest: Option<String>,
+}
+
+impl BuildInfo {
+    /// Returns the build of the running binary.
+    pub fn current() -> Self {
+        let features = [
+            ("render", cfg!(feature = "render")),
+            ("chat", cfg!(feature = "chat")),
+        ];
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: env!("HERB_GIT_HASH").to_string(),
+            profile: env!("HERB_PROFILE").to_string(),
+            features: features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+            config_digest: None,
+        }
+    }
+
+    /// Returns the build of the running binary together with the digest of the config.
+    pub fn with_config(config: &Config) -> Self {
+        BuildInfo {
+            config_digest: Some(config_digest(config)),
+            ..BuildInfo::current()
+        }
+    }
+
+    /// Tag the record with the build and the config digest, if there is one.
+    pub fn stamp(&self, record: &mut GameRecord) {
+        let build = BuildInfo {
+            config_digest: None,
+            ..self.clone()
+        };
+        record.tags.insert("Build".to_string(), build.to_string());
+        if let Some(digest) = &self.config_digest {
+            record.tags.insert("Config".to_string(), digest.clone());
+        }
+    }
+
+    /// Write the build as json next to the file at the path, e.g. `samples.jsonl` gets
+    /// `samples.build.json`.
+    pub fn save_alongside(&self, path: &Path) -> io::Result<()> {
+        fs::write(sidecar_path(path), serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Returns the path of the build file belonging to the file at the path.
+pub fn sidecar_path(path: &Path) -> PathBuf {
+    path.with_extension("build.json")
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "herb {} {} {}",
+            self.version, self.git_hash, self.profile
+        )?;
+        for feature in &self.features {
+            write!(f, " +{}", feature)?;
+        }
+        if let Some(digest) = &self.config_digest {
+            write!(f, " config {}", digest)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let build = BuildInfo::current();
+        assert_eq!(build.version, env!("CARGO_PKG_VERSION"));
+        assert!(!build.git_hash.is_empty());
+        assert!(["debug", "release"].contains(&build.profile.as_str()));
+        assert_eq!(build.config_digest, None);
+        assert!(build.to_string().starts_with("herb 0.1.0 "));
+
+        let config = Config::default();
+        let build = BuildInfo::with_config(&config);
+        assert_eq!(build.config_digest, Some(config_digest(&config)));
+        let mut record = GameRecord::new();
+        build.stamp(&mut record);
+        assert_eq!(record.tags["Build"], BuildInfo::current().to_string());
+        assert_eq!(record.tags["Config"], config_digest(&config));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.jsonl");
+        build.save_alongside(&path).unwrap();
+        let saved = fs::read_to_string(dir.path().join("samples.build.json")).unwrap();
+        assert_eq!(serde_json::from_str::<BuildInfo>(&saved).unwrap(), build);
+    }
+}
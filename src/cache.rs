@@ -0,0 +1,203 @@
+//! Caches of search results keyed by Zobrist hash.
+//!
+//! # Evaluations
+//!
+//! The static part of an evaluation only depends on the discs on the board and the player to
+//! move, yet transpositions reach the same position again and again, in the tree as well as
+//! in playouts. The [`EvalCache`] remembers evaluations by the position's
+//! [`zobrist_hash`](crate::othello::Game::zobrist_hash).
+//!
+//! The cache has a fixed number of entries so it never grows during a game. It is split into
+//! shards, each behind its own lock, so threads rarely wait on each other. Within a shard
+//! every hash maps to a single slot and a new evaluation replaces whatever was there.
+//!
+//! # Playout outcomes
+//!
+//! Late in a game many playouts funnel through the same few positions. An [`OutcomeTable`]
+//! remembers how the playouts from positions with few empty squares ended, so a later playout
+//! reaching one of them can stop there, a lightweight endgame tablebase built while searching.
+//! It belongs to a single tree and uses the same single slot replacement.
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::othello::Color;
+use crate::telemetry::CacheStats;
+
+const SHARDS: usize = 16;
+
+/// A shard's slots, each empty or holding a hash and its evaluation.
+type Shard = Vec<Option<(u64, f64)>>;
+
+/// A bounded, sharded map from Zobrist hashes to evaluations.
+#[derive(Debug)]
+pub struct EvalCache {
+    shards: Vec<Mutex<Shard>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl EvalCache {
+    /// Create a cache with room for about `capacity` evaluations, at least one per shard.
+    pub fn new(capacity: usize) -> Self {
+        let slots = capacity.div_ceil(SHARDS).max(1);
+        EvalCache {
+            shards: (0..SHARDS).map(|_| Mutex::new(vec![None; slots])).collect(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the number of evaluations the cache can hold.
+    pub fn capacity(&self) -> usize {
+        self.shards.len() * self.shards[0].lock().unwrap().len()
+    }
+
+    /// Returns the cached evaluation of the hash, or computes, caches and returns it.
+    ///
+    /// The lock is not held while evaluating, so two threads may both evaluate a position
+    /// that neither found.
+    pub fn get_or_insert_with(&self, hash: u64, evaluate: impl FnOnce() -> f64) -> f64 {
+        let shard = &self.shards[hash as usize % SHARDS];
+        let slot = |len: usize| (hash / SHARDS as u64) as usize % len;
+        {
+            let entries = shard.lock().unwrap();
+            if let Some((key, value)) = entries[slot(entries.len())] {
+                if key == hash {
+                    self.hits.fetch_add(1, Ordering::Relaxed);
+                    return value;
+                }
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let value = evaluate();
+        let mut entries = shard.lock().unwrap();
+        let index = slot(entries.len());
+        entries[index] = Some((hash, value));
+        value
+    }
+
+    /// Returns the hits and misses since the cache was created or the statistics were last
+    /// taken, and starts counting again from zero.
+    pub fn take_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.swap(0, Ordering::Relaxed),
+            misses: self.misses.swap(0, Ordering::Relaxed),
+        }
+    }
+}
+
+/// A bounded map from Zobrist hashes to the outcomes of playouts, `None` for a draw.
+#[derive(Clone, Debug)]
+pub struct OutcomeTable {
+    slots: Vec<Option<(u64, Option<Color>)>>,
+    stats: CacheStats,
+}
+
+impl OutcomeTable {
+    /// Create a table with room for `capacity` outcomes, at least one.
+    pub fn new(capacity: usize) -> Self {
+        OutcomeTable {
+            slots: vec![None; capacity.max(1)],
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Returns the stored outcome of the hash, `Some(None)` for a draw.
+    pub fn get(&mut self, hash: u64) -> Option<Option<Color>> {
+        match self.slots[hash as usize % self.slots.len()] {
+            Some((key, winner)) if key == hash => {
+                self.stats.hits += 1;
+                Some(winner)
+            }
+            _ => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Store the outcome of the hash, replacing whatever shared its slot.
+    pub fn insert(&mut self, hash: u64, winner: Option<Color>) {
+        let index = hash as usize % self.slots.len();
+        self.slots[index] = Some((hash, winner));
+    }
+
+    /// Add the outcomes of another table to the empty slots of this one, and its lookups to
+    /// the statistics.
+    pub fn merge(&mut self, other: OutcomeTable) {
+        for (hash, winner) in other.slots.into_iter().flatten() {
+            let index = hash as usize % self.slots.len();
+            self.slots[index].get_or_insert((hash, winner));
+        }
+        self.stats.merge(&other.stats);
+    }
+
+    /// Returns the hits and misses since the table was created or the statistics were last
+    /// taken, and starts counting again from zero.
+    pub fn take_stats(&mut self) -> CacheStats {
+        std::mem::take(&mut self.stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_eval_cache() {
+        let cache = EvalCache::new(100);
+        assert_eq!(cache.capacity(), 112);
+        assert_eq!(cache.get_or_insert_with(7, || 1.5), 1.5);
+        assert_eq!(cache.get_or_insert_with(7, || unreachable!()), 1.5);
+        assert_eq!(cache.take_stats(), CacheStats { hits: 1, misses: 1 });
+        assert_eq!(cache.take_stats(), CacheStats::default());
+
+        // hashes sharing a slot replace each other
+        let other = 7 + (SHARDS * 7) as u64;
+        assert_eq!(cache.get_or_insert_with(other, || 2.5), 2.5);
+        assert_eq!(cache.get_or_insert_with(7, || 3.5), 3.5);
+        assert_eq!(cache.take_stats().misses, 2);
+    }
+
+    #[test]
+    fn test_eval_cache_threads() {
+        let cache = Arc::new(EvalCache::new(1 << 10));
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                thread::spawn(move || {
+                    for hash in 0..256u64 {
+                        assert_eq!(cache.get_or_insert_with(hash, || hash as f64), hash as f64);
+                    }
+                })
+            })
+            .collect();
+        handles.into_iter().for_each(|h| h.join().unwrap());
+        let stats = cache.take_stats();
+        assert_eq!(stats.hits + stats.misses, 1024);
+        assert!(stats.misses >= 256);
+    }
+
+    #[test]
+    fn test_outcome_table() {
+        let mut table = OutcomeTable::new(8);
+        assert_eq!(table.get(3), None);
+        table.insert(3, Some(Color::White));
+        table.insert(4, None);
+        assert_eq!(table.get(3), Some(Some(Color::White)));
+        assert_eq!(table.get(4), Some(None));
+        table.insert(11, Some(Color::Black));
+        assert_eq!(table.get(3), None);
+
+        let mut other = OutcomeTable::new(8);
+        other.insert(5, None);
+        other.insert(12, Some(Color::White));
+        assert_eq!(other.get(1), None);
+        table.merge(other);
+        assert_eq!(table.get(5), Some(None));
+        assert_eq!(table.get(4), Some(None));
+        assert_eq!(table.take_stats(), CacheStats { hits: 4, misses: 3 });
+    }
+}
@@ -0,0 +1,369 @@
+//! Herb as a chat bot.
+//!
+//! Enabled with the `chat` feature. A [`ChatBot`] plays one game per channel against whoever
+//! writes in it. It reads chat messages and answers them, so it works with any chat service:
+//! a Discord bot, a Matrix bridge or `herb-chat` on a terminal only have to pass the messages
+//! of each channel in and post the replies back. Messages for the bot start with the
+//! [`ChatConfig::prefix`]:
+//! ```text
+//! !herb new white      start a game, the human playing white
+//! !herb d3             play a move in algebraic notation
+//! !herb board          show the position
+//! !herb resign         give up the game
+//! ```
+//! Herb answers every move with its own, searching for a fixed number of iterations so a busy
+//! channel can not hold up the others. The board is drawn as a text or emoji grid, and with
+//! the `render` feature as well each reply about the position carries an SVG diagram to attach.
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::MctsConfig;
+use crate::golden::herb_move;
+use crate::othello::Color::{Black, White};
+use crate::othello::Move::Pass;
+use crate::othello::{Color, Game, Move};
+use crate::record::{format_move, parse_move};
+
+/// Settings of a [`ChatBot`].
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatConfig {
+    /// Messages for the bot start with this word.
+    pub prefix: String,
+    /// Search iterations Herb spends on each of its moves.
+    pub move_iterations: u64,
+    /// Draw the board with emoji instead of letters.
+    pub emoji: bool,
+    pub mcts_config: MctsConfig,
+}
+
+impl Default for ChatConfig {
+    fn default() -> Self {
+        ChatConfig {
+            prefix: "!herb".to_string(),
+            move_iterations: 20_000,
+            emoji: true,
+            mcts_config: MctsConfig::default(),
+        }
+    }
+}
+
+/// The bot's answer to a message.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Reply {
+    pub text: String,
+    /// An SVG diagram of the position, with the `render` feature.
+    pub image: Option<String>,
+}
+
+/// A game in a channel.
+#[derive(Clone, Debug)]
+struct ChannelGame {
+    game: Game,
+    /// The color of whoever plays Herb.
+    human: Color,
+}
+
+/// Plays a game of Othello in each channel it is sent messages from, see the
+/// [module](self) documentation.
+#[derive(Debug, Default)]
+pub struct ChatBot {
+    config: ChatConfig,
+    games: HashMap<String, ChannelGame>,
+}
+
+impl ChatBot {
+    pub fn new(config: ChatConfig) -> Self {
+        ChatBot {
+            config,
+            games: HashMap::new(),
+        }
+    }
+
+    /// Returns the number of channels with a game in progress.
+    pub fn active_games(&self) -> usize {
+        self.games.len()
+    }
+
+    /// Answer a message written in the channel, `None` if it is not for the bot.
+    pub fn handle(&mut self, channel: &str, message: &str) -> Option<Reply> {
+        let mut words = message.split_whitespace();
+        if words.next() != Some(self.config.prefix.as_str()) {
+            return None;
+        }
+        let command = words.next().unwrap_or("help").to_lowercase();
+        let reply = match command.as_str() {
+            "new" => match words.next().map(str::to_lowercase).as_deref() {
+                None | Some("black") => self.new_game(channel, Black),
+                Some("white") => self.new_game(channel, White),
+                Some(other) => text(format!("Play black or white, not {}", other)),
+            },
+            "board" => match self.games.get(channel) {
+                Some(game) => self.position(game, String::new()),
+                None => text(self.no_game()),
+            },
+            "resign" => match self.games.remove(channel) {
+                Some(_) => text("Good game! Herb wins by resignation.".to_string()),
+                None => text(self.no_game()),
+            },
+            "help" => text(self.help()),
+            token => match parse_move(token) {
+                Ok(mv) => self.play(channel, mv),
+                Err(_) => text(self.help()),
+            },
+        };
+        Some(reply)
+    }
+
+    /// Start a game in the channel, replacing any game in progress there.
+    fn new_game(&mut self, channel: &str, human: Color) -> Reply {
+        let mut game = ChannelGame {
+            game: Game::new(),
+            human,
+        };
+        let mut text = format!("New game, you play {}.", color_name(human));
+        self.herb_turn(&mut game, &mut text);
+        self.finish(channel, game, text)
+    }
+
+    /// Play the human's move and answer it.
+    fn play(&mut self, channel: &str, mv: Move) -> Reply {
+        let Some(mut game) = self.games.get(channel).cloned() else {
+            return text(self.no_game());
+        };
+        if game.game.to_move() != game.human {
+            return text("It is Herb's turn.".to_string());
+        }
+        if game.game.play_next_turn(mv).is_err() {
+            let legal: Vec<String> = game
+                .game
+                .legal_moves()
+                .into_iter()
+                .map(format_move)
+                .collect();
+            return text(format!(
+                "{} is not a legal move. Try one of {}.",
+                format_move(mv),
+                legal.join(" ")
+            ));
+        }
+        let mut text = format!("You played {}.", format_move(mv));
+        self.herb_turn(&mut game, &mut text);
+        self.finish(channel, game, text)
+    }
+
+    /// Let Herb move until it is the human's turn with a move to make or the game is over,
+    /// passing for the human when it has none.
+    fn herb_turn(&self, channel_game: &mut ChannelGame, text: &mut String) {
+        let game = &mut channel_game.game;
+        while !game.is_over() {
+            if game.to_move() == channel_game.human {
+                if !game.legal_moves().is_empty() {
+                    break;
+                }
+                write!(text, " You have no moves and pass.").unwrap();
+                game.play_next_turn(Pass).unwrap();
+                continue;
+            }
+            let mv = match herb_move(*game, &self.config.mcts_config, self.config.move_iterations) {
+                // a search that found nothing falls back like Herb does
+                (Pass, _) => game.greedy_move(),
+                (mv, _) => mv,
+            };
+            match mv {
+                Pass => write!(text, " Herb passes.").unwrap(),
+                mv => write!(text, " Herb plays {}.", format_move(mv)).unwrap(),
+            }
+            game.play_next_turn(mv).unwrap();
+        }
+    }
+
+    /// Keep the game for the channel unless it is over, and describe the position.
+    fn finish(&mut self, channel: &str, game: ChannelGame, mut text: String) -> Reply {
+        let board = &game.game;
+        if board.is_over() {
+            let (black, white) = board.disc_counts();
+            let outcome = match board.winner() {
+                None => "It's a draw",
+                Some(winner) if winner == game.human => "You win",
+                Some(_) => "Herb wins",
+            };
+            write!(text, " Game over, {} {}-{}.", outcome, black, white).unwrap();
+            self.games.remove(channel);
+        } else {
+            write!(text, " Your move.").unwrap();
+            self.games.insert(channel.to_string(), game.clone());
+        }
+        self.position(&game, text)
+    }
+
+    /// The text followed by the board, with a diagram when rendering is enabled.
+    fn position(&self, game: &ChannelGame, text: String) -> Reply {
+        let mut text = text;
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text += &draw(&game.game, self.config.emoji);
+        #[cfg(feature = "render")]
+        let image = Some(crate::render::render_svg(
+            &game.game,
+            &crate::render::RenderOptions {
+                chosen: game.game.last_move(),
+                show_legal_moves: game.game.to_move() == game.human,
+                ..Default::default()
+            },
+        ));
+        #[cfg(not(feature = "render"))]
+        let image = None;
+        Reply { text, image }
+    }
+
+    fn no_game(&self) -> String {
+        format!(
+            "No game in this channel, start one with `{} new`.",
+            self.config.prefix
+        )
+    }
+
+    fn help(&self) -> String {
+        let prefix = &self.config.prefix;
+        format!(
+            "`{0} new [black|white]` starts a game, `{0} d3` plays a move, `{0} board` \
+             shows the position and `{0} resign` gives up.",
+            prefix
+        )
+    }
+}
+
+fn text(text: String) -> Reply {
+    Reply { text, image: None }
+}
+
+fn color_name(color: Color) -> &'static str {
+    match color {
+        Black => "black",
+        White => "white",
+    }
+}
+
+/// Draw the board as a grid in a code block, rows 1 to 8 from the top and columns `a` to `h`
+/// from the left.
+fn draw(game: &Game, emoji: bool) -> String {
+    let board = game.get_board();
+    let (black, white) = (board.get_black(), board.get_white());
+    let (black_disc, white_disc, empty, separator) = if emoji {
+        ("⚫", "⚪", "🟩", "")
+    } else {
+        ("X", "O", ".", " ")
+    };
+    let mut grid = String::from("```\n");
+    let header: Vec<String> = (b'a'..=b'h')
+        .map(|col| {
+            if emoji {
+                // fullwidth letters line up with the emoji
+                char::from_u32(0xff41 + (col - b'a') as u32)
+                    .unwrap()
+                    .to_string()
+            } else {
+                (col as char).to_string()
+            }
+        })
+        .collect();
+    writeln!(grid, "  {}", header.join(separator)).unwrap();
+    for row in 0..8 {
+        let squares: Vec<&str> = (0..8)
+            .map(|col| {
+                let bit = 1u64 << (row * 8 + col);
+                if black & bit != 0 {
+                    black_disc
+                } else if white & bit != 0 {
+                    white_disc
+                } else {
+                    empty
+                }
+            })
+            .collect();
+        writeln!(grid, "{} {}", row + 1, squares.join(separator)).unwrap();
+    }
+    let (black_count, white_count) = game.disc_counts();
+    writeln!(
+        grid,
+        "{} {}  {} {}",
+        black_disc, black_count, white_disc, white_count
+    )
+    .unwrap();
+    grid += "```";
+    grid
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bot() -> ChatBot {
+        ChatBot::new(ChatConfig {
+            move_iterations: 50,
+            emoji: false,
+            ..ChatConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_game_in_channel() {
+        let mut bot = bot();
+        assert_eq!(bot.handle("general", "hello there"), None);
+        assert!(bot
+            .handle("general", "!herb d3")
+            .unwrap()
+            .text
+            .contains("No game"));
+
+        let reply = bot.handle("general", "!herb new").unwrap();
+        assert!(reply
+            .text
+            .starts_with("New game, you play black. Your move."));
+        assert!(reply.text.contains("4 . . . O X . . ."));
+        assert_eq!(reply.image.is_some(), cfg!(feature = "render"));
+
+        let reply = bot.handle("general", "!herb a1").unwrap();
+        assert!(reply.text.contains("not a legal move"));
+        let reply = bot.handle("general", "!herb d3").unwrap();
+        assert!(reply.text.starts_with("You played d3. Herb plays "));
+        assert!(reply.text.contains("X 3  O 3"));
+
+        // channels have games of their own
+        let reply = bot.handle("other", "!herb new white").unwrap();
+        assert!(reply.text.contains("Herb plays"));
+        assert_eq!(bot.active_games(), 2);
+        assert!(bot
+            .handle("other", "!herb resign")
+            .unwrap()
+            .text
+            .contains("resignation"));
+        assert_eq!(bot.active_games(), 1);
+        assert!(bot.handle("general", "!herb").unwrap().text.contains("new"));
+    }
+
+    #[test]
+    fn test_game_to_the_end() {
+        let mut bot = bot();
+        let mut reply = bot.handle("general", "!herb new").unwrap();
+        while bot.active_games() > 0 {
+            let game = bot.games["general"].game;
+            let mv = game.greedy_move();
+            reply = bot
+                .handle("general", &format!("!herb {}", format_move(mv)))
+                .unwrap();
+        }
+        assert!(reply.text.contains("Game over"));
+    }
+
+    #[test]
+    fn test_emoji_board() {
+        let board = draw(&Game::new(), true);
+        assert!(board.contains("4 🟩🟩🟩⚪⚫🟩🟩🟩"));
+        assert!(board.contains("⚫ 2  ⚪ 2"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Scorekeeping for games between two people.
+//!
+//! A [`Scorekeeper`] referees a game played by two humans at a club: it only accepts legal
+//! moves, plays forced passes for whoever has to pass, runs a clock for each side and ends the
+//! game when a flag falls or a player resigns. A player can ask to take back the last move,
+//! which only happens once the opponent agrees. The game is kept as a [`GameRecord`] with each
+//! mover's remaining clock, ready to be saved as a transcript. `herb-club` puts it on the
+//! terminal, with Herb commenting on the position if asked.
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::Duration;
+
+use crate::othello::Color::{Black, White};
+use crate::othello::Move::Pass;
+use crate::othello::{Color, Game, GameError, Move};
+use crate::record::{GameRecord, RecordedMove};
+
+/// Enumerates the ways a [`Scorekeeper`] can refuse a request.
+#[derive(Debug)]
+pub enum ClubError {
+    /// The move is not legal, or the game is over.
+    Game(GameError),
+    /// The player to move ran out of time, which ended the game.
+    OutOfTime(Color),
+    /// There is no move to take back, or no takeback to agree to.
+    NoTakeback,
+    /// A player tried to agree to their own takeback.
+    OwnTakeback,
+}
+
+impl From<GameError> for ClubError {
+    fn from(e: GameError) -> Self {
+        ClubError::Game(e)
+    }
+}
+
+impl Display for ClubError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            ClubError::Game(e) => write!(f, "{}", e),
+            ClubError::OutOfTime(color) => write!(f, "{} ran out of time", color),
+            ClubError::NoTakeback => write!(f, "There is nothing to take back"),
+            ClubError::OwnTakeback => write!(f, "The opponent has to agree to a takeback"),
+        }
+    }
+}
+
+impl Error for ClubError {}
+
+/// How a refereed game ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ending {
+    /// Neither side could move.
+    Board,
+    /// The color ran out of time.
+    Time(Color),
+    /// The color resigned.
+    Resignation(Color),
+}
+
+/// Referees a game between two people, see the [module](self) documentation.
+#[derive(Clone, Debug)]
+pub struct Scorekeeper {
+    game: Game,
+    record: GameRecord,
+    max_time: Duration,
+    black_time: Duration,
+    white_time: Duration,
+    /// The color asking to take back the last move.
+    takeback: Option<Color>,
+    ending: Option<Ending>,
+}
+
+impl Scorekeeper {
+    /// Start a game between the named players with `max_time` on each clock.
+    pub fn new(black: &str, white: &str, max_time: Duration) -> Self {
+        let mut record = GameRecord::new();
+        record.tags.insert("Black".to_string(), black.to_string());
+        record.tags.insert("White".to_string(), white.to_string());
+        Scorekeeper {
+            game: Game::new(),
+            record,
+            max_time,
+            black_time: Duration::ZERO,
+            white_time: Duration::ZERO,
+            takeback: None,
+            ending: None,
+        }
+    }
+
+    /// Returns the current position.
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    /// Returns the game so far.
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    /// Returns the time left on the color's clock.
+    pub fn remaining(&self, color: Color) -> Duration {
+        self.max_time.saturating_sub(self.used(color))
+    }
+
+    /// Returns how the game ended, `None` while it is in progress.
+    pub fn ending(&self) -> Option<Ending> {
+        self.ending
+    }
+
+    /// Returns the color asking to take back the last move, if any.
+    pub fn takeback(&self) -> Option<Color> {
+        self.takeback
+    }
+
+    /// Returns the winner of a finished game, `None` for a draw or a game in progress.
+    pub fn winner(&self) -> Option<Color> {
+        match self.ending? {
+            Ending::Board => self.game.winner(),
+            Ending::Time(color) | Ending::Resignation(color) => Some(color.opponent()),
+        }
+    }
+
+    /// Play the move of the player to move, who took `elapsed` to make it. A forced pass by
+    /// the next player is played straight away. Any takeback request lapses.
+    ///
+    /// Returns an error if the move is illegal or the game is over, the position is unchanged
+    /// then, or if the player ran out of time, which ends the game.
+    pub fn play(&mut self, mv: Move, elapsed: Duration) -> Result<(), ClubError> {
+        if self.ending.is_some() {
+            return Err(GameError::GameOver.into());
+        }
+        let color = self.game.to_move();
+        self.charge(color, elapsed);
+        if self.remaining(color).is_zero() {
+            self.ending = Some(Ending::Time(color));
+            return Err(ClubError::OutOfTime(color));
+        }
+        self.game.play_next_turn(mv)?;
+        self.push(color, mv);
+        self.takeback = None;
+        if !self.game.is_over() && self.game.legal_moves().is_empty() {
+            let passer = self.game.to_move();
+            self.game.play_next_turn(Pass)?;
+            self.push(passer, Pass);
+        }
+        if self.game.is_over() {
+            self.ending = Some(Ending::Board);
+        }
+        Ok(())
+    }
+
+    /// Charge time to the player to move without a move, e.g. while the clocks are shown.
+    /// Running out ends the game.
+    pub fn tick(&mut self, elapsed: Duration) -> Option<Ending> {
+        if self.ending.is_none() {
+            let color = self.game.to_move();
+            self.charge(color, elapsed);
+            if self.remaining(color).is_zero() {
+                self.ending = Some(Ending::Time(color));
+            }
+        }
+        self.ending
+    }
+
+    /// The color resigns the game.
+    pub fn resign(&mut self, color: Color) -> Result<(), ClubError> {
+        if self.ending.is_some() {
+            return Err(GameError::GameOver.into());
+        }
+        self.ending = Some(Ending::Resignation(color));
+        Ok(())
+    }
+
+    /// The color asks to take back the last move played by a player, along with the forced
+    /// passes after it.
+    ///
+    /// Returns an error if there is no such move or the game is over.
+    pub fn request_takeback(&mut self, color: Color) -> Result<(), ClubError> {
+        if self.ending.is_some() {
+            return Err(GameError::GameOver.into());
+        }
+        if !self.record.moves.iter().any(|recorded| recorded.mv != Pass) {
+            return Err(ClubError::NoTakeback);
+        }
+        self.takeback = Some(color);
+        Ok(())
+    }
+
+    /// The color agrees to the opponent's takeback request, which takes the last move back.
+    /// Time already used is not given back.
+    ///
+    /// Returns the move taken back, or an error if the opponent did not ask for a takeback.
+    pub fn agree_takeback(&mut self, color: Color) -> Result<Move, ClubError> {
+        match self.takeback {
+            None => return Err(ClubError::NoTakeback),
+            Some(requester) if requester == color => return Err(ClubError::OwnTakeback),
+            Some(_) => {}
+        }
+        self.takeback = None;
+        let mv = loop {
+            let recorded = self.record.moves.pop().ok_or(ClubError::NoTakeback)?;
+            if recorded.mv != Pass {
+                break recorded.mv;
+            }
+        };
+        self.game = self.record.game()?;
+        Ok(mv)
+    }
+
+    /// The color turns down the opponent's takeback request.
+    pub fn decline_takeback(&mut self, color: Color) -> Result<(), ClubError> {
+        match self.takeback {
+            Some(requester) if requester != color => {
+                self.takeback = None;
+                Ok(())
+            }
+            Some(_) => Err(ClubError::OwnTakeback),
+            None => Err(ClubError::NoTakeback),
+        }
+    }
+
+    /// Returns the record of the game with how it ended, if it has.
+    pub fn finished_record(&self) -> GameRecord {
+        let mut record = self.record.clone();
+        let termination = match self.ending {
+            Some(Ending::Time(_)) => "time forfeit",
+            Some(Ending::Resignation(_)) => "resignation",
+            _ => return record,
+        };
+        record
+            .tags
+            .insert("Termination".to_string(), termination.to_string());
+        if let Some(winner) = self.winner() {
+            record.tags.insert("Winner".to_string(), winner.to_string());
+        }
+        record
+    }
+
+    fn used(&self, color: Color) -> Duration {
+        match color {
+            Black => self.black_time,
+            White => self.white_time,
+        }
+    }
+
+    fn charge(&mut self, color: Color, elapsed: Duration) {
+        match color {
+            Black => self.black_time += elapsed,
+            White => self.white_time += elapsed,
+        }
+    }
+
+    fn push(&mut self, color: Color, mv: Move) {
+        self.record.push(RecordedMove {
+            clock: Some(self.remaining(color).as_secs_f64()),
+            ..RecordedMove::new(mv)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::parse_move;
+
+    fn mv(token: &str) -> Move {
+        parse_move(token).unwrap()
+    }
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_moves_and_clocks() {
+        let mut keeper = Scorekeeper::new("Ann", "Bob", Duration::from_secs(60));
+        assert!(matches!(
+            keeper.play(mv("a1"), SECOND),
+            Err(ClubError::Game(GameError::InvalidMove))
+        ));
+        // the illegal move's time still counts
+        assert_eq!(keeper.remaining(Black), Duration::from_secs(59));
+        keeper.play(mv("d3"), SECOND).unwrap();
+        keeper.play(mv("c5"), 3 * SECOND).unwrap();
+        assert_eq!(keeper.remaining(Black), Duration::from_secs(58));
+        assert_eq!(keeper.remaining(White), Duration::from_secs(57));
+        assert_eq!(keeper.record().moves[1].clock, Some(57.0));
+        assert_eq!(keeper.record().tags["White"], "Bob");
+
+        assert_eq!(
+            keeper.tick(Duration::from_secs(100)),
+            Some(Ending::Time(Black))
+        );
+        assert_eq!(keeper.winner(), Some(White));
+        assert!(keeper.play(mv("c4"), SECOND).is_err());
+        assert_eq!(keeper.finished_record().tags["Termination"], "time forfeit");
+    }
+
+    #[test]
+    fn test_flag_falls_on_a_move() {
+        let mut keeper = Scorekeeper::new("Ann", "Bob", Duration::from_secs(5));
+        assert!(matches!(
+            keeper.play(mv("d3"), Duration::from_secs(6)),
+            Err(ClubError::OutOfTime(Black))
+        ));
+        assert_eq!(keeper.game().get_turn(), 0);
+        assert_eq!(keeper.ending(), Some(Ending::Time(Black)));
+    }
+
+    #[test]
+    fn test_takeback_needs_agreement() {
+        let mut keeper = Scorekeeper::new("Ann", "Bob", Duration::from_secs(60));
+        assert!(matches!(
+            keeper.request_takeback(Black),
+            Err(ClubError::NoTakeback)
+        ));
+        keeper.play(mv("d3"), SECOND).unwrap();
+        keeper.request_takeback(Black).unwrap();
+        assert!(matches!(
+            keeper.agree_takeback(Black),
+            Err(ClubError::OwnTakeback)
+        ));
+        keeper.decline_takeback(White).unwrap();
+        assert!(keeper.agree_takeback(White).is_err());
+
+        keeper.request_takeback(Black).unwrap();
+        assert_eq!(keeper.agree_takeback(White).unwrap(), mv("d3"));
+        assert_eq!(keeper.game(), Game::new());
+        assert!(keeper.record().moves.is_empty());
+        // the time is not given back
+        assert_eq!(keeper.remaining(Black), Duration::from_secs(59));
+
+        // a move lets a request lapse
+        keeper.play(mv("d3"), SECOND).unwrap();
+        keeper.request_takeback(Black).unwrap();
+        keeper.play(mv("c5"), SECOND).unwrap();
+        assert_eq!(keeper.takeback(), None);
+    }
+
+    #[test]
+    fn test_game_to_the_end() {
+        let mut keeper = Scorekeeper::new("Ann", "Bob", Duration::from_secs(60));
+        while keeper.ending().is_none() {
+            let game = keeper.game();
+            keeper.play(game.greedy_move(), Duration::ZERO).unwrap();
+        }
+        assert_eq!(keeper.ending(), Some(Ending::Board));
+        assert_eq!(keeper.winner(), keeper.game().winner());
+        // forced passes are recorded, so the record replays
+        let record = keeper.finished_record();
+        assert_eq!(record.game().unwrap(), keeper.game());
+        assert!(!record.tags.contains_key("Termination"));
+
+        let mut keeper = Scorekeeper::new("Ann", "Bob", Duration::from_secs(60));
+        keeper.resign(White).unwrap();
+        assert_eq!(keeper.winner(), Some(Black));
+        assert_eq!(keeper.finished_record().tags["Winner"], "B");
+    }
+}
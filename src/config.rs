@@ -6,8 +6,15 @@
 //! {
 //!     "max_time": 100.0,
 //!     "log": true,
+//!     "telemetry": false,
+//!     "warm_up": 5.0,
+//!     "eval_cache": 1048576,
+//!     "worker_time": 0.05,
 //!     "mcts_config": {
-//!         "exploration_factor": 1.418
+//!         "exploration_factor": 1.418,
+//!         "outcome_empties": 10,
+//!         "playouts": 1,
+//!         "expansion": "one"
 //!     }
 //! }
 //! ```
@@ -15,15 +22,74 @@
 //!
 //! # List of Configuration Settings
 //! - max_time: float total time limit for a game in seconds
-//! - log: boolean output logging info
+//! - log: boolean output logging info, the same as a verbosity of `"debug"` when true and
+//!   `"moves"` when false. Ignored if verbosity is given
+//! - verbosity: how much Herb comments to the referee, see [`Verbosity`]: `"silent"` nothing,
+//!   `"results"` warnings, errors and the result of the game, `"moves"` also a summary of every
+//!   move and `"debug"` everything. Left out, it follows log
+//! - telemetry: boolean report latency histograms of the search iterations, the principal
+//!   variation and the pruned root moves after every move, the named openings the game passes
+//!   through, and the material of every turn at the end of the game
+//! - warm_up: float seconds of background search between getting ready and the first move, 0
+//!   disables the warm-up
+//! - eval_cache: integer number of static evaluations cached across the search threads, 0
+//!   disables the cache, see [`cache`](crate::cache)
+//! - worker_time: float seconds of a turn each search thread needs to pay off, shorter turns
+//!   use fewer threads and turns under this use a single one, 0 always uses every cpu
+//! - session: string path of a file every line sent to and received from the referee is recorded
+//!   to, see [`session`](crate::session). Left out, nothing is recorded
+//! - autosave: string path of a file the game in progress is saved to after every move, so it can
+//!   be continued with `--resume` after a crash, see [`autosave`](crate::autosave). Left out,
+//!   nothing is saved
+//! - lessons: string path of a file of blunders Herb has learned not to repeat, see
+//!   [`lessons`](crate::lessons). Left out, Herb has no lessons
+//! - repertoire: opening lines Herb is restricted to for the first plies of a game, see
+//!   [`repertoire`](crate::repertoire)
+//! - rng: `"chacha"` or `"philox"`, the generator Herb's random choices are drawn from, see
+//!   [`RngBackend`]. ChaCha8 when left out
+//! - time: how the game's time is planned over its phases, how each move's search is stopped
+//!   early, extended or cut short when the clock is low, and the minimum and maximum seconds of
+//!   any move, see [`time_manager`](crate::time_manager)
 //! - mcts_config: Configuration setting for the [`mcts`] module.
 //!     - exploration_factor: float used in UCB1 to determine when to explore unknown parts of the tree.
+//!     - outcome_empties: integer, playouts remember their outcomes from positions with at most this
+//!       many empty squares and stop early when they reach one again, 0 disables it. See
+//!       [`OutcomeTable`](crate::cache::OutcomeTable).
+//!     - playouts: integer number of playouts run from every node added to the tree, 1 when left
+//!       out. The first follows the best moves, the others play a random move at a share
+//!       `explore_rate` of their plies, so they sample the positions around it.
+//!     - explore_rate: float between 0 and 1, the share of the plies of the repeated playouts of
+//!       a node that play a random move, 0.1 when left out.
+//!     - expansion: `"one"` adds the first unexplored child of the selected leaf each iteration,
+//!       `"all"` adds all of them and `"priors"` adds all of them with priors from the static
+//!       evaluation, see [`Expansion`].
+//!     - prior_visits: float number of visits the prior of a child added by `"priors"` counts
+//!       as, 1 when left out.
+//!     - expansion_order: `"board"` expands the children of a leaf in the order of the legal
+//!       moves, `"squares"` corners first, then edges, then the rest, and `"prior"` the best
+//!       static evaluation first, see [`ExpansionOrder`].
+//!     - prune_margin: float, a root move whose upper confidence bound is more than this below
+//!       the lower confidence bound of another root move gets no more search iterations. Left
+//!       out, no move is pruned.
+//!     - playout_cutoff: integer, playouts stop after this many plies and are won by the player
+//!       ahead on the `weight_table`. Left out, playouts are played to the end of the game.
+//!     - weight_table: 8 rows of 8 integer weights of the squares, see
+//!       [`WeightTable`](crate::evaluation::WeightTable). Left out, the classic table is used.
+//!     - parity_empties: integer, with at most this many empty squares playouts only choose
+//!       among the moves that keep the tempo in their region, see
+//!       [`parity_moves`](crate::othello::regions::parity_moves). 12 when left out, 0 disables
+//!       it.
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::fs::File;
 use std::io::Read;
 
 use serde::{Deserialize, Serialize};
 
 use crate::drmecref::DrMecRef;
+use crate::evaluation::WeightTable;
+use crate::random::RngBackend;
+use crate::repertoire::Repertoire;
+use crate::time_manager::TimeConfig;
 
 /// Configuration Settings for [`Herb`]
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -33,6 +99,28 @@ pub struct Config {
     #[serde(default)]
     pub log: bool,
     #[serde(default)]
+    pub verbosity: Option<Verbosity>,
+    #[serde(default)]
+    pub telemetry: bool,
+    #[serde(default)]
+    pub warm_up: f64,
+    #[serde(default)]
+    pub eval_cache: usize,
+    #[serde(default)]
+    pub worker_time: f64,
+    #[serde(default)]
+    pub session: Option<String>,
+    #[serde(default)]
+    pub autosave: Option<String>,
+    #[serde(default)]
+    pub lessons: Option<String>,
+    #[serde(default)]
+    pub repertoire: Repertoire,
+    #[serde(default)]
+    pub rng: RngBackend,
+    #[serde(default)]
+    pub time: TimeConfig,
+    #[serde(default)]
     pub mcts_config: MctsConfig,
 }
 
@@ -41,12 +129,180 @@ pub struct Config {
 pub struct MctsConfig {
     #[serde(default)]
     pub exploration_factor: f64,
+    #[serde(default)]
+    pub outcome_empties: u64,
+    #[serde(default = "default_playouts")]
+    pub playouts: u64,
+    #[serde(default)]
+    pub expansion: Expansion,
+    #[serde(default = "default_prior_visits")]
+    pub prior_visits: f64,
+    #[serde(default)]
+    pub expansion_order: ExpansionOrder,
+    #[serde(default)]
+    pub prune_margin: Option<f64>,
+    #[serde(default)]
+    pub playout_cutoff: Option<u64>,
+    #[serde(default)]
+    pub weight_table: WeightTable,
+    #[serde(default = "default_parity_empties")]
+    pub parity_empties: u64,
+    #[serde(default = "default_explore_rate")]
+    pub explore_rate: f64,
+}
+
+fn default_playouts() -> u64 {
+    1
+}
+
+fn default_prior_visits() -> f64 {
+    1.0
+}
+
+fn default_parity_empties() -> u64 {
+    12
+}
+
+fn default_explore_rate() -> f64 {
+    0.1
+}
+
+impl MctsConfig {
+    /// Returns an error if a setting is out of its range.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.explore_rate) {
+            return Err(ConfigError::OutOfRange {
+                setting: "mcts_config.explore_rate",
+                value: self.explore_rate,
+                range: "between 0 and 1",
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Errors from settings of a parsed configuration that Herb cannot play with.
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    /// The setting has a value outside the range it must be in.
+    OutOfRange {
+        setting: &'static str,
+        value: f64,
+        range: &'static str,
+    },
+    /// The setting is larger than another setting that is its upper limit.
+    Exceeds {
+        setting: &'static str,
+        value: f64,
+        limit: &'static str,
+        limit_value: f64,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            ConfigError::OutOfRange {
+                setting,
+                value,
+                range,
+            } => write!(f, "{} is {}, it must be {}", setting, value, range),
+            ConfigError::Exceeds {
+                setting,
+                value,
+                limit,
+                limit_value,
+            } => write!(
+                f,
+                "{} is {}, more than {} of {}",
+                setting, value, limit, limit_value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// How many children of the selected leaf a search iteration adds to the [`Tree`](crate::mcts::Tree).
+///
+/// Every added child gets `playouts` playouts, so together with `playouts` this sets how many
+/// playouts the search runs per node it adds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Expansion {
+    /// Add the first unexplored child.
+    #[default]
+    One,
+    /// Add every unexplored child at once.
+    All,
+    /// Add every unexplored child at once, each starting out with `prior_visits` visits won
+    /// at the rate the static evaluation of the child predicts, rather than being treated as
+    /// a draw until its first playout comes back.
+    Priors,
+}
+
+/// The order in which a search iteration considers the unexplored children of the selected
+/// leaf, so with [`Expansion::One`] which child it adds first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpansionOrder {
+    /// The order of the legal moves.
+    #[default]
+    Board,
+    /// Moves to corners first, then moves to the other edge squares, then the rest, each in
+    /// the order of the legal moves.
+    Squares,
+    /// The best static evaluation for the player making the move first.
+    Prior,
+}
+
+/// How much Herb comments to the referee. Each level includes the comments of the levels
+/// before it.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Verbosity {
+    /// No comments at all.
+    Silent,
+    /// Warnings, errors and the result of the game.
+    Results,
+    /// A summary of every move: the turn, the move sent and, with telemetry, the search
+    /// threads, the principal variation and the search totals.
+    Moves,
+    /// Everything, including the configuration, the iterations of every search thread and the
+    /// value of every move considered.
+    #[default]
+    Debug,
+}
+
+impl Verbosity {
+    /// Returns true if comments at `level` are made at this verbosity.
+    pub fn allows(self, level: Verbosity) -> bool {
+        level != Verbosity::Silent && level <= self
+    }
 }
 
 impl Config {
+    /// Returns the verbosity of the comments, the one given or the one `log` stands for.
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity.unwrap_or(if self.log {
+            Verbosity::Debug
+        } else {
+            Verbosity::Moves
+        })
+    }
+
+    /// Returns an error if a setting is out of its range, see [`MctsConfig::validate`] and
+    /// [`TimeConfig::validate`].
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.mcts_config.validate()?;
+        self.time.validate()
+    }
+
     /// Create a new Config from the given json config file.
-    /// If the reading or parsing the given config file fails,
-    /// a default Config is used.
+    /// If the reading or parsing the given config file fails, or a setting is out of its
+    /// range, a default Config is used.
     pub fn new(config_file: &str) -> Self {
         if let Ok(config) = File::open(config_file) {
             Config::parse(config)
@@ -61,12 +317,19 @@ impl Config {
         let mut config_file = config_file;
 
         if config_file.read_to_string(&mut config_str).is_err() {
+            // there is no verbosity without a configuration
             DrMecRef::comment("Failed to read the configuration file; using defaults.");
             return Config::default();
         }
 
         match serde_json::from_str::<Config>(&config_str) {
-            Ok(parsed_config) => parsed_config,
+            Ok(parsed_config) => match parsed_config.validate() {
+                Ok(()) => parsed_config,
+                Err(e) => {
+                    DrMecRef::comment(format!("Invalid configuration: {}; using defaults.", e));
+                    Config::default()
+                }
+            },
             Err(e) => {
                 DrMecRef::comment(format!(
                     "Failed to parse the configuration file: {}; using defaults.",
@@ -84,6 +347,17 @@ impl Default for Config {
             max_time: 120.0,
             mcts_config: MctsConfig::default(),
             log: true,
+            verbosity: None,
+            telemetry: false,
+            warm_up: 5.0,
+            eval_cache: 1 << 20,
+            worker_time: 0.05,
+            session: None,
+            autosave: None,
+            lessons: None,
+            repertoire: Repertoire::default(),
+            rng: RngBackend::ChaCha,
+            time: TimeConfig::default(),
         }
     }
 }
@@ -92,6 +366,16 @@ impl Default for MctsConfig {
     fn default() -> Self {
         MctsConfig {
             exploration_factor: std::f64::consts::SQRT_2,
+            outcome_empties: 0,
+            playouts: default_playouts(),
+            expansion: Expansion::One,
+            prior_visits: default_prior_visits(),
+            expansion_order: ExpansionOrder::Board,
+            prune_margin: None,
+            playout_cutoff: None,
+            weight_table: WeightTable::CLASSIC,
+            parity_empties: default_parity_empties(),
+            explore_rate: default_explore_rate(),
         }
     }
 }
@@ -0,0 +1,143 @@
+//! Benchmark positions with stable IDs.
+//!
+//! Performance numbers are only comparable when they are measured on the same inputs, so the
+//! benchmarks use this fixed [`CORPUS`] rather than positions generated at run time. Each
+//! position is stored as the moves that lead to it, in the algebraic notation of
+//! [`record`](crate::record). IDs are never reused or renumbered, positions are only added.
+//!
+//! The positions were taken from games of Herb against itself with fixed search budgets.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::othello::{Game, Move};
+use crate::record::parse_move;
+
+/// The phase of the game a [`BenchPosition`] represents.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    Opening,
+    Midgame,
+    /// The side to move can take a corner.
+    Tactical,
+    /// Fourteen empty squares left.
+    Endgame,
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Category::Opening => "opening",
+            Category::Midgame => "midgame",
+            Category::Tactical => "tactical",
+            Category::Endgame => "endgame",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A position in the benchmark corpus.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BenchPosition {
+    pub id: &'static str,
+    pub category: Category,
+    moves: &'static str,
+}
+
+impl BenchPosition {
+    /// Returns the moves leading to the position.
+    pub fn moves(&self) -> Vec<Move> {
+        self.moves
+            .split_whitespace()
+            .map(|token| parse_move(token).expect("corpus moves are valid"))
+            .collect()
+    }
+
+    /// Returns the position.
+    pub fn game(&self) -> Game {
+        let mut game = Game::new();
+        for mv in self.moves() {
+            game.play_next_turn(mv).expect("corpus moves are legal");
+        }
+        game
+    }
+}
+
+/// Every benchmark position.
+pub const CORPUS: [BenchPosition; 8] = [
+    BenchPosition {
+        id: "opening-1",
+        category: Category::Opening,
+        moves: "d3 c3 c4 c5 c6 f4 f3 e3",
+    },
+    BenchPosition {
+        id: "opening-2",
+        category: Category::Opening,
+        moves: "e6 d6 c6 f6 f4 g4 g7 h8",
+    },
+    BenchPosition {
+        id: "midgame-1",
+        category: Category::Midgame,
+        moves: "e6 d6 c6 f6 f4 g4 g7 h8 e3 d2 h4 g5 g3 g2 c1 f2 e1 e2 h6 c2 g6 d7 c7 f7 c5 c4 \
+                f8 g8",
+    },
+    BenchPosition {
+        id: "midgame-2",
+        category: Category::Midgame,
+        moves: "d3 c3 c4 e3 e2 e1 f1 g1 f4 g3 g4 g5 h4 b5 b3 c5 e6 d6 c6 a3 a2 a1 c2 d2 d1 c1 \
+                f3 b4",
+    },
+    BenchPosition {
+        id: "tactical-1",
+        category: Category::Tactical,
+        moves: "d3 c3 c4 c5 c6 f4 f3 e3 b3 f2 f6 c7 g4 g5 g3 e7 e1 f5 g7 f1 g1 h5 h4 h3 g2",
+    },
+    BenchPosition {
+        id: "tactical-2",
+        category: Category::Tactical,
+        moves: "e6 d6 c6 f6 f4 g4 g7 h8 e3 d2 h4 g5 g3 g2 c1 f2 e1 e2 h2 g1 g6 c2 h6 c7 b8 d7 \
+                e8 f5 h3 h5 f1",
+    },
+    BenchPosition {
+        id: "endgame-1",
+        category: Category::Endgame,
+        moves: "e6 d6 c6 f6 f4 g4 g7 h8 e3 d2 h4 g5 g3 g2 c1 f2 e1 e2 h6 c2 g6 d7 c7 f7 c5 c4 \
+                f8 g8 d3 c3 d1 f5 h5 f3 f1 g1 h1 e8 h3 h2 h7 b8 b1 b2 e7 b4",
+    },
+    BenchPosition {
+        id: "endgame-2",
+        category: Category::Endgame,
+        moves: "d3 c3 c4 e3 e2 e1 f1 g1 f4 g3 g4 g5 h4 b5 b3 c5 e6 d6 c6 a3 a2 a1 c2 d2 d1 c1 \
+                f3 b4 a4 a5 b6 h5 b2 f5 g6 a6 h6 b1 f6 b7 a7 a8 f2 c7 h3 g2",
+    },
+];
+
+/// Find a position by its ID.
+pub fn find(id: &str) -> Option<&'static BenchPosition> {
+    CORPUS.iter().find(|position| position.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_corpus_positions() {
+        let ids: HashSet<_> = CORPUS.iter().map(|position| position.id).collect();
+        assert_eq!(ids.len(), CORPUS.len());
+
+        for position in &CORPUS {
+            let game = position.game();
+            assert!(!game.is_over(), "{}", position.id);
+            match position.category {
+                Category::Opening => assert!(game.get_turn() <= 10),
+                Category::Midgame => assert!(game.empty_squares() > 14),
+                Category::Tactical => assert!(game.legal_moves().iter().any(|mv| {
+                    matches!((mv.get_col(), mv.get_row()), (Some(0 | 7), Some(0 | 7)))
+                })),
+                Category::Endgame => assert_eq!(game.empty_squares(), 14),
+            }
+        }
+        assert_eq!(find("endgame-2"), Some(&CORPUS[7]));
+        assert_eq!(find("missing"), None);
+    }
+}
@@ -0,0 +1,368 @@
+//! Audits of the deterministic search.
+//!
+//! A [`Tree`] searched for a fixed number of iterations is meant to make the same choices
+//! every time, which the golden games, the snapshot diffs and reproducible tests rely on.
+//! [`audit`] checks it: it plays a line with a fresh tree searched for the budget at every
+//! decision, searches every position of the line again on another thread, which has its own
+//! random number generator and hash seeds, and reports each decision where the root
+//! statistics or the principal variation differ as a [`Divergence`].
+//!
+//! A divergence only says that something is not deterministic, [`scan`] says where to look.
+//! It lists every line of the [`SEARCH_MODULES`] that uses a source of nondeterminism: an
+//! unseeded random number generator, a hash map or set whose iteration order could leak into
+//! a choice, threads or the clock. Uses that were checked and cannot change a choice are in
+//! [`REVIEWED`] with the reason, anything else is flagged, so a new use shows up until someone
+//! has looked at it.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+
+use crate::config::MctsConfig;
+use crate::mcts::Tree;
+use crate::othello::Move::Pass;
+use crate::othello::{Game, Move};
+use crate::record::format_move;
+use crate::snapshot::TreeSnapshot;
+
+/// The modules the fixed-iteration search runs, relative to the crate root.
+pub const SEARCH_MODULES: [&str; 4] = [
+    "src/cache.rs",
+    "src/mcts.rs",
+    "src/othello.rs",
+    "src/othello/bits.rs",
+];
+
+/// A use of a source of nondeterminism that cannot change the search's choices.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Reviewed {
+    pub file: &'static str,
+    /// Part of the line of code.
+    pub code: &'static str,
+    pub reason: &'static str,
+}
+
+/// Every reviewed use in the [`SEARCH_MODULES`].
+pub const REVIEWED: [Reviewed; 4] = [
+    Reviewed {
+        file: "src/cache.rs",
+        code: "Mutex",
+        reason: "the cached evaluations only depend on the position, whichever thread stores them",
+    },
+    Reviewed {
+        file: "src/cache.rs",
+        code: "AtomicU64",
+        reason: "hit and miss counts are statistics, the search never reads them",
+    },
+    Reviewed {
+        file: "src/mcts.rs",
+        code: "HashMap",
+        reason: "nodes are looked up by hash, the map is never iterated",
+    },
+    Reviewed {
+        file: "src/mcts.rs",
+        code: "Instant::now",
+        reason: "timings are measured, never decided on",
+    },
+];
+
+/// What kind of nondeterminism a [`Source`] is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    /// A random number generator that is not seeded.
+    Rng,
+    /// A hash map or set, iterated in a different order every run.
+    HashOrder,
+    /// Threads, which are scheduled differently every run.
+    Threads,
+    /// The clock.
+    Clock,
+}
+
+impl Display for SourceKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            SourceKind::Rng => "unseeded rng",
+            SourceKind::HashOrder => "hash order",
+            SourceKind::Threads => "threads",
+            SourceKind::Clock => "clock",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What gives away each kind of source in the code.
+const PATTERNS: [(&str, SourceKind); 13] = [
+    ("thread_rng", SourceKind::Rng),
+    ("from_entropy", SourceKind::Rng),
+    ("rand::random", SourceKind::Rng),
+    ("OsRng", SourceKind::Rng),
+    ("HashMap", SourceKind::HashOrder),
+    ("HashSet", SourceKind::HashOrder),
+    ("thread::spawn", SourceKind::Threads),
+    ("rayon", SourceKind::Threads),
+    ("par_iter", SourceKind::Threads),
+    ("Atomic", SourceKind::Threads),
+    ("Mutex", SourceKind::Threads),
+    ("Instant::now", SourceKind::Clock),
+    ("SystemTime", SourceKind::Clock),
+];
+
+/// A line of code that uses a source of nondeterminism.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Source {
+    pub kind: SourceKind,
+    pub file: String,
+    /// The line number, counting from 1.
+    pub line: usize,
+    pub code: String,
+    /// Why the use cannot change a choice, `None` if it has not been reviewed.
+    pub reason: Option<&'static str>,
+}
+
+impl Display for Source {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{}:{}: {}: {}",
+            self.file, self.line, self.kind, self.code
+        )?;
+        if let Some(reason) = self.reason {
+            write!(f, " (reviewed: {})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Find the sources of nondeterminism in the code of a file, up to its tests. Comments and
+/// imports are skipped.
+pub fn scan_source(file: &str, text: &str) -> Vec<Source> {
+    let mut sources = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let code = line.trim();
+        if code.starts_with("#[cfg(test)]") {
+            break;
+        }
+        if code.starts_with("//") || code.starts_with("use ") {
+            continue;
+        }
+        let Some(&(_, kind)) = PATTERNS.iter().find(|(pattern, _)| code.contains(pattern)) else {
+            continue;
+        };
+        let reason = REVIEWED
+            .iter()
+            .find(|reviewed| reviewed.file == file && code.contains(reviewed.code))
+            .map(|reviewed| reviewed.reason);
+        sources.push(Source {
+            kind,
+            file: file.to_string(),
+            line: i + 1,
+            code: code.to_string(),
+            reason,
+        });
+    }
+    sources
+}
+
+/// Find the sources of nondeterminism in the [`SEARCH_MODULES`] of the crate at `root`.
+pub fn scan(root: &Path) -> io::Result<Vec<Source>> {
+    let mut sources = Vec::new();
+    for file in SEARCH_MODULES {
+        let text = fs::read_to_string(root.join(file))?;
+        sources.extend(scan_source(file, &text));
+    }
+    Ok(sources)
+}
+
+/// What a fresh tree made of a position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decision {
+    /// Plies from the start of the audited line.
+    pub ply: usize,
+    /// The move the tree plays.
+    pub mv: Move,
+    pub snapshot: TreeSnapshot,
+    pub pv: Vec<Move>,
+}
+
+impl Decision {
+    /// Search the position with a fresh tree for the given number of iterations.
+    pub fn search(game: Game, config: &MctsConfig, iterations: u64, ply: usize) -> Self {
+        let mut tree = Tree::from_config(config.clone());
+        tree.search_n(game, iterations);
+        let mv = match tree.best_move(game) {
+            Pass => game.greedy_move(),
+            mv => mv,
+        };
+        Decision {
+            ply,
+            mv,
+            snapshot: TreeSnapshot::capture(&tree, game, &ply.to_string()),
+            pv: tree
+                .principal_variation(game)
+                .iter()
+                .map(|step| step.mv)
+                .collect(),
+        }
+    }
+
+    /// Whether the other decision has exactly the same statistics, bit for bit.
+    pub fn matches(&self, other: &Decision) -> bool {
+        let (a, b) = (&self.snapshot, &other.snapshot);
+        a.position == b.position
+            && self.mv == other.mv
+            && a.iterations == b.iterations
+            && a.best == b.best
+            && self.pv == other.pv
+            && a.moves.len() == b.moves.len()
+            && a.moves.iter().zip(&b.moves).all(|(a, b)| {
+                a.mv == b.mv
+                    && a.visits.to_bits() == b.visits.to_bits()
+                    && a.wins.to_bits() == b.wins.to_bits()
+                    && a.value.to_bits() == b.value.to_bits()
+            })
+    }
+}
+
+/// A decision the two searches of an [`audit`] did not agree on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    pub first: Decision,
+    pub second: Decision,
+}
+
+impl Display for Divergence {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let (first, second) = (&self.first.snapshot, &self.second.snapshot);
+        write!(f, "Ply {}:", self.first.ply)?;
+        if first.best != second.best {
+            write!(f, " plays {} then {},", first.best, second.best)?;
+        }
+        for (a, b) in first.moves.iter().zip(&second.moves) {
+            if (a.visits, a.wins) != (b.visits, b.wins) {
+                write!(
+                    f,
+                    " {} {}/{} then {}/{},",
+                    a.mv, a.wins, a.visits, b.wins, b.visits
+                )?;
+            }
+        }
+        if self.first.pv != self.second.pv {
+            let line = |pv: &[Move]| {
+                pv.iter()
+                    .map(|mv| format_move(*mv))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            write!(
+                f,
+                " pv {} then {},",
+                line(&self.first.pv),
+                line(&self.second.pv)
+            )?;
+        }
+        write!(f, " after {} iterations", first.iterations)
+    }
+}
+
+/// The outcome of an [`audit`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Audit {
+    /// Decisions searched twice.
+    pub decisions: usize,
+    pub divergences: Vec<Divergence>,
+}
+
+/// Play up to `plies` decisions from the start position, choosing each move with a fresh
+/// tree searched for `iterations`, then search every decision again on another thread and
+/// compare. Forced passes are played without a decision.
+pub fn audit(start: Game, config: &MctsConfig, iterations: u64, plies: usize) -> Audit {
+    let mut first = Vec::new();
+    let mut game = start;
+    let mut ply = 0;
+    while first.len() < plies && !game.is_over() {
+        let mv = if game.legal_moves().is_empty() {
+            Pass
+        } else {
+            let decision = Decision::search(game, config, iterations, ply);
+            let mv = decision.mv;
+            first.push(decision);
+            mv
+        };
+        game.play_next_turn(mv).unwrap();
+        ply += 1;
+    }
+
+    let config = config.clone();
+    let positions: Vec<(Game, usize)> = first
+        .iter()
+        .map(|decision| (decision.snapshot.position, decision.ply))
+        .collect();
+    let second = thread::spawn(move || {
+        positions
+            .into_iter()
+            .map(|(game, ply)| Decision::search(game, &config, iterations, ply))
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .expect("the second search does not panic");
+
+    Audit {
+        decisions: first.len(),
+        divergences: first
+            .into_iter()
+            .zip(second)
+            .filter(|(first, second)| !first.matches(second))
+            .map(|(first, second)| Divergence { first, second })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit() {
+        let config = MctsConfig::default();
+        let audit = audit(Game::new(), &config, 100, 3);
+        assert_eq!(audit.decisions, 3);
+        assert!(audit.divergences.is_empty(), "{:#?}", audit.divergences);
+
+        let first = Decision::search(Game::new(), &config, 100, 0);
+        let mut second = first.clone();
+        assert!(first.matches(&second));
+        second.snapshot.moves[0].visits += 1.0;
+        second.pv.pop();
+        assert!(!first.matches(&second));
+        let divergence = Divergence { first, second }.to_string();
+        assert!(divergence.starts_with("Ply 0: "));
+        assert!(divergence.contains(" pv "));
+    }
+
+    #[test]
+    fn test_scan() {
+        let text = "use rand::thread_rng;\n\
+                    // a thread_rng in a comment\n\
+                    fn f() {\n    let rng = thread_rng();\n}\n\
+                    #[cfg(test)]\n\
+                    fn g() {\n    thread::spawn(|| {});\n}\n";
+        let sources = scan_source("src/new.rs", text);
+        assert_eq!(sources.len(), 1);
+        assert_eq!((sources[0].kind, sources[0].line), (SourceKind::Rng, 4));
+        assert_eq!(sources[0].reason, None);
+
+        // every use in the search modules has been reviewed
+        let sources = scan(Path::new(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let flagged: Vec<String> = sources
+            .iter()
+            .filter(|source| source.reason.is_none())
+            .map(Source::to_string)
+            .collect();
+        assert!(flagged.is_empty(), "{:#?}", flagged);
+        assert!(sources
+            .iter()
+            .any(|source| source.kind == SourceKind::HashOrder));
+    }
+}
@@ -0,0 +1,480 @@
+//! Drives a single game between two players.
+//!
+//! A [`Match`] takes a [`Seat`] for each color. A local seat is any [`Player`] in this
+//! process, a remote seat is an opponent behind a [`GameInterface`], such as the referee. Every
+//! move is sent to the other seat when it is remote, and moves read from a remote seat are
+//! played leniently since they are outside input. The match keeps each side's clock, tells the
+//! local players about every move and can comment on the game as it goes. An interrupted game
+//! can be resumed from its moves and clocks with [`Match::resume`]:
+//! ```rust
+//! use herb::driver::{Match, Seat};
+//! use herb::opponents::Opponent;
+//!
+//! let mut black = Opponent::Greedy;
+//! let mut white = Opponent::Random;
+//! let result = Match::new(Seat::Local(&mut black), Seat::Local(&mut white))
+//!     .play()
+//!     .unwrap();
+//! assert!(result.game.is_over());
+//! ```
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::config::Verbosity;
+use crate::drmecref::DrMecRef;
+use crate::openings;
+use crate::othello::Color::{Black, White};
+use crate::othello::{Color, Game, GameError, MaterialHistory, Move, RulesMode, RulesWarning};
+use crate::{GameInterface, Player};
+
+/// Who plays one color in a [`Match`].
+pub enum Seat<'a> {
+    /// A player in this process.
+    Local(&'a mut dyn Player),
+    /// An opponent whose moves are sent and received through an interface.
+    Remote(&'a dyn GameInterface),
+}
+
+/// The outcome of a single game.
+#[derive(Clone, Debug)]
+pub struct GameResult {
+    /// Every move played, passes included.
+    pub moves: Vec<Move>,
+    /// The final position.
+    pub game: Game,
+    /// Time black took to choose its moves.
+    pub black_time: Duration,
+    /// Time white took to choose its moves.
+    pub white_time: Duration,
+}
+
+impl GameResult {
+    /// Returns the winner of the game, `None` for a draw.
+    pub fn winner(&self) -> Option<Color> {
+        self.game.winner()
+    }
+}
+
+/// Enumerates the ways a [`Match`] can end before the game is over.
+#[derive(Debug)]
+pub enum MatchError {
+    /// A local player made an illegal move, or a remote one a move that could not be read as
+    /// a legal one.
+    Game(GameError),
+    /// Sending or receiving a move through a remote seat's interface failed.
+    Interface(io::Error),
+}
+
+impl From<GameError> for MatchError {
+    fn from(e: GameError) -> Self {
+        MatchError::Game(e)
+    }
+}
+
+impl From<io::Error> for MatchError {
+    fn from(e: io::Error) -> Self {
+        MatchError::Interface(e)
+    }
+}
+
+impl Display for MatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            MatchError::Game(e) => write!(f, "{}", e),
+            MatchError::Interface(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for MatchError {}
+
+/// Called with the game so far after every move.
+type Observer<'a> = Box<dyn FnMut(&GameResult) + 'a>;
+
+/// A game between two seats, see the [module](self) documentation.
+pub struct Match<'a> {
+    black: Seat<'a>,
+    white: Seat<'a>,
+    game: Game,
+    moves: Vec<Move>,
+    black_time: Duration,
+    white_time: Duration,
+    label: Option<&'a str>,
+    verbosity: Verbosity,
+    telemetry: bool,
+    observer: Option<Observer<'a>>,
+}
+
+impl<'a> Match<'a> {
+    /// Create a match from the starting position.
+    pub fn new(black: Seat<'a>, white: Seat<'a>) -> Self {
+        Match {
+            black,
+            white,
+            game: Game::new(),
+            moves: Vec::new(),
+            black_time: Duration::ZERO,
+            white_time: Duration::ZERO,
+            label: None,
+            verbosity: Verbosity::Silent,
+            telemetry: false,
+            observer: None,
+        }
+    }
+
+    /// Play from the given position instead of the starting position.
+    pub fn from_position(mut self, game: Game) -> Self {
+        self.game = game;
+        self
+    }
+
+    /// Continue a game that was interrupted after the given moves from the match's position,
+    /// with the time each side had already used. The moves are part of the result, and the
+    /// local players are only asked for the moves after them.
+    ///
+    /// Returns an error if any of the moves is illegal.
+    pub fn resume(
+        mut self,
+        moves: &[Move],
+        black_time: Duration,
+        white_time: Duration,
+    ) -> Result<Self, GameError> {
+        for mv in moves {
+            self.game.play_next_turn(*mv)?;
+        }
+        self.moves.extend_from_slice(moves);
+        self.black_time += black_time;
+        self.white_time += white_time;
+        Ok(self)
+    }
+
+    /// Comment on every turn and the end of the game to the referee, prefixed with the label,
+    /// e.g. `Main: start turn 12`, as far as the verbosity allows.
+    pub fn with_log(mut self, label: &'a str, verbosity: Verbosity) -> Self {
+        self.label = Some(label);
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Also comment the named openings the game passes through and, at the end, the material
+    /// of every turn. Only has an effect with [`Match::with_log`].
+    pub fn with_telemetry(mut self, telemetry: bool) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+
+    /// Call the observer with the game so far after every move.
+    pub fn with_observer(mut self, observer: impl FnMut(&GameResult) + 'a) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
+    /// Play the game to the end.
+    ///
+    /// The local players are told their colors before the first move and that the game is
+    /// over at the end. Returns an error if a move is illegal or a remote seat's interface
+    /// fails, the local players are still told the game is over.
+    pub fn play(mut self) -> Result<GameResult, MatchError> {
+        let mut result = GameResult {
+            moves: std::mem::take(&mut self.moves),
+            game: self.game,
+            black_time: self.black_time,
+            white_time: self.white_time,
+        };
+        for color in [Black, White] {
+            if let Seat::Local(player) = self.seat(color) {
+                player.new_game(color);
+            }
+        }
+        let mut material =
+            (self.telemetry && self.label.is_some()).then(|| MaterialHistory::new(&result.game));
+
+        let outcome = loop {
+            let game = result.game;
+            self.comment(Verbosity::Moves, format!("start turn {}", game.get_turn()));
+            if game.is_over() {
+                self.comment(
+                    Verbosity::Results,
+                    format!("game over at turn {}", game.get_turn()),
+                );
+                break Ok(());
+            }
+
+            let color = game.to_move();
+            let start = Instant::now();
+            let played = self.turn(&mut result.game);
+            match color {
+                Black => result.black_time += start.elapsed(),
+                White => result.white_time += start.elapsed(),
+            }
+            let mv = match played {
+                Ok(mv) => mv,
+                Err(e) => break Err(e),
+            };
+            result.moves.push(mv);
+
+            if let Some(material) = &mut material {
+                material.push(&result.game);
+                if let Some(opening) = openings::name(&result.game) {
+                    self.comment(Verbosity::Moves, format!("opening {}", opening));
+                }
+            }
+            if let Some(observer) = &mut self.observer {
+                observer(&result);
+            }
+        };
+
+        for color in [Black, White] {
+            if let Seat::Local(player) = self.seat(color) {
+                player.game_over(&result);
+            }
+        }
+        self.comment(
+            Verbosity::Results,
+            format!(
+                "time used: B {:.1}s, W {:.1}s",
+                result.black_time.as_secs_f64(),
+                result.white_time.as_secs_f64()
+            ),
+        );
+        if let Some(material) = material {
+            self.comment(Verbosity::Moves, format!("material by turn {}", material));
+        }
+        outcome.map(|()| result)
+    }
+
+    /// Play the move of the player to move, and tell the other seat about it.
+    fn turn(&mut self, game: &mut Game) -> Result<Move, MatchError> {
+        let before = *game;
+        let color = before.to_move();
+        let mv = match self.seat(color) {
+            Seat::Local(player) => {
+                let mv = player.get_next_move(before);
+                game.play_next_turn(mv)?;
+                mv
+            }
+            Seat::Remote(interface) => {
+                let mv = interface.receive_move()?;
+                let warning = game.play_with_rules(mv, RulesMode::Lenient)?;
+                if let Some(warning) = &warning {
+                    self.comment(Verbosity::Results, format!("warning: {}", warning));
+                }
+                // a move when forced to pass is played as the pass
+                match warning {
+                    Some(RulesWarning::MoveWhenForcedToPass(_)) => Move::Pass,
+                    _ => mv,
+                }
+            }
+        };
+        match self.seat(color.opponent()) {
+            Seat::Local(player) => player.opponent_moved(&before, mv),
+            Seat::Remote(interface) => interface.send_move(mv, color)?,
+        }
+        if let Seat::Local(player) = self.seat(color) {
+            player.move_played(&before, mv);
+        }
+        Ok(mv)
+    }
+
+    fn seat(&mut self, color: Color) -> &mut Seat<'a> {
+        match color {
+            Black => &mut self.black,
+            White => &mut self.white,
+        }
+    }
+
+    fn comment(&self, level: Verbosity, message: String) {
+        match self.label {
+            Some(label) if self.verbosity.allows(level) => {
+                DrMecRef::comment(format!("{}: {}", label, message))
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::autosave::Autosave;
+    use crate::config::Config;
+    use crate::drmecref::{MockReferee, Scripted};
+    use crate::opponents::Opponent;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Plays greedily and counts the notifications it gets.
+    #[derive(Default)]
+    struct Counting {
+        color: Option<Color>,
+        own: usize,
+        opponent: usize,
+        ended: bool,
+    }
+
+    impl Player for Counting {
+        fn get_next_move(&mut self, game_state: Game) -> Move {
+            Opponent::Greedy.choose(&game_state)
+        }
+
+        fn move_played(&mut self, _game: &Game, _mv: Move) {
+            self.own += 1;
+        }
+
+        fn opponent_moved(&mut self, _game: &Game, _mv: Move) {
+            self.opponent += 1;
+        }
+
+        fn new_game(&mut self, color: Color) {
+            self.color = Some(color);
+        }
+
+        fn color(&self) -> Option<Color> {
+            self.color
+        }
+
+        fn game_over(&mut self, _result: &GameResult) {
+            self.ended = true;
+        }
+    }
+
+    /// Always plays a1.
+    struct Illegal;
+
+    impl Player for Illegal {
+        fn get_next_move(&mut self, _game_state: Game) -> Move {
+            Move::from_col_row(0, 0).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_local_match() {
+        let mut black = Counting::default();
+        let mut white = Opponent::Random;
+        let mut positions = 0;
+        let result = Match::new(Seat::Local(&mut black), Seat::Local(&mut white))
+            .with_observer(|_| positions += 1)
+            .play()
+            .unwrap();
+        assert!(result.game.is_over());
+        assert_eq!(positions, result.moves.len());
+        assert_eq!(black.own + black.opponent, result.moves.len());
+        assert!(black.ended);
+        assert_eq!(black.color(), Some(Black));
+
+        let mut black = Counting::default();
+        let result = Match::new(Seat::Local(&mut black), Seat::Local(&mut Illegal)).play();
+        assert!(matches!(
+            result,
+            Err(MatchError::Game(GameError::InvalidMove))
+        ));
+        assert_eq!(black.own, 1);
+        assert!(black.ended);
+    }
+
+    #[test]
+    fn test_remote_match() {
+        let referee = MockReferee::new(Black, Vec::new());
+        let mut black = Counting::default();
+        let result = Match::new(Seat::Local(&mut black), Seat::Remote(&referee))
+            .play()
+            .unwrap();
+        assert!(result.game.is_over());
+        assert_eq!(result.game.get_board(), referee.game().get_board());
+        assert_eq!(referee.sent().len(), black.own);
+
+        let a1 = Move::from_col_row(0, 0).unwrap();
+        let referee = MockReferee::from_moves(White, &[a1]);
+        let mut white = Counting::default();
+        let result = Match::new(Seat::Remote(&referee), Seat::Local(&mut white)).play();
+        assert!(matches!(result, Err(MatchError::Game(_))));
+        assert!(white.ended);
+
+        let referee = MockReferee::new(White, vec![Scripted::Line("B z 9".to_string())]);
+        let result = Match::new(Seat::Remote(&referee), Seat::Local(&mut white)).play();
+        assert!(matches!(result, Err(MatchError::Interface(_))));
+    }
+
+    #[test]
+    fn test_remote_move_when_forced_to_pass() {
+        // find a game where a player has to pass, and let the referee play that player
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let (before, game) = loop {
+            let mut game = Game::new();
+            let mut moves = Vec::new();
+            while !game.is_over() && !game.must_pass() {
+                let mv = game.random_move(&mut rng);
+                game.play_next_turn(mv).unwrap();
+                moves.push(mv);
+            }
+            if !game.is_over() {
+                break (moves, game);
+            }
+        };
+        let passer = game.to_move();
+        let board = game.get_board();
+        let empty = Move::Move((board.get_black() | board.get_white()).trailing_ones() as u64);
+        let referee = MockReferee::from_moves(passer.opponent(), &[empty]).from_position(game);
+        let mut herb = Counting::default();
+        let (black, white) = match passer {
+            Black => (Seat::Remote(&referee), Seat::Local(&mut herb)),
+            White => (Seat::Local(&mut herb), Seat::Remote(&referee)),
+        };
+        let result = Match::new(black, white)
+            .resume(&before, Duration::ZERO, Duration::ZERO)
+            .unwrap()
+            .play()
+            .unwrap();
+        assert_eq!(result.moves[before.len()], Move::Pass);
+        assert_eq!(result.game.get_board(), referee.game().get_board());
+
+        // the autosave holds the pass, so the game resumes from it
+        let config = Config::default();
+        let mut autosave = Autosave::new(&config, passer.opponent());
+        autosave.update(&config, &result);
+        let path =
+            std::env::temp_dir().join(format!("herb-driver-pass-{}.json", std::process::id()));
+        autosave.save(&path).unwrap();
+        let loaded = Autosave::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.moves(), result.moves);
+        let resumed = Match::new(
+            Seat::Local(&mut Opponent::Greedy),
+            Seat::Local(&mut Opponent::Greedy),
+        )
+        .resume(&loaded.moves(), Duration::ZERO, Duration::ZERO)
+        .unwrap()
+        .play()
+        .unwrap();
+        assert_eq!(resumed.game, result.game);
+    }
+
+    #[test]
+    fn test_resume() {
+        let played = Match::new(
+            Seat::Local(&mut Opponent::Greedy),
+            Seat::Local(&mut Opponent::Greedy),
+        )
+        .play()
+        .unwrap();
+        let mut black = Counting::default();
+        let mut white = Counting::default();
+        let result = Match::new(Seat::Local(&mut black), Seat::Local(&mut white))
+            .resume(&played.moves[..10], Duration::from_secs(3), Duration::ZERO)
+            .unwrap()
+            .play()
+            .unwrap();
+        assert_eq!(result.moves, played.moves);
+        assert_eq!(result.game, played.game);
+        assert!(result.black_time >= Duration::from_secs(3));
+        assert_eq!(black.own + white.own, played.moves.len() - 10);
+        assert_eq!(white.color(), Some(White));
+
+        let d3 = played.moves[0];
+        assert!(Match::new(Seat::Local(&mut black), Seat::Local(&mut white))
+            .resume(&[d3, d3], Duration::ZERO, Duration::ZERO)
+            .is_err());
+    }
+}
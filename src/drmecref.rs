@@ -1,14 +1,22 @@
 //! Interface to Dr. Cameron's Referee.
 //!
-//! The [`DrMecRef`] struct implements the [`Player`] and [`GameInterface`] traits.
+//! The [`DrMecRef`] struct implements the [`Player`], [`GameInterface`] and [`Referee`]
+//! traits. Every line it sends or receives is recorded to the [`session`](crate::session) when
+//! one was started. The [`MockReferee`] stands in for the referee in tests.
 //!
+use std::collections::VecDeque;
 use std::fmt::Display;
 use std::io;
 use std::io::ErrorKind::InvalidInput;
 use std::io::{stdin, Error};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
 
+use crate::config::Verbosity;
 use crate::othello::Color::{Black, White};
-use crate::othello::{Color, Game, Move};
+use crate::othello::{Color, Game, Move, RulesMode};
+use crate::session::{self, Direction};
 use crate::{GameInterface, Player};
 
 /// Maps column indexes to their character values
@@ -26,23 +34,391 @@ fn map_col(col: u8) -> &'static str {
     }
 }
 
-/// Maps column characters back to their indexes
-fn unmap_col(col: &str) -> u8 {
-    match col {
-        "a" => 0,
-        "b" => 1,
-        "c" => 2,
-        "d" => 3,
-        "e" => 4,
-        "f" => 5,
-        "g" => 6,
-        "h" => 7,
-        _ => 11,
+/// Formats a move the way the referee expects it, e.g. `B d 3`, or just the color for a pass.
+pub fn format_move(mv: Move, color: Color) -> String {
+    match (mv.get_col(), mv.get_row()) {
+        (Some(col), Some(row)) => format!("{} {} {}", color, map_col(col), row + 1),
+        _ => format!("{}", color),
+    }
+}
+
+/// A line of the referee protocol.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RefereeMessage {
+    /// `I <color>`, the referee assigning a color.
+    Init(Color),
+    /// `R <color>`, a player reporting it is ready.
+    Ready(Color),
+    /// `<color> <col> <row>`, a move by the given color.
+    OpponentMove(Color, Move),
+    /// `<color>` on its own, or `<color> pass`, the given color passing.
+    Pass(Color),
+    /// `T <color> <seconds>`, the time left on a player's clock.
+    TimeUpdate { color: Color, seconds: f64 },
+    /// `E <text>`, the referee ending the game, e.g. `E White wins 40-24 timeout`.
+    GameEnd(OfficialResult),
+    /// `C <text>`, a comment.
+    Comment(String),
+    /// Any other line, kept as it was received.
+    Unknown(String),
+}
+
+impl RefereeMessage {
+    /// Returns the move if this is a move or a pass.
+    pub fn as_move(&self) -> Option<Move> {
+        match self {
+            RefereeMessage::OpponentMove(_, mv) => Some(*mv),
+            RefereeMessage::Pass(_) => Some(Move::Pass),
+            _ => None,
+        }
     }
 }
 
+/// Why the referee ended a game.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EndReason {
+    /// Neither player could move.
+    Normal,
+    /// A player ran out of time.
+    Timeout,
+    /// A player made an illegal move.
+    IllegalMove,
+    /// A player resigned.
+    Resignation,
+    /// A reason the parser does not know, kept as it was received.
+    Other(String),
+}
+
+/// The result of a game as reported by the referee, which is final even when it disagrees
+/// with the board, e.g. after a forfeit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfficialResult {
+    /// The winner, `None` for a draw or when the referee did not say.
+    pub winner: Option<Color>,
+    /// Black's and white's disc counts, if reported.
+    pub score: Option<(u32, u32)>,
+    pub reason: EndReason,
+}
+
+impl OfficialResult {
+    /// Parse the text of an end of game message. Words may come in any order: a color with
+    /// `wins`, or `draw`, a `black-white` score and a reason such as `timeout`, optionally
+    /// after the color that forfeited. When the winner is not named it is the opponent of the
+    /// player that forfeited, or else taken from the score.
+    fn parse(text: &str) -> Self {
+        let tokens = tokenize(text);
+        let word = |token: &Token, words: &[&str]| match token {
+            Token::Word(word) => words.contains(&word.as_str()),
+            _ => false,
+        };
+
+        let mut winner = None;
+        let mut named_winner = false;
+        let mut score = None;
+        let mut reason = None;
+        let mut loser = None;
+        for (i, token) in tokens.iter().enumerate() {
+            let previous_color = i
+                .checked_sub(1)
+                .and_then(|j| parse_color(tokens.get(j)).ok());
+            if word(token, &["wins", "win", "winner", "won"]) {
+                // "black wins" or "winner white"
+                let color = previous_color.or_else(|| parse_color(tokens.get(i + 1)).ok());
+                if let Some(color) = color {
+                    winner = Some(color);
+                    named_winner = true;
+                }
+            } else if word(token, &["draw", "tie"]) {
+                named_winner = true;
+            } else if word(token, &["time", "timeout", "clock"]) {
+                reason = reason.or(Some(EndReason::Timeout));
+                loser = loser.or(previous_color);
+            } else if word(token, &["illegal"]) {
+                reason = reason.or(Some(EndReason::IllegalMove));
+                loser = loser.or(previous_color);
+            } else if word(token, &["resign", "resigns", "resigned", "resignation"]) {
+                reason = reason.or(Some(EndReason::Resignation));
+                loser = loser.or(previous_color);
+            } else if let (
+                Token::Number(black),
+                Some(Token::Symbol('-')),
+                Some(Token::Number(white)),
+            ) = (token, tokens.get(i + 1), tokens.get(i + 2))
+            {
+                if let (Ok(black), Ok(white)) = (black.parse(), white.parse()) {
+                    score = score.or(Some((black, white)));
+                }
+            }
+        }
+
+        if !named_winner {
+            winner = match score {
+                _ if loser.is_some() => loser.map(|color| color.opponent()),
+                Some((black, white)) if black > white => Some(Black),
+                Some((black, white)) if white > black => Some(White),
+                _ => None,
+            };
+        }
+        let reason = match reason {
+            Some(reason) => reason,
+            None if named_winner || score.is_some() || text.is_empty() => EndReason::Normal,
+            None => EndReason::Other(text.to_string()),
+        };
+        OfficialResult {
+            winner,
+            score,
+            reason,
+        }
+    }
+}
+
+impl std::error::Error for OfficialResult {}
+
+impl Display for OfficialResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.winner {
+            Some(Black) => write!(f, "Black wins")?,
+            Some(White) => write!(f, "White wins")?,
+            None => write!(f, "Draw")?,
+        }
+        if let Some((black, white)) = self.score {
+            write!(f, " {}-{}", black, white)?;
+        }
+        match &self.reason {
+            EndReason::Normal => Ok(()),
+            EndReason::Timeout => write!(f, " on time"),
+            EndReason::IllegalMove => write!(f, " by illegal move"),
+            EndReason::Resignation => write!(f, " by resignation"),
+            EndReason::Other(text) => write!(f, " ({})", text),
+        }
+    }
+}
+
+/// A token of a protocol line. Words are lowercased.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    /// The digits of a number with an optional fraction, e.g. `93.5`.
+    Number(String),
+    Symbol(char),
+}
+
+/// Split a line into runs of letters, numbers and single symbols, dropping whitespace.
+/// `d3` and `d 3` both give a word followed by a number.
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_alphabetic() {
+            let mut word = String::new();
+            while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+                word.extend(c.to_lowercase());
+            }
+            tokens.push(Token::Word(word));
+        } else if c.is_ascii_digit() {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                number.push(c);
+            }
+            if chars.next_if_eq(&'.').is_some() {
+                number.push('.');
+                while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                    number.push(c);
+                }
+            }
+            tokens.push(Token::Number(number));
+        } else {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        }
+    }
+    tokens
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn parse_color(token: Option<&Token>) -> io::Result<Color> {
+    match token {
+        Some(Token::Word(word)) if word == "b" || word == "black" => Ok(Black),
+        Some(Token::Word(word)) if word == "w" || word == "white" => Ok(White),
+        _ => Err(invalid("Missing color")),
+    }
+}
+
+/// Returns the text after the single letter keyword that starts the line.
+fn rest_of_line(line: &str) -> String {
+    let text = line.trim_start().get(1..).unwrap_or_default();
+    text.trim().to_string()
+}
+
+/// Parses a line of the referee protocol.
+///
+/// The grammar is
+/// ```text
+/// message = "I" color | "R" color | color [square] | "T" color seconds | "E" text | "C" text
+/// square  = column row | "pass"
+/// color   = "B" | "W" | "black" | "white"
+/// column  = "a" .. "h"
+/// row     = "1" .. "8"
+/// ```
+/// Letters may be either case, tokens may be separated by any amount of whitespace or none
+/// at all, and anything after a complete message is ignored. A line that does not start with
+/// any of the keywords is [`RefereeMessage::Unknown`], one that starts with a keyword but is
+/// malformed is an error. Neither ever panics.
+pub fn parse_message(line: &str) -> io::Result<RefereeMessage> {
+    let tokens = tokenize(line);
+    let Some(Token::Word(keyword)) = tokens.first() else {
+        return Ok(RefereeMessage::Unknown(line.trim().to_string()));
+    };
+    match keyword.as_str() {
+        "i" => Ok(RefereeMessage::Init(parse_color(tokens.get(1))?)),
+        "r" => Ok(RefereeMessage::Ready(parse_color(tokens.get(1))?)),
+        // the keywords are single ascii letters
+        "c" => Ok(RefereeMessage::Comment(rest_of_line(line))),
+        "e" => Ok(RefereeMessage::GameEnd(OfficialResult::parse(
+            &rest_of_line(line),
+        ))),
+        "t" => {
+            let color = parse_color(tokens.get(1))?;
+            let seconds = match tokens.get(2) {
+                Some(Token::Number(number)) => number.parse().ok(),
+                _ => None,
+            };
+            let seconds = seconds.ok_or_else(|| invalid("Missing seconds"))?;
+            Ok(RefereeMessage::TimeUpdate { color, seconds })
+        }
+        "b" | "black" | "w" | "white" => {
+            let color = parse_color(tokens.first())?;
+            match (tokens.get(1), tokens.get(2)) {
+                (None, _) => Ok(RefereeMessage::Pass(color)),
+                (Some(Token::Word(word)), _) if word == "pass" => Ok(RefereeMessage::Pass(color)),
+                (Some(Token::Word(col)), Some(Token::Number(row))) => {
+                    let col = match col.as_bytes() {
+                        [c @ b'a'..=b'h'] => c - b'a',
+                        _ => return Err(invalid("Column out of range")),
+                    };
+                    let row = match row.as_bytes() {
+                        [r @ b'1'..=b'8'] => r - b'1',
+                        _ => return Err(invalid("Row out of range")),
+                    };
+                    let mv = Move::from_col_row(col as u64, row as u64)
+                        .map_err(|_| invalid("Illegal Move."))?;
+                    Ok(RefereeMessage::OpponentMove(color, mv))
+                }
+                _ => Err(invalid("Failed to parse move")),
+            }
+        }
+        _ => Ok(RefereeMessage::Unknown(line.trim().to_string())),
+    }
+}
+
+/// Returns how a message is written by the referee, or `None` for messages that carry free
+/// text.
+fn canonical(message: &RefereeMessage) -> Option<String> {
+    match message {
+        RefereeMessage::Init(color) => Some(format!("I {}", color)),
+        RefereeMessage::Ready(color) => Some(format!("R {}", color)),
+        RefereeMessage::OpponentMove(color, mv) => Some(format_move(*mv, *color)),
+        RefereeMessage::Pass(color) => Some(format_move(Move::Pass, *color)),
+        _ => None,
+    }
+}
+
+/// Parses a line like [`parse_message`], applying the given [`RulesMode`].
+///
+/// In [`RulesMode::Strict`] a message must be written exactly as the referee writes it, e.g.
+/// `B d 3`, and unknown lines are an error. In [`RulesMode::Lenient`] anything
+/// [`parse_message`] accepts is read, along with a warning when the line had to be
+/// interpreted.
+pub fn parse_message_with_rules(
+    line: &str,
+    mode: RulesMode,
+) -> io::Result<(RefereeMessage, Option<String>)> {
+    let message = parse_message(line)?;
+    let line = line.trim_end_matches(['\r', '\n']);
+    let irregular = match (&message, canonical(&message)) {
+        (RefereeMessage::Unknown(_), _) => Some(format!("Unknown message: {}", line)),
+        (_, Some(expected)) if expected != line => {
+            Some(format!("Read '{}' as '{}'", line, expected))
+        }
+        _ => None,
+    };
+    match (mode, irregular) {
+        (RulesMode::Strict, Some(irregular)) => Err(invalid(&irregular)),
+        (_, irregular) => Ok((message, irregular)),
+    }
+}
+
+/// Parses a move line from the referee, e.g. `W c 4`. A line with only a color is a pass.
+pub fn parse_move(input: &str) -> io::Result<Move> {
+    parse_message(input)?
+        .as_move()
+        .ok_or_else(|| invalid("Not a move"))
+}
+
+/// Write a line to the referee.
+fn send_line(line: impl Display) {
+    let line = format!("{}\n", line);
+    print!("{}", line);
+    session::record(Direction::Sent, &line);
+}
+
+/// The referee's side of a game, as seen by the game loop in
+/// [`play_referee_game`](crate::play_referee_game).
+pub trait Referee: GameInterface {
+    /// Wait for the referee to assign a color.
+    fn init(&self) -> io::Result<Color>;
+
+    /// Tell the referee we are ready to play as the given [`Color`].
+    fn ready(&self, color: Color);
+
+    /// Wait for the next message from the referee.
+    fn receive_message(&self) -> io::Result<RefereeMessage>;
+}
+
+/// Wait for the referee's next move, commenting on the messages in between as far as the
+/// verbosity allows. The referee ending the game is an error carrying its [`OfficialResult`],
+/// see [`official_result`].
+fn receive_referee_move(referee: &impl Referee, verbosity: Verbosity) -> io::Result<Move> {
+    loop {
+        match referee.receive_message()? {
+            RefereeMessage::GameEnd(result) => {
+                if verbosity.allows(Verbosity::Results) {
+                    DrMecRef::comment(format!("Referee: official result: {}", result));
+                }
+                return Err(Error::new(io::ErrorKind::ConnectionAborted, result));
+            }
+            RefereeMessage::TimeUpdate { color, seconds } => {
+                if verbosity.allows(Verbosity::Moves) {
+                    DrMecRef::comment(format!("Referee: {} has {}s left", color, seconds));
+                }
+            }
+            message => match message.as_move() {
+                Some(mv) => return Ok(mv),
+                None if verbosity.allows(Verbosity::Debug) => {
+                    DrMecRef::comment(format!("Referee: ignoring {:?}", message))
+                }
+                None => {}
+            },
+        }
+    }
+}
+
+/// Returns the referee's result if the error is the referee ending the game while a move was
+/// expected. The referee's result is final, even if the board disagrees.
+pub fn official_result(e: &io::Error) -> Option<&OfficialResult> {
+    e.get_ref()?.downcast_ref()
+}
+
 /// Interface to Dr. Cameron's Referee
-pub struct DrMecRef {}
+pub struct DrMecRef {
+    rules: RulesMode,
+    verbosity: Verbosity,
+}
 
 impl Default for DrMecRef {
     fn default() -> Self {
@@ -51,44 +427,85 @@ impl Default for DrMecRef {
 }
 
 impl DrMecRef {
+    /// Create an interface that reads the referee's messages in [`RulesMode::Lenient`].
     pub fn new() -> Self {
-        DrMecRef {}
+        DrMecRef::with_rules(RulesMode::Lenient)
+    }
+
+    /// Create an interface that reads the referee's messages in the given [`RulesMode`]. It
+    /// comments on them at [`Verbosity::Results`] until [`DrMecRef::with_verbosity`] says
+    /// otherwise.
+    pub fn with_rules(rules: RulesMode) -> Self {
+        DrMecRef {
+            rules,
+            verbosity: Verbosity::Results,
+        }
+    }
+
+    /// Comment on the referee's messages as far as the verbosity allows, e.g. the one of the
+    /// [`Config`](crate::config::Config) Herb plays with.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
     }
 
     /// Print a message in as a comment to the referee
     pub fn comment(message: impl Display) {
-        println!("C {}", message);
+        send_line(format!("C {}", message));
     }
 
     /// Tell the referee we are ready to play as the given [`Color`].
     pub fn ready(&self, color: Color) {
         match color {
             White => {
-                println!("R W");
+                send_line("R W");
             }
             Black => {
-                println!("R B");
+                send_line("R B");
             }
         }
     }
 
     /// Read on stdin waiting to get the initialization message from the referee.
     pub fn init(&self) -> Result<Color, Error> {
-        let mut input: String = String::new();
-        stdin().read_line(&mut input)?;
-        let input_lower: String = input.to_lowercase();
-
-        if input_lower.starts_with('i') {
-            if input_lower.contains('b') {
-                Ok(Black)
-            } else if input_lower.contains('w') {
-                Ok(White)
-            } else {
-                Err(Error::from(InvalidInput))
+        match self.receive_message() {
+            Ok(RefereeMessage::Init(color)) => Ok(color),
+            _ => Err(Error::from(InvalidInput)),
+        }
+    }
+
+    /// Read the next line from the referee.
+    ///
+    /// Returns an error at the end of input or if the line is a malformed message, see
+    /// [`parse_message_with_rules`]. Warnings about lines that had to be interpreted are
+    /// sent back as comments.
+    pub fn receive_message(&self) -> io::Result<RefereeMessage> {
+        let mut input = String::new();
+        if stdin().read_line(&mut input)? == 0 {
+            return Err(Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        session::record(Direction::Received, &input);
+        let (message, warning) = parse_message_with_rules(&input, self.rules)?;
+        if let Some(warning) = warning {
+            if self.verbosity.allows(Verbosity::Results) {
+                DrMecRef::comment(format!("Warning: {}", warning));
             }
-        } else {
-            Err(Error::from(InvalidInput))
         }
+        Ok(message)
+    }
+}
+
+impl Referee for DrMecRef {
+    fn init(&self) -> io::Result<Color> {
+        DrMecRef::init(self)
+    }
+
+    fn ready(&self, color: Color) {
+        DrMecRef::ready(self, color)
+    }
+
+    fn receive_message(&self) -> io::Result<RefereeMessage> {
+        DrMecRef::receive_message(self)
     }
 }
 
@@ -104,64 +521,325 @@ impl Player for DrMecRef {
 
 impl GameInterface for DrMecRef {
     fn send_move(&self, mv: Move, color: Color) -> io::Result<()> {
-        match mv {
-            Move::Move(_position) => {
-                if let Some(col) = mv.get_col() {
-                    if let Some(row) = mv.get_row() {
-                        let col = map_col(col);
-                        let row = row + 1;
-                        println!("{} {} {}", color, col, row);
+        send_line(format_move(mv, color));
+        Ok(())
+    }
+
+    fn receive_move(&self) -> io::Result<Move> {
+        receive_referee_move(self, self.verbosity)
+    }
+}
+
+/// A line of a [`MockReferee`]'s script.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Scripted {
+    /// Send the opponent's move, whether it is legal or not.
+    Move(Move),
+    /// Send the line as it is, e.g. a malformed message or a time update.
+    Line(String),
+    /// Wait before sending the next line.
+    Delay(Duration),
+}
+
+/// A stand-in for the referee that plays a scripted game, for testing the game loop and the
+/// protocol layer without a referee process.
+///
+/// Each time a message is expected the next line of the script is sent, so moves from the
+/// script are sent when it is the opponent's turn. Once the script runs out the opponent plays
+/// its first legal move, or passes, and when the game is over the referee ends it with the
+/// result of the board. Every line is read back through [`parse_message_with_rules`], so
+/// malformed lines fail exactly as they would from the real referee.
+pub struct MockReferee {
+    color: Color,
+    rules: RulesMode,
+    state: Mutex<MockState>,
+}
+
+struct MockState {
+    script: VecDeque<Scripted>,
+    game: Game,
+    sent: Vec<String>,
+}
+
+impl MockReferee {
+    /// Create a referee that assigns Herb the given color and plays the scripted lines.
+    pub fn new(color: Color, script: Vec<Scripted>) -> Self {
+        MockReferee {
+            color,
+            rules: RulesMode::Lenient,
+            state: Mutex::new(MockState {
+                script: script.into(),
+                game: Game::new(),
+                sent: Vec::new(),
+            }),
+        }
+    }
+
+    /// Create a referee whose opponent plays the given moves, then its first legal moves.
+    pub fn from_moves(color: Color, moves: &[Move]) -> Self {
+        MockReferee::new(color, moves.iter().map(|mv| Scripted::Move(*mv)).collect())
+    }
+
+    /// Play from the given position instead of the starting position, e.g. a resumed game.
+    pub fn from_position(self, game: Game) -> Self {
+        self.state.lock().unwrap().game = game;
+        self
+    }
+
+    /// Read the scripted lines in the given [`RulesMode`] instead of [`RulesMode::Lenient`].
+    pub fn with_rules(mut self, rules: RulesMode) -> Self {
+        self.rules = rules;
+        self
+    }
+
+    /// Returns the lines sent to the referee so far, excluding comments.
+    pub fn sent(&self) -> Vec<String> {
+        self.state.lock().unwrap().sent.clone()
+    }
+
+    /// Returns the position as the referee sees it.
+    pub fn game(&self) -> Game {
+        self.state.lock().unwrap().game
+    }
+}
+
+impl Referee for MockReferee {
+    fn init(&self) -> io::Result<Color> {
+        match parse_message(&format!("I {}", self.color))? {
+            RefereeMessage::Init(color) => Ok(color),
+            _ => Err(Error::from(InvalidInput)),
+        }
+    }
+
+    fn ready(&self, color: Color) {
+        let line = format!("R {}", color);
+        self.state.lock().unwrap().sent.push(line);
+    }
+
+    fn receive_message(&self) -> io::Result<RefereeMessage> {
+        let opponent = self.color.opponent();
+        let line = loop {
+            let next = self.state.lock().unwrap().script.pop_front();
+            match next {
+                Some(Scripted::Delay(delay)) => thread::sleep(delay),
+                Some(Scripted::Line(line)) => break line,
+                Some(Scripted::Move(mv)) => break format_move(mv, opponent),
+                None => {
+                    let game = self.game();
+                    if game.is_over() {
+                        let (black, white) = game.disc_counts();
+                        break format!("E {}-{}", black, white);
                     }
+                    let mv = *game.legal_moves().first().unwrap_or(&Move::Pass);
+                    break format_move(mv, opponent);
                 }
-                Ok(())
-            }
-
-            Move::Pass => {
-                println!("{}", color);
-                Ok(())
             }
+        };
+        let (message, _) = parse_message_with_rules(&line, self.rules)?;
+        if let Some(mv) = message.as_move() {
+            let _ = self
+                .state
+                .lock()
+                .unwrap()
+                .game
+                .play_with_rules(mv, RulesMode::Lenient);
         }
+        Ok(message)
+    }
+}
+
+impl GameInterface for MockReferee {
+    fn send_move(&self, mv: Move, color: Color) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.sent.push(format_move(mv, color));
+        state
+            .game
+            .play_with_rules(mv, RulesMode::Lenient)
+            .map_err(|e| invalid(&e.to_string()))?;
+        Ok(())
     }
 
     fn receive_move(&self) -> io::Result<Move> {
-        let mut input = String::new();
-        loop {
-            input.clear();
-            stdin().read_line(&mut input)?;
-            if input.starts_with('B') || input.starts_with('W') {
-                break;
-            } else {
-                DrMecRef::comment(&input);
+        // a scripted game has nobody to comment to
+        receive_referee_move(self, Verbosity::Silent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_parse_message_variants() {
+        let d3 = Move::from_col_row(3, 2).unwrap();
+        for line in [
+            "B d 3",
+            "b D 3\n",
+            "B  d   3",
+            "\tB d3",
+            "B d 3 junk",
+            "black d3",
+        ] {
+            assert_eq!(
+                parse_message(line).unwrap(),
+                RefereeMessage::OpponentMove(Black, d3),
+                "{}",
+                line
+            );
+        }
+        assert_eq!(parse_message("W").unwrap(), RefereeMessage::Pass(White));
+        assert_eq!(
+            parse_message("w pass").unwrap(),
+            RefereeMessage::Pass(White)
+        );
+        assert_eq!(parse_message("I  b").unwrap(), RefereeMessage::Init(Black));
+        assert_eq!(
+            parse_message("r W\r\n").unwrap(),
+            RefereeMessage::Ready(White)
+        );
+        assert_eq!(
+            parse_message("C  hello there ").unwrap(),
+            RefereeMessage::Comment("hello there".to_string())
+        );
+        assert_eq!(
+            parse_message("T w 93.5").unwrap(),
+            RefereeMessage::TimeUpdate {
+                color: White,
+                seconds: 93.5
             }
+        );
+        assert_eq!(
+            parse_message("E Black wins 40-24").unwrap(),
+            RefereeMessage::GameEnd(OfficialResult {
+                winner: Some(Black),
+                score: Some((40, 24)),
+                reason: EndReason::Normal,
+            })
+        );
+        assert_eq!(
+            parse_message("n 12").unwrap(),
+            RefereeMessage::Unknown("n 12".to_string())
+        );
+        for line in [
+            "B z 3", "B d 0", "B d 9", "B d 10", "B d", "I", "B 3 d", "T B",
+        ] {
+            assert!(parse_message(line).is_err(), "{}", line);
         }
+    }
 
-        // parse the move
-        if input.len() > 1 {
-            let mut tokens = input.trim().split(' ');
+    #[test]
+    fn test_official_results() {
+        let cases = [
+            ("30-34", Some(White), Some((30, 34)), EndReason::Normal),
+            ("draw 32-32", None, Some((32, 32)), EndReason::Normal),
+            ("W wins, B timeout", Some(White), None, EndReason::Timeout),
+            (
+                "winner black: illegal move by W",
+                Some(Black),
+                None,
+                EndReason::IllegalMove,
+            ),
+            (
+                "B resigns 40-20",
+                Some(White),
+                Some((40, 20)),
+                EndReason::Resignation,
+            ),
+            ("W timeout", Some(Black), None, EndReason::Timeout),
+            ("", None, None, EndReason::Normal),
+            (
+                "power outage",
+                None,
+                None,
+                EndReason::Other("power outage".to_string()),
+            ),
+        ];
+        for (text, winner, score, reason) in cases {
+            let result = OfficialResult::parse(text);
+            assert_eq!(result.winner, winner, "{}", text);
+            assert_eq!(result.score, score, "{}", text);
+            assert_eq!(result.reason, reason, "{}", text);
+        }
+        assert_eq!(
+            OfficialResult::parse("W wins 20-44 timeout").to_string(),
+            "White wins 20-44 on time"
+        );
+    }
 
-            let _color = tokens
-                .next()
-                .ok_or(io::Error::new(io::ErrorKind::InvalidData, "Missing color"))?;
+    #[test]
+    fn test_rules_modes() {
+        let (message, warning) = parse_message_with_rules("B d 3\n", RulesMode::Strict).unwrap();
+        assert_eq!(message.as_move(), Some(Move::from_col_row(3, 2).unwrap()));
+        assert_eq!(warning, None);
+        assert!(parse_message_with_rules("C anything goes", RulesMode::Strict).is_ok());
 
-            let col = tokens
-                .next()
-                .ok_or(io::Error::new(io::ErrorKind::InvalidData, "Missing column"))?;
-            let col = unmap_col(col);
+        for line in ["b d3", "B d 3 junk", "hello"] {
+            assert!(
+                parse_message_with_rules(line, RulesMode::Strict).is_err(),
+                "{}",
+                line
+            );
+            let (_, warning) = parse_message_with_rules(line, RulesMode::Lenient).unwrap();
+            assert!(warning.is_some(), "{}", line);
+        }
+        // malformed moves are an error either way
+        assert!(parse_message_with_rules("B z 9", RulesMode::Lenient).is_err());
+    }
 
-            let row_str = tokens
-                .next()
-                .ok_or(io::Error::new(io::ErrorKind::InvalidData, "Missing row"))?;
+    #[test]
+    fn test_mock_referee() {
+        let d3 = Move::from_col_row(3, 2).unwrap();
+        let referee = MockReferee::new(
+            White,
+            vec![
+                Scripted::Line("T B 93.5".to_string()),
+                Scripted::Delay(Duration::from_millis(1)),
+                Scripted::Move(d3),
+            ],
+        );
+        assert_eq!(Referee::init(&referee).unwrap(), White);
+        Referee::ready(&referee, White);
+        assert_eq!(referee.receive_move().unwrap(), d3);
+        referee
+            .send_move(Move::from_col_row(2, 2).unwrap(), White)
+            .unwrap();
+        assert_eq!(referee.sent(), vec!["R W", "W c 3"]);
+        assert_eq!(referee.game().get_turn(), 2);
+        // out of script the opponent plays its first legal move
+        let mv = referee.receive_move().unwrap();
+        assert_eq!(referee.game().last_move(), Some(mv));
 
-            let row = row_str
-                .parse::<u8>()
-                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Failed to parse row"))?;
+        let ended = MockReferee::new(White, vec![Scripted::Line("E B wins 40-24".to_string())]);
+        let e = ended.receive_move().unwrap_err();
+        assert_eq!(official_result(&e).unwrap().winner, Some(Black));
+        assert!(official_result(&Error::from(InvalidInput)).is_none());
 
-            Ok(Move::from_col_row(col as u64, (row - 1) as u64).expect("Illegal Move."))
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::InvalidData,
-                "Failed to parse move",
-            ))
+        let strict = MockReferee::new(White, vec![Scripted::Line("b d3".to_string())])
+            .with_rules(RulesMode::Strict);
+        assert!(strict.receive_message().is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn parse_message_never_panics(line in "\\PC*") {
+            let _ = parse_message(&line);
+        }
+
+        #[test]
+        fn parse_move_round_trips(
+            col in 0u64..8,
+            row in 0u64..8,
+            black in any::<bool>(),
+            space in "[ \t]{1,3}",
+            uppercase in any::<bool>(),
+            junk in "( [ -~]*)?",
+        ) {
+            let mv = Move::from_col_row(col, row).unwrap();
+            let color = if black { Black } else { White };
+            let line = format_move(mv, color).replace(' ', &space) + &junk;
+            let line = if uppercase { line.to_uppercase() } else { line.to_lowercase() };
+            prop_assert_eq!(parse_message(&line).unwrap(), RefereeMessage::OpponentMove(color, mv));
         }
     }
 }
@@ -0,0 +1,320 @@
+//! Playing referees that only send board dumps.
+//!
+//! Some referees never say which move was played, they print the whole board after every
+//! move instead. A [`DumpReferee`] reads such dumps and works out the opponent's moves with
+//! [`Game::infer_moves`], so Herb plays them like any other [`Referee`]. A dump is eight rows
+//! of eight squares, top to bottom:
+//! ```text
+//!   a b c d e f g h
+//! 1 . . . . . . . .
+//! ...
+//! 4 . . . W B . . .
+//! 5 . . . B W . . .
+//! ...
+//! ```
+//! Black discs are `B`, `X` or `*`, white ones `W` or `O` and empty squares `.`, `-` or `_`.
+//! Whitespace and a leading row number are ignored, as is every line that is not a row, such
+//! as column headers. Herb's moves are sent in the usual referee protocol, e.g. `B d 3`.
+//!
+//! Updates may go missing: a dump several moves on is followed as long as it is at most
+//! [`MAX_MISSED_MOVES`] moves away, and dumps that repeat the position or can not follow from
+//! it are skipped with a comment.
+use std::io::{self, BufRead, Error, Write};
+use std::sync::Mutex;
+
+use crate::config::Verbosity;
+use crate::drmecref::{format_move, DrMecRef, Referee, RefereeMessage};
+use crate::othello::Color;
+use crate::othello::Move::Pass;
+use crate::othello::{Bitboard, Game, Move};
+use crate::GameInterface;
+
+/// The most moves a dump may be ahead of the position Herb knows.
+pub const MAX_MISSED_MOVES: usize = 8;
+
+/// Parses a row of a board dump, returning the black and white discs of the row with the
+/// first column in the lowest bit.
+fn parse_row(line: &str) -> Option<(u8, u8)> {
+    let squares: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    let squares = match squares.as_slice() {
+        [row, rest @ ..] if rest.len() == 8 && ('1'..='8').contains(row) => rest,
+        squares => squares,
+    };
+    if squares.len() != 8 {
+        return None;
+    }
+    let (mut black, mut white) = (0, 0);
+    for (col, square) in squares.iter().enumerate() {
+        match square {
+            'B' | 'X' | '*' => black |= 1 << col,
+            'W' | 'O' => white |= 1 << col,
+            '.' | '-' | '_' => {}
+            _ => return None,
+        }
+    }
+    Some((black, white))
+}
+
+/// Parses a board dump from its rows, see the [module](self) documentation.
+///
+/// Returns `None` unless there are exactly eight rows.
+pub fn parse_dump(rows: &[&str]) -> Option<Bitboard> {
+    if rows.len() != 8 {
+        return None;
+    }
+    let (mut black, mut white) = (0, 0);
+    for (row, line) in rows.iter().enumerate() {
+        let (row_black, row_white) = parse_row(line)?;
+        black |= (row_black as u64) << (row * 8);
+        white |= (row_white as u64) << (row * 8);
+    }
+    Some(Bitboard::from_discs(black, white))
+}
+
+/// A referee that sends board dumps instead of moves, see the [module](self) documentation.
+pub struct DumpReferee<R, W> {
+    color: Color,
+    verbosity: Verbosity,
+    state: Mutex<DumpState<R, W>>,
+}
+
+struct DumpState<R, W> {
+    input: R,
+    output: W,
+    /// The position as far as the moves are known.
+    game: Game,
+    /// The latest dump, while the position has not caught up with it.
+    target: Option<Bitboard>,
+}
+
+impl<R: BufRead, W: Write> DumpReferee<R, W> {
+    /// Create a referee that reads dumps from the input and writes Herb's moves to the
+    /// output. Such referees do not assign colors, so Herb plays the given one. Boards that
+    /// are skipped are commented on at [`Verbosity::Results`] until
+    /// [`DumpReferee::with_verbosity`] says otherwise.
+    pub fn new(color: Color, input: R, output: W) -> Self {
+        DumpReferee {
+            color,
+            verbosity: Verbosity::Results,
+            state: Mutex::new(DumpState {
+                input,
+                output,
+                game: Game::new(),
+                target: None,
+            }),
+        }
+    }
+
+    /// Comment as far as the verbosity allows, e.g. the one of the
+    /// [`Config`](crate::config::Config) Herb plays with.
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Returns the position as far as the moves are known.
+    pub fn game(&self) -> Game {
+        self.state.lock().unwrap().game
+    }
+
+    /// Returns the output, e.g. to see what was sent.
+    pub fn into_output(self) -> W {
+        self.state.into_inner().unwrap().output
+    }
+}
+
+impl<R: BufRead, W: Write> DumpState<R, W> {
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.output, "{}", line)?;
+        self.output.flush()
+    }
+
+    /// Read up to the next complete dump.
+    fn read_dump(&mut self) -> io::Result<Bitboard> {
+        let mut rows: Vec<String> = Vec::new();
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.input.read_line(&mut line)? == 0 {
+                return Err(Error::from(io::ErrorKind::UnexpectedEof));
+            }
+            if parse_row(&line).is_none() {
+                // rows are consecutive, anything else starts over
+                rows.clear();
+                continue;
+            }
+            rows.push(line.clone());
+            if rows.len() == 8 {
+                let rows: Vec<&str> = rows.iter().map(String::as_str).collect();
+                return Ok(parse_dump(&rows).expect("every row parsed"));
+            }
+        }
+    }
+
+    /// Returns the next move towards the latest dump, reading dumps until one is ahead and
+    /// commenting on the ones skipped as far as the verbosity allows.
+    fn next_move(&mut self, verbosity: Verbosity) -> io::Result<Move> {
+        loop {
+            let Some(target) = self.target else {
+                self.target = Some(self.read_dump()?);
+                continue;
+            };
+            match self.game.infer_moves(&target, MAX_MISSED_MOVES) {
+                Some(moves) if !moves.is_empty() => {
+                    // the moves after this one may be Herb's, which it plays itself
+                    let mv = moves[0];
+                    self.game.play_next_turn(mv).unwrap();
+                    if moves.len() == 1 {
+                        self.target = None;
+                    }
+                    return Ok(mv);
+                }
+                Some(_) => self.target = None,
+                None => {
+                    if verbosity.allows(Verbosity::Results) {
+                        DrMecRef::comment(
+                            "Herb: skipping a board that does not follow from the game",
+                        );
+                    }
+                    self.target = None;
+                }
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> Referee for DumpReferee<R, W> {
+    fn init(&self) -> io::Result<Color> {
+        Ok(self.color)
+    }
+
+    fn ready(&self, color: Color) {
+        let _ = self.state.lock().unwrap().send(&format!("R {}", color));
+    }
+
+    fn receive_message(&self) -> io::Result<RefereeMessage> {
+        let mut state = self.state.lock().unwrap();
+        let color = state.game.to_move();
+        Ok(match state.next_move(self.verbosity)? {
+            Pass => RefereeMessage::Pass(color),
+            mv => RefereeMessage::OpponentMove(color, mv),
+        })
+    }
+}
+
+impl<R: BufRead, W: Write> GameInterface for DumpReferee<R, W> {
+    fn send_move(&self, mv: Move, color: Color) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if let Err(e) = state.game.play_next_turn(mv) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, e.to_string()));
+        }
+        state.send(&format_move(mv, color))
+    }
+
+    fn receive_move(&self) -> io::Result<Move> {
+        let message = self.receive_message()?;
+        Ok(message.as_move().expect("dumps only give moves"))
+    }
+}
+
+/// Returns the board dump of a position in the format the module reads.
+pub fn format_dump(game: &Game) -> String {
+    let board = game.get_board();
+    let mut dump = String::from("  a b c d e f g h\n");
+    for row in 0..8 {
+        dump += &(row + 1).to_string();
+        for col in 0..8 {
+            let bit = 1u64 << (row * 8 + col);
+            dump += if board.get_black() & bit != 0 {
+                " B"
+            } else if board.get_white() & bit != 0 {
+                " W"
+            } else {
+                " ."
+            };
+        }
+        dump.push('\n');
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::play_referee_game;
+
+    /// Dumps of the position after each of the moves, skipping the given ones.
+    fn dumps(moves: &[Move], missed: &[usize]) -> String {
+        let mut game = Game::new();
+        let mut text = String::new();
+        for (i, mv) in moves.iter().enumerate() {
+            game.play_next_turn(*mv).unwrap();
+            if !missed.contains(&i) {
+                text += &format_dump(&game);
+                text += "\n";
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn test_parse_dump() {
+        let game = Game::new();
+        let dump = format_dump(&game);
+        let rows: Vec<&str> = dump.lines().skip(1).collect();
+        assert_eq!(parse_dump(&rows), Some(game.get_board()));
+        let other = [
+            "--------", "--------", "--------", "---OX---", "---XO---", "--------", "--------",
+            "--------",
+        ];
+        assert_eq!(parse_dump(&other), Some(game.get_board()));
+        assert_eq!(parse_dump(&other[1..]), None);
+        assert_eq!(parse_row("a b c d e f g h"), None);
+    }
+
+    #[test]
+    fn test_missed_updates() {
+        let mut game = Game::new();
+        let mut moves = Vec::new();
+        for _ in 0..6 {
+            let mv = game.greedy_move();
+            game.play_next_turn(mv).unwrap();
+            moves.push(mv);
+        }
+        // the referee echoes every move but two updates never arrive
+        let input = dumps(&moves, &[1, 2]);
+        let referee = DumpReferee::new(Color::White, input.as_bytes(), Vec::new());
+        for (i, mv) in moves.iter().enumerate() {
+            if i % 2 == 0 {
+                assert_eq!(referee.receive_move().unwrap(), *mv);
+            } else {
+                referee.send_move(*mv, Color::White).unwrap();
+            }
+        }
+        assert_eq!(referee.game(), game);
+        assert!(referee.receive_move().is_err());
+        let sent = String::from_utf8(referee.into_output()).unwrap();
+        assert_eq!(sent.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_play_from_dumps() {
+        let mut game = Game::new();
+        game.play_next_turn(game.greedy_move()).unwrap();
+        // a repeated dump changes nothing
+        let input = format_dump(&game).repeat(2);
+        let referee = DumpReferee::new(Color::White, input.as_bytes(), Vec::new());
+        let config = Config {
+            max_time: 2.0,
+            log: false,
+            warm_up: 0.0,
+            ..Config::default()
+        };
+        // the dumps run out while Herb waits for the opponent's second move
+        assert!(play_referee_game(config, &referee).is_err());
+        assert_eq!(referee.game().get_turn(), 2);
+        let sent = String::from_utf8(referee.into_output()).unwrap();
+        assert!(sent.starts_with("R W\nW "));
+    }
+}
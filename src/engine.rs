@@ -0,0 +1,478 @@
+//! Engines running in their own process.
+//!
+//! An [`EngineProcess`] spawns any program that speaks Dr. Cameron's referee protocol,
+//! such as the `herb` binary, and plays it as a [`Player`]. Running engines out of process
+//! keeps a crashing or runaway engine from taking down the program driving the game, and lets
+//! each engine be given its own [`ResourceLimits`].
+//!
+//! An engine is read on a background thread, so one that hangs can be noticed. With a
+//! [`Supervision`] an engine that gives no answer within what is left of its clock counts as
+//! hung, and a crashed or hung engine is either left to lose the game or restarted, as the
+//! [`FailurePolicy`] of the match says. A restarted `herb` can be given the game so far, see
+//! [`FailurePolicy::Resync`].
+use std::ffi::OsString;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tempfile::NamedTempFile;
+
+use crate::autosave::Autosave;
+use crate::drmecref::{format_move, parse_message, parse_move, RefereeMessage};
+use crate::othello::Color::{Black, White};
+use crate::othello::{Color, Game, Move};
+use crate::record::GameRecord;
+use crate::Player;
+
+/// Extra time an engine gets beyond what is left of its clock before it counts as hung,
+/// covering the overhead of starting it and passing moves around.
+const HANG_GRACE: Duration = Duration::from_secs(1);
+
+/// Operating system limits applied to an engine process.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceLimits {
+    /// Maximum CPU time in seconds, summed over all of the engine's threads.
+    pub cpu_seconds: Option<u64>,
+    /// Maximum size of the engine's address space in bytes.
+    pub memory_bytes: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Apply the limits to a command before it is spawned.
+    #[cfg(unix)]
+    fn apply(&self, command: &mut Command) {
+        use std::os::unix::process::CommandExt;
+
+        let limits = *self;
+        // SAFETY: setrlimit is async-signal-safe and only touches the child process.
+        unsafe {
+            command.pre_exec(move || {
+                if let Some(seconds) = limits.cpu_seconds {
+                    set_limit(libc::RLIMIT_CPU, seconds)?;
+                }
+                if let Some(bytes) = limits.memory_bytes {
+                    set_limit(libc::RLIMIT_AS, bytes)?;
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply(&self, _command: &mut Command) {}
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type Resource = libc::__rlimit_resource_t;
+#[cfg(all(unix, not(all(target_os = "linux", target_env = "gnu"))))]
+type Resource = libc::c_int;
+
+#[cfg(unix)]
+fn set_limit(resource: Resource, value: u64) -> io::Result<()> {
+    let limit = libc::rlimit {
+        rlim_cur: value as libc::rlim_t,
+        rlim_max: value as libc::rlim_t,
+    };
+    if unsafe { libc::setrlimit(resource, &limit) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// What the referee does when an engine crashes or hangs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FailurePolicy {
+    /// The engine loses the game, e.g. as a forfeit in a
+    /// [`Tournament`](crate::tournament::Tournament).
+    #[default]
+    Adjudicate,
+    /// Start the engine again, at most `max_restarts` times a game. A new process knows
+    /// nothing of the game, so this only saves engines that fail before their first move,
+    /// later failures are adjudicated.
+    Restart { max_restarts: u32 },
+    /// Start the engine again with the game so far, at most `max_restarts` times a game. The
+    /// moves and the engine's time are written to an [`Autosave`] file that is passed to the
+    /// new process as `--resume <file>`, which `herb` continues the game from.
+    Resync { max_restarts: u32 },
+}
+
+impl FailurePolicy {
+    /// Returns how many times an engine may be restarted in a game.
+    fn max_restarts(&self) -> u32 {
+        match *self {
+            FailurePolicy::Adjudicate => 0,
+            FailurePolicy::Restart { max_restarts } | FailurePolicy::Resync { max_restarts } => {
+                max_restarts
+            }
+        }
+    }
+}
+
+/// How an [`EngineProcess`] is watched over during a game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Supervision {
+    /// The engine's time for the game. An engine that takes longer than what is left of it,
+    /// plus a second of grace, to get ready or answer a move counts as hung. Without a clock
+    /// the engine is waited on for as long as it takes.
+    pub clock: Option<Duration>,
+    pub on_failure: FailurePolicy,
+}
+
+/// A [`Player`] backed by an engine running in a child process.
+///
+/// The engine is told its color on the first call to `get_next_move` and is sent each of
+/// the opponent's moves as they are played, so it must be used for a single game played
+/// from the starting position. An engine is `Send` but not `Sync`, it reads the engine's
+/// replies from a channel only one thread can receive on.
+pub struct EngineProcess {
+    program: OsString,
+    args: Vec<OsString>,
+    limits: ResourceLimits,
+    supervision: Supervision,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<io::Result<String>>,
+    color: Option<Color>,
+    assigned: Option<Color>,
+    /// Every move of the game so far, as far as the engine has seen it.
+    moves: Vec<Move>,
+    /// Time the engine took to answer its moves.
+    used: Duration,
+    restarts: u32,
+    /// The game handed to the last restarted process, kept until the engine is dropped.
+    resync: Option<NamedTempFile>,
+}
+
+impl EngineProcess {
+    /// Spawn the engine with the given arguments and resource limits.
+    pub fn spawn<I, S>(program: &str, args: I, limits: ResourceLimits) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        let program = OsString::from(program);
+        let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+        let (child, stdin, lines) = start(&program, &args, limits)?;
+        Ok(EngineProcess {
+            program,
+            args,
+            limits,
+            supervision: Supervision::default(),
+            child,
+            stdin,
+            lines,
+            color: None,
+            assigned: None,
+            moves: Vec::new(),
+            used: Duration::ZERO,
+            restarts: 0,
+            resync: None,
+        })
+    }
+
+    /// Watch over the engine as the supervision says, instead of waiting on it forever and
+    /// adjudicating any failure.
+    pub fn with_supervision(mut self, supervision: Supervision) -> Self {
+        self.supervision = supervision;
+        self
+    }
+
+    /// Returns the number of times the engine has been restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Read the next line from the engine that is not a comment, giving up once it has used
+    /// up its clock.
+    fn read_line(&mut self) -> io::Result<String> {
+        let deadline = self
+            .supervision
+            .clock
+            .map(|clock| Instant::now() + clock.saturating_sub(self.used) + HANG_GRACE);
+        loop {
+            let line = match deadline {
+                Some(deadline) => {
+                    let timeout = deadline.saturating_duration_since(Instant::now());
+                    match self.lines.recv_timeout(timeout) {
+                        Ok(line) => line?,
+                        Err(RecvTimeoutError::Timeout) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "Engine did not answer in time",
+                            ))
+                        }
+                        Err(RecvTimeoutError::Disconnected) => return Err(exited()),
+                    }
+                }
+                None => self.lines.recv().map_err(|_| exited())??,
+            };
+            if !line.starts_with('C') && !line.trim().is_empty() {
+                return Ok(line);
+            }
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{}", line)?;
+        self.stdin.flush()
+    }
+
+    /// Tell the engine its color and wait for it to report ready.
+    fn init(&mut self, color: Color) -> io::Result<()> {
+        self.send(&format!("I {}", color))?;
+        let reply = self.read_line()?;
+        if !matches!(parse_message(&reply), Ok(RefereeMessage::Ready(_))) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Engine did not report ready: {}", reply.trim()),
+            ));
+        }
+        self.color = Some(color);
+        Ok(())
+    }
+
+    /// Forward the opponent's last move and read the engine's reply.
+    fn exchange(&mut self, game: Game) -> io::Result<Move> {
+        if let Some(last_move) = game.last_move() {
+            if self.moves.len() < game.get_turn() {
+                self.moves.push(last_move);
+            }
+        }
+        let start = Instant::now();
+        let mut reply = self.ask(game);
+        while let Err(e) = &reply {
+            if !self.restart(game)? {
+                return Err(io::Error::new(e.kind(), e.to_string()));
+            }
+            reply = self.read_line().and_then(|line| parse_move(&line));
+        }
+        self.used += start.elapsed();
+        let mv = reply?;
+        self.moves.push(mv);
+        Ok(mv)
+    }
+
+    /// Tell a new engine its color, forward the opponent's last move and read the reply.
+    fn ask(&mut self, game: Game) -> io::Result<Move> {
+        if self.color.is_none() {
+            self.init(self.assigned.unwrap_or(game.to_move()))?;
+        }
+        if let Some(last_move) = game.last_move() {
+            self.send(&format_move(last_move, game.to_move().opponent()))?;
+        }
+        parse_move(&self.read_line()?)
+    }
+
+    /// Replace a failed engine with a new process that is ready to answer the move of the
+    /// given game, if the [`FailurePolicy`] allows it.
+    ///
+    /// Returns `Ok(false)` if the failure is to be adjudicated instead.
+    fn restart(&mut self, game: Game) -> io::Result<bool> {
+        if self.restarts >= self.supervision.on_failure.max_restarts()
+            || self.moves.len() != game.get_turn()
+        {
+            return Ok(false);
+        }
+        let color = self.assigned.unwrap_or(game.to_move());
+        let mut args = self.args.clone();
+        match self.supervision.on_failure {
+            FailurePolicy::Resync { .. } => {
+                let file = self.write_resync(color)?;
+                args.push("--resume".into());
+                args.push(file.path().into());
+                self.resync = Some(file);
+            }
+            // the new process starts from the initial position, so it must not have moved yet
+            _ if game.get_turn() > 1 => return Ok(false),
+            _ => {}
+        }
+
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let (child, stdin, lines) = start(&self.program, &args, self.limits)?;
+        self.child = child;
+        self.stdin = stdin;
+        self.lines = lines;
+        self.color = None;
+        self.restarts += 1;
+        self.init(color)?;
+        if !matches!(self.supervision.on_failure, FailurePolicy::Resync { .. }) {
+            if let Some(last_move) = game.last_move() {
+                self.send(&format_move(last_move, game.to_move().opponent()))?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Write the game so far for a restarted engine playing `color`.
+    fn write_resync(&self, color: Color) -> io::Result<NamedTempFile> {
+        let used = self.used.as_secs_f64();
+        let saved = Autosave {
+            color,
+            record: GameRecord::from_moves(&self.moves),
+            black_time: if color == Black { used } else { 0.0 },
+            white_time: if color == White { used } else { 0.0 },
+            // the engine's configuration is not known here
+            config_digest: String::new(),
+        };
+        let file = NamedTempFile::new()?;
+        saved.save(file.path())?;
+        Ok(file)
+    }
+}
+
+/// Spawn the engine and start reading its output in the background.
+fn start(
+    program: &OsString,
+    args: &[OsString],
+    limits: ResourceLimits,
+) -> io::Result<(Child, ChildStdin, Receiver<io::Result<String>>)> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    limits.apply(&mut command);
+
+    let mut child = command.spawn()?;
+    let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    let stdout = child.stdout.take().ok_or(io::ErrorKind::BrokenPipe)?;
+    let (sender, lines) = channel();
+    // stops when the engine exits or is killed, or nobody is listening any more
+    thread::spawn(move || {
+        let mut stdout = BufReader::new(stdout);
+        loop {
+            let mut line = String::new();
+            let read = stdout.read_line(&mut line);
+            let done = !matches!(read, Ok(n) if n > 0);
+            let line = match read {
+                Ok(0) => Err(exited()),
+                read => read.map(|_| line),
+            };
+            if sender.send(line).is_err() || done {
+                break;
+            }
+        }
+    });
+    Ok((child, stdin, lines))
+}
+
+fn exited() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Engine exited")
+}
+
+impl Player for EngineProcess {
+    /// Get the engine's next move, restarting the engine if it fails and its [`Supervision`]
+    /// allows it.
+    ///
+    /// # Panics
+    /// Panics if the engine exits, hangs or answers with something that is not a move, and is
+    /// not restarted. Callers that need to survive a broken engine should catch the panic,
+    /// the [`Tournament`](crate::tournament::Tournament) runner counts it as a forfeit.
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        match self.exchange(game_state) {
+            Ok(mv) => mv,
+            Err(e) => panic!("Engine failed: {}", e),
+        }
+    }
+
+    /// The engine is told its color when it is first asked for a move, which is the assigned
+    /// color if there is one.
+    fn new_game(&mut self, color: Color) {
+        self.assigned = Some(color);
+    }
+
+    fn color(&self) -> Option<Color> {
+        self.color.or(self.assigned)
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::record::parse_move as parse_algebraic;
+
+    /// An engine running the shell script, with its arguments from `$1` on.
+    fn script(script: &str, on_failure: FailurePolicy) -> EngineProcess {
+        EngineProcess::spawn("sh", ["-c", script, "engine"], ResourceLimits::default())
+            .unwrap()
+            .with_supervision(Supervision {
+                clock: Some(Duration::from_millis(200)),
+                on_failure,
+            })
+    }
+
+    fn fails(engine: &mut EngineProcess, game: Game) -> bool {
+        panic::catch_unwind(AssertUnwindSafe(|| engine.get_next_move(game))).is_err()
+    }
+
+    #[test]
+    fn test_hung_engine_is_adjudicated() {
+        let start = Instant::now();
+        let mut engine = script("read l; echo R B; sleep 30", FailurePolicy::Adjudicate);
+        assert!(fails(&mut engine, Game::new()));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(engine.restarts(), 0);
+    }
+
+    #[test]
+    fn test_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("started");
+        // crashes the first time it is started
+        let crashes_once = format!(
+            "if [ -e {0} ]; then read l; echo R B; echo B d 3; sleep 5; else touch {0}; fi",
+            marker.display()
+        );
+        let mut engine = script(&crashes_once, FailurePolicy::Restart { max_restarts: 1 });
+        assert_eq!(
+            engine.get_next_move(Game::new()),
+            parse_algebraic("d3").unwrap()
+        );
+        assert_eq!(engine.restarts(), 1);
+
+        std::fs::remove_file(&marker).unwrap();
+        let mut engine = script(&crashes_once, FailurePolicy::Adjudicate);
+        assert!(fails(&mut engine, Game::new()));
+    }
+
+    #[test]
+    fn test_resync() {
+        // answers its first move, then crashes, unless it is resuming a game
+        let engine = "if [ \"$1\" = --resume ] && [ -s \"$2\" ]; then read l; echo R B; \
+                      echo B c 4; sleep 5; else read l; echo R B; echo B d 3; read l; fi";
+        let mut game = Game::new();
+        let mut resynced = script(engine, FailurePolicy::Resync { max_restarts: 1 });
+        let mut restarted = script(engine, FailurePolicy::Restart { max_restarts: 1 });
+        let d3 = resynced.get_next_move(game);
+        assert_eq!(restarted.get_next_move(game), d3);
+        game.play_next_turn(d3).unwrap();
+        let reply = game.legal_moves()[0];
+        game.play_next_turn(reply).unwrap();
+
+        assert_eq!(resynced.get_next_move(game), parse_algebraic("c4").unwrap());
+        assert_eq!(resynced.restarts(), 1);
+        let saved = Autosave::load(resynced.resync.as_ref().unwrap().path()).unwrap();
+        assert_eq!(saved.moves(), [d3, reply]);
+        assert_eq!(saved.color, Black);
+
+        // a new process without the game can not take over after the first move
+        assert!(fails(&mut restarted, game));
+        assert_eq!(restarted.restarts(), 0);
+    }
+}
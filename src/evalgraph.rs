@@ -0,0 +1,172 @@
+//! Move-by-move evaluation of finished games, for evaluation graphs.
+//!
+//! An [`EvalSeries`] lists every ply of a [`GameRecord`] with the evaluation the mover's
+//! search gave it during the game, if the record has one, and the disc counts after it.
+//! [`EvalSeries::reanalyze`] adds a second, uniform evaluation: every position of the game is
+//! searched again with the same budget and black's win rate recorded, so both sides and every
+//! phase are measured with the same yardstick however the game was played. The series is
+//! written as CSV or json for plotting tools to draw the classic evaluation graph:
+//! ```text
+//! ply,color,move,clock,eval,black_discs,white_discs,black_win_rate
+//! 1,B,d3,118.2,12.41,4,1,0.3312
+//! 2,W,c5,,,3,3,0.6521
+//! ```
+//! Missing values are left empty. Each position is searched from the side to move, whose
+//! prospects Herb's search tends to flatter, so the win rate swings a little with the turn and
+//! the trend reads best across pairs of plies.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde::Serialize;
+
+use crate::analysis::analyze_line;
+use crate::config::MctsConfig;
+use crate::othello::{Color, Game, GameError, Move};
+use crate::record::{format_move, GameRecord};
+
+/// One ply of an [`EvalSeries`].
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EvalPoint {
+    /// Plies played including this one, passes included.
+    pub ply: usize,
+    /// The color that played the move.
+    pub color: Color,
+    /// The move in algebraic notation.
+    #[serde(rename = "move")]
+    pub mv: String,
+    /// Seconds left on the mover's clock after the move, if recorded.
+    pub clock: Option<f64>,
+    /// The mover's evaluation of the move during the game, if recorded.
+    pub eval: Option<f64>,
+    pub black_discs: u32,
+    pub white_discs: u32,
+    /// Black's win rate after the move in the uniform re-analysis, if it was made.
+    pub black_win_rate: Option<f64>,
+}
+
+/// The evaluation of a game ply by ply, see the [module](self) documentation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EvalSeries {
+    /// The tags of the game, such as the players.
+    pub tags: BTreeMap<String, String>,
+    pub points: Vec<EvalPoint>,
+    /// Search iterations spent on each position in the re-analysis, if it was made.
+    pub reanalysis_iterations: Option<u64>,
+    #[serde(skip)]
+    moves: Vec<Move>,
+}
+
+impl EvalSeries {
+    /// Create the series of a game from its record, with the evaluations made during the
+    /// game.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn from_record(record: &GameRecord) -> Result<Self, GameError> {
+        let mut game = Game::new();
+        let mut points = Vec::with_capacity(record.moves.len());
+        for (i, recorded) in record.moves.iter().enumerate() {
+            let color = game.to_move();
+            game.play_next_turn(recorded.mv)?;
+            let (black_discs, white_discs) = game.disc_counts();
+            points.push(EvalPoint {
+                ply: i + 1,
+                color,
+                mv: format_move(recorded.mv),
+                clock: recorded.clock,
+                eval: recorded.eval,
+                black_discs,
+                white_discs,
+                black_win_rate: None,
+            });
+        }
+        Ok(EvalSeries {
+            tags: record.tags.clone(),
+            points,
+            reanalysis_iterations: None,
+            moves: record.moves.iter().map(|recorded| recorded.mv).collect(),
+        })
+    }
+
+    /// Search the position after every ply for `budget` iterations and record black's win
+    /// rate in it. The searches are deterministic, so the same budget always gives the same
+    /// series.
+    pub fn reanalyze(&mut self, config: &MctsConfig, budget: u64) {
+        for (i, point) in self.points.iter_mut().enumerate() {
+            // the moves were replayed when the series was created
+            let analysis = analyze_line(Game::new(), &self.moves[..=i], config, budget)
+                .expect("the moves are legal");
+            point.black_win_rate = Some(analysis.win_rate);
+        }
+        self.reanalysis_iterations = Some(budget);
+    }
+
+    /// Returns the series as CSV, a header row and one row per ply with missing values left
+    /// empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv =
+            "ply,color,move,clock,eval,black_discs,white_discs,black_win_rate\n".to_string();
+        let optional = |value: Option<f64>, precision: usize| {
+            value.map_or(String::new(), |value| format!("{:.*}", precision, value))
+        };
+        for point in &self.points {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                point.ply,
+                point.color,
+                point.mv,
+                optional(point.clock, 1),
+                optional(point.eval, 2),
+                point.black_discs,
+                point.white_discs,
+                optional(point.black_win_rate, 4),
+            );
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+    use crate::othello::Color::{Black, White};
+    use crate::tournament::play_game;
+
+    #[test]
+    fn test_eval_series() {
+        let result = play_game(&mut Opponent::Greedy, &mut Opponent::Greedy).unwrap();
+        let mut record = GameRecord::from_moves(&result.moves);
+        record.moves[0].eval = Some(12.41);
+        record.moves[1].clock = Some(59.5);
+
+        let mut series = EvalSeries::from_record(&record).unwrap();
+        assert_eq!(series.points.len(), result.moves.len());
+        assert_eq!(series.points[0].color, Black);
+        assert_eq!(series.points[1].color, White);
+        assert_eq!(series.points[0].black_discs, 4);
+        assert_eq!(series.points[0].white_discs, 1);
+        let csv = series.to_csv();
+        assert_eq!(csv.lines().count(), result.moves.len() + 1);
+        let mut rows = csv.lines().skip(1);
+        assert_eq!(
+            rows.next().unwrap(),
+            format!("1,B,{},,12.41,4,1,", series.points[0].mv)
+        );
+        assert!(rows.next().unwrap().contains(",59.5,,"));
+
+        series.points.truncate(4);
+        series.reanalyze(&MctsConfig::default(), 50);
+        assert_eq!(series.reanalysis_iterations, Some(50));
+        assert!(series.points.iter().all(|point| point
+            .black_win_rate
+            .is_some_and(|rate| (0.0..=1.0).contains(&rate))));
+        let json = serde_json::to_value(&series).unwrap();
+        assert_eq!(json["points"][0]["move"], series.points[0].mv);
+        assert_eq!(json["points"][0]["color"], "Black");
+
+        let mut illegal = record.clone();
+        illegal.moves.swap(0, 1);
+        assert!(EvalSeries::from_record(&illegal).is_err());
+    }
+}
@@ -0,0 +1,486 @@
+//! Static evaluations of positions and a report of which of their features matter.
+//!
+//! An [`Evaluator`] scores the position after a move for the player who made it. Playing the
+//! best scoring move gives a cheap player whose moves can be compared against deep searches.
+//! There are two: an [`Evaluation`] is a weighted sum of [`Feature`]s, each the difference
+//! between the mover and the opponent, and a [`WeightTable`] is the classic table of a weight
+//! per square, summed over the mover's discs less the opponent's. The table is cheap enough
+//! to score the positions where playouts are cut off, see `playout_cutoff` in the
+//! [`MctsConfig`](crate::config::MctsConfig), and can be loaded from the config as 8 rows of
+//! 8 weights:
+//! ```json
+//! "weight_table": [
+//!     [100, -20, 10, 5, 5, 10, -20, 100],
+//!     [-20, -50, -2, -2, -2, -2, -50, -20],
+//!     [10, -2, -1, -1, -1, -1, -2, 10],
+//!     [5, -2, -1, -1, -1, -1, -2, 5],
+//!     [5, -2, -1, -1, -1, -1, -2, 5],
+//!     [10, -2, -1, -1, -1, -1, -2, 10],
+//!     [-20, -50, -2, -2, -2, -2, -50, -20],
+//!     [100, -20, 10, 5, 5, 10, -20, 100]
+//! ]
+//! ```
+//!
+//! [`feature_importance`] perturbs one weight at a time, dropping it, lowering it and raising
+//! it, and measures how the agreement with reference moves from a deep search changes across
+//! a set of positions. A feature whose weight can be changed without changing any move is not
+//! pulling its weight, while a large drop in agreement shows a feature worth tuning further.
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::{Deserialize, Serialize};
+
+use crate::corpus::CORPUS;
+use crate::othello::bits::neighbours;
+use crate::othello::edges::{Edge, EdgeClass};
+use crate::othello::Move::Pass;
+use crate::othello::{BoardSize, Color, Game, Move, Variant};
+use crate::Player;
+
+/// The inner 4x4 squares, of the 6x6 board as well.
+const CENTER: u64 = 0x0000_3C3C_3C3C_0000;
+
+/// Scores positions for the player who just moved.
+pub trait Evaluator {
+    /// Returns the score of the position after a move by `mover`, higher is better for them.
+    fn evaluate(&self, after: &Game, mover: Color) -> f64;
+
+    /// Returns the first legal move with the best score, or [`Pass`] when there are none.
+    fn choose(&self, game: &Game) -> Move {
+        let mut best_move = Pass;
+        let mut best_score = f64::NEG_INFINITY;
+        for mv in game.legal_moves() {
+            let mut after = *game;
+            after.play_next_turn(mv).unwrap();
+            let score = self.evaluate(&after, game.to_move());
+            if score > best_score {
+                best_score = score;
+                best_move = mv;
+            }
+        }
+        best_move
+    }
+}
+
+/// A property of a position, measured for the player who just moved against their opponent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Feature {
+    Discs,
+    /// Minus the number of legal moves left to the opponent.
+    Mobility,
+    Corners,
+    XSquares,
+    CSquares,
+    Edges,
+    Center,
+    /// Discs next to an empty square, which tend to give the opponent moves.
+    Frontier,
+    /// 1 if the mover gets the last move of the game, -1 otherwise.
+    Parity,
+    /// Edges where the mover's discs are safe less those where they are poisoned, see
+    /// [`edges`](crate::othello::edges). Only edges with discs of the player count.
+    SafeEdges,
+}
+
+impl Feature {
+    /// Every feature, in the order of the weights of an [`Evaluation`].
+    pub const ALL: [Feature; 10] = [
+        Feature::Discs,
+        Feature::Mobility,
+        Feature::Corners,
+        Feature::XSquares,
+        Feature::CSquares,
+        Feature::Edges,
+        Feature::Center,
+        Feature::Frontier,
+        Feature::Parity,
+        Feature::SafeEdges,
+    ];
+
+    /// Returns the value of the feature in the position after a move by `mover`. In
+    /// [`Variant::Misere`] the features that count discs change sign, the discs the mover
+    /// holds are the ones it wants to be rid of, mobility and parity do not.
+    pub fn value(&self, after: &Game, mover: Color) -> f64 {
+        let board = after.get_board();
+        let (own, opponent) = match mover {
+            Color::Black => (board.get_black(), board.get_white()),
+            Color::White => (board.get_white(), board.get_black()),
+        };
+        let sign = match after.variant() {
+            Variant::Standard => 1.0,
+            Variant::Misere => -1.0,
+        };
+        let difference = |mask: u64| {
+            sign * ((own & mask).count_ones() as f64 - (opponent & mask).count_ones() as f64)
+        };
+        let size = after.size();
+        match self {
+            Feature::Discs => difference(!0),
+            Feature::Mobility => -(after.mobility() as f64),
+            Feature::Corners => difference(size.corners()),
+            Feature::XSquares => difference(size.x_squares()),
+            Feature::CSquares => difference(size.c_squares()),
+            Feature::Edges => difference(size.edges() & !size.corners() & !size.c_squares()),
+            Feature::Center => difference(CENTER),
+            Feature::Frontier => difference(neighbours(!(own | opponent))),
+            Feature::Parity => {
+                if after.empty_squares().is_multiple_of(2) {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Feature::SafeEdges => sign * (safe_edges(own, opponent) - safe_edges(opponent, own)),
+        }
+    }
+}
+
+/// Returns the edges where the discs of the player with the `own` discs are safe, less those
+/// where they are poisoned.
+fn safe_edges(own: u64, opponent: u64) -> f64 {
+    Edge::ALL
+        .iter()
+        .filter(|edge| edge.squares().iter().any(|&square| own >> square & 1 == 1))
+        .map(|edge| match edge.classify(own, opponent) {
+            EdgeClass::Safe => 1.0,
+            EdgeClass::Unsettled => 0.0,
+            EdgeClass::Poisoned => -1.0,
+        })
+        .sum()
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Feature::Discs => "discs",
+            Feature::Mobility => "mobility",
+            Feature::Corners => "corners",
+            Feature::XSquares => "x-squares",
+            Feature::CSquares => "c-squares",
+            Feature::Edges => "edges",
+            Feature::Center => "center",
+            Feature::Frontier => "frontier",
+            Feature::Parity => "parity",
+            Feature::SafeEdges => "safe edges",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A weighted sum of [`Feature`]s.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Evaluation {
+    weights: [f64; Feature::ALL.len()],
+}
+
+impl Default for Evaluation {
+    fn default() -> Self {
+        Evaluation {
+            weights: [1.0, 8.0, 30.0, -15.0, -5.0, 2.0, 1.0, -3.0, 2.0, 6.0],
+        }
+    }
+}
+
+impl Evaluation {
+    /// Returns the weight of the feature.
+    pub fn weight(&self, feature: Feature) -> f64 {
+        self.weights[feature as usize]
+    }
+
+    /// Returns this evaluation with the weight of the feature replaced.
+    pub fn with_weight(mut self, feature: Feature, weight: f64) -> Self {
+        self.weights[feature as usize] = weight;
+        self
+    }
+}
+
+impl Evaluator for Evaluation {
+    fn evaluate(&self, after: &Game, mover: Color) -> f64 {
+        Feature::ALL
+            .iter()
+            .map(|feature| self.weight(*feature) * feature.value(after, mover))
+            .sum()
+    }
+}
+
+impl Player for Evaluation {
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        self.choose(&game_state)
+    }
+}
+
+/// A weight for every square, summed over the discs of a player.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct WeightTable {
+    /// The weights by row then column, the first row is row 1.
+    pub weights: [[i32; 8]; 8],
+}
+
+impl WeightTable {
+    /// The classic table: corners are worth the most, the squares diagonally next to them
+    /// give them away and cost the most.
+    pub const CLASSIC: WeightTable = WeightTable {
+        weights: [
+            [100, -20, 10, 5, 5, 10, -20, 100],
+            [-20, -50, -2, -2, -2, -2, -50, -20],
+            [10, -2, -1, -1, -1, -1, -2, 10],
+            [5, -2, -1, -1, -1, -1, -2, 5],
+            [5, -2, -1, -1, -1, -1, -2, 5],
+            [10, -2, -1, -1, -1, -1, -2, 10],
+            [-20, -50, -2, -2, -2, -2, -50, -20],
+            [100, -20, 10, 5, 5, 10, -20, 100],
+        ],
+    };
+
+    /// Returns the summed weights of the squares of the bitboard.
+    pub fn score(&self, discs: u64) -> i32 {
+        self.score_on(discs, BoardSize::Eight)
+    }
+
+    /// Returns the summed weights of the squares of the bitboard on a board of the given size.
+    /// A smaller board is weighted by the rows and columns of the table nearest to its edges,
+    /// so its corners get the weights of the corners.
+    pub fn score_on(&self, mut discs: u64, size: BoardSize) -> i32 {
+        let side = size.side();
+        let offset = (8 - side) / 2;
+        // the index into the table of a row or column of the board
+        let index = |line: usize| {
+            let line = line - offset;
+            if line < side / 2 {
+                line
+            } else {
+                line + 8 - side
+            }
+        };
+        let mut score = 0;
+        discs &= size.squares();
+        while discs != 0 {
+            let square = discs.trailing_zeros() as usize;
+            score += self.weights[index(square / 8)][index(square % 8)];
+            discs &= discs - 1;
+        }
+        score
+    }
+
+    /// Returns the color ahead on the table, `None` if both score the same.
+    pub fn leader(&self, game: &Game) -> Option<Color> {
+        let board = game.get_board();
+        let score = |discs| self.score_on(discs, game.size());
+        match score(board.get_black()).cmp(&score(board.get_white())) {
+            Ordering::Greater => Some(Color::Black),
+            Ordering::Less => Some(Color::White),
+            Ordering::Equal => None,
+        }
+    }
+}
+
+impl Default for WeightTable {
+    fn default() -> Self {
+        WeightTable::CLASSIC
+    }
+}
+
+impl Evaluator for WeightTable {
+    fn evaluate(&self, after: &Game, mover: Color) -> f64 {
+        let board = after.get_board();
+        let (own, opponent) = match mover {
+            Color::Black => (board.get_black(), board.get_white()),
+            Color::White => (board.get_white(), board.get_black()),
+        };
+        let score =
+            (self.score_on(own, after.size()) - self.score_on(opponent, after.size())) as f64;
+        match after.variant() {
+            Variant::Standard => score,
+            Variant::Misere => -score,
+        }
+    }
+}
+
+impl Player for WeightTable {
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        self.choose(&game_state)
+    }
+}
+
+/// Returns every position with a choice of moves along the lines of the benchmark
+/// [`CORPUS`], without duplicates.
+pub fn corpus_positions() -> Vec<Game> {
+    let mut positions: Vec<Game> = Vec::new();
+    for position in &CORPUS {
+        let mut game = Game::new();
+        for mv in position.moves() {
+            if game.legal_moves().len() > 1 && !positions.contains(&game) {
+                positions.push(game);
+            }
+            game.play_next_turn(mv).expect("corpus moves are legal");
+        }
+        if game.legal_moves().len() > 1 && !positions.contains(&game) {
+            positions.push(game);
+        }
+    }
+    positions
+}
+
+/// Returns the fraction of the positions in which the evaluation plays the reference move.
+pub fn agreement(evaluation: &Evaluation, references: &[(Game, Move)]) -> f64 {
+    if references.is_empty() {
+        return 0.0;
+    }
+    let agreed = references
+        .iter()
+        .filter(|(game, mv)| evaluation.choose(game) == *mv)
+        .count();
+    agreed as f64 / references.len() as f64
+}
+
+/// How the agreement with the reference moves changes when one weight is perturbed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FeatureImportance {
+    pub feature: Feature,
+    pub weight: f64,
+    /// Agreement with the weight set to zero.
+    pub dropped: f64,
+    /// Agreement with the weight scaled down by the perturbation.
+    pub lowered: f64,
+    /// Agreement with the weight scaled up by the perturbation.
+    pub raised: f64,
+}
+
+impl FeatureImportance {
+    /// Returns the largest loss of agreement from any of the perturbations, compared to the
+    /// baseline agreement of the unperturbed evaluation. Negative if every perturbation
+    /// improved the agreement.
+    pub fn impact(&self, baseline: f64) -> f64 {
+        baseline - self.dropped.min(self.lowered).min(self.raised)
+    }
+}
+
+/// Returns the agreement of the evaluation with the reference moves and the importance of
+/// each of its features, most important first.
+///
+/// Each weight is in turn dropped and scaled by `1 - perturbation` and `1 + perturbation`
+/// while the others stay as they are.
+pub fn feature_importance(
+    evaluation: &Evaluation,
+    references: &[(Game, Move)],
+    perturbation: f64,
+) -> (f64, Vec<FeatureImportance>) {
+    let baseline = agreement(evaluation, references);
+    let mut importance: Vec<_> = Feature::ALL
+        .iter()
+        .map(|&feature| {
+            let weight = evaluation.weight(feature);
+            let with = |weight| agreement(&evaluation.with_weight(feature, weight), references);
+            FeatureImportance {
+                feature,
+                weight,
+                dropped: with(0.0),
+                lowered: with(weight * (1.0 - perturbation)),
+                raised: with(weight * (1.0 + perturbation)),
+            }
+        })
+        .collect();
+    importance.sort_by(|a, b| b.impact(baseline).total_cmp(&a.impact(baseline)));
+    (baseline, importance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+
+    #[test]
+    fn test_features() {
+        let game = Game::new();
+        let d3 = game.legal_moves()[0];
+        let mut after = game;
+        after.play_next_turn(d3).unwrap();
+        assert_eq!(Feature::Discs.value(&after, Color::Black), 3.0);
+        assert_eq!(Feature::Discs.value(&after, Color::White), -3.0);
+        assert_eq!(Feature::Mobility.value(&after, Color::Black), -3.0);
+        assert_eq!(Feature::Center.value(&after, Color::Black), 3.0);
+        assert_eq!(Feature::Corners.value(&after, Color::Black), 0.0);
+        // 59 empty squares, so white gets the last move
+        assert_eq!(Feature::Parity.value(&after, Color::Black), -1.0);
+        // every disc still touches an empty square
+        assert_eq!(Feature::Frontier.value(&after, Color::Black), 3.0);
+        let size = BoardSize::Eight;
+        assert_eq!(
+            (size.edges() & !size.corners() & !size.c_squares()).count_ones(),
+            16
+        );
+        assert_eq!(Feature::SafeEdges.value(&after, Color::Black), 0.0);
+
+        // black's a1 to c1 are safe, white's lone f1 can be flipped from e1 and g1
+        let game: Game = "BBB2W2/8/8/3WB3/3BW3/8/8/8 W 10 -".parse().unwrap();
+        assert_eq!(Feature::SafeEdges.value(&game, Color::Black), 2.0);
+        assert_eq!(Feature::SafeEdges.value(&game, Color::White), -2.0);
+
+        // in misère discs count against their owner, the moves left to the opponent do not
+        let mut misere = after;
+        misere.set_variant(Variant::Misere);
+        assert_eq!(Feature::Discs.value(&misere, Color::Black), -3.0);
+        assert_eq!(Feature::Center.value(&misere, Color::Black), -3.0);
+        assert_eq!(Feature::Mobility.value(&misere, Color::Black), -3.0);
+        assert_eq!(
+            WeightTable::CLASSIC.evaluate(&misere, Color::Black),
+            -WeightTable::CLASSIC.evaluate(&after, Color::Black)
+        );
+    }
+
+    #[test]
+    fn test_feature_importance() {
+        // reference moves from a player that only counts discs
+        let references: Vec<_> = corpus_positions()
+            .into_iter()
+            .map(|game| (game, Opponent::Greedy.choose(&game)))
+            .collect();
+        assert!(references.len() > 20);
+
+        let greedy = Feature::ALL
+            .iter()
+            .fold(Evaluation::default(), |evaluation, &feature| {
+                evaluation.with_weight(feature, 0.0)
+            })
+            .with_weight(Feature::Discs, 1.0);
+        let (baseline, importance) = feature_importance(&greedy, &references, 0.5);
+        assert_eq!(baseline, 1.0);
+        assert_eq!(importance[0].feature, Feature::Discs);
+        assert!(importance[0].dropped < 1.0);
+        assert!(importance[1..].iter().all(|i| i.impact(baseline) == 0.0));
+    }
+
+    #[test]
+    fn test_weight_table() {
+        let table = WeightTable::CLASSIC;
+        let game = Game::new();
+        assert_eq!(table.score(game.get_board().get_black()), -2);
+        assert_eq!(table.leader(&game), None);
+        assert_eq!(table.score(BoardSize::Eight.corners()), 400);
+        assert_eq!(table.score(BoardSize::Eight.x_squares()), -200);
+        // the corners of the small board are worth as much
+        let six = BoardSize::Six;
+        assert_eq!(table.score_on(six.corners(), six), 400);
+        assert_eq!(table.score_on(six.x_squares(), six), -200);
+        assert_eq!(table.score_on(six.c_squares(), six), -160);
+
+        let d3 = game.legal_moves()[0];
+        let mut after = game;
+        after.play_next_turn(d3).unwrap();
+        let score = table.evaluate(&after, Color::Black);
+        assert_eq!(score, -table.evaluate(&after, Color::White));
+        assert_eq!(score, -4.0 + 1.0);
+
+        // the table is read from the config as rows of weights
+        let json = serde_json::to_string(&table).unwrap();
+        assert!(json.starts_with("[[100,-20,"));
+        let corners_only: WeightTable = serde_json::from_str(&format!(
+            "[[1,0,0,0,0,0,0,1],{}[1,0,0,0,0,0,0,1]]",
+            "[0,0,0,0,0,0,0,0],".repeat(6)
+        ))
+        .unwrap();
+        assert_eq!(corners_only.score(!0), 4);
+        assert_eq!(corners_only.leader(&after), None);
+    }
+}
@@ -0,0 +1,128 @@
+//! Hooks into the turns of a game played by [`Herb`](crate::Herb).
+//!
+//! Anything that wants to follow a game, e.g. telemetry, a recorder or a GUI, implements
+//! [`Listener`] and subscribes with [`Herb::subscribe`](crate::Herb::subscribe). Herb
+//! reports its own turns, the game loop reports the moves it sends and receives and the end of
+//! the game.
+use crate::othello::{Game, Move};
+
+/// Receives the events of a game. Every method does nothing by default, so a listener only
+/// implements the events it cares about.
+pub trait Listener: Send {
+    /// Herb is about to search the given position.
+    fn on_turn_start(&mut self, _game: &Game) {}
+
+    /// Herb finished searching the given position and chose `mv` after `iterations` search
+    /// iterations.
+    fn on_search_complete(&mut self, _game: &Game, _mv: Move, _iterations: u64) {}
+
+    /// Herb's move was sent from the given position.
+    fn on_move_sent(&mut self, _game: &Game, _mv: Move) {}
+
+    /// The opponent played a move from the given position.
+    fn on_opponent_move(&mut self, _game: &Game, _mv: Move) {}
+
+    /// The game ended in the given position.
+    fn on_game_end(&mut self, _game: &Game) {}
+}
+
+/// The listeners subscribed to a game, in the order they subscribed.
+#[derive(Default)]
+pub struct Events {
+    listeners: Vec<Box<dyn Listener>>,
+}
+
+impl Events {
+    /// Add a listener that receives every following event.
+    pub fn subscribe(&mut self, listener: Box<dyn Listener>) {
+        self.listeners.push(listener);
+    }
+
+    /// Returns the number of subscribed listeners.
+    pub fn len(&self) -> usize {
+        self.listeners.len()
+    }
+
+    /// Returns true if nothing is listening.
+    pub fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    pub fn turn_start(&mut self, game: &Game) {
+        self.listeners
+            .iter_mut()
+            .for_each(|listener| listener.on_turn_start(game));
+    }
+
+    pub fn search_complete(&mut self, game: &Game, mv: Move, iterations: u64) {
+        self.listeners
+            .iter_mut()
+            .for_each(|listener| listener.on_search_complete(game, mv, iterations));
+    }
+
+    pub fn move_sent(&mut self, game: &Game, mv: Move) {
+        self.listeners
+            .iter_mut()
+            .for_each(|listener| listener.on_move_sent(game, mv));
+    }
+
+    pub fn opponent_move(&mut self, game: &Game, mv: Move) {
+        self.listeners
+            .iter_mut()
+            .for_each(|listener| listener.on_opponent_move(game, mv));
+    }
+
+    pub fn game_end(&mut self, game: &Game) {
+        self.listeners
+            .iter_mut()
+            .for_each(|listener| listener.on_game_end(game));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    struct Log(Arc<Mutex<Vec<String>>>);
+
+    impl Listener for Log {
+        fn on_turn_start(&mut self, game: &Game) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("start {}", game.get_turn()));
+        }
+
+        fn on_move_sent(&mut self, _game: &Game, mv: Move) {
+            self.0.lock().unwrap().push(format!("sent {}", mv));
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut events = Events::default();
+        assert!(events.is_empty());
+        events.subscribe(Box::new(Log(log.clone())));
+        events.subscribe(Box::new(Log(log.clone())));
+        assert_eq!(events.len(), 2);
+
+        let game = Game::new();
+        let d3 = game.legal_moves()[0];
+        events.turn_start(&game);
+        events.search_complete(&game, d3, 10);
+        events.move_sent(&game, d3);
+        events.game_end(&game);
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "start 0".to_string(),
+                "start 0".to_string(),
+                format!("sent {}", d3),
+                format!("sent {}", d3)
+            ]
+        );
+    }
+}
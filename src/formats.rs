@@ -0,0 +1,334 @@
+//! Versioned on-disk formats.
+//!
+//! Every file Herb writes for itself to read back later starts with a header line naming the
+//! format and its version, followed by the contents as json:
+//! ```text
+//! HERB lessons 1
+//! {
+//!     "lessons": []
+//! }
+//! ```
+//! A [`VersionedReader`] checks the header before it parses anything. A file of another
+//! format, or one written by a newer Herb, is rejected with a [`FormatError`] rather than
+//! being read into the wrong struct. A file of an older version is upgraded by the reader's
+//! migrations, one version at a time, and every file can be validated once it is parsed, so
+//! nothing that would silently corrupt a search gets through. Files from before the headers
+//! were introduced are version 0.
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::autosave::temporary_path;
+
+/// The magic the header line of every versioned file starts with.
+pub const MAGIC: &str = "HERB";
+
+/// The name and current version of a format.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub kind: &'static str,
+    pub version: u32,
+}
+
+impl Header {
+    /// Create the header of version `version` of the format `kind`.
+    pub const fn new(kind: &'static str, version: u32) -> Self {
+        Header { kind, version }
+    }
+}
+
+impl Display for Header {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "{} {} {}", MAGIC, self.kind, self.version)
+    }
+}
+
+/// Upgrades the json contents of a version of a format to the next version.
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// Checks the contents of a file once they are parsed.
+pub type Validation<T> = fn(&T) -> Result<(), String>;
+
+/// Errors from reading a versioned file.
+#[derive(Debug, PartialEq)]
+pub enum FormatError {
+    /// The header line starts with the magic but can not be parsed.
+    Header(String),
+    /// The file holds another format.
+    Kind {
+        expected: &'static str,
+        found: String,
+    },
+    /// The file was written by a newer version of Herb.
+    TooNew {
+        kind: &'static str,
+        version: u32,
+        supported: u32,
+    },
+    /// No migration upgrades this version of the format.
+    Unsupported { kind: &'static str, version: u32 },
+    /// The contents do not parse, or a migration or the validation rejected them.
+    Invalid(String),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            FormatError::Header(line) => write!(f, "Malformed header: {}", line),
+            FormatError::Kind { expected, found } => {
+                write!(f, "Expected a {} file, found a {} file", expected, found)
+            }
+            FormatError::TooNew {
+                kind,
+                version,
+                supported,
+            } => write!(
+                f,
+                "The {} file is version {}, newer than the supported version {}",
+                kind, version, supported
+            ),
+            FormatError::Unsupported { kind, version: 0 } => {
+                write!(f, "The {} file has no header and can not be upgraded", kind)
+            }
+            FormatError::Unsupported { kind, version } => write!(
+                f,
+                "Version {} of the {} format can no longer be read",
+                version, kind
+            ),
+            FormatError::Invalid(message) => write!(f, "Invalid contents: {}", message),
+        }
+    }
+}
+
+impl Error for FormatError {}
+
+impl From<FormatError> for io::Error {
+    fn from(e: FormatError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, e)
+    }
+}
+
+/// Reads the files of a format, upgrading older versions and validating the contents.
+pub struct VersionedReader<T> {
+    header: Header,
+    migrations: Vec<(u32, Migration)>,
+    validation: Option<Validation<T>>,
+    format: PhantomData<fn() -> T>,
+}
+
+impl<T: DeserializeOwned> VersionedReader<T> {
+    /// Create a reader of the format that reads only its current version.
+    pub fn new(header: Header) -> Self {
+        VersionedReader {
+            header,
+            migrations: Vec::new(),
+            validation: None,
+            format: PhantomData,
+        }
+    }
+
+    /// Upgrade version `from` of the format to version `from + 1` with the migration.
+    pub fn migration(mut self, from: u32, migration: Migration) -> Self {
+        self.migrations.push((from, migration));
+        self
+    }
+
+    /// Check the contents of every file read with the validation.
+    pub fn validation(mut self, validation: Validation<T>) -> Self {
+        self.validation = Some(validation);
+        self
+    }
+
+    /// Returns the header files of the format are written with.
+    pub fn header(&self) -> Header {
+        self.header
+    }
+
+    /// Parse the contents of a file.
+    pub fn read(&self, text: &str) -> Result<T, FormatError> {
+        let (kind, mut version, body) = split_header(text)?;
+        if let Some(kind) = kind.filter(|kind| *kind != self.header.kind) {
+            return Err(FormatError::Kind {
+                expected: self.header.kind,
+                found: kind.to_string(),
+            });
+        }
+        if version > self.header.version {
+            return Err(FormatError::TooNew {
+                kind: self.header.kind,
+                version,
+                supported: self.header.version,
+            });
+        }
+        let mut value: Value =
+            serde_json::from_str(body).map_err(|e| FormatError::Invalid(e.to_string()))?;
+        while version < self.header.version {
+            let (_, migration) = self
+                .migrations
+                .iter()
+                .find(|(from, _)| *from == version)
+                .ok_or(FormatError::Unsupported {
+                    kind: self.header.kind,
+                    version,
+                })?;
+            value = migration(value).map_err(FormatError::Invalid)?;
+            version += 1;
+        }
+        let contents =
+            serde_json::from_value(value).map_err(|e| FormatError::Invalid(e.to_string()))?;
+        if let Some(validation) = self.validation {
+            validation(&contents).map_err(FormatError::Invalid)?;
+        }
+        Ok(contents)
+    }
+
+    /// Read the file.
+    ///
+    /// Returns an error if it can not be read, or [`FormatError`] as invalid data.
+    pub fn read_file(&self, path: &Path) -> io::Result<T> {
+        Ok(self.read(&fs::read_to_string(path)?)?)
+    }
+}
+
+/// Returns the kind and version of the header of the text, `None` and 0 if it has none,
+/// and the contents after it.
+fn split_header(text: &str) -> Result<(Option<&str>, u32, &str), FormatError> {
+    if !text.starts_with(MAGIC) {
+        return Ok((None, 0, text));
+    }
+    let (line, body) = text.split_once('\n').unwrap_or((text, ""));
+    match line.split_whitespace().collect::<Vec<_>>()[..] {
+        [MAGIC, kind, version] => {
+            let version = version
+                .parse()
+                .map_err(|_| FormatError::Header(line.to_string()))?;
+            Ok((Some(kind), version, body))
+        }
+        _ => Err(FormatError::Header(line.to_string())),
+    }
+}
+
+/// Returns the contents as the header followed by pretty json.
+pub fn to_string<T: Serialize>(header: Header, contents: &T) -> serde_json::Result<String> {
+    Ok(format!(
+        "{}\n{}\n",
+        header,
+        serde_json::to_string_pretty(contents)?
+    ))
+}
+
+/// Write the contents to the file under the header, replacing it only once the new contents
+/// are safely on disk.
+pub fn save<T: Serialize>(path: &Path, header: Header, contents: &T) -> io::Result<()> {
+    let temporary = temporary_path(path);
+    let mut file = File::create(&temporary)?;
+    file.write_all(to_string(header, contents)?.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&temporary, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    /// Version 2 renamed `n` to `count`, version 1 introduced the header.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Counter {
+        count: u32,
+    }
+
+    fn reader() -> VersionedReader<Counter> {
+        VersionedReader::new(Header::new("counter", 2))
+            .migration(0, Ok)
+            .migration(1, |mut value| {
+                let n = value
+                    .as_object_mut()
+                    .and_then(|object| object.remove("n"))
+                    .ok_or("no n")?;
+                Ok(json!({ "count": n }))
+            })
+            .validation(|counter| match counter.count {
+                0..=100 => Ok(()),
+                _ => Err("too many".to_string()),
+            })
+    }
+
+    #[test]
+    fn test_versioned_reader() {
+        let reader = reader();
+        let counter = Counter { count: 3 };
+        let text = to_string(reader.header(), &counter).unwrap();
+        assert!(text.starts_with("HERB counter 2\n"));
+        assert_eq!(reader.read(&text), Ok(counter));
+
+        // older versions are upgraded
+        assert_eq!(
+            reader.read("HERB counter 1\n{\"n\": 4}"),
+            Ok(Counter { count: 4 })
+        );
+        assert_eq!(reader.read("{\"n\": 5}"), Ok(Counter { count: 5 }));
+        assert!(matches!(
+            reader.read("HERB counter 1\n{\"count\": 4}"),
+            Err(FormatError::Invalid(_))
+        ));
+
+        // anything else fails loudly
+        assert_eq!(
+            reader.read("HERB lessons 2\n{\"count\": 4}"),
+            Err(FormatError::Kind {
+                expected: "counter",
+                found: "lessons".to_string()
+            })
+        );
+        assert_eq!(
+            reader.read("HERB counter 3\n{\"count\": 4}"),
+            Err(FormatError::TooNew {
+                kind: "counter",
+                version: 3,
+                supported: 2
+            })
+        );
+        assert!(matches!(
+            reader.read("HERB counter two\n{}"),
+            Err(FormatError::Header(_))
+        ));
+        assert!(matches!(
+            reader.read("HERB counter 2\n{\"count\": 101}"),
+            Err(FormatError::Invalid(_))
+        ));
+        assert!(matches!(
+            reader.read("HERB counter 2\n{"),
+            Err(FormatError::Invalid(_))
+        ));
+        let strict = VersionedReader::<Counter>::new(Header::new("counter", 2));
+        assert_eq!(
+            strict.read("{\"count\": 4}"),
+            Err(FormatError::Unsupported {
+                kind: "counter",
+                version: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_save() {
+        let path = std::env::temp_dir().join(format!("herb-formats-{}.json", std::process::id()));
+        save(&path, Header::new("counter", 2), &Counter { count: 7 }).unwrap();
+        assert!(!temporary_path(&path).exists());
+        assert_eq!(reader().read_file(&path).unwrap(), Counter { count: 7 });
+        let e = VersionedReader::<Counter>::new(Header::new("counter", 1))
+            .read_file(&path)
+            .unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+}
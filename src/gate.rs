@@ -0,0 +1,130 @@
+//! Gating of candidate configurations.
+//!
+//! A candidate configuration plays a match against the current best configuration and
+//! replaces it only if it scores above a threshold. Each promotion swaps the two
+//! configuration files and bumps the version in a metadata file kept next to the best
+//! configuration, `best.json` has its metadata in `best.meta.json`:
+//! ```json
+//! {
+//!     "version": 3,
+//!     "score": 0.6,
+//!     "games": 20
+//! }
+//! ```
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tournament::MatchScore;
+
+/// Default fraction of points the candidate must exceed to be promoted.
+pub const DEFAULT_THRESHOLD: f64 = 0.55;
+
+/// Version information for the current best configuration.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GateMetadata {
+    /// Incremented on every promotion.
+    pub version: u32,
+    /// Score of the promoted candidate in its gating match.
+    pub score: f64,
+    /// Number of games in the gating match.
+    pub games: u32,
+}
+
+impl GateMetadata {
+    /// Read the metadata for the given best configuration file.
+    /// Missing or unreadable metadata is treated as version `0`.
+    pub fn load(best: &Path) -> Self {
+        let mut contents = String::new();
+        match File::open(metadata_path(best)) {
+            Ok(mut file) => {
+                if file.read_to_string(&mut contents).is_err() {
+                    return GateMetadata::default();
+                }
+                serde_json::from_str(&contents).unwrap_or_default()
+            }
+            Err(_) => GateMetadata::default(),
+        }
+    }
+
+    /// Write the metadata for the given best configuration file.
+    pub fn save(&self, best: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(metadata_path(best), contents)
+    }
+}
+
+/// Returns the path of the metadata file belonging to the given configuration file.
+pub fn metadata_path(best: &Path) -> PathBuf {
+    best.with_extension("meta.json")
+}
+
+/// Returns true if the candidate's match score is good enough to be promoted.
+pub fn should_promote(score: &MatchScore, threshold: f64) -> bool {
+    score.games() > 0 && score.score() > threshold
+}
+
+/// Swap the candidate and best configuration files and bump the version.
+///
+/// Afterwards `best` holds the candidate configuration and `candidate` holds the
+/// previous best, so a promotion can be undone by hand.
+pub fn promote(candidate: &Path, best: &Path, score: &MatchScore) -> io::Result<GateMetadata> {
+    let swap = best.with_extension("swap");
+    fs::rename(best, &swap)?;
+    fs::rename(candidate, best)?;
+    fs::rename(&swap, candidate)?;
+
+    let previous = GateMetadata::load(best);
+    let metadata = GateMetadata {
+        version: previous.version + 1,
+        score: score.score(),
+        games: score.games(),
+    };
+    metadata.save(best)?;
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_should_promote() {
+        let score = MatchScore {
+            wins: 6,
+            losses: 3,
+            draws: 1,
+        };
+        assert!(should_promote(&score, DEFAULT_THRESHOLD));
+        assert!(!should_promote(&score, 0.7));
+        assert!(!should_promote(&MatchScore::default(), 0.0));
+    }
+
+    #[test]
+    fn test_promote_swaps_files_and_bumps_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let candidate = dir.path().join("candidate.json");
+        let best = dir.path().join("best.json");
+        fs::write(&candidate, "candidate").unwrap();
+        fs::write(&best, "best").unwrap();
+
+        let score = MatchScore {
+            wins: 3,
+            losses: 1,
+            draws: 0,
+        };
+        let metadata = promote(&candidate, &best, &score).unwrap();
+        assert_eq!(metadata.version, 1);
+        assert_eq!(fs::read_to_string(&best).unwrap(), "candidate");
+        assert_eq!(fs::read_to_string(&candidate).unwrap(), "best");
+
+        let metadata = promote(&candidate, &best, &score).unwrap();
+        assert_eq!(metadata.version, 2);
+        assert_eq!(GateMetadata::load(&best), metadata);
+        assert_eq!(fs::read_to_string(&best).unwrap(), "best");
+    }
+}
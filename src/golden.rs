@@ -0,0 +1,185 @@
+//! Regression checks against recorded "golden" games.
+//!
+//! A golden game is a [`GameRecord`] of Herb playing with a fixed number of search
+//! iterations per move. A single [`Tree`] searched for a fixed number of iterations always
+//! makes the same choices, so replaying Herb's side of the game after a change that is meant
+//! to be neutral, such as a refactor or a speedup, must reproduce the recorded moves. Any
+//! position where it does not is reported as a [`Drift`].
+//!
+//! The search settings are stored in the record's tags so the replay uses the same ones:
+//! ```text
+//! [Exploration "1.4142135623730951"]
+//! [Herb "B"]
+//! [Iterations "50"]
+//! ```
+//! Each of Herb's moves is annotated with its evaluation. A different move with an evaluation
+//! within the tolerance of the recorded one is an equally good choice and is not a drift.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::config::MctsConfig;
+use crate::mcts::Tree;
+use crate::othello::Move::Pass;
+use crate::othello::{Color, Game, Move};
+use crate::record::{GameRecord, RecordError, RecordedMove};
+use crate::Player;
+
+/// Largest difference from a recorded evaluation that is not a drift. Evaluations are
+/// written to transcripts with two decimals.
+pub const DEFAULT_TOLERANCE: f64 = 0.01;
+
+/// A position where Herb no longer plays the recorded move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Drift {
+    /// Index of the move in the game.
+    pub ply: usize,
+    /// The recorded move and evaluation.
+    pub expected: RecordedMove,
+    /// The move Herb chooses now.
+    pub actual: Move,
+    /// Herb's evaluation of the move it chooses now.
+    pub eval: f64,
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "Ply {}: expected {} ({:?}), got {} ({:.2})",
+            self.ply, self.expected.mv, self.expected.eval, self.actual, self.eval
+        )
+    }
+}
+
+/// Choose Herb's move with a fresh tree searched for the given number of iterations.
+///
+/// Returns the move and its evaluation.
+pub fn herb_move(game: Game, config: &MctsConfig, iterations: u64) -> (Move, f64) {
+    let mut tree = Tree::from_config(config.clone());
+    tree.search_n(game, iterations);
+    tree.best_move_with_value(game)
+}
+
+/// Play Herb with a fixed number of search iterations per move against the given opponent
+/// and record the game as a golden game.
+pub fn record(
+    opponent: &mut dyn Player,
+    herb_color: Color,
+    config: &MctsConfig,
+    iterations: u64,
+) -> GameRecord {
+    let mut record = GameRecord::new();
+    record
+        .tags
+        .insert("Herb".to_string(), herb_color.to_string());
+    record
+        .tags
+        .insert("Iterations".to_string(), iterations.to_string());
+    record.tags.insert(
+        "Exploration".to_string(),
+        config.exploration_factor.to_string(),
+    );
+
+    opponent.new_game(herb_color.opponent());
+    let mut game = Game::new();
+    while !game.is_over() {
+        let recorded = if game.legal_moves().is_empty() {
+            RecordedMove::new(Pass)
+        } else if game.to_move() == herb_color {
+            let (mv, eval) = herb_move(game, config, iterations);
+            RecordedMove {
+                mv,
+                clock: None,
+                eval: Some(eval),
+            }
+        } else {
+            RecordedMove::new(opponent.get_next_move(game))
+        };
+        // the opponents are trusted to play legal moves
+        game.play_next_turn(recorded.mv).unwrap();
+        record.push(recorded);
+    }
+    record
+}
+
+/// Replay Herb's side of a golden game and return every position where its choice differs
+/// from the record.
+///
+/// Returns an error if the record is missing the search settings or has an illegal move.
+pub fn check(record: &GameRecord, tolerance: f64) -> Result<Vec<Drift>, RecordError> {
+    let tag = |name: &str| {
+        record
+            .tags
+            .get(name)
+            .ok_or_else(|| RecordError::Parse(format!("Missing tag: {}", name)))
+    };
+    let herb_color = match tag("Herb")?.as_str() {
+        "B" => Color::Black,
+        "W" => Color::White,
+        other => return Err(RecordError::Parse(format!("Bad color: {}", other))),
+    };
+    let iterations = tag("Iterations")?
+        .parse()
+        .map_err(|_| RecordError::Parse("Bad iterations".to_string()))?;
+    let config = MctsConfig {
+        exploration_factor: tag("Exploration")?
+            .parse()
+            .map_err(|_| RecordError::Parse("Bad exploration factor".to_string()))?,
+        ..MctsConfig::default()
+    };
+
+    let mut drifts = Vec::new();
+    let mut game = Game::new();
+    for (ply, expected) in record.moves.iter().enumerate() {
+        if game.to_move() == herb_color && !game.legal_moves().is_empty() {
+            let (actual, eval) = herb_move(game, &config, iterations);
+            let matches = match expected.eval {
+                Some(expected_eval) => (eval - expected_eval).abs() <= tolerance,
+                None => actual == expected.mv,
+            };
+            if !matches {
+                drifts.push(Drift {
+                    ply,
+                    expected: *expected,
+                    actual,
+                    eval,
+                });
+            }
+        }
+        game.play_next_turn(expected.mv)?;
+    }
+    Ok(drifts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+
+    #[test]
+    fn test_golden_games() {
+        for transcript in [
+            include_str!("../golden/greedy-black.txt"),
+            include_str!("../golden/greedy-white.txt"),
+        ] {
+            let record = GameRecord::from_transcript(transcript).unwrap();
+            let drifts = check(&record, DEFAULT_TOLERANCE).unwrap();
+            assert!(drifts.is_empty(), "{:#?}", drifts);
+        }
+    }
+
+    #[test]
+    fn test_drift_detected() {
+        let config = MctsConfig::default();
+        let mut record = record(&mut Opponent::Greedy, Color::White, &config, 5);
+        assert!(check(&record, DEFAULT_TOLERANCE).unwrap().is_empty());
+
+        // white's first move
+        record.moves[1].eval = record.moves[1].eval.map(|eval| eval + 1.0);
+        let drifts = check(&record, DEFAULT_TOLERANCE).unwrap();
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(drifts[0].ply, 1);
+
+        record.tags.remove("Iterations");
+        assert!(check(&record, DEFAULT_TOLERANCE).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+//! Adapters for [`GameInterface`]s.
+//!
+//! Interfaces like [`DrMecRef`](crate::drmecref::DrMecRef) block on stdin until the opponent
+//! moves, so a caller waiting on them can neither give up at a deadline nor do anything else,
+//! such as searching, in the meantime. [`Nonblocking`] moves the blocking reads onto a
+//! background thread and hands the moves over through a channel, so waiting for a move can
+//! be bounded with [`GameInterface::receive_move_until`] or polled with
+//! [`GameInterface::try_receive_move`] in between slices of search.
+use std::io;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::othello::{Color, Move};
+use crate::GameInterface;
+
+/// Wraps a blocking [`GameInterface`] so it honors deadlines.
+///
+/// A background thread calls the wrapped interface's `receive_move` in a loop, so moves are
+/// read ahead of time and kept in order until they are asked for. The thread stops after the
+/// first error, which is passed on, or when the adapter is dropped and the next move arrives.
+pub struct Nonblocking<I> {
+    inner: Arc<I>,
+    moves: Mutex<Receiver<io::Result<Move>>>,
+}
+
+impl<I> Nonblocking<I>
+where
+    I: GameInterface + Send + Sync + 'static,
+{
+    /// Start reading moves from the given interface in the background.
+    pub fn new(inner: I) -> Self {
+        let inner = Arc::new(inner);
+        let (sender, receiver) = channel();
+        let reader = Arc::clone(&inner);
+        thread::spawn(move || loop {
+            let received = reader.receive_move();
+            let failed = received.is_err();
+            if sender.send(received).is_err() || failed {
+                break;
+            }
+        });
+        Nonblocking {
+            inner,
+            moves: Mutex::new(receiver),
+        }
+    }
+
+    /// Returns the wrapped interface.
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+}
+
+/// The error returned once the background reader has stopped.
+fn disconnected() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "Interface closed")
+}
+
+impl<I: GameInterface> GameInterface for Nonblocking<I> {
+    fn send_move(&self, mv: Move, color: Color) -> io::Result<()> {
+        self.inner.send_move(mv, color)
+    }
+
+    fn receive_move(&self) -> io::Result<Move> {
+        let moves = self.moves.lock().unwrap();
+        moves.recv().map_err(|_| disconnected())?
+    }
+
+    fn receive_move_until(&self, deadline: Instant) -> io::Result<Option<Move>> {
+        let moves = self.moves.lock().unwrap();
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        match moves.recv_timeout(timeout) {
+            Ok(received) => received.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(disconnected()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Receives the scripted moves, each after a delay, then fails.
+    struct Slow {
+        moves: Mutex<Vec<Move>>,
+        delay: Duration,
+    }
+
+    impl GameInterface for Slow {
+        fn send_move(&self, _mv: Move, _color: Color) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn receive_move(&self) -> io::Result<Move> {
+            thread::sleep(self.delay);
+            self.moves
+                .lock()
+                .unwrap()
+                .pop()
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+        }
+    }
+
+    #[test]
+    fn test_nonblocking_deadlines() {
+        let d3 = Move::from_col_row(3, 2).unwrap();
+        let interface = Nonblocking::new(Slow {
+            moves: Mutex::new(vec![Move::Pass, d3]),
+            delay: Duration::from_millis(50),
+        });
+
+        assert!(interface.try_receive_move().unwrap().is_none());
+        let deadline = Instant::now() + Duration::from_secs(5);
+        assert_eq!(interface.receive_move_until(deadline).unwrap(), Some(d3));
+        assert_eq!(interface.receive_move().unwrap(), Move::Pass);
+
+        // the inner interface fails, then the reader is gone
+        assert!(interface.receive_move().is_err());
+        assert!(interface.receive_move_until(deadline).is_err());
+    }
+}
@@ -0,0 +1,493 @@
+//! Passive commentary on a game Herb is not playing.
+//!
+//! A [`Kibitzer`] follows a game move by move and keeps searching whatever position is on the
+//! board, so it can comment at any time on who stands better, what Herb would play and how
+//! the last move changed the picture. It never plays a move of its own, which makes it a
+//! commentator for casting tournaments between other engines or students.
+//!
+//! The moves come from a feed, one line at a time, see [`parse_feed_line`]:
+//! - the referee protocol, e.g. `B d 3` from a referee log,
+//! - a [session](crate::session) recording,
+//! - the server-sent events of a [spectator feed](crate::spectator), e.g. from `curl -N`.
+//!
+//! `herb-kibitz` reads such a feed from standard input and prints the commentary.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::Serialize;
+
+use crate::analysis::win_rate;
+use crate::config::MctsConfig;
+use crate::drmecref::{parse_message, RefereeMessage};
+use crate::mcts::Tree;
+use crate::openings;
+use crate::othello::corners::CornerThreat;
+use crate::othello::edges::{Edge, EdgeClass};
+use crate::othello::Color::{self, Black, White};
+use crate::othello::{Game, GameError, Move, RulesMode, RulesWarning};
+use crate::record::format_move;
+use crate::session::SessionEntry;
+use crate::spectator::SpectatorEvent;
+
+/// A loss of this much of the mover's win rate makes the move a mistake.
+const MISTAKE: f64 = 0.1;
+/// A loss of this much of the mover's win rate makes the move a blunder.
+pub(crate) const BLUNDER: f64 = 0.2;
+
+/// What a line of a feed says about the game.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeedItem {
+    /// A new game starts.
+    NewGame,
+    /// A move or pass by the player to move.
+    Move(Move),
+    /// The position of a game in progress from a spectator feed.
+    Position(Box<SpectatorEvent>),
+    /// The game ended.
+    End,
+}
+
+/// Parse a line of a feed: the referee protocol, a line of a session recording or an event of
+/// a spectator feed. Returns `None` for lines that say nothing about the moves, such as
+/// comments, clock updates and empty lines.
+pub fn parse_feed_line(line: &str) -> Option<FeedItem> {
+    let line = line.trim();
+    if let Some(data) = line.strip_prefix("data:") {
+        let event: SpectatorEvent = serde_json::from_str(data.trim()).ok()?;
+        return Some(FeedItem::Position(Box::new(event)));
+    }
+    if line.starts_with('{') {
+        let entry: SessionEntry = serde_json::from_str(line).ok()?;
+        return parse_feed_line(&entry.data);
+    }
+    match parse_message(line).ok()? {
+        RefereeMessage::Init(_) => Some(FeedItem::NewGame),
+        RefereeMessage::OpponentMove(_, mv) => Some(FeedItem::Move(mv)),
+        RefereeMessage::Pass(_) => Some(FeedItem::Move(Move::Pass)),
+        RefereeMessage::GameEnd(_) => Some(FeedItem::End),
+        _ => None,
+    }
+}
+
+/// Herb's view of the position on the board.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Commentary {
+    /// Moves played so far, passes included.
+    pub turn: usize,
+    /// The last move and who played it, `None` at the start.
+    pub last_move: Option<(Color, Move)>,
+    /// The name of the opening while the position is a named one.
+    pub opening: Option<&'static str>,
+    pub to_move: Color,
+    /// The move Herb would play, [`Move::Pass`] if there is none.
+    pub best_move: Move,
+    /// Fraction of the simulations from the position won by the player to move, draws
+    /// counting half.
+    pub win_rate: f64,
+    /// The win rate the last move gave up against Herb's choice, as the search of the
+    /// position before it saw them. `None` if that position was not searched.
+    pub loss: Option<f64>,
+    /// The move Herb preferred instead of the last one, if it did.
+    pub preferred: Option<Move>,
+    /// Search iterations spent on the position.
+    pub iterations: u64,
+    /// The line Herb expects to be played.
+    pub pv: Vec<Move>,
+    /// The empty corners that can be taken or given away over the next turns.
+    pub corner_threats: Vec<CornerThreat>,
+    /// The edges where a player's discs are poisoned, open to a wedge by the opponent.
+    pub poisoned_edges: Vec<(Color, Edge)>,
+    pub game_over: bool,
+}
+
+impl Commentary {
+    /// Returns a word on the last move: a blunder, a mistake, or the move Herb would have
+    /// played.
+    pub fn verdict(&self) -> Option<&'static str> {
+        let loss = self.loss?;
+        if loss >= BLUNDER {
+            Some("a blunder")
+        } else if loss >= MISTAKE {
+            Some("a mistake")
+        } else if self.preferred.is_none() {
+            Some("Herb's choice too")
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Commentary {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self.last_move {
+            Some((color, mv)) => write!(f, "{}. {} {}", self.turn, color, format_move(mv))?,
+            None => write!(f, "{}. start", self.turn)?,
+        }
+        if let Some(opening) = self.opening {
+            write!(f, " ({})", opening)?;
+        }
+        if let Some(verdict) = self.verdict() {
+            write!(f, ", {}", verdict)?;
+        }
+        if let (Some(preferred), Some(loss)) = (self.preferred, self.loss) {
+            write!(
+                f,
+                ", Herb preferred {} ({:+.0})",
+                format_move(preferred),
+                -loss * 100.0
+            )?;
+        }
+        if self.game_over {
+            return write!(f, ". Game over.");
+        }
+        let pv: Vec<String> = self.pv.iter().map(|mv| format_move(*mv)).collect();
+        write!(
+            f,
+            ". {} to move wins {:.0}%, Herb expects {}, pv {}",
+            self.to_move,
+            self.win_rate * 100.0,
+            format_move(self.best_move),
+            pv.join(" ")
+        )?;
+        for threat in &self.corner_threats {
+            write!(f, ", {}", describe_threat(threat, self.to_move))?;
+        }
+        for (color, edge) in &self.poisoned_edges {
+            write!(f, ", {}'s {} is poisoned", color, edge)?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns a few words on what the corner threat means for the player to move.
+fn describe_threat(threat: &CornerThreat, to_move: Color) -> String {
+    let corner = format_move(threat.corner);
+    if threat.can_take {
+        format!("{} can take {}", to_move, corner)
+    } else if threat.must_concede {
+        format!("{} must give up {}", to_move, corner)
+    } else if threat.can_force {
+        format!("{} can force {}", to_move, corner)
+    } else {
+        format!("{} threatens {}", to_move.opponent(), corner)
+    }
+}
+
+/// Follows a game and comments on it, see the [module](self) documentation.
+#[derive(Debug)]
+pub struct Kibitzer {
+    config: MctsConfig,
+    /// The tree searched while black is to move. The tree keeps every result from the
+    /// perspective of whoever is to move where its searches start, so each side has its own.
+    black_tree: Tree,
+    white_tree: Tree,
+    game: Game,
+    /// Search iterations spent on the current position.
+    iterations: u64,
+    /// The judgement of the last move, see [`Commentary::loss`].
+    loss: Option<f64>,
+    preferred: Option<Move>,
+}
+
+impl Kibitzer {
+    pub fn new(config: MctsConfig) -> Self {
+        Kibitzer {
+            black_tree: Tree::from_config(config.clone()),
+            white_tree: Tree::from_config(config.clone()),
+            config,
+            game: Game::new(),
+            iterations: 0,
+            loss: None,
+            preferred: None,
+        }
+    }
+
+    /// Returns the position on the board.
+    pub fn game(&self) -> Game {
+        self.game
+    }
+
+    /// Returns the search iterations spent on the position on the board.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Start following a new game, forgetting the search of the last one.
+    pub fn new_game(&mut self) {
+        *self = Kibitzer::new(self.config.clone());
+    }
+
+    /// Play the next move of the game. Moves are read leniently, as the feed is not Herb's
+    /// to correct.
+    ///
+    /// Returns a warning for an irregular move, or an error if it is illegal.
+    pub fn play(&mut self, mv: Move) -> Result<Option<RulesWarning>, GameError> {
+        let (loss, preferred) = self.judge(mv);
+        let turn = self.game.get_turn();
+        let warning = self.game.play_with_rules(mv, RulesMode::Lenient)?;
+        if self.game.get_turn() != turn {
+            self.loss = loss;
+            self.preferred = preferred;
+            self.iterations = 0;
+        }
+        Ok(warning)
+    }
+
+    /// Follow the game from a spectator event: the next move is played, an event for the
+    /// start of a game starts a new one and a repeated event changes nothing.
+    ///
+    /// Returns whether the position changed, or an error if the event does not follow from
+    /// the position on the board.
+    pub fn follow(&mut self, event: &SpectatorEvent) -> Result<bool, GameError> {
+        if event.turn == 0 {
+            let changed = self.game.get_turn() != 0;
+            if changed {
+                self.new_game();
+            }
+            return Ok(changed);
+        }
+        if event.turn == self.game.get_turn() {
+            return Ok(false);
+        }
+        match event.last_move {
+            Some(mv) if event.turn == self.game.get_turn() + 1 => {
+                let mut next = self.game;
+                next.play_with_rules(mv, RulesMode::Lenient)?;
+                let board = next.get_board();
+                if board.get_black() != event.black || board.get_white() != event.white {
+                    return Err(GameError::InvalidMove);
+                }
+                self.play(mv)?;
+                Ok(true)
+            }
+            _ => Err(GameError::InvalidMove),
+        }
+    }
+
+    /// Search the position on the board for some more iterations.
+    pub fn think(&mut self, iterations: u64) {
+        if !self.game.is_over() {
+            let game = self.game;
+            self.tree_mut().search_n(game, iterations);
+            self.iterations += iterations;
+        }
+    }
+
+    /// Returns Herb's view of the position on the board as far as it has searched it.
+    pub fn commentary(&self) -> Commentary {
+        let last_move = self
+            .game
+            .last_move()
+            .map(|mv| (self.game.to_move().opponent(), mv));
+        Commentary {
+            turn: self.game.get_turn(),
+            last_move,
+            opening: openings::name(&self.game),
+            to_move: self.game.to_move(),
+            best_move: self.best_move(),
+            win_rate: win_rate(self.tree(), self.game, self.game.to_move()),
+            loss: self.loss,
+            preferred: self.preferred,
+            iterations: self.iterations,
+            pv: self
+                .tree()
+                .principal_variation(self.game)
+                .iter()
+                .map(|step| step.mv)
+                .collect(),
+            corner_threats: self
+                .game
+                .corner_threats()
+                .into_iter()
+                .filter(|threat| !threat.is_quiet())
+                .collect(),
+            poisoned_edges: [Black, White]
+                .into_iter()
+                .flat_map(|color| {
+                    Edge::ALL
+                        .into_iter()
+                        .filter(move |&edge| {
+                            self.game.edge_class(edge, color) == EdgeClass::Poisoned
+                        })
+                        .map(move |edge| (color, edge))
+                })
+                .collect(),
+            game_over: self.game.is_over(),
+        }
+    }
+
+    /// Returns the win rate the move gives up against Herb's choice in the search of the
+    /// position on the board, and Herb's choice if it is a different move.
+    fn judge(&self, mv: Move) -> (Option<f64>, Option<Move>) {
+        if self.iterations == 0 || !self.game.legal_moves().contains(&mv) {
+            return (None, None);
+        }
+        // the tree keeps the results of the player to move, so the moves compare directly
+        let ratio = |mv: Move| {
+            let mut child = self.game;
+            child.play_next_turn(mv).ok()?;
+            self.tree()
+                .map
+                .get(&child.zobrist_hash())
+                .map(|node| node.ratio())
+        };
+        let best = self.best_move();
+        let loss = match (ratio(best), ratio(mv)) {
+            (Some(best), Some(played)) => Some((best - played).max(0.0)),
+            _ => None,
+        };
+        (loss, Some(best).filter(|best| *best != mv))
+    }
+
+    fn best_move(&self) -> Move {
+        self.tree().best_move_with_value(self.game).0
+    }
+
+    fn tree(&self) -> &Tree {
+        match self.game.to_move() {
+            Black => &self.black_tree,
+            White => &self.white_tree,
+        }
+    }
+
+    fn tree_mut(&mut self) -> &mut Tree {
+        match self.game.to_move() {
+            Black => &mut self.black_tree,
+            White => &mut self.white_tree,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::parse_move;
+
+    fn config() -> MctsConfig {
+        MctsConfig::default()
+    }
+
+    #[test]
+    fn test_parse_feed_line() {
+        let d3 = parse_move("d3").unwrap();
+        assert_eq!(parse_feed_line("B d 3"), Some(FeedItem::Move(d3)));
+        assert_eq!(parse_feed_line("W"), Some(FeedItem::Move(Move::Pass)));
+        assert_eq!(parse_feed_line("I B"), Some(FeedItem::NewGame));
+        assert_eq!(parse_feed_line("E Black wins 40-24"), Some(FeedItem::End));
+        assert_eq!(parse_feed_line("T B 10.5"), None);
+        assert_eq!(parse_feed_line(""), None);
+        assert_eq!(
+            parse_feed_line(r#"{"elapsed":1.9,"direction":"sent","data":"B d 3\n"}"#),
+            Some(FeedItem::Move(d3))
+        );
+
+        let mut game = Game::new();
+        game.play_next_turn(d3).unwrap();
+        let event = SpectatorEvent::new(0, &game);
+        let line = format!("data: {}", serde_json::to_string(&event).unwrap());
+        assert_eq!(
+            parse_feed_line(&line),
+            Some(FeedItem::Position(Box::new(event)))
+        );
+    }
+
+    #[test]
+    fn test_commentary() {
+        let mut kibitzer = Kibitzer::new(config());
+        kibitzer.think(500);
+        let start = kibitzer.commentary();
+        assert_eq!(start.turn, 0);
+        assert_eq!(start.last_move, None);
+        assert_eq!(start.loss, None);
+        assert_eq!(start.iterations, 500);
+        assert!(!start.pv.is_empty());
+
+        let mv = start.best_move;
+        kibitzer.play(mv).unwrap();
+        let comment = kibitzer.commentary();
+        assert_eq!(comment.last_move, Some((Black, mv)));
+        assert_eq!(comment.iterations, 0);
+        assert_eq!(comment.preferred, None);
+        assert_eq!(comment.loss, Some(0.0));
+        assert_eq!(comment.verdict(), Some("Herb's choice too"));
+
+        // a move from a position that was not searched is not judged
+        let reply = kibitzer.game().legal_moves()[0];
+        kibitzer.play(reply).unwrap();
+        assert_eq!(kibitzer.commentary().loss, None);
+        assert!(comment
+            .to_string()
+            .starts_with(&format!("1. B {}", format_move(mv))));
+
+        assert!(kibitzer.play(parse_move("a1").unwrap()).is_err());
+        assert_eq!(kibitzer.game().get_turn(), 2);
+    }
+
+    #[test]
+    fn test_verdict() {
+        let comment = Commentary {
+            turn: 20,
+            last_move: Some((White, parse_move("b2").unwrap())),
+            opening: None,
+            to_move: Black,
+            best_move: parse_move("a1").unwrap(),
+            win_rate: 0.8,
+            loss: Some(0.25),
+            preferred: Some(parse_move("c3").unwrap()),
+            iterations: 100,
+            pv: vec![parse_move("a1").unwrap()],
+            corner_threats: Vec::new(),
+            poisoned_edges: Vec::new(),
+            game_over: false,
+        };
+        assert_eq!(comment.verdict(), Some("a blunder"));
+        assert_eq!(
+            comment.to_string(),
+            "20. W b2, a blunder, Herb preferred c3 (-25). B to move wins 80%, Herb expects a1, pv a1"
+        );
+        let comment = Commentary {
+            loss: Some(0.05),
+            preferred: None,
+            ..comment
+        };
+        assert_eq!(comment.verdict(), Some("Herb's choice too"));
+
+        let threat = |can_take, must_concede| CornerThreat {
+            corner: parse_move("a1").unwrap(),
+            can_take,
+            opponent_can_take: true,
+            must_concede,
+            can_force: false,
+        };
+        let comment = Commentary {
+            corner_threats: vec![threat(true, false), threat(false, true)],
+            poisoned_edges: vec![(White, Edge::FileA)],
+            ..comment
+        };
+        assert!(comment
+            .to_string()
+            .ends_with("pv a1, B can take a1, B must give up a1, W's a-file is poisoned"));
+    }
+
+    #[test]
+    fn test_follow_spectator_events() {
+        let mut kibitzer = Kibitzer::new(config());
+        let mut game = Game::new();
+        assert!(!kibitzer.follow(&SpectatorEvent::new(0, &game)).unwrap());
+        game.play_next_turn(parse_move("d3").unwrap()).unwrap();
+        assert!(kibitzer.follow(&SpectatorEvent::new(0, &game)).unwrap());
+        // repeated events change nothing
+        assert!(!kibitzer.follow(&SpectatorEvent::new(0, &game)).unwrap());
+        assert_eq!(kibitzer.game(), game);
+
+        // a missed move can not be followed
+        let mut later = game;
+        later.play_next_turn(parse_move("c5").unwrap()).unwrap();
+        later.play_next_turn(later.greedy_move()).unwrap();
+        assert!(kibitzer.follow(&SpectatorEvent::new(0, &later)).is_err());
+
+        assert!(kibitzer
+            .follow(&SpectatorEvent::new(1, &Game::new()))
+            .unwrap());
+        assert_eq!(kibitzer.game(), Game::new());
+    }
+}
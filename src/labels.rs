@@ -0,0 +1,266 @@
+//! Endgame positions labeled with the exact score of every move.
+//!
+//! Agreement with a deep search, as in [`evaluation`](crate::evaluation), only says how much
+//! an evaluation plays like Herb, whose own moves are not always right. In positions with few
+//! empty squares the [`solver`] knows the right answer, so a [`LabeledPosition`] stores the
+//! exact final disc difference of every legal move and [`score`] measures how often a player
+//! picks a best move and how many discs its choices give away.
+//!
+//! Labels are generated from random games, seeded so the same arguments always give the same
+//! positions, see `herb-labels`. A labels file has one position per line, the moves leading to
+//! it followed by the score of every legal move:
+//! ```text
+//! f5 d6 c3 d3 c4 f4 ... h8 | a1:+6 b7:-2 g1:+6
+//! ```
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::othello::Move::Pass;
+use crate::othello::{Game, Move};
+use crate::random::RngBackend;
+use crate::record::{format_move, parse_move, RecordError};
+use crate::solver;
+use crate::Player;
+
+/// A position and the exact score of each of its moves.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabeledPosition {
+    /// The moves from the starting position, passes included.
+    pub moves: Vec<Move>,
+    /// Every legal move and its final disc difference for the player to move under perfect
+    /// play, see [`solver::move_scores`].
+    pub scores: Vec<(Move, i32)>,
+}
+
+impl LabeledPosition {
+    /// Solve the position the moves lead to.
+    ///
+    /// Returns an error if a move is illegal.
+    pub fn label(moves: Vec<Move>) -> Result<Self, RecordError> {
+        let game = play(&moves)?;
+        Ok(LabeledPosition {
+            scores: solver::move_scores(&game),
+            moves,
+        })
+    }
+
+    /// Returns the position.
+    pub fn game(&self) -> Game {
+        play(&self.moves).expect("labeled moves are legal")
+    }
+
+    /// Returns the exact score of the position, [`i32::MIN`] if it has no moves.
+    pub fn best_score(&self) -> i32 {
+        self.scores
+            .iter()
+            .map(|(_, score)| *score)
+            .max()
+            .unwrap_or(i32::MIN)
+    }
+
+    /// Returns the discs the move gives away compared to the best move, `None` if it is not
+    /// one of the labeled moves.
+    pub fn loss(&self, mv: Move) -> Option<i32> {
+        self.scores
+            .iter()
+            .find(|(labeled, _)| *labeled == mv)
+            .map(|(_, score)| self.best_score() - score)
+    }
+}
+
+impl Display for LabeledPosition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let moves: Vec<String> = self.moves.iter().map(|mv| format_move(*mv)).collect();
+        let scores: Vec<String> = self
+            .scores
+            .iter()
+            .map(|(mv, score)| format!("{}:{:+}", format_move(*mv), score))
+            .collect();
+        write!(f, "{} | {}", moves.join(" "), scores.join(" "))
+    }
+}
+
+/// Parse a line of a labels file.
+pub fn parse_label(line: &str) -> Result<LabeledPosition, RecordError> {
+    let bad_line = || RecordError::Parse(format!("Bad label: {}", line));
+    let (moves, scores) = line.split_once('|').ok_or_else(bad_line)?;
+    let moves = moves
+        .split_whitespace()
+        .map(parse_move)
+        .collect::<Result<Vec<_>, _>>()?;
+    let scores = scores
+        .split_whitespace()
+        .map(|token| {
+            let (mv, score) = token.split_once(':').ok_or_else(bad_line)?;
+            Ok((parse_move(mv)?, score.parse().map_err(|_| bad_line())?))
+        })
+        .collect::<Result<Vec<_>, RecordError>>()?;
+    play(&moves)?;
+    Ok(LabeledPosition { moves, scores })
+}
+
+/// Parse the contents of a labels file, skipping empty lines and lines starting with `#`.
+pub fn parse_labels(text: &str) -> Result<Vec<LabeledPosition>, RecordError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_label)
+        .collect()
+}
+
+/// Generate `count` distinct labeled positions with `empties` empty squares and a choice of
+/// at least two moves, from random games seeded with `seed`, drawn from the ChaCha8 stream of
+/// the seed's first game and worker. The positions are solved in parallel.
+///
+/// # Panics
+/// Panics if `empties` is not between 1 and 59, no game reaches such a position then.
+pub fn generate(count: usize, empties: u64, seed: u64) -> Vec<LabeledPosition> {
+    assert!(
+        (1..60).contains(&empties),
+        "Can not label {} empties",
+        empties
+    );
+    let mut rng = RngBackend::ChaCha.stream(seed, 0, 0);
+    let mut positions: Vec<Game> = Vec::new();
+    let mut lines = Vec::new();
+    while lines.len() < count {
+        let mut game = Game::new();
+        let mut moves = Vec::new();
+        while !game.is_over() && game.empty_squares() > empties {
+            let legal_moves = game.legal_moves();
+            let mv = if legal_moves.is_empty() {
+                Pass
+            } else {
+                legal_moves[rng.gen_range(0..legal_moves.len())]
+            };
+            game.play_next_turn(mv).unwrap();
+            moves.push(mv);
+        }
+        if game.empty_squares() != empties
+            || game.legal_moves().len() < 2
+            || positions.contains(&game)
+        {
+            continue;
+        }
+        positions.push(game);
+        lines.push(moves);
+    }
+    // solving is what takes the time, one position per thread
+    lines
+        .into_par_iter()
+        .map(|moves| LabeledPosition::label(moves).unwrap())
+        .collect()
+}
+
+/// How well a player does on a set of labeled positions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LabelScore {
+    pub positions: usize,
+    /// Fraction of the positions in which the player chose a best move.
+    pub accuracy: f64,
+    /// Mean discs given away per position compared to the best move.
+    pub mean_loss: f64,
+}
+
+impl Display for LabelScore {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "positions={} accuracy={:.1}% mean loss={:.2} discs",
+            self.positions,
+            self.accuracy * 100.0,
+            self.mean_loss
+        )
+    }
+}
+
+/// Let the player choose a move in each of the positions and compare its choices with the
+/// labels. A move that is not labeled, which can only be an illegal one, counts as losing
+/// every disc there is to lose, 128.
+pub fn score(player: &mut dyn Player, labels: &[LabeledPosition]) -> LabelScore {
+    let mut best = 0;
+    let mut loss = 0;
+    for label in labels {
+        let chosen = player.get_next_move(label.game());
+        let chosen_loss = label.loss(chosen).unwrap_or(128);
+        if chosen_loss == 0 {
+            best += 1;
+        }
+        loss += chosen_loss as i64;
+    }
+    let positions = labels.len();
+    let total = positions.max(1) as f64;
+    LabelScore {
+        positions,
+        accuracy: best as f64 / total,
+        mean_loss: loss as f64 / total,
+    }
+}
+
+fn play(moves: &[Move]) -> Result<Game, RecordError> {
+    let mut game = Game::new();
+    for mv in moves {
+        game.play_next_turn(*mv).map_err(RecordError::Game)?;
+    }
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+
+    /// Plays the solver's best move.
+    struct Perfect;
+
+    impl Player for Perfect {
+        fn get_next_move(&mut self, game_state: Game) -> Move {
+            solver::best_move(&game_state).0
+        }
+    }
+
+    #[test]
+    fn test_generate_and_parse() {
+        let labels = generate(5, 8, 7);
+        assert_eq!(labels.len(), 5);
+        assert_eq!(labels, generate(5, 8, 7));
+        for label in &labels {
+            let game = label.game();
+            assert_eq!(game.empty_squares(), 8);
+            assert_eq!(label.scores.len(), game.legal_moves().len());
+            assert_eq!(label.best_score(), solver::solve(&game));
+            assert_eq!(parse_label(&label.to_string()).unwrap(), *label);
+        }
+        let text: Vec<String> = labels.iter().map(|label| label.to_string()).collect();
+        let text = format!("# seed 7\n\n{}\n", text.join("\n"));
+        assert_eq!(parse_labels(&text).unwrap(), labels);
+
+        assert!(parse_label("f5 d6").is_err());
+        assert!(parse_label("f5 f5 | a1:+2").is_err());
+        assert!(parse_label("f5 | a1:two").is_err());
+    }
+
+    #[test]
+    fn test_shipped_labels() {
+        let labels = parse_labels(include_str!("../labels/endgame-12.txt")).unwrap();
+        assert_eq!(labels.len(), 2000);
+        assert_eq!(labels[0].game().empty_squares(), 12);
+        assert_eq!(labels[0], generate(1, 12, 0)[0]);
+    }
+
+    #[test]
+    fn test_score() {
+        let labels = generate(10, 6, 1);
+        let perfect = score(&mut Perfect, &labels);
+        assert_eq!(perfect.positions, 10);
+        assert_eq!(perfect.accuracy, 1.0);
+        assert_eq!(perfect.mean_loss, 0.0);
+
+        let greedy = score(&mut Opponent::Greedy, &labels);
+        assert!(greedy.accuracy <= 1.0);
+        assert!(greedy.mean_loss >= 0.0);
+        assert_eq!(score(&mut Opponent::Greedy, &[]).accuracy, 0.0);
+    }
+}
@@ -0,0 +1,430 @@
+//! Round-robin and Swiss events between more than two players.
+//!
+//! A [`League`] keeps the results of every game between its players and ranks them by
+//! points, a win scoring 1 and a draw ½, with the sum of their opponents' scores (SOS) as the
+//! tie-break. Pairings come from [`round_robin`], every player meeting every other once per
+//! cycle, or from [`League::swiss_pairings`], which pairs players on equal scores who have not
+//! met yet and needs far fewer rounds for a large field.
+//!
+//! Games are played through a single game [`Tournament`], so illegal moves and crashes are
+//! forfeited the same way as in head-to-head matches.
+use std::fmt::Write;
+
+use serde::{Deserialize, Serialize};
+
+use crate::othello::Color;
+use crate::othello::Color::{Black, White};
+use crate::tournament::Tournament;
+use crate::Player;
+
+/// Creates a fresh player for every game, given the game's id, e.g. to seed it with the game's
+/// random stream by [`Herb::with_stream`](crate::Herb::with_stream).
+pub type PlayerFactory<'a> = dyn Fn(u64) -> Box<dyn Player> + Sync + 'a;
+
+/// A pairing of two players by their index in the [`League`], black first.
+pub type Pairing = (usize, usize);
+
+/// A game played in a [`League`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LeagueGame {
+    pub round: usize,
+    pub black: usize,
+    pub white: usize,
+    pub winner: Option<Color>,
+}
+
+impl LeagueGame {
+    /// Returns the points the given player scored in this game, `None` if they did not play.
+    pub fn points(&self, player: usize) -> Option<f64> {
+        let color = if player == self.black {
+            Black
+        } else if player == self.white {
+            White
+        } else {
+            return None;
+        };
+        Some(match self.winner {
+            None => 0.5,
+            Some(winner) if winner == color => 1.0,
+            Some(_) => 0.0,
+        })
+    }
+
+    /// Returns the opponent of the given player in this game, `None` if they did not play.
+    pub fn opponent(&self, player: usize) -> Option<usize> {
+        if player == self.black {
+            Some(self.white)
+        } else if player == self.white {
+            Some(self.black)
+        } else {
+            None
+        }
+    }
+}
+
+/// A player's place in the [`League::standings`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standing {
+    pub player: usize,
+    pub points: f64,
+    pub games: u32,
+    /// Sum of the points of every opponent, once per game against them.
+    pub sos: f64,
+}
+
+/// The players and results of a round-robin or Swiss event.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct League {
+    names: Vec<String>,
+    games: Vec<LeagueGame>,
+    /// Players that sat out a round, each bye scores a win.
+    byes: Vec<usize>,
+}
+
+impl League {
+    /// Create a league of the named players, who are referred to by their index.
+    pub fn new(names: Vec<String>) -> Self {
+        League {
+            names,
+            ..League::default()
+        }
+    }
+
+    /// Returns the names of the players.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Returns every game played so far.
+    pub fn games(&self) -> &[LeagueGame] {
+        &self.games
+    }
+
+    /// Record a finished game.
+    pub fn record(&mut self, round: usize, (black, white): Pairing, winner: Option<Color>) {
+        self.games.push(LeagueGame {
+            round,
+            black,
+            white,
+            winner,
+        });
+    }
+
+    /// Record that the player sat out a round.
+    pub fn record_bye(&mut self, player: usize) {
+        self.byes.push(player);
+    }
+
+    /// Returns the points of the given player, byes included.
+    pub fn points(&self, player: usize) -> f64 {
+        let byes = self.byes.iter().filter(|&&bye| bye == player).count();
+        self.games
+            .iter()
+            .filter_map(|game| game.points(player))
+            .sum::<f64>()
+            + byes as f64
+    }
+
+    /// Returns the sum of the points of the given player's opponents.
+    pub fn sos(&self, player: usize) -> f64 {
+        self.games
+            .iter()
+            .filter_map(|game| game.opponent(player))
+            .map(|opponent| self.points(opponent))
+            .sum()
+    }
+
+    /// Returns the players ranked by points, then SOS, ties keeping their index order.
+    pub fn standings(&self) -> Vec<Standing> {
+        let mut standings: Vec<_> = (0..self.names.len())
+            .map(|player| Standing {
+                player,
+                points: self.points(player),
+                games: self
+                    .games
+                    .iter()
+                    .filter(|g| g.points(player).is_some())
+                    .count() as u32,
+                sos: self.sos(player),
+            })
+            .collect();
+        standings.sort_by(|a, b| {
+            b.points
+                .total_cmp(&a.points)
+                .then(b.sos.total_cmp(&a.sos))
+                .then(a.player.cmp(&b.player))
+        });
+        standings
+    }
+
+    /// Returns the number of games the player has played as black.
+    fn blacks(&self, player: usize) -> usize {
+        self.games.iter().filter(|g| g.black == player).count()
+    }
+
+    /// Returns true if the two players have already met.
+    fn have_met(&self, a: usize, b: usize) -> bool {
+        self.games
+            .iter()
+            .any(|g| g.opponent(a) == Some(b) || g.opponent(b) == Some(a))
+    }
+
+    /// Returns the pairings of the next Swiss round and the player with a bye, if the number
+    /// of players is odd.
+    ///
+    /// Going down the standings, each player is paired with the highest ranked player they
+    /// have not met that still lets everybody below them be paired without a rematch. Only if
+    /// that is impossible are rematches allowed. The lowest ranked player without a bye sits
+    /// out. The player who has had black less often gets black.
+    pub fn swiss_pairings(&self) -> (Vec<Pairing>, Option<usize>) {
+        let mut unpaired: Vec<_> = self.standings().into_iter().map(|s| s.player).collect();
+        let mut bye = None;
+        if unpaired.len() % 2 == 1 {
+            let sits_out = unpaired
+                .iter()
+                .rposition(|player| !self.byes.contains(player))
+                .unwrap_or(unpaired.len() - 1);
+            bye = Some(unpaired.remove(sits_out));
+        }
+
+        let pairs = self
+            .pair_without_rematches(&unpaired)
+            .unwrap_or_else(|| unpaired.chunks(2).map(|pair| (pair[0], pair[1])).collect());
+        let pairings = pairs
+            .into_iter()
+            .map(|(a, b)| {
+                if self.blacks(b) < self.blacks(a) {
+                    (b, a)
+                } else {
+                    (a, b)
+                }
+            })
+            .collect();
+        (pairings, bye)
+    }
+
+    /// Pair the players, given in ranked order, so nobody meets an opponent again. Returns
+    /// `None` if that is impossible.
+    fn pair_without_rematches(&self, players: &[usize]) -> Option<Vec<Pairing>> {
+        let Some((&player, rest)) = players.split_first() else {
+            return Some(Vec::new());
+        };
+        for (i, &opponent) in rest.iter().enumerate() {
+            if self.have_met(player, opponent) {
+                continue;
+            }
+            let mut others = rest.to_vec();
+            others.remove(i);
+            if let Some(mut pairings) = self.pair_without_rematches(&others) {
+                pairings.insert(0, (player, opponent));
+                return Some(pairings);
+            }
+        }
+        None
+    }
+
+    /// Play one game for every pairing, creating fresh players for each, and record the
+    /// results under the given round. Each game starts with `opening_plies` random moves
+    /// drawn with the seed. The `i`-th game of round `round` has the id `1000 * round + i`.
+    pub fn play_round(
+        &mut self,
+        round: usize,
+        pairings: &[Pairing],
+        players: &[&PlayerFactory<'_>],
+        opening_plies: usize,
+        seed: u64,
+    ) {
+        for (i, &(black, white)) in pairings.iter().enumerate() {
+            let game_id = (round * 1000 + i) as u64;
+            let mut tournament = Tournament::new(1, opening_plies, seed ^ game_id);
+            let mut first = players[black](game_id);
+            let mut second = players[white](game_id);
+            let winner = tournament
+                .play_next(&mut first, &mut second)
+                .and_then(|result| result.winner);
+            self.record(round, (black, white), winner);
+        }
+    }
+
+    /// Returns the standings as a crosstable with one row per player, in ranked order, and
+    /// the points scored against every opponent in the columns.
+    pub fn crosstable(&self) -> String {
+        let standings = self.standings();
+        let width = self.names.iter().map(|n| n.len()).max().unwrap_or(0).max(4);
+        let mut table = format!("{:>3} {:<width$}", "#", "Name");
+        for rank in 1..=standings.len() {
+            let _ = write!(table, " {:>5}", rank);
+        }
+        table.push_str("  Points    SOS\n");
+
+        for (rank, standing) in standings.iter().enumerate() {
+            let _ = write!(
+                table,
+                "{:>3} {:<width$}",
+                rank + 1,
+                self.names[standing.player]
+            );
+            for opponent in &standings {
+                let cell = if opponent.player == standing.player {
+                    "-".to_string()
+                } else {
+                    self.head_to_head(standing.player, opponent.player)
+                        .map_or(String::new(), |points| points.to_string())
+                };
+                let _ = write!(table, " {:>5}", cell);
+            }
+            let _ = writeln!(table, "  {:>6} {:>6}", standing.points, standing.sos);
+        }
+        table
+    }
+
+    /// Returns the crosstable as CSV, a header row and one row per player in ranked order.
+    pub fn crosstable_csv(&self) -> String {
+        let standings = self.standings();
+        let mut csv = "rank,name,points,sos".to_string();
+        for standing in &standings {
+            let _ = write!(csv, ",{}", self.names[standing.player]);
+        }
+        csv.push('\n');
+        for (rank, standing) in standings.iter().enumerate() {
+            let _ = write!(
+                csv,
+                "{},{},{},{}",
+                rank + 1,
+                self.names[standing.player],
+                standing.points,
+                standing.sos
+            );
+            for opponent in &standings {
+                let points = self.head_to_head(standing.player, opponent.player);
+                let _ = write!(csv, ",{}", points.map_or(String::new(), |p| p.to_string()));
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Returns the points the player scored against the opponent, `None` if they have not met.
+    fn head_to_head(&self, player: usize, opponent: usize) -> Option<f64> {
+        let points: Vec<_> = self
+            .games
+            .iter()
+            .filter(|g| g.opponent(player) == Some(opponent))
+            .filter_map(|g| g.points(player))
+            .collect();
+        (!points.is_empty()).then(|| points.iter().sum())
+    }
+}
+
+/// Returns the rounds of a round-robin between `players` players, every player meeting every
+/// other once. With an odd number of players one sits out each round.
+///
+/// Uses the circle method: the first player stays put while the others rotate around it.
+/// Colors alternate between rounds so every player gets black about half the time, play the
+/// rounds again with the colors swapped for a double round-robin.
+pub fn round_robin(players: usize) -> Vec<Vec<Pairing>> {
+    // a player that does not exist stands in for the bye
+    let slots = players + players % 2;
+    let mut circle: Vec<_> = (0..slots).collect();
+    let mut rounds = Vec::new();
+    for round in 0..slots.saturating_sub(1) {
+        let mut pairings = Vec::new();
+        for i in 0..slots / 2 {
+            let (a, b) = (circle[i], circle[slots - 1 - i]);
+            if a >= players || b >= players {
+                continue;
+            }
+            if (round + i).is_multiple_of(2) {
+                pairings.push((a, b));
+            } else {
+                pairings.push((b, a));
+            }
+        }
+        rounds.push(pairings);
+        circle[1..].rotate_right(1);
+    }
+    rounds
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::othello::{Game, Move};
+
+    /// Plays the first legal move.
+    struct FirstMove;
+
+    impl Player for FirstMove {
+        fn get_next_move(&mut self, game_state: Game) -> Move {
+            *game_state.legal_moves().first().unwrap_or(&Move::Pass)
+        }
+    }
+
+    /// Panics instead of moving, forfeiting every game.
+    struct Crashes;
+
+    impl Player for Crashes {
+        fn get_next_move(&mut self, _game_state: Game) -> Move {
+            panic!("crashed")
+        }
+    }
+
+    #[test]
+    fn test_round_robin() {
+        for players in 2..=7 {
+            let rounds = round_robin(players);
+            let pairs: Vec<_> = rounds.iter().flatten().collect();
+            let unique: HashSet<_> = pairs.iter().map(|&&(a, b)| (a.min(b), a.max(b))).collect();
+            assert_eq!(pairs.len(), players * (players - 1) / 2);
+            assert_eq!(unique.len(), pairs.len());
+            for round in &rounds {
+                let seated: HashSet<_> = round.iter().flat_map(|&(a, b)| [a, b]).collect();
+                assert_eq!(seated.len(), 2 * round.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_standings_and_swiss_pairings() {
+        let names = ["a", "b", "c", "d", "e"].map(String::from).to_vec();
+        let mut league = League::new(names);
+        league.record(0, (0, 1), Some(Black));
+        league.record(0, (2, 3), None);
+        league.record_bye(4);
+
+        let standings = league.standings();
+        let order: Vec<_> = standings.iter().map(|s| s.player).collect();
+        assert_eq!(order, [0, 4, 2, 3, 1]);
+        assert_eq!(standings[0].sos, 0.0);
+        assert_eq!(league.sos(2), 0.5);
+
+        let (pairings, bye) = league.swiss_pairings();
+        assert_eq!(bye, Some(1));
+        // the drawn players have met, so the leader takes one of them instead of the bye
+        assert_eq!(pairings, [(0, 2), (4, 3)]);
+    }
+
+    #[test]
+    fn test_play_round_and_crosstable() {
+        let names = ["first", "second", "crash"].map(String::from).to_vec();
+        let mut league = League::new(names);
+        let first = |_| Box::new(FirstMove) as Box<dyn Player>;
+        let crash = |_| Box::new(Crashes) as Box<dyn Player>;
+        let players: [&PlayerFactory<'_>; 3] = [&first, &first, &crash];
+        for (round, pairings) in round_robin(3).iter().enumerate() {
+            league.play_round(round, pairings, &players, 2, 0);
+        }
+        assert_eq!(league.games().len(), 3);
+        assert_eq!(league.points(2), 0.0);
+        assert_eq!(league.standings().last().unwrap().player, 2);
+
+        let table = league.crosstable();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().last().unwrap().contains("crash"));
+        let csv = league.crosstable_csv();
+        assert!(csv.starts_with("rank,name,points,sos,"));
+        assert!(csv.lines().last().unwrap().starts_with("3,crash,0,"));
+    }
+}
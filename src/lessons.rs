@@ -0,0 +1,233 @@
+//! Lessons learned from Herb's own blunders.
+//!
+//! [`post_mortem`] goes through every position of a finished game where Herb was to move and
+//! searches each legal move as a forced line, see [`compare_moves`]. A move that gives up at
+//! least the threshold of Herb's win rate against the best of them is a blunder, and becomes
+//! a [`Lesson`]: the position, the bad move and the opponent's refutation of it.
+//!
+//! The lessons are kept in a json file named by `lessons` in the [`Config`](crate::config::Config).
+//! Before searching, Herb looks the position up and vetoes the moves it has been taught
+//! not to play, so the search never selects them and never returns them unless nothing else
+//! is legal. Positions are stored in their canonical orientation, so a lesson also applies
+//! to every rotation and reflection of the position it was learned in.
+//!
+//! `herb-lessons` adds the blunders of game transcripts to a lessons file and lists them.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::compare_moves;
+use crate::config::MctsConfig;
+use crate::formats::{self, Header, VersionedReader};
+use crate::othello::{Color, Game, GameError, Move};
+use crate::record::{format_move, GameRecord};
+
+/// The format of lessons files.
+pub const FORMAT: Header = Header::new("lessons", 1);
+
+/// Share of the win rate a move must give up to be learned from, the loss the
+/// [`kibitz`](crate::kibitz) commentary calls a blunder.
+pub const DEFAULT_THRESHOLD: f64 = crate::kibitz::BLUNDER;
+
+/// A move that lost too much, learned from a game.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lesson {
+    /// The position before the move, in its canonical orientation.
+    pub position: Game,
+    /// The bad move, in the orientation of `position`.
+    pub mv: Move,
+    /// The opponent's best reply to the bad move, in the orientation of `position`.
+    pub refutation: Move,
+    /// The share of the mover's win rate the move gave up against the best move.
+    pub loss: f64,
+}
+
+impl Lesson {
+    /// Create the lesson that `mv` loses `loss` of the win rate in the game, refuted by
+    /// `refutation`.
+    pub fn new(game: &Game, mv: Move, refutation: Move, loss: f64) -> Self {
+        let (position, symmetry) = game.canonical();
+        Lesson {
+            position,
+            mv: mv.transform(symmetry),
+            refutation: refutation.transform(symmetry),
+            loss,
+        }
+    }
+
+    /// Returns the bad move if the lesson applies to the game, in the game's orientation.
+    pub fn applies_to(&self, game: &Game) -> Option<Move> {
+        let (position, symmetry) = game.canonical();
+        (position.zobrist_hash() == self.position.zobrist_hash())
+            .then(|| self.mv.transform(symmetry.inverse()))
+    }
+}
+
+impl Display for Lesson {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "turn {}: {} {} loses {:.0}%, refuted by {}",
+            self.position.get_turn() + 1,
+            self.position.to_move(),
+            format_move(self.mv),
+            self.loss * 100.0,
+            format_move(self.refutation)
+        )
+    }
+}
+
+/// A collection of [`Lesson`]s.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lessons {
+    pub lessons: Vec<Lesson>,
+}
+
+impl Lessons {
+    /// Read a lessons file. A file that does not exist holds no lessons yet.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let reader = VersionedReader::new(FORMAT).migration(0, Ok);
+        match reader.read_file(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Lessons::default()),
+            result => result,
+        }
+    }
+
+    /// Write the lessons to the file, replacing it only once the new contents are safely on
+    /// disk.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        formats::save(path, FORMAT, self)
+    }
+
+    /// Add the lesson, unless the same move in the same position has been learned already.
+    /// Returns whether it was added.
+    pub fn learn(&mut self, lesson: Lesson) -> bool {
+        let known = self.lessons.iter().any(|known| {
+            known.mv == lesson.mv && known.position.zobrist_hash() == lesson.position.zobrist_hash()
+        });
+        if !known {
+            self.lessons.push(lesson);
+        }
+        !known
+    }
+
+    /// Returns the legal moves of the game that a lesson says not to play.
+    pub fn vetoes(&self, game: &Game) -> Vec<Move> {
+        let legal_moves = game.legal_moves();
+        let mut vetoes: Vec<Move> = self
+            .lessons
+            .iter()
+            .filter_map(|lesson| lesson.applies_to(game))
+            .filter(|mv| legal_moves.contains(mv))
+            .collect();
+        vetoes.dedup();
+        vetoes
+    }
+}
+
+/// Search every legal move in every position of the game where `color` was to move for
+/// `budget` iterations and return a [`Lesson`] for every move played that gave up at least
+/// `threshold` of the mover's win rate against the best of them.
+///
+/// Returns an error if the record holds an illegal move.
+pub fn post_mortem(
+    record: &GameRecord,
+    color: Color,
+    config: &MctsConfig,
+    budget: u64,
+    threshold: f64,
+) -> Result<Vec<Lesson>, GameError> {
+    let mut lessons = Vec::new();
+    let mut game = Game::new();
+    for recorded in &record.moves {
+        let mv = recorded.mv;
+        if game.to_move() == color && game.legal_moves().len() > 1 {
+            let analyses = compare_moves(game, &game.legal_moves(), config, budget)?;
+            let played = analyses.iter().find(|analysis| analysis.line == [mv]);
+            if let (Some(best), Some(played)) = (analyses.first(), played) {
+                let loss = best.win_rate - played.win_rate;
+                if loss >= threshold && best.line != played.line {
+                    lessons.push(Lesson::new(&game, mv, played.best_move, loss));
+                }
+            }
+        }
+        game.play_next_turn(mv)?;
+    }
+    Ok(lessons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::othello::Symmetry;
+    use crate::record::parse_move;
+
+    #[test]
+    fn test_lessons() {
+        let mut game = Game::new();
+        game.play_next_turn(parse_move("d3").unwrap()).unwrap();
+        let (c3, c5) = (parse_move("c3").unwrap(), parse_move("c5").unwrap());
+        let lesson = Lesson::new(&game, c3, parse_move("c4").unwrap(), 0.3);
+        assert_eq!(lesson.applies_to(&game), Some(c3));
+        assert_eq!(lesson.applies_to(&Game::new()), None);
+        // the same lesson in a reflection of the position
+        let mirrored = game.transform(Symmetry::FlipDiagonal);
+        assert_eq!(
+            lesson.applies_to(&mirrored),
+            Some(c3.transform(Symmetry::FlipDiagonal))
+        );
+
+        let mut lessons = Lessons::default();
+        assert!(lessons.learn(lesson));
+        assert!(!lessons.learn(Lesson::new(
+            &mirrored,
+            c3.transform(Symmetry::FlipDiagonal),
+            c5,
+            0.5
+        )));
+        assert!(lessons.learn(Lesson::new(&game, c5, c3, 0.2)));
+        assert_eq!(lessons.vetoes(&game), vec![c3, c5]);
+        assert!(lessons.vetoes(&Game::new()).is_empty());
+
+        let path = std::env::temp_dir().join(format!("herb-lessons-{}.json", std::process::id()));
+        assert_eq!(Lessons::load(&path).unwrap(), Lessons::default());
+        lessons.save(&path).unwrap();
+        assert_eq!(Lessons::load(&path).unwrap(), lessons);
+        fs::write(&path, "{").unwrap();
+        assert!(Lessons::load(&path).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_post_mortem() {
+        let config = MctsConfig::default();
+        let record = GameRecord::from_moves(
+            &["d3", "c5", "f6", "f5", "e6", "e3"]
+                .iter()
+                .map(|mv| parse_move(mv).unwrap())
+                .collect::<Vec<_>>(),
+        );
+        // any move but the best is a lesson
+        let lessons = post_mortem(&record, Color::White, &config, 100, f64::MIN).unwrap();
+        assert!(!lessons.is_empty());
+        let mut game = Game::new();
+        for recorded in &record.moves {
+            if let Some(lesson) = lessons.iter().find(|l| l.applies_to(&game).is_some()) {
+                assert_eq!(game.to_move(), Color::White);
+                assert_eq!(lesson.applies_to(&game), Some(recorded.mv));
+                let mut after = game;
+                after.play_next_turn(recorded.mv).unwrap();
+                let refutation = lesson.refutation.transform(game.canonical().1.inverse());
+                assert!(after.legal_moves().contains(&refutation));
+            }
+            game.play_next_turn(recorded.mv).unwrap();
+        }
+        assert!(post_mortem(&record, Color::White, &config, 100, 1.1)
+            .unwrap()
+            .is_empty());
+    }
+}
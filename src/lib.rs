@@ -4,55 +4,277 @@
 //!
 //! Created by: Hayden Holbrook
 //!
+use std::fmt::Display;
 use std::io;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
 
+use rand::Rng;
 use rayon::current_num_threads;
 use rayon::prelude::*;
 
-use crate::config::Config;
-use crate::drmecref::DrMecRef;
+use crate::autosave::{config_digest, Autosave};
+use crate::build_info::BuildInfo;
+use crate::cache::EvalCache;
+use crate::config::{Config, Verbosity};
+use crate::driver::{GameResult, Match, MatchError, Seat};
+use crate::drmecref::{DrMecRef, Referee};
+use crate::events::{Events, Listener};
+use crate::lessons::Lessons;
 use crate::mcts::Tree;
 use crate::othello::Move::Pass;
 use crate::othello::{Color, Game, Move};
+use crate::random::StreamRng;
+use crate::record::format_move;
+use crate::telemetry::{SearchStats, WorkerStats};
+use crate::time_manager::{GamePlan, MoveController};
 
+pub mod analysis;
+pub mod autosave;
+pub mod bench;
+pub mod build_info;
+pub mod cache;
+#[cfg(feature = "chat")]
+pub mod chat;
+pub mod club;
 pub mod config;
+pub mod corpus;
+pub mod determinism;
+pub mod driver;
 pub mod drmecref;
+pub mod dumps;
+pub mod engine;
+pub mod evalgraph;
+pub mod evaluation;
+pub mod events;
+pub mod formats;
+pub mod gate;
+pub mod golden;
+pub mod heatmap;
+pub mod interface;
+pub mod kibitz;
+pub mod labels;
+pub mod league;
+pub mod lessons;
 pub mod mcts;
+pub mod online;
+pub mod openings;
+pub mod opponents;
 pub mod othello;
+pub mod random;
+pub mod record;
+#[cfg(feature = "render")]
+pub mod render;
+pub mod repertoire;
+pub mod scaling;
+pub mod session;
+pub mod snapshot;
+pub mod solver;
+pub mod spectator;
+pub mod strength;
+pub mod sweep;
+pub mod telemetry;
+pub mod time_manager;
+pub mod tournament;
+pub mod training;
+pub mod wthor;
 
-// Time allocations per turn as a percentage of the remaining time
-const TIME_ALLOCATIONS: [f64; 70] = [
-    0.015, 0.015, 0.015, 0.015, 0.025, 0.025, 0.025, 0.025, 0.025, 0.025, 0.048, 0.048, 0.048,
-    0.048, 0.048, 0.048, 0.050, 0.051, 0.052, 0.053, 0.044, 0.045, 0.049, 0.049, 0.049, 0.051,
-    0.053, 0.055, 0.057, 0.059, 0.060, 0.060, 0.061, 0.062, 0.063, 0.064, 0.065, 0.065, 0.065,
-    0.065, 0.167, 0.168, 0.169, 0.169, 0.171, 0.172, 0.173, 0.175, 0.180, 0.180, 0.181, 0.187,
-    0.196, 0.199, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060, 0.060,
-    0.060, 0.060, 0.060, 0.060, 0.060,
-];
+// Embedders run several engines in one process, each on a thread of its own, and share
+// trees, caches and positions between threads.
+const _: () = {
+    const fn send<T: Send>() {}
+    const fn send_sync<T: Send + Sync>() {}
+    send::<Herb>();
+    send::<engine::EngineProcess>();
+    send_sync::<mcts::Tree>();
+    send_sync::<cache::EvalCache>();
+    send_sync::<config::Config>();
+    send_sync::<othello::Game>();
+    send_sync::<kibitz::Kibitzer>();
+    send_sync::<club::Scorekeeper>();
+};
 
+/// Herb, the MCTS player.
+///
+/// A Herb keeps all of its state to itself: its random number generator is its own and its
+/// comments go to the function it was created with, so several can be embedded in one
+/// process. Herb is `Send` and can be moved to a thread of its own, but not `Sync`, because
+/// its [`Listener`]s are only `Send`.
 pub struct Herb {
     config: Config,
     mcts: Tree,
+    /// Chooses among the repertoire's moves.
+    rng: StreamRng,
+    comments: Box<dyn FnMut(&str) + Send>,
     search_iterations: u64,
+    fallbacks: u64,
     time_remaining: f64,
+    /// Time already off the clock of the next game, see [`Herb::resume`].
+    resumed_time: Option<Duration>,
+    plan: GamePlan,
+    /// The color of the current game, see [`Player::new_game`].
+    color: Option<Color>,
+    events: Events,
+    warm_up: Option<WarmUp>,
+    eval_cache: Option<Arc<EvalCache>>,
+    /// Moves not to play again, see [`lessons`].
+    lessons: Lessons,
+}
+
+/// A search running in the background before Herb's first move.
+struct WarmUp {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Tree>,
 }
 
 impl Herb {
-    /// Create a new instance of Herb using the given [`Config`].
+    /// Create a new instance of Herb using the given [`Config`], commenting to the referee.
     pub fn new(config: Config) -> Herb {
-        let tree = Tree::from_config(config.mcts_config.clone());
-        let max_time = config.max_time;
-        if config.log {
-            DrMecRef::comment(format!("{:?}", config));
+        Herb::with_comments(config, |message| DrMecRef::comment(message))
+    }
+
+    /// Create a new instance of Herb using the given [`Config`] that passes its comments,
+    /// the referee's `C` lines, to the given function instead.
+    pub fn with_comments(config: Config, comments: impl FnMut(&str) + Send + 'static) -> Herb {
+        let eval_cache =
+            (config.eval_cache > 0).then(|| Arc::new(EvalCache::new(config.eval_cache)));
+        let mut tree = Tree::from_config(config.mcts_config.clone());
+        if let Some(cache) = &eval_cache {
+            tree.set_eval_cache(cache.clone());
         }
-        Herb {
+        let (lessons, lessons_error) = match &config.lessons {
+            Some(path) => match Lessons::load(Path::new(path)) {
+                Ok(lessons) => (lessons, None),
+                Err(e) => (Lessons::default(), Some(e)),
+            },
+            None => (Lessons::default(), None),
+        };
+        let max_time = config.max_time;
+        let plan = GamePlan::new(
+            config.time.plan.clone(),
+            max_time,
+            config.repertoire.book_plies(),
+        );
+        let mut herb = Herb {
             config,
             mcts: tree,
+            rng: StreamRng::from_entropy(),
+            comments: Box::new(comments),
             search_iterations: 0,
+            fallbacks: 0,
             time_remaining: max_time,
+            resumed_time: None,
+            plan,
+            color: None,
+            events: Events::default(),
+            warm_up: None,
+            eval_cache,
+            lessons,
+        };
+        if let Some(e) = lessons_error {
+            herb.comment(
+                Verbosity::Results,
+                format!("Herb: failed to read the lessons: {}", e),
+            );
+        }
+        herb.comment(Verbosity::Debug, format!("{:?}", herb.config));
+        let invalid: Vec<String> = herb
+            .config
+            .repertoire
+            .invalid_lines()
+            .into_iter()
+            .map(|line| format!("Herb: Ignoring invalid opening line {}", line.moves))
+            .collect();
+        invalid
+            .into_iter()
+            .for_each(|message| herb.comment(Verbosity::Results, message));
+        herb
+    }
+
+    /// Seed the random number generator, so Herb chooses among the repertoire's moves the same
+    /// way every time.
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_stream(seed, 0, 0)
+    }
+
+    /// Seed the random number generator with a stream of its own for the game and worker, so
+    /// Herbs playing many games on many workers choose differently in every game and the same
+    /// way on every run, see [`random`].
+    pub fn with_stream(mut self, seed: u64, game_id: u64, worker_id: u32) -> Self {
+        self.rng = self.config.rng.stream(seed, game_id, worker_id);
+        self
+    }
+
+    /// Comment if the [`Verbosity`] of the configuration is at least `level`.
+    fn comment(&mut self, level: Verbosity, message: impl Display) {
+        if self.config.verbosity().allows(level) {
+            (self.comments)(&message.to_string());
+        }
+    }
+
+    /// Start searching the given game in the background, so the idle time between getting
+    /// ready and the first move request is not wasted.
+    ///
+    /// The warm-up stops after `warm_up` seconds from the [`Config`], or as soon as Herb is
+    /// asked for a move, which merges its tree into Herb's. The tree is a transposition table,
+    /// so a warm-up from the initial position also helps when the opponent moves first.
+    pub fn warm_up(&mut self, game: Game) {
+        if self.config.warm_up <= 0.0 || self.warm_up.is_some() {
+            return;
+        }
+        let deadline = Instant::now() + Duration::from_secs_f64(self.config.warm_up);
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut tree = self.new_tree();
+        let handle = thread::spawn({
+            let stop = stop.clone();
+            move || {
+                while !stop.load(Ordering::Relaxed) && Instant::now() <= deadline {
+                    tree.search(game);
+                }
+                tree
+            }
+        });
+        self.warm_up = Some(WarmUp { stop, handle });
+    }
+
+    /// Stop the warm-up, if one is running, and merge its tree into Herb's.
+    fn finish_warm_up(&mut self) {
+        if let Some(warm_up) = self.warm_up.take() {
+            warm_up.stop.store(true, Ordering::Relaxed);
+            if let Ok(tree) = warm_up.handle.join() {
+                self.comment(
+                    Verbosity::Debug,
+                    format!(
+                        "Herb: Warm-up completed {} iterations",
+                        tree.search_iterations
+                    ),
+                );
+                self.mcts.merge(tree);
+            }
+        }
+    }
+
+    /// Create a tree searching with Herb's configuration and evaluation cache.
+    fn new_tree(&self) -> Tree {
+        let mut tree = Tree::from_config(self.config.mcts_config.clone());
+        if let Some(cache) = &self.eval_cache {
+            tree.set_eval_cache(cache.clone());
         }
+        tree
+    }
+
+    /// Add a [`Listener`] that is told about every following event of the game.
+    pub fn subscribe(&mut self, listener: Box<dyn Listener>) {
+        self.events.subscribe(listener);
+    }
+
+    /// Returns the subscribed listeners, to report events Herb does not see itself when it is
+    /// not played through a [`Match`], e.g. `herb.events().move_sent(&game, mv)`.
+    pub fn events(&mut self) -> &mut Events {
+        &mut self.events
     }
 
     /// Returns the total number of search iterations performed by this tree.
@@ -60,109 +282,413 @@ impl Herb {
         self.search_iterations
     }
 
-    /// Calculate the time allocation for a turn based on the given game state.
-    fn dynamic_time_limit(&mut self, game: Game) -> Duration {
-        let turn_num = game.get_turn();
-        let time_for_turn = self.time_remaining * TIME_ALLOCATIONS[turn_num];
-        self.time_remaining -= time_for_turn;
-        Duration::from_secs_f64(time_for_turn)
+    /// Returns the number of moves the search had no legal move for, which were replaced by
+    /// [`Game::greedy_move`]. Playouts count theirs in the search telemetry.
+    pub fn fallbacks(&self) -> u64 {
+        self.fallbacks
+    }
+
+    /// Start the next game with `time_used` already off Herb's clock, for a game resumed
+    /// after it was interrupted.
+    pub fn resume(&mut self, time_used: Duration) {
+        self.resumed_time = Some(time_used);
+    }
+
+    /// Returns the controller for the search of the given game, with the target of the
+    /// [`GamePlan`] for the time left on Herb's clock.
+    fn move_controller(&self, game: Game) -> MoveController {
+        let target = self.plan.target(&game, self.time_remaining);
+        MoveController::new(&self.config.time.controller, target, self.time_remaining)
+            .guard_corners(&game)
+    }
+
+    /// Charge the time a search of the given game took to Herb's clock. Only positions with
+    /// Herb's own color to move are charged, others are searched for as long as Herb's own
+    /// turn would be.
+    fn charge(&mut self, game: Game, elapsed: Duration) {
+        if self.color.is_none_or(|color| color == game.to_move()) {
+            self.time_remaining -= elapsed.as_secs_f64();
+        }
     }
 
-    /// Get Herb's move for the given game. Herb assumes that `game.to_move()` is Herb's color
-    /// and will choose a move from the legal moves available for the given game.
+    /// Returns the number of search threads worth running for a turn of the given length.
+    ///
+    /// Every thread searches a tree of its own that is merged into Herb's at the end of the
+    /// turn, which only pays off when each of them gets `worker_time` seconds or more. A short
+    /// turn is searched by a single thread, and every `worker_time` seconds more adds another,
+    /// up to the number of cpus. A `worker_time` of 0 always uses all of them.
+    fn worker_count(&self, budget: Duration) -> usize {
+        let max_workers = current_num_threads();
+        if self.config.worker_time <= 0.0 {
+            return max_workers;
+        }
+        let workers = (budget.as_secs_f64() / self.config.worker_time).floor() as usize;
+        workers.clamp(1, max_workers)
+    }
+
+    /// Get the move for the color to move in the given game, chosen from its legal moves.
     fn get_move(&mut self, game: Game) -> Move {
+        self.finish_warm_up();
         let start_time = Instant::now();
-        let time_limit = start_time + self.dynamic_time_limit(game);
+        let controller = self.move_controller(game);
+        let workers = self.worker_count(controller.target());
+        if self.config.telemetry {
+            self.comment(
+                Verbosity::Moves,
+                format!(
+                    "Herb: {} search threads for {:?}, at most {:?}{}{}",
+                    workers,
+                    controller.target(),
+                    controller.limit(),
+                    if controller.is_panicking() {
+                        ", low on time"
+                    } else {
+                        ""
+                    },
+                    if controller.is_guarded() {
+                        ", a corner at stake"
+                    } else {
+                        ""
+                    }
+                ),
+            );
+        }
+        let vetoes = self.lessons.vetoes(&game);
+        if !vetoes.is_empty() {
+            let vetoed: Vec<String> = vetoes.iter().map(|mv| format_move(*mv)).collect();
+            self.comment(
+                Verbosity::Moves,
+                format!("Herb: Avoiding {} from the lessons", vetoed.join(", ")),
+            );
+        }
+        self.mcts.set_vetoes(game, &vetoes);
         // self.single_threaded_search(game, time_limit);
-        let trees = self.multi_threaded_search(game, time_limit);
+        let (trees, iterations) =
+            self.multi_threaded_search(game, start_time, &controller, workers, &vetoes);
+        self.charge(game, start_time.elapsed());
 
         trees.into_iter().for_each(|tree| {
             self.mcts.merge(tree);
         });
 
-        self.mcts.best_move(game, true)
+        if let Some(timings) = self.mcts.take_timings() {
+            for (phase, histogram) in timings.phases() {
+                self.comment(Verbosity::Debug, format!("Herb: {} {}", phase, histogram));
+            }
+            if let Some(cache) = &self.eval_cache {
+                self.comment(
+                    Verbosity::Debug,
+                    format!("Herb: eval cache {}", cache.take_stats()),
+                );
+            }
+            if let Some(stats) = self.mcts.take_outcome_stats() {
+                self.comment(
+                    Verbosity::Debug,
+                    format!("Herb: playout outcomes {}", stats),
+                );
+            }
+        }
+
+        if self.config.telemetry {
+            let pv: Vec<String> = self
+                .mcts
+                .principal_variation(game)
+                .iter()
+                .map(|step| step.to_string())
+                .collect();
+            self.comment(Verbosity::Moves, format!("Herb: pv {}", pv.join(", ")));
+            let pruned: Vec<String> = self
+                .mcts
+                .pruned_moves(game)
+                .into_iter()
+                .map(format_move)
+                .collect();
+            if !pruned.is_empty() {
+                self.comment(
+                    Verbosity::Moves,
+                    format!("Herb: pruned {}", pruned.join(", ")),
+                );
+            }
+        }
+
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            let value = self.mcts.evaluate(child);
+            let contributions = self.mcts.contributions(child);
+            self.comment(
+                Verbosity::Debug,
+                format!(
+                    "MCTS: Considering Move {}, Value: {} ({})",
+                    mv, value, contributions
+                ),
+            );
+        }
+        let mv = self.mcts.best_move(game);
+        self.events.search_complete(&game, mv, iterations);
+        mv
     }
 
     /// Perform the MCTS algorithm in a single thread until the time_limit is reached.
     /// The search will use the given game as the starting point in the tree.
     fn _single_threaded_search(&mut self, game: Game, time_limit: Instant) {
         while Instant::now() <= time_limit {
-            self.mcts.search(game)
+            self.mcts.search(game);
         }
     }
 
-    /// Perform the MCTS algorithm in the maximum number of threads equal to the number of cpus
-    /// available on whatever machine Herb is running on.
+    /// Perform the MCTS algorithm in the given number of threads, at most the number of cpus
+    /// available on whatever machine Herb is running on. Each thread stops when the controller
+    /// tells it to, judging by its own tree. No thread searches the vetoed moves.
     ///
-    /// Return forest, a `Vec<Tree>`, all rooted at the given game.
-    fn multi_threaded_search(&mut self, game: Game, time_limit: Instant) -> Vec<Tree> {
-        let num_trees = current_num_threads();
-
-        let search_counters: Vec<_> = (0..num_trees).map(|_| AtomicUsize::new(0)).collect();
+    /// Return forest, a `Vec<Tree>`, all rooted at the given game, and the total number of
+    /// iterations searched.
+    fn multi_threaded_search(
+        &mut self,
+        game: Game,
+        start_time: Instant,
+        controller: &MoveController,
+        num_trees: usize,
+        vetoes: &[Move],
+    ) -> (Vec<Tree>, u64) {
+        let workers: Vec<_> = (0..num_trees).map(|_| WorkerStats::default()).collect();
+        let telemetry = self.config.telemetry;
+        let eval_cache = &self.eval_cache;
+        let mcts_config = &self.config.mcts_config;
+        // every thread samples its repeated playouts from a stream of its own
+        let seed: u64 = self.rng.gen();
+        let backend = self.config.rng;
 
         // kick off the threads
         let trees: Vec<_> = (0..num_trees)
             .into_par_iter()
             .enumerate()
             .map(|(index, _)| {
-                let mut local_tree = Tree::new();
+                let mut local_tree = Tree::from_config(mcts_config.clone());
+                local_tree.set_rng(backend.stream(seed, 0, index as u32));
+                if let Some(cache) = &eval_cache {
+                    local_tree.set_eval_cache(cache.clone());
+                }
+                if telemetry {
+                    local_tree.enable_timings();
+                }
+                local_tree.set_vetoes(game, vetoes);
                 let local_game = game;
-                let counter = &search_counters[index];
+                let stats = &workers[index];
 
-                while Instant::now() <= time_limit {
-                    local_tree.search(local_game);
-                    counter.fetch_add(1, Ordering::SeqCst);
+                while !controller.should_stop(start_time.elapsed(), || {
+                    local_tree.best_move_share(local_game)
+                }) {
+                    stats.record(&local_tree.search(local_game));
                 }
                 local_tree
             })
             .collect();
 
-        if self.config.log {
-            let mut total = 0;
-            for (index, counter) in search_counters.iter().enumerate() {
-                DrMecRef::comment(format!(
+        let mut total = SearchStats::default();
+        for (index, worker) in workers.iter().enumerate() {
+            let stats = worker.snapshot();
+            self.comment(
+                Verbosity::Debug,
+                format!(
                     "Herb: Thread {} completed {} iterations",
-                    index,
-                    counter.load(Ordering::SeqCst)
-                ));
-                total += counter.load(Ordering::SeqCst);
-            }
-            DrMecRef::comment(format!(
+                    index, stats.iterations
+                ),
+            );
+            total.merge(&stats);
+        }
+        self.comment(
+            Verbosity::Debug,
+            format!(
                 "Herb: Total search iterations this turn: {}",
-                total
-            ));
-            self.search_iterations += total as u64;
+                total.iterations
+            ),
+        );
+        self.search_iterations += total.iterations;
+        if telemetry {
+            self.comment(Verbosity::Moves, format!("Herb: search {}", total));
         }
 
-        trees
+        (trees, total.iterations)
+    }
+}
+
+impl Drop for Herb {
+    fn drop(&mut self) {
+        if let Some(warm_up) = &self.warm_up {
+            warm_up.stop.store(true, Ordering::Relaxed);
+        }
     }
 }
 
 impl Player for Herb {
     /// Get Herb's next move for the given game.
     fn get_next_move(&mut self, game_state: Game) -> Move {
+        self.events.turn_start(&game_state);
         let legal_moves = game_state.legal_moves();
+        if let Some(mv) = self.config.repertoire.choose(&game_state, &mut self.rng) {
+            self.comment(
+                Verbosity::Moves,
+                format!("Herb: Playing {} from the repertoire", mv),
+            );
+            return mv;
+        }
         if !legal_moves.is_empty() {
             let mv = self.get_move(game_state);
-            return if legal_moves.contains(&mv) {
-                if self.config.log {
-                    DrMecRef::comment(format!(
+            if legal_moves.contains(&mv) {
+                self.comment(
+                    Verbosity::Debug,
+                    format!(
                         "Herb: Total Search iterations this game: {}",
                         self.search_iterations
-                    ));
-                    DrMecRef::comment(format!("Herb: Sending move: {}", mv));
-                }
+                    ),
+                );
+                self.comment(Verbosity::Moves, format!("Herb: Sending move: {}", mv));
                 mv
             } else {
-                if self.config.log {
-                    DrMecRef::comment("Herb: Got illegal move from search! Sending random move!!");
-                }
-                *legal_moves.first().unwrap_or(&Pass)
-            };
+                self.fallbacks += 1;
+                let fallback = game_state.greedy_move();
+                self.comment(
+                    Verbosity::Results,
+                    format!(
+                        "Herb: Search returned {}, sending greedy move {} ({} fallbacks so far)",
+                        mv, fallback, self.fallbacks
+                    ),
+                );
+                fallback
+            }
         } else {
             Pass
         }
     }
+
+    /// Start a game as `color` with the full time of the [`Config`], less the time of a
+    /// resumed game. The tree is kept, it is a transposition table of positions that may come
+    /// up again.
+    fn new_game(&mut self, color: Color) {
+        self.color = Some(color);
+        let resumed = self.resumed_time.take().unwrap_or_default();
+        self.time_remaining = self.config.max_time - resumed.as_secs_f64();
+    }
+
+    fn color(&self) -> Option<Color> {
+        self.color
+    }
+
+    fn move_played(&mut self, game: &Game, mv: Move) {
+        self.events.move_sent(game, mv);
+    }
+
+    fn opponent_moved(&mut self, game: &Game, mv: Move) {
+        self.events.opponent_move(game, mv);
+    }
+
+    fn game_over(&mut self, result: &GameResult) {
+        self.events.game_end(&result.game);
+    }
+}
+
+/// Play a game for Herb against the referee, from the referee assigning Herb a color until
+/// the game is over. This is the game loop of the `herb` binary, a [`Match`] between Herb and
+/// the referee as a remote seat.
+///
+/// Returns an error if reading from the referee fails, including the referee ending the game
+/// early, in which case [`official_result`](drmecref::official_result) has the final result.
+pub fn play_referee_game(config: Config, referee: &impl Referee) -> Result<GameResult, MatchError> {
+    referee_game(config, referee, None)
+}
+
+/// Continue the game of an [`Autosave`] against the referee, after the referee assigns Herb
+/// the same color again. Herb's clock starts with the time it had already used.
+///
+/// Returns an error if the referee assigns the other color, an error of
+/// [`play_referee_game`] otherwise.
+pub fn resume_referee_game(
+    config: Config,
+    referee: &impl Referee,
+    saved: &Autosave,
+) -> Result<GameResult, MatchError> {
+    referee_game(config, referee, Some(saved))
+}
+
+fn referee_game(
+    config: Config,
+    referee: &impl Referee,
+    saved: Option<&Autosave>,
+) -> Result<GameResult, MatchError> {
+    let verbosity = config.verbosity();
+    let comment = move |level: Verbosity, message: String| {
+        if verbosity.allows(level) {
+            DrMecRef::comment(message);
+        }
+    };
+    let herb_color = referee.init()?;
+    if let Some(saved) = saved {
+        if saved.color != herb_color {
+            return Err(MatchError::Interface(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Assigned {} but the autosaved game was played as {}",
+                    herb_color, saved.color
+                ),
+            )));
+        }
+        // a supervising referee that does not know the configuration leaves the digest empty
+        if !saved.config_digest.is_empty() && saved.config_digest != config_digest(&config) {
+            comment(
+                Verbosity::Results,
+                "Herb: resuming a game saved with a different configuration".to_string(),
+            );
+        }
+    }
+
+    // Let the ref know we are ready
+    referee.ready(herb_color);
+
+    let telemetry = config.telemetry;
+    if telemetry {
+        comment(
+            Verbosity::Moves,
+            format!("Herb: {}", BuildInfo::with_config(&config)),
+        );
+    }
+    let mut herb = Herb::new(config.clone());
+    let mut start = Game::new();
+    if let Some(saved) = saved {
+        herb.resume(saved.herb_time());
+        start = saved.game()?;
+    }
+    herb.warm_up(start);
+    let (black, white) = match herb_color {
+        Color::Black => (Seat::Local(&mut herb), Seat::Remote(referee)),
+        Color::White => (Seat::Remote(referee), Seat::Local(&mut herb)),
+    };
+    let mut game = Match::new(black, white)
+        .with_log("Main", verbosity)
+        .with_telemetry(telemetry);
+    if let Some(saved) = saved {
+        comment(
+            Verbosity::Results,
+            format!("Herb: resuming at turn {}", start.get_turn()),
+        );
+        game = game.resume(&saved.moves(), saved.black_time(), saved.white_time())?;
+    }
+    if let Some(path) = config.autosave.clone() {
+        let mut autosave = match saved {
+            Some(saved) => saved.clone(),
+            None => Autosave::new(&config, herb_color),
+        };
+        game = game.with_observer(move |result| {
+            autosave.update(&config, result);
+            if let Err(e) = autosave.save(Path::new(&path)) {
+                comment(
+                    Verbosity::Results,
+                    format!("Herb: failed to autosave: {}", e),
+                );
+            }
+        });
+    }
+    game.play()
 }
 
 pub trait GameInterface {
@@ -171,8 +697,412 @@ pub trait GameInterface {
 
     /// Receive a Move.
     fn receive_move(&self) -> io::Result<Move>;
+
+    /// Receive a Move, giving up at the deadline.
+    ///
+    /// Returns `Ok(None)` if no move arrived in time. The default implementation ignores the
+    /// deadline and blocks in [`GameInterface::receive_move`], wrap blocking interfaces in
+    /// [`Nonblocking`](crate::interface::Nonblocking) to make them honor it.
+    fn receive_move_until(&self, deadline: Instant) -> io::Result<Option<Move>> {
+        let _ = deadline;
+        self.receive_move().map(Some)
+    }
+
+    /// Receive a Move if one has already arrived, otherwise return `Ok(None)` straight away.
+    ///
+    /// This lets the caller keep searching while it waits for the opponent. It only returns
+    /// immediately if [`GameInterface::receive_move_until`] honors deadlines.
+    fn try_receive_move(&self) -> io::Result<Option<Move>> {
+        self.receive_move_until(Instant::now())
+    }
 }
 
+/// Something that chooses moves, e.g. Herb, a scripted opponent or the referee.
+///
+/// A player is told its color when a game starts, but [`Player::get_next_move`] chooses a move
+/// for whichever color is to move in the given position, which need not be the player's own,
+/// e.g. when analysing a game or consulting a player for the opponent's best reply.
 pub trait Player {
+    /// Choose a move for the color to move in the given position.
     fn get_next_move(&mut self, game_state: Game) -> Move;
+
+    /// A new game starts with the player assigned `color`. Does nothing by default.
+    fn new_game(&mut self, _color: Color) {}
+
+    /// Returns the color assigned by [`Player::new_game`], `None` by default and before the
+    /// first game.
+    fn color(&self) -> Option<Color> {
+        None
+    }
+
+    /// The player's move was played from the given position. Does nothing by default.
+    fn move_played(&mut self, _game: &Game, _mv: Move) {}
+
+    /// The opponent played a move from the given position. Does nothing by default.
+    fn opponent_moved(&mut self, _game: &Game, _mv: Move) {}
+
+    /// The game is over, or was stopped early with the given partial result. Does nothing by
+    /// default.
+    fn game_over(&mut self, _result: &GameResult) {}
+}
+
+impl<P: Player + ?Sized> Player for Box<P> {
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        (**self).get_next_move(game_state)
+    }
+
+    fn move_played(&mut self, game: &Game, mv: Move) {
+        (**self).move_played(game, mv)
+    }
+
+    fn opponent_moved(&mut self, game: &Game, mv: Move) {
+        (**self).opponent_moved(game, mv)
+    }
+
+    fn new_game(&mut self, color: Color) {
+        (**self).new_game(color)
+    }
+
+    fn color(&self) -> Option<Color> {
+        (**self).color()
+    }
+
+    fn game_over(&mut self, result: &GameResult) {
+        (**self).game_over(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ConfigError, MctsConfig};
+    use crate::drmecref::{official_result, MockReferee, Scripted};
+    use crate::time_manager::TimeConfig;
+
+    #[test]
+    fn test_warm_up() {
+        let config = Config {
+            log: false,
+            warm_up: 0.05,
+            ..Config::default()
+        };
+        let mut herb = Herb::new(config);
+        herb.warm_up(Game::new());
+        thread::sleep(Duration::from_millis(100));
+        herb.finish_warm_up();
+        assert!(herb.warm_up.is_none());
+        assert!(herb.mcts.search_iterations > 0);
+        // the children of the warm-up position are in the tree
+        let game = Game::new();
+        assert!(game.legal_moves().iter().any(|&mv| {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            herb.mcts.map.contains_key(&child.zobrist_hash())
+        }));
+
+        let config = Config {
+            warm_up: 0.0,
+            ..Config::default()
+        };
+        let mut herb = Herb::new(config);
+        herb.warm_up(Game::new());
+        assert!(herb.warm_up.is_none());
+    }
+
+    #[test]
+    fn test_herbs_on_threads() {
+        let config = Config {
+            max_time: 1.0,
+            warm_up: 0.0,
+            ..Config::default()
+        };
+        let handles: Vec<_> = (0..2)
+            .map(|seed| {
+                let comments = Arc::new(std::sync::Mutex::new(Vec::new()));
+                let mut herb = Herb::with_comments(config.clone(), {
+                    let comments = comments.clone();
+                    move |message: &str| comments.lock().unwrap().push(message.to_string())
+                })
+                .with_seed(seed);
+                thread::spawn(move || {
+                    let mv = herb.get_next_move(Game::new());
+                    (mv, comments.lock().unwrap().clone())
+                })
+            })
+            .collect();
+        for handle in handles {
+            let (mv, comments) = handle.join().unwrap();
+            assert!(Game::new().legal_moves().contains(&mv));
+            assert!(comments
+                .iter()
+                .any(|c| c.starts_with("MCTS: Considering Move")));
+            assert!(comments.contains(&format!("Herb: Sending move: {}", mv)));
+        }
+    }
+
+    #[test]
+    fn test_verbosity() {
+        assert_eq!(Config::default().verbosity(), Verbosity::Debug);
+        let quiet = Config {
+            log: false,
+            ..Config::default()
+        };
+        assert_eq!(quiet.verbosity(), Verbosity::Moves);
+        let config: Config = serde_json::from_str(r#"{"verbosity": "results"}"#).unwrap();
+        assert_eq!(config.verbosity(), Verbosity::Results);
+        assert!(Verbosity::Moves.allows(Verbosity::Results));
+        assert!(!Verbosity::Moves.allows(Verbosity::Debug));
+        assert!(!Verbosity::Silent.allows(Verbosity::Silent));
+
+        let comments_at = |verbosity| {
+            let comments = Arc::new(std::sync::Mutex::new(Vec::new()));
+            let config = Config {
+                max_time: 1.0,
+                warm_up: 0.0,
+                verbosity: Some(verbosity),
+                ..Config::default()
+            };
+            let mut herb = Herb::with_comments(config, {
+                let comments = comments.clone();
+                move |message: &str| comments.lock().unwrap().push(message.to_string())
+            });
+            let mv = herb.get_next_move(Game::new());
+            let comments = comments.lock().unwrap().clone();
+            (mv, comments)
+        };
+        let (_, comments) = comments_at(Verbosity::Silent);
+        assert!(comments.is_empty());
+        let (_, comments) = comments_at(Verbosity::Results);
+        assert!(comments.is_empty());
+        let (mv, comments) = comments_at(Verbosity::Moves);
+        assert_eq!(comments, vec![format!("Herb: Sending move: {}", mv)]);
+        let (_, comments) = comments_at(Verbosity::Debug);
+        assert!(comments
+            .iter()
+            .any(|c| c.starts_with("MCTS: Considering Move")));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert_eq!(Config::default().validate(), Ok(()));
+        let with_rate = |explore_rate| Config {
+            mcts_config: MctsConfig {
+                explore_rate,
+                ..MctsConfig::default()
+            },
+            ..Config::default()
+        };
+        assert_eq!(with_rate(0.0).validate(), Ok(()));
+        assert_eq!(with_rate(1.0).validate(), Ok(()));
+        for explore_rate in [-0.1, 1.5, f64::NAN] {
+            assert!(matches!(
+                with_rate(explore_rate).validate(),
+                Err(ConfigError::OutOfRange {
+                    setting: "mcts_config.explore_rate",
+                    ..
+                })
+            ));
+        }
+
+        // a configuration that does not validate is replaced by the defaults
+        let path = std::env::temp_dir().join(format!("herb-config-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{"max_time": 10.0, "mcts_config": {"explore_rate": 2.0}}"#,
+        )
+        .unwrap();
+        let config = Config::new(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.max_time, Config::default().max_time);
+        assert_eq!(config.mcts_config.explore_rate, 0.1);
+
+        // and so is one whose time settings would make a negative move time
+        std::fs::write(
+            &path,
+            r#"{"max_time": 10.0, "time": {"controller": {"max_share": -0.5}}}"#,
+        )
+        .unwrap();
+        let config = Config::new(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(config.max_time, Config::default().max_time);
+        assert_eq!(config.time, TimeConfig::default());
+    }
+
+    #[test]
+    fn test_lessons() {
+        let mut game = Game::new();
+        game.play_next_turn(game.legal_moves()[0]).unwrap();
+        let config = Config {
+            max_time: 1.0,
+            warm_up: 0.0,
+            log: false,
+            ..Config::default()
+        };
+        let mv = Herb::new(config.clone()).get_next_move(game);
+
+        let path =
+            std::env::temp_dir().join(format!("herb-lib-lessons-{}.json", std::process::id()));
+        let mut lessons = Lessons::default();
+        let mut after = game;
+        after.play_next_turn(mv).unwrap();
+        lessons.learn(lessons::Lesson::new(&game, mv, after.legal_moves()[0], 0.5));
+        lessons.save(&path).unwrap();
+
+        let comments = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let config = Config {
+            log: true,
+            lessons: Some(path.to_string_lossy().into_owned()),
+            ..config
+        };
+        let mut herb = Herb::with_comments(config, {
+            let comments = comments.clone();
+            move |message: &str| comments.lock().unwrap().push(message.to_string())
+        });
+        let lesson_move = herb.get_next_move(game);
+        assert_ne!(lesson_move, mv);
+        assert!(game.legal_moves().contains(&lesson_move));
+        let avoiding = format!("Herb: Avoiding {} from the lessons", format_move(mv));
+        assert!(comments.lock().unwrap().contains(&avoiding));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    fn referee_config() -> Config {
+        Config {
+            max_time: 2.0,
+            log: false,
+            telemetry: true,
+            warm_up: 0.0,
+            ..Config::default()
+        }
+    }
+
+    #[test]
+    fn test_play_referee_game() {
+        let referee = MockReferee::new(
+            Color::Black,
+            vec![
+                Scripted::Line("T W 93.5".to_string()),
+                Scripted::Delay(Duration::from_millis(5)),
+                Scripted::Line("hello".to_string()),
+            ],
+        );
+        let game = play_referee_game(referee_config(), &referee).unwrap().game;
+        assert!(game.is_over());
+        assert_eq!(game.get_board(), referee.game().get_board());
+        let sent = referee.sent();
+        assert_eq!(sent[0], "R B");
+        assert!(sent[1..].iter().all(|line| line.starts_with("B")));
+        assert_eq!(sent.len() - 1, game.get_turn().div_ceil(2));
+    }
+
+    #[test]
+    fn test_play_referee_game_errors() {
+        // a malformed move
+        let referee = MockReferee::new(Color::White, vec![Scripted::Line("B z 9".to_string())]);
+        assert!(play_referee_game(referee_config(), &referee).is_err());
+
+        // an illegal move
+        let a1 = Move::from_col_row(0, 0).unwrap();
+        let referee = MockReferee::from_moves(Color::White, &[a1]);
+        assert!(play_referee_game(referee_config(), &referee).is_err());
+
+        // the referee ending the game early
+        let referee = MockReferee::new(
+            Color::White,
+            vec![Scripted::Line("E W timeout".to_string())],
+        );
+        match play_referee_game(referee_config(), &referee) {
+            Err(MatchError::Interface(e)) => {
+                let result = official_result(&e).unwrap();
+                assert_eq!(result.winner, Some(Color::Black));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resume_referee_game() {
+        let path = std::env::temp_dir().join(format!("herb-resume-{}.json", std::process::id()));
+        let config = Config {
+            autosave: Some(path.to_string_lossy().into_owned()),
+            ..referee_config()
+        };
+        let referee = MockReferee::new(Color::Black, Vec::new());
+        let played = play_referee_game(config.clone(), &referee).unwrap();
+        let saved = Autosave::load(&path).unwrap();
+        assert_eq!(saved.moves(), played.moves);
+        assert_eq!(saved.color, Color::Black);
+        assert_eq!(saved.config_digest, config_digest(&config));
+
+        // continue from the middle of the game as if Herb had crashed there
+        let mut result = played.clone();
+        result.moves.truncate(20);
+        let mut interrupted = Autosave::new(&config, Color::Black);
+        interrupted.update(&config, &result);
+        let referee =
+            MockReferee::new(Color::Black, Vec::new()).from_position(interrupted.game().unwrap());
+        let resumed = resume_referee_game(config.clone(), &referee, &interrupted).unwrap();
+        assert!(resumed.game.is_over());
+        assert_eq!(resumed.moves[..20], played.moves[..20]);
+
+        let referee = MockReferee::new(Color::White, Vec::new());
+        assert!(resume_referee_game(config, &referee, &interrupted).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_assigned_color() {
+        let mut herb = Herb::new(Config {
+            log: false,
+            max_time: 10.0,
+            ..Config::default()
+        });
+        herb.new_game(Color::Black);
+        assert_eq!(herb.color(), Some(Color::Black));
+
+        // searching White's turn does not use Black's time
+        let mut game = Game::new();
+        game.play_next_turn(game.legal_moves()[0]).unwrap();
+        assert!(herb.move_controller(game).target() > Duration::ZERO);
+        herb.charge(game, Duration::from_secs(1));
+        assert_eq!(herb.time_remaining, 10.0);
+        herb.charge(Game::new(), Duration::from_secs(1));
+        assert_eq!(herb.time_remaining, 9.0);
+
+        // a new game resets the clock
+        herb.new_game(Color::White);
+        assert_eq!(herb.time_remaining, 10.0);
+        assert_eq!(herb.color(), Some(Color::White));
+
+        // unless it resumes an interrupted one, only the next game
+        herb.resume(Duration::from_secs(4));
+        herb.new_game(Color::White);
+        assert_eq!(herb.time_remaining, 6.0);
+        herb.new_game(Color::White);
+        assert_eq!(herb.time_remaining, 10.0);
+    }
+
+    #[test]
+    fn test_worker_count() {
+        let max_workers = current_num_threads();
+        let herb = Herb::new(Config {
+            log: false,
+            worker_time: 0.05,
+            ..Config::default()
+        });
+        assert_eq!(herb.worker_count(Duration::from_millis(20)), 1);
+        assert_eq!(herb.worker_count(Duration::from_millis(99)), 1);
+        assert_eq!(
+            herb.worker_count(Duration::from_millis(100)),
+            max_workers.min(2)
+        );
+        assert_eq!(herb.worker_count(Duration::from_secs(60)), max_workers);
+
+        let herb = Herb::new(Config {
+            log: false,
+            worker_time: 0.0,
+            ..Config::default()
+        });
+        assert_eq!(herb.worker_count(Duration::ZERO), max_workers);
+    }
 }
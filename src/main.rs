@@ -1,64 +1,77 @@
 use std::env;
 use std::error::Error;
+use std::io;
+use std::path::Path;
 
+use herb::autosave::Autosave;
 use herb::config::Config;
-use herb::drmecref::DrMecRef;
-use herb::othello::Move::Pass;
-use herb::othello::{Game, Move};
-use herb::{GameInterface, Herb, Player};
+use herb::driver::MatchError;
+use herb::drmecref::{official_result, DrMecRef};
+use herb::dumps::DumpReferee;
+use herb::othello::Color;
+use herb::session;
+use herb::{play_referee_game, resume_referee_game};
 
 /// Plays through a game of Othello interfacing with Dr. Cameron's referee.
+///
+/// Usage: `herb [config.json] [--resume [autosave.json]] [--dumps <black|white>]`, where
+/// `--resume` continues the game saved to the given file, or to the config's `autosave` file
+/// if none is given, and `--dumps` plays the given color against a referee that only sends
+/// board dumps.
 fn main() -> Result<(), Box<dyn Error>> {
-    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let resume = match args.iter().position(|arg| arg == "--resume") {
+        Some(i) => {
+            let path = args.get(i + 1).cloned();
+            args.drain(i..(i + 2).min(args.len()));
+            Some(path)
+        }
+        None => None,
+    };
+    let dumps = match args.iter().position(|arg| arg == "--dumps") {
+        Some(i) => {
+            let color = match args.get(i + 1).map(|color| color.to_lowercase()).as_deref() {
+                Some("b" | "black") => Color::Black,
+                Some("w" | "white") => Color::White,
+                _ => return Err("--dumps needs black or white".into()),
+            };
+            args.drain(i..i + 2);
+            Some(color)
+        }
+        None => None,
+    };
     let config = if args.is_empty() {
         Config::default()
     } else {
         Config::new(&args[0])
     };
 
-    let mut opponent = DrMecRef::new();
-    let herb_color = opponent.init()?;
-
-    // Let the ref know we are ready
-    opponent.ready(herb_color);
-
-    let mut herb = Herb::new(config);
-    let mut game: Game = Game::new();
+    if let Some(path) = &config.session {
+        session::start(Path::new(path))?;
+    }
 
-    // Game loop
-    loop {
-        // println!("Board:\n{}\n", game.get_board()); // debug, violates the referee
-        DrMecRef::comment(format!("Main: start turn {}", game.get_turn()));
-
-        if game.is_over() {
-            DrMecRef::comment(format!("Main: game over at turn {}", game.get_turn()));
-            break;
+    let result = match (resume, dumps) {
+        (_, Some(color)) => {
+            let referee = DumpReferee::new(color, io::stdin().lock(), io::stdout())
+                .with_verbosity(config.verbosity());
+            play_referee_game(config, &referee)
         }
-
-        let legal_moves = game.legal_moves();
-        if game.to_move() == herb_color {
-            // it is herb's turn
-            let mut herbs_move = herb.get_next_move(game);
-            if !legal_moves.is_empty() && !legal_moves.contains(&herbs_move) {
-                DrMecRef::comment("Main: Got an illegal move from Herb.");
-                herbs_move = *legal_moves.first().unwrap_or(&Pass);
-            }
-            opponent.send_move(herbs_move, herb_color)?;
-            game.play_next_turn(herbs_move)?;
-        } else {
-            // it the opponents turn, get their next move and update the game
-            let opponents_move = opponent.get_next_move(game);
-            match opponents_move {
-                Move::Move(_mv) => {
-                    DrMecRef::comment(format!("Main: got opponent move {}", opponents_move))
-                }
-                Pass => {
-                    DrMecRef::comment("Main: got opponent move Pass");
-                }
-            }
-            game.play_next_turn(opponents_move)?;
+        (Some(path), None) => {
+            let path = path
+                .or_else(|| config.autosave.clone())
+                .ok_or("--resume needs an autosave file")?;
+            let saved = Autosave::load(Path::new(&path))?;
+            let referee = DrMecRef::new().with_verbosity(config.verbosity());
+            resume_referee_game(config, &referee, &saved)
         }
-    } // end game loop
-
-    Ok(())
+        (None, None) => {
+            let referee = DrMecRef::new().with_verbosity(config.verbosity());
+            play_referee_game(config, &referee)
+        }
+    };
+    match result {
+        // the referee's result is final, even if the board disagrees
+        Err(MatchError::Interface(e)) if official_result(&e).is_some() => Ok(()),
+        result => result.map(|_| ()).map_err(Into::into),
+    }
 }
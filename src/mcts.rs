@@ -2,22 +2,44 @@
 //!
 //!
 use std::collections::HashMap;
-use std::fmt::{Debug, Formatter};
+use std::fmt::{Debug, Display, Formatter};
+use std::sync::Arc;
+use std::time::Instant;
 
-use crate::config::MctsConfig;
+use rand::Rng;
+
+use crate::cache::{EvalCache, OutcomeTable};
+use crate::config::{Expansion, ExpansionOrder, MctsConfig};
 use serde::{Deserialize, Serialize};
 
-use crate::drmecref::DrMecRef;
 use crate::othello::Move::Pass;
-use crate::othello::{Color, Game, Move};
+use crate::othello::{Color, Game, Move, Moves, Variant};
+use crate::random::{RngBackend, StreamRng};
+use crate::record::format_move;
+use crate::telemetry::{CacheStats, IterationStats, SearchTimings};
+
+/// Number of playout outcomes a tree remembers when `outcome_empties` is set.
+const OUTCOME_TABLE_SIZE: usize = 1 << 16;
+
+/// Difference in [`Tree::heuristic`] that makes a prior win rate about 73%, one `e` in odds.
+const PRIOR_SCALE: f64 = 4.0;
 
 /// Represents a Monte Carlo Search Tree.
 ///
-/// The tree is represented as a map of game states to tree nodes.
+/// The tree is represented as a map of game states to tree nodes. A tree is `Send` and
+/// `Sync`, searching only needs the tree itself and a shared evaluation cache is safe to use
+/// from any thread.
 pub struct Tree {
     pub(crate) config: MctsConfig,
     pub(crate) map: HashMap<u64, Node>,
     pub(crate) search_iterations: u64,
+    pub(crate) timings: Option<SearchTimings>,
+    pub(crate) eval_cache: Option<Arc<EvalCache>>,
+    pub(crate) outcomes: Option<OutcomeTable>,
+    /// The Zobrist hash of a position and the squares of its moves that are vetoed.
+    vetoes: Option<(u64, u64)>,
+    /// Chooses the random moves of repeated playouts.
+    rng: StreamRng,
 }
 
 impl Tree {
@@ -29,13 +51,69 @@ impl Tree {
 
     /// Create a new MCTS Tree using the given [`MctsConfig`].
     pub fn from_config(config: MctsConfig) -> Self {
+        let outcomes = (config.outcome_empties > 0).then(|| OutcomeTable::new(OUTCOME_TABLE_SIZE));
         Tree {
             config,
             map: HashMap::new(),
             search_iterations: 0,
+            timings: None,
+            eval_cache: None,
+            outcomes,
+            vetoes: None,
+            rng: RngBackend::ChaCha.stream(0, 0, 0),
+        }
+    }
+
+    /// Draw the random moves of repeated playouts from the given generator instead of the
+    /// fixed one every tree starts with, e.g. a stream of its own for each search thread.
+    pub fn set_rng(&mut self, rng: StreamRng) {
+        self.rng = rng;
+    }
+
+    /// Look up and store static evaluations in the given cache, which may be shared with
+    /// other trees.
+    pub fn set_eval_cache(&mut self, cache: Arc<EvalCache>) {
+        self.eval_cache = Some(cache);
+    }
+
+    /// Never select, expand or choose the given moves in the given game, unless every legal
+    /// move is vetoed. Replaces the vetoes set before, no moves clears them.
+    pub fn set_vetoes(&mut self, game: Game, moves: &[Move]) {
+        let mask = moves
+            .iter()
+            .filter_map(Move::get_position)
+            .fold(0, |mask, square| mask | square);
+        let legal = game.legal_moves_mask();
+        self.vetoes =
+            (mask & legal != 0 && legal & !mask != 0).then(|| (game.zobrist_hash(), mask));
+    }
+
+    /// The squares of the vetoed moves of the given game as a bitboard.
+    fn vetoed_mask(&self, game: Game) -> u64 {
+        match self.vetoes {
+            Some((hash, mask)) if hash == game.zobrist_hash() => mask,
+            _ => 0,
         }
     }
 
+    /// Start timing every search iteration and its phases. Timing adds a little overhead
+    /// to each iteration, so it is off by default.
+    pub fn enable_timings(&mut self) {
+        self.timings.get_or_insert_with(SearchTimings::default);
+    }
+
+    /// Returns the timings collected since they were enabled or last taken, leaving an empty
+    /// set in their place.
+    pub fn take_timings(&mut self) -> Option<SearchTimings> {
+        self.timings.as_mut().map(std::mem::take)
+    }
+
+    /// Returns the lookups of the playout outcome table since it was created or they were last
+    /// taken, `None` if the tree does not keep one.
+    pub fn take_outcome_stats(&mut self) -> Option<CacheStats> {
+        self.outcomes.as_mut().map(OutcomeTable::take_stats)
+    }
+
     /// Merge the given tree with this tree.
     ///
     /// A merge adds the values from any [`Node`]s the trees
@@ -52,12 +130,22 @@ impl Tree {
                 .or_insert(value);
         }
         self.search_iterations += other.search_iterations;
+        if let (Some(outcomes), Some(other_outcomes)) = (&mut self.outcomes, other.outcomes) {
+            outcomes.merge(other_outcomes);
+        }
+        if let Some(other_timings) = other.timings {
+            match &mut self.timings {
+                Some(timings) => timings.merge(&other_timings),
+                None => self.timings = Some(other_timings),
+            }
+        }
     }
 }
 
 impl Tree {
     /// Perform one full iteration of a Monte Carlo Tree Search. Each
-    /// call to search grows the tree by one node. It uses the [`Game`] instances
+    /// call to search grows the tree by one node, or by every child of the selected leaf with
+    /// [`Expansion::All`]. It uses the [`Game`] instances
     /// as the nodes in the tree with the legal [`Move`]s for the given game
     /// state as the edges. The 'tree' is traversed by calling 'play_next_turn(some_move)' on
     /// a [`Game`] node. This means that there are no 'parent' pointers so the search
@@ -70,21 +158,70 @@ impl Tree {
     /// 2. Expand the tree by adding a child of the selected leaf to the tree.
     ///     - The tree is expanded by stepping down a level from the selected leaf and adding a new node to the tree.
     /// 3. Simulate to the end of the game starting the newly created node, keeping track of the winner
+    ///     - Each new node is simulated `playouts` times.
     /// 4. Backpropagate up the tree updating the `wins` and `visits` values at each node.
     ///
     /// A game does not have to progress strictly in sequence for the tree to work, you can pass in a [`Game`]
     /// in any state and the tree will grow starting from that 'node'. The `wins` and `visits` are
     /// stored in a [`Node`] struct, a [`HashMap`] is used to map a [`Game`] to a [`Node`].
-    pub fn search(&mut self, game: Game) {
-        if !game.is_over() {
-            let (leaf, mut stack) = self.select(game);
-            let child = self.expand(leaf);
-            if child != game {
-                stack.push(child);
+    ///
+    /// Returns what the iteration did, all zeros if the game is over.
+    pub fn search(&mut self, game: Game) -> IterationStats {
+        if self.timings.is_some() {
+            self.timed_search(game)
+        } else if !game.is_over() {
+            let nodes = self.map.len();
+            let (leaf, stack) = self.select(game);
+            let select_depth = stack.len() as u64;
+            let children = self.expand(leaf);
+            let (results, playout_plies, fallbacks) = self.simulate_children(&children);
+            for (child, winner) in results {
+                self.backpropagate(winner, path(game, &stack, leaf, child));
             }
-            let winner = self.simulate(child);
-            self.backpropagate(game.to_move(), winner, stack);
             self.search_iterations += 1;
+            IterationStats {
+                select_depth,
+                playout_plies,
+                nodes_created: (self.map.len() - nodes) as u64,
+                fallbacks,
+            }
+        } else {
+            IterationStats::default()
+        }
+    }
+
+    /// Same as [`Tree::search`], recording how long the iteration and each of its phases take.
+    fn timed_search(&mut self, game: Game) -> IterationStats {
+        if game.is_over() {
+            return IterationStats::default();
+        }
+        let nodes = self.map.len();
+        let start = Instant::now();
+        let (leaf, stack) = self.select(game);
+        let selected = Instant::now();
+        let select_depth = stack.len() as u64;
+        let children = self.expand(leaf);
+        let expanded = Instant::now();
+        let (results, playout_plies, fallbacks) = self.simulate_children(&children);
+        let simulated = Instant::now();
+        for (child, winner) in results {
+            self.backpropagate(winner, path(game, &stack, leaf, child));
+        }
+        let end = Instant::now();
+        self.search_iterations += 1;
+
+        if let Some(timings) = &mut self.timings {
+            timings.select.record(selected - start);
+            timings.expand.record(expanded - selected);
+            timings.simulate.record(simulated - expanded);
+            timings.backpropagate.record(end - simulated);
+            timings.iteration.record(end - start);
+        }
+        IterationStats {
+            select_depth,
+            playout_plies,
+            nodes_created: (self.map.len() - nodes) as u64,
+            fallbacks,
         }
     }
 
@@ -93,85 +230,239 @@ impl Tree {
     ///
     /// Returns a two-tuple with the first element being the selected leaf node and the second
     /// is the stack of nodes that were visited on the way to the selected node.
+    ///
+    /// With a `prune_margin`, the [pruned moves](Tree::pruned_moves) of the given game are
+    /// never selected, and neither are its vetoed moves.
     fn select(&self, game: Game) -> (Game, Vec<Game>) {
         let mut stack = Vec::new();
         let mut sim_game = game;
+        let mut pruned = self.pruned_mask(game) | self.vetoed_mask(game);
         while !sim_game.is_over() && !self.leaf_p(sim_game) {
             stack.push(sim_game);
-            let mv = self.ucb1(sim_game);
+            let mv = self.ucb1(sim_game, pruned);
             sim_game.play_next_turn(mv).unwrap();
+            pruned = 0;
         }
         (sim_game, stack)
     }
 
-    /// Expands the tree by creating a new child node from the passed in leaf node.
-    /// Returns the new child node.
-    fn expand(&self, leaf: Game) -> Game {
-        let legal_moves = leaf.legal_moves();
-        for mv in legal_moves {
+    /// Expands the tree by creating new child nodes from the passed in leaf node, the first
+    /// unexplored child or all of them depending on the [`Expansion`]. With
+    /// [`Expansion::Priors`] the children are inserted right away, holding their priors.
+    /// Returns the new child nodes, or the leaf itself if it has no unexplored children.
+    ///
+    /// The unexplored children are considered in the [`ExpansionOrder`].
+    fn expand(&mut self, leaf: Game) -> Vec<Game> {
+        let mut children = Vec::new();
+        let vetoed = self.vetoed_mask(leaf);
+        for mv in self.expansion_order(leaf) {
+            if mv.get_position().is_some_and(|square| square & vetoed != 0) {
+                continue;
+            }
             let mut sim_game = leaf;
             sim_game.play_next_turn(mv).unwrap();
-            if !self.map.contains_key(&sim_game.get_hash()) {
-                return sim_game;
+            if !self.map.contains_key(&sim_game.zobrist_hash()) {
+                children.push(sim_game);
+                if self.config.expansion == Expansion::One {
+                    break;
+                }
+            }
+        }
+        if self.config.expansion == Expansion::Priors {
+            for &child in &children {
+                let prior = self.prior(child);
+                self.map.insert(child.zobrist_hash(), prior);
+            }
+        }
+        if children.is_empty() {
+            children.push(leaf);
+        }
+        children
+    }
+
+    /// Returns the legal moves of the given leaf in the configured [`ExpansionOrder`]. Moves
+    /// that are equally good by the order keep the order of the legal moves.
+    fn expansion_order(&self, leaf: Game) -> Vec<Move> {
+        let mut moves = leaf.legal_moves();
+        match self.config.expansion_order {
+            ExpansionOrder::Board => {}
+            ExpansionOrder::Squares => moves.sort_by_key(|mv| {
+                let square = mv.get_position().unwrap_or(0);
+                if square & leaf.size().corners() != 0 {
+                    0
+                } else if square & leaf.size().edges() != 0 {
+                    1
+                } else {
+                    2
+                }
+            }),
+            ExpansionOrder::Prior => {
+                let mut scored: Vec<(Move, f64)> = moves
+                    .into_iter()
+                    .map(|mv| {
+                        let mut child = leaf;
+                        child.play_next_turn(mv).unwrap();
+                        (mv, self.static_value(child))
+                    })
+                    .collect();
+                scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+                moves = scored.into_iter().map(|(mv, _)| mv).collect();
+            }
+        }
+        moves
+    }
+
+    /// The prior of a child that has not been played out yet: `prior_visits` visits, won at
+    /// the rate the static evaluation predicts for the player who moved into it.
+    fn prior(&self, child: Game) -> Node {
+        let rate = 1.0 / (1.0 + (-self.static_value(child) / PRIOR_SCALE).exp());
+        Node {
+            visits: self.config.prior_visits,
+            wins: self.config.prior_visits * rate,
+        }
+    }
+
+    /// Runs `playouts` playouts from each of the given children, at least one. Returns the
+    /// winner of every playout along with the child it started from, and the plies and
+    /// fallbacks of all of the playouts together, see [`Tree::simulate`].
+    ///
+    /// The first playout of a child follows the best moves. The ones after it play a random
+    /// legal move instead at a share `explore_rate` of the plies, so they sample the positions
+    /// around that line rather than repeat its result.
+    fn simulate_children(&mut self, children: &[Game]) -> (Vec<(Game, Option<Color>)>, u64, u64) {
+        let playouts = self.config.playouts.max(1);
+        let mut results = Vec::with_capacity(children.len() * playouts as usize);
+        let mut plies = 0;
+        let mut fallbacks = 0;
+        for &child in children {
+            for playout in 0..playouts {
+                let (winner, playout_plies, playout_fallbacks) = self.simulate(child, playout > 0);
+                results.push((child, winner));
+                plies += playout_plies;
+                fallbacks += playout_fallbacks;
             }
         }
-        leaf
+        (results, plies, fallbacks)
     }
 
-    /// Simulates to the end of the given game and reports the winner.
-    /// If the winner is `None` the game ended in a draw, otherwise
-    /// the returned `Some(Color)` will contain the winner.
-    fn simulate(&self, mut game: Game) -> Option<Color> {
+    /// Simulates to the end of the given game and reports the winner, the number of plies
+    /// played and the number of them [`Tree::best_move`] had no move for, which were played by
+    /// [`Game::greedy_move`] instead. If the winner is `None` the game ended in a draw,
+    /// otherwise the returned `Some(Color)` will contain the winner.
+    ///
+    /// With an outcome table, the playout stops at the first position with at most
+    /// `outcome_empties` empty squares that an earlier playout passed through, and the
+    /// outcome of the playout is stored for each such position it passed through itself.
+    ///
+    /// With a `playout_cutoff`, the playout stops after that many plies and the player ahead
+    /// on the `weight_table` wins it, the player behind in [`Variant::Misere`].
+    ///
+    /// With at most `parity_empties` empty squares, the moves are chosen among the
+    /// [`Game::parity_moves`] of the player, the tempo in
+    /// each region decides more of the final count than anything else by then.
+    ///
+    /// An `exploring` playout plays a random legal move at a share `explore_rate` of its
+    /// plies.
+    fn simulate(&mut self, mut game: Game, exploring: bool) -> (Option<Color>, u64, u64) {
+        let mut funnel = Vec::new();
+        let mut known = None;
+        let mut plies = 0;
+        let mut fallbacks = 0;
         while !game.is_over() {
-            let mut mv = self.best_move(game, false);
-            if mv == Pass {
-                mv = game.random_move()
+            if self
+                .config
+                .playout_cutoff
+                .is_some_and(|cutoff| plies >= cutoff)
+            {
+                let leader = self.config.weight_table.leader(&game);
+                known = Some(match game.variant() {
+                    Variant::Standard => leader,
+                    Variant::Misere => leader.map(|leader| leader.opponent()),
+                });
+                break;
+            }
+            if let Some(outcomes) = &mut self.outcomes {
+                if game.empty_squares() <= self.config.outcome_empties {
+                    let hash = game.zobrist_hash();
+                    known = outcomes.get(hash);
+                    if known.is_some() {
+                        break;
+                    }
+                    funnel.push(hash);
+                }
+            }
+            let mut mv = if exploring && self.rng.gen_bool(self.config.explore_rate) {
+                game.random_move(&mut self.rng)
+            } else if game.empty_squares() <= self.config.parity_empties {
+                self.best_move_among(game, game.parity_moves())
+            } else {
+                self.best_move(game)
+            };
+            if mv == Pass && game.legal_moves_mask() != 0 {
+                mv = game.greedy_move();
+                fallbacks += 1;
             }
             game.play_next_turn(mv).unwrap();
+            plies += 1;
+        }
+        let winner = known.unwrap_or_else(|| game.winner());
+        if let Some(outcomes) = &mut self.outcomes {
+            for hash in funnel {
+                outcomes.insert(hash, winner);
+            }
         }
-        game.winner()
+        (winner, plies, fallbacks)
     }
 
-    /// Walk back up the tree by popping nodes off the stack. 'Visit' each node updating the
+    /// Walk back up the tree along the path of a playout. 'Visit' each node updating the
     /// `wins` and `visits` if the [`Node`] is in the tree or inserting a new node.
-    fn backpropagate(&mut self, player: Color, winner: Option<Color>, stack: Vec<Game>) {
-        let result_value = match winner {
-            // A draw is worth half a win.
-            None => 0.5,
-            Some(winner) if winner == player => 1.0,
-            _ => 0.0,
-        };
-        for game in stack {
+    ///
+    /// Every node is credited with the result of the player who moved into it, given along
+    /// with it by [`path`], so UCB1 always picks the child that is best for the player choosing
+    /// among them.
+    fn backpropagate(&mut self, winner: Option<Color>, path: impl Iterator<Item = (Game, Color)>) {
+        for (game, mover) in path {
+            let result_value = match winner {
+                // A draw is worth half a win.
+                None => 0.5,
+                Some(winner) if winner == mover => 1.0,
+                _ => 0.0,
+            };
             self.map
-                .entry(game.get_hash())
+                .entry(game.zobrist_hash())
                 .and_modify(|node| {
                     node.wins += result_value;
                     node.visits += 1.0;
                 })
-                .or_insert_with(|| Node::first_visit(result_value == 1.0));
+                .or_insert_with(|| Node::first_visit(result_value));
         }
     }
 
     /// The UCB1 formula for deciding which child nodes to visit during the select phase
     /// of MCTS.
-    fn ucb1(&self, game: Game) -> Move {
-        let mut best_move = game.random_move();
+    ///
+    /// Ties go to the first legal move, which is also chosen when no value is above
+    /// [`f64::MIN`], e.g. when every value is NaN. Moves to the `pruned` squares are skipped.
+    fn ucb1(&self, game: Game, pruned: u64) -> Move {
+        let legal_moves = game.legal_moves_iter();
+        let mut best_move = legal_moves.clone().next().unwrap_or(Pass);
         let mut best_value = f64::MIN;
 
         let parent_visits = self
             .map
-            .get(&game.get_hash())
+            .get(&game.zobrist_hash())
             .map_or(1.0, |node| node.visits);
 
-        let legal_moves = game.legal_moves();
-
         for mv in legal_moves {
+            if mv.get_position().is_some_and(|square| square & pruned != 0) {
+                continue;
+            }
             let mut sim_game = game;
             sim_game.play_next_turn(mv).unwrap();
 
             let node = self
                 .map
-                .get(&sim_game.get_hash())
+                .get(&sim_game.zobrist_hash())
                 .map_or_else(Node::cold_start, |n| *n);
 
             let visits = node.visits.max(1.0);
@@ -195,30 +486,187 @@ impl Tree {
         best_move
     }
 
+    /// Returns the moves of the given game that the search no longer selects because the upper
+    /// confidence bound of their win rate is more than `prune_margin` below the lower bound of
+    /// another move, in the order of the legal moves. The bounds are the win rate plus and
+    /// minus the exploration term of UCB1, so moves that have not been visited much are never
+    /// pruned. Without a `prune_margin` no move is pruned.
+    pub fn pruned_moves(&self, game: Game) -> Vec<Move> {
+        Moves::new(self.pruned_mask(game)).collect()
+    }
+
+    /// The squares of the [`Tree::pruned_moves`] as a bitboard.
+    fn pruned_mask(&self, game: Game) -> u64 {
+        let Some(margin) = self.config.prune_margin else {
+            return 0;
+        };
+        let Some(parent) = self.map.get(&game.zobrist_hash()) else {
+            return 0;
+        };
+        let bounds: Vec<(u64, f64, f64)> = game
+            .legal_moves_iter()
+            .filter_map(|mv| {
+                let mut child = game;
+                child.play_next_turn(mv).unwrap();
+                let node = self.map.get(&child.zobrist_hash())?;
+                let radius = self.config.exploration_factor
+                    * ((parent.visits.ln() + 1e-5) / node.visits.max(1.0)).sqrt();
+                let ratio = node.ratio();
+                Some((mv.get_position()?, ratio - radius, ratio + radius))
+            })
+            .collect();
+        let best_lower = bounds
+            .iter()
+            .map(|(_, lower, _)| *lower)
+            .fold(f64::MIN, f64::max);
+        bounds
+            .iter()
+            .filter(|(_, _, upper)| *upper + margin < best_lower)
+            .fold(0, |mask, (square, _, _)| mask | square)
+    }
+
+    /// Run the given number of search iterations starting from the given game.
+    ///
+    /// A single tree searched for a fixed number of iterations always grows the same way,
+    /// unlike a timed search, so this is what reproducible tests should use.
+    pub fn search_n(&mut self, game: Game, iterations: u64) {
+        for _ in 0..iterations {
+            self.search(game);
+        }
+    }
+
     /// Picks the best move according to various attributes of the nodes that are
     /// in the tree.
-    pub fn best_move(&self, game: Game, last: bool) -> Move {
+    pub fn best_move(&self, game: Game) -> Move {
+        self.best_move_with_value(game).0
+    }
+
+    /// Like [`Tree::best_move`], also returning the value of the chosen move. The value is
+    /// [`f64::MIN`] when there are no legal moves.
+    ///
+    /// The move is [`Pass`] when no legal move evaluates above [`f64::MIN`], e.g. when every
+    /// evaluation is NaN. Callers that must play a move fall back on [`Game::greedy_move`].
+    /// Vetoed moves are never chosen.
+    pub fn best_move_with_value(&self, game: Game) -> (Move, f64) {
+        self.best_among(game, game.legal_moves_mask())
+    }
+
+    /// Like [`Tree::best_move`], choosing only among the legal moves to the given squares.
+    fn best_move_among(&self, game: Game, squares: u64) -> Move {
+        self.best_among(game, squares).0
+    }
+
+    fn best_among(&self, game: Game, squares: u64) -> (Move, f64) {
         let mut best_move = Pass;
         let mut best_value = f64::MIN;
-        let legal_moves = game.legal_moves();
 
-        for mv in legal_moves {
+        let legal = game.legal_moves_mask() & squares & !self.vetoed_mask(game);
+        for mv in Moves::new(legal) {
             let mut sim_game = game;
             sim_game.play_next_turn(mv).unwrap();
 
             let value = self.evaluate(sim_game);
-
-            if last {
-                DrMecRef::comment(format!("MCTS: Considering Move {}, Value: {}", mv, value));
-            }
-
             if value > best_value {
                 best_value = value;
                 best_move = mv;
             }
         }
 
-        best_move
+        (best_move, best_value)
+    }
+
+    /// Returns the normalized visit counts of the children of the given game, indexed by
+    /// [`Move::to_index`].
+    ///
+    /// This is the policy target for training. If none of the children have been visited the
+    /// visits are spread evenly over the legal moves, and when the player to move has no legal
+    /// moves all of the weight goes to pass.
+    pub fn policy_target(&self, game: Game) -> Vec<f64> {
+        let mut policy = vec![0.0; Move::INDICES];
+        let legal_moves = game.legal_moves();
+        if legal_moves.is_empty() {
+            policy[Move::PASS_INDEX] = 1.0;
+            return policy;
+        }
+
+        let mut total = 0.0;
+        for mv in &legal_moves {
+            let mut sim_game = game;
+            sim_game.play_next_turn(*mv).unwrap();
+            let visits = self
+                .map
+                .get(&sim_game.zobrist_hash())
+                .map_or(0.0, |node| node.visits);
+            policy[mv.to_index()] = visits;
+            total += visits;
+        }
+
+        if total > 0.0 {
+            policy.iter_mut().for_each(|p| *p /= total);
+        } else {
+            let uniform = 1.0 / legal_moves.len() as f64;
+            for mv in &legal_moves {
+                policy[mv.to_index()] = uniform;
+            }
+        }
+        policy
+    }
+
+    /// Returns the share of the visits to the children of the given game that the most visited
+    /// one has, 0 if none of them has been visited.
+    pub fn best_move_share(&self, game: Game) -> f64 {
+        let (most, total) = game
+            .legal_moves()
+            .iter()
+            .filter_map(|mv| {
+                let mut child = game;
+                child.play_next_turn(*mv).unwrap();
+                self.map.get(&child.zobrist_hash())
+            })
+            .fold((0.0, 0.0), |(most, total): (f64, f64), node| {
+                (most.max(node.visits), total + node.visits)
+            });
+        if total > 0.0 {
+            most / total
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the principal variation from the given game, the line the search expects to be
+    /// played. It follows the most visited child at every depth, the first one on ties, and
+    /// ends where the tree does.
+    pub fn principal_variation(&self, game: Game) -> Vec<PvStep> {
+        let mut pv = Vec::new();
+        let mut position = game;
+        while !position.is_over() {
+            let mut moves = position.legal_moves();
+            if moves.is_empty() {
+                moves.push(Pass);
+            }
+            let mut best: Option<(Game, PvStep)> = None;
+            for mv in moves {
+                let mut child = position;
+                child.play_next_turn(mv).unwrap();
+                let Some(node) = self.map.get(&child.zobrist_hash()) else {
+                    continue;
+                };
+                if best.is_none_or(|(_, step)| node.visits > step.visits) {
+                    let step = PvStep {
+                        mv,
+                        visits: node.visits,
+                        win_ratio: node.ratio(),
+                    };
+                    best = Some((child, step));
+                }
+            }
+            let Some((child, step)) = best else {
+                break;
+            };
+            pv.push(step);
+            position = child;
+        }
+        pv
     }
 
     /// This is the evaluation function that ultimately determines what
@@ -227,12 +675,47 @@ impl Tree {
     /// passed is the result of a legal move from some player. The score that
     /// is returned will be high if it is a desirable state to move to from the
     /// calling player's perspective.
-    fn evaluate(&self, game: Game) -> f64 {
-        let node = match self.map.get(&game.get_hash()) {
+    pub(crate) fn evaluate(&self, game: Game) -> f64 {
+        let node = match self.map.get(&game.zobrist_hash()) {
+            None => Node::cold_start(),
+            Some(node) => *node,
+        };
+
+        let normalized_visits = 10.0 * 1.0 / (1.0 + (-node.visits).exp());
+        let win_ratio = node.ratio();
+
+        normalized_visits + 10.0 * win_ratio + self.static_value(game)
+    }
+
+    /// The [`Tree::heuristic`] of the given game, through the evaluation cache if there is one.
+    fn static_value(&self, game: Game) -> f64 {
+        match &self.eval_cache {
+            Some(cache) => cache.get_or_insert_with(game.zobrist_hash(), || Self::heuristic(game)),
+            None => Self::heuristic(game),
+        }
+    }
+
+    /// The part of [`Tree::evaluate`] that only depends on the position, not on the search.
+    fn heuristic(game: Game) -> f64 {
+        Self::heuristic_terms(game).heuristic()
+    }
+
+    /// Returns the terms of [`Tree::evaluate`] for the given game, the value of a move into
+    /// it broken down so a log can show which of them decided between the moves.
+    pub fn contributions(&self, game: Game) -> Contributions {
+        let node = match self.map.get(&game.zobrist_hash()) {
             None => Node::cold_start(),
             Some(node) => *node,
         };
+        Contributions {
+            visits: 10.0 * 1.0 / (1.0 + (-node.visits).exp()),
+            win_ratio: 10.0 * node.ratio(),
+            ..Self::heuristic_terms(game)
+        }
+    }
 
+    /// The terms of [`Tree::heuristic`], without the search terms.
+    fn heuristic_terms(game: Game) -> Contributions {
         let (black_corners, white_corners) = game.num_corners_held();
         let (own_corners_held, opponent_corners_held) = match game.to_move() {
             Color::Black => (white_corners as f64, black_corners as f64),
@@ -278,19 +761,30 @@ impl Tree {
 
         let opponent_mobility = game.mobility() as f64;
 
-        let normalized_visits = 10.0 * 1.0 / (1.0 + (node.visits * -1.0).exp());
-        let win_ratio = node.ratio();
-
-        let mut value: f64 = normalized_visits;
-        value += 10.0 * win_ratio;
-        value += 2.0 * corners_difference;
-        value += 1.5 * edges_difference;
-        value += 1.75 * diagonals_difference;
-        value += center_4_difference;
-        value += inner_board_difference;
-        value -= 1.5 * opponent_mobility;
-        value -= x_moves_difference;
-        value
+        let terms = Contributions {
+            visits: 0.0,
+            win_ratio: 0.0,
+            corners: 2.0 * corners_difference,
+            edges: 1.5 * edges_difference,
+            diagonals: 1.75 * diagonals_difference,
+            center_4: center_4_difference,
+            inner_board: inner_board_difference,
+            mobility: -1.5 * opponent_mobility,
+            x_moves: -x_moves_difference,
+        };
+        match game.variant() {
+            Variant::Standard => terms,
+            // the squares worth holding are the ones to stay off
+            Variant::Misere => Contributions {
+                corners: -terms.corners,
+                edges: -terms.edges,
+                diagonals: -terms.diagonals,
+                center_4: -terms.center_4,
+                inner_board: -terms.inner_board,
+                x_moves: -terms.x_moves,
+                ..terms
+            },
+        }
     }
 
     /// Determines if the given game is a 'Leaf' node in the MCTS Tree.
@@ -299,11 +793,10 @@ impl Tree {
         if game.is_over() {
             return true;
         }
-        let legal_move = game.legal_moves();
-        for mv in legal_move {
+        for mv in game.legal_moves_iter() {
             let mut sim_game = game;
             sim_game.play_next_turn(mv).unwrap();
-            if self.map.contains_key(&sim_game.get_hash()) {
+            if self.map.contains_key(&sim_game.zobrist_hash()) {
                 return false;
             }
         }
@@ -311,6 +804,33 @@ impl Tree {
     }
 }
 
+/// Returns the nodes a search iteration from the `root` passed through, the `stack` of
+/// [`Tree::select`] and the `child` of its `leaf` that was played out, each with the player
+/// who moved into it.
+///
+/// The mover is the player to move before the move, not the opponent of the player to move
+/// after it: with [`Game::set_auto_pass`] a move that forces a pass leaves the mover to move
+/// again. Nobody moved into the root during the search, it is credited to the opponent of its
+/// player to move.
+fn path(
+    root: Game,
+    stack: &[Game],
+    leaf: Game,
+    child: Game,
+) -> impl Iterator<Item = (Game, Color)> {
+    let mut mover = root.to_move().opponent();
+    let mut nodes = Vec::with_capacity(stack.len() + 1);
+    for &node in stack {
+        nodes.push((node, mover));
+        mover = node.to_move();
+    }
+    if child != root {
+        let mover = if child == leaf { mover } else { leaf.to_move() };
+        nodes.push((child, mover));
+    }
+    nodes.into_iter()
+}
+
 impl Debug for Tree {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Im a tree!")
@@ -323,6 +843,87 @@ impl Default for Tree {
     }
 }
 
+/// The terms [`Tree::evaluate`] adds up to the value of a move, see [`Tree::contributions`].
+/// Each is weighted already, so they add up to the value as they are.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Contributions {
+    /// How much the move has been searched, from 7.3 before the first visit up to 10.
+    pub visits: f64,
+    /// The share of the playouts through the move won by the mover, up to 10.
+    pub win_ratio: f64,
+    pub corners: f64,
+    pub edges: f64,
+    pub diagonals: f64,
+    pub center_4: f64,
+    pub inner_board: f64,
+    /// Minus the moves left to the opponent.
+    pub mobility: f64,
+    /// The discs on the squares next to the corners, see [`Game::num_x_moves_held`].
+    pub x_moves: f64,
+}
+
+impl Contributions {
+    /// Returns the part of the value that only depends on the position.
+    pub fn heuristic(&self) -> f64 {
+        self.corners
+            + self.edges
+            + self.diagonals
+            + self.center_4
+            + self.inner_board
+            + self.mobility
+            + self.x_moves
+    }
+
+    /// Returns the value of the move, the sum of all of the terms.
+    pub fn total(&self) -> f64 {
+        self.visits + self.win_ratio + self.heuristic()
+    }
+}
+
+impl Display for Contributions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let terms = [
+            ("visits", self.visits),
+            ("wins", self.win_ratio),
+            ("corners", self.corners),
+            ("edges", self.edges),
+            ("diagonals", self.diagonals),
+            ("center", self.center_4),
+            ("inner", self.inner_board),
+            ("mobility", self.mobility),
+            ("x-squares", self.x_moves),
+        ];
+        let terms: Vec<String> = terms
+            .iter()
+            .map(|(name, value)| format!("{} {:+.2}", name, value))
+            .collect();
+        write!(f, "{}", terms.join(", "))
+    }
+}
+
+/// A move of a [principal variation](Tree::principal_variation).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PvStep {
+    pub mv: Move,
+    /// Search iterations that went through the move.
+    pub visits: f64,
+    /// Fraction of those iterations won by the player who made the move, whose results the
+    /// tree holds.
+    pub win_ratio: f64,
+}
+
+impl Display for PvStep {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} ({} visits, {:.0}%)",
+            format_move(self.mv),
+            self.visits,
+            self.win_ratio * 100.0
+        )
+    }
+}
+
 /// Holds the visits and wins for a node in the tree
 #[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Node {
@@ -339,12 +940,12 @@ impl Node {
         }
     }
 
-    /// Create a new node with visits set to `1.0` and wins set to `0.0` or `1.0`
-    /// depending on the passed in win bool.
-    pub fn first_visit(win: bool) -> Self {
+    /// Create a new node with visits set to `1.0` and wins set to the result of the visit,
+    /// `1.0` for a win, `0.5` for a draw and `0.0` for a loss.
+    pub fn first_visit(result: f64) -> Self {
         Node {
             visits: 1.0,
-            wins: if win { 1.0 } else { 0.0 },
+            wins: result,
         }
     }
 
@@ -372,26 +973,463 @@ impl Default for Node {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::record::parse_move;
 
     #[test]
     fn test_one_search_iteration() {
         let mut tree = Tree::new();
         let game = Game::new();
-        tree.search(game);
-        let mv = tree.best_move(game, false);
+        let stats = tree.search(game);
+        assert_eq!(stats.select_depth, 0);
+        assert_eq!(stats.nodes_created, 1);
+        assert!(stats.playout_plies >= 50);
 
-        let _legal_moves = game.legal_moves();
-        let mut sim_game = game;
-        sim_game.play_next_turn(mv).unwrap();
-        assert!(tree.map.contains_key(&sim_game.get_hash()));
+        // the one expanded child; best_move may prefer an unvisited one after a lost playout
+        let children: Vec<Game> = game
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut sim_game = game;
+                sim_game.play_next_turn(mv).unwrap();
+                sim_game
+            })
+            .filter(|child| tree.map.contains_key(&child.zobrist_hash()))
+            .collect();
+        assert_eq!(children.len(), 1);
+        let sim_game = children[0];
 
-        if let Some(node) = tree.map.get(&sim_game.get_hash()) {
+        if let Some(node) = tree.map.get(&sim_game.zobrist_hash()) {
             assert_ne!(node.visits, 0.0);
         } else {
             panic!("Node not found in tree!");
         }
     }
 
+    #[test]
+    fn test_playouts_and_expansion() {
+        let game = Game::new();
+        let mut tree = Tree::from_config(MctsConfig {
+            playouts: 3,
+            ..MctsConfig::default()
+        });
+        let stats = tree.search(game);
+        assert_eq!(stats.nodes_created, 1);
+        assert!(stats.playout_plies >= 3 * 50);
+        let child = tree.map.values().next().unwrap();
+        assert_eq!(child.visits, 3.0);
+
+        // repeated playouts sample the positions around the first one's line
+        let winners: Vec<_> = (0..20).map(|_| tree.simulate(game, true).0).collect();
+        assert!(winners.iter().any(|winner| *winner != winners[0]));
+        assert!((0..5).all(|_| tree.simulate(game, false).0 == tree.simulate(game, false).0));
+
+        // without an explore rate they follow the first one's line
+        let mut tree = Tree::from_config(MctsConfig {
+            explore_rate: 0.0,
+            ..MctsConfig::default()
+        });
+        let first = tree.simulate(game, false);
+        assert!((0..5).all(|_| tree.simulate(game, true) == first));
+
+        let mut tree = Tree::from_config(MctsConfig {
+            expansion: Expansion::All,
+            ..MctsConfig::default()
+        });
+        let stats = tree.search(game);
+        assert_eq!(stats.nodes_created, 4);
+        assert!(tree.map.values().all(|node| node.visits == 1.0));
+        // every child of the root is explored, the next iteration goes a level deeper
+        let stats = tree.search(game);
+        assert_eq!(stats.select_depth, 1);
+        assert_eq!(tree.map.get(&game.zobrist_hash()).unwrap().visits, 3.0);
+    }
+
+    #[test]
+    fn test_playout_cutoff() {
+        let game = Game::new();
+        let mut tree = Tree::from_config(MctsConfig {
+            playout_cutoff: Some(0),
+            ..MctsConfig::default()
+        });
+        // after black's first move white is ahead on the table, with one disc on the
+        // negatively weighted inner squares to black's four
+        let mut after = game;
+        after.play_next_turn(game.legal_moves()[0]).unwrap();
+        assert_eq!(tree.simulate(after, false), (Some(Color::White), 0, 0));
+        assert_eq!(tree.simulate(game, false), (None, 0, 0));
+
+        let mut tree = Tree::from_config(MctsConfig {
+            playout_cutoff: Some(10),
+            ..MctsConfig::default()
+        });
+        let stats = tree.search(game);
+        assert_eq!(stats.playout_plies, 10);
+    }
+
+    #[test]
+    fn test_priors() {
+        let game = Game::new();
+        let mut tree = Tree::from_config(MctsConfig {
+            expansion: Expansion::Priors,
+            prior_visits: 4.0,
+            ..MctsConfig::default()
+        });
+        let stats = tree.search(game);
+        assert_eq!(stats.nodes_created, 4);
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            let node = tree.map[&child.zobrist_hash()];
+            // the prior and one playout
+            assert_eq!(node.visits, 5.0);
+            let prior = tree.prior(child);
+            assert!(prior.wins > 0.0 && prior.wins < 4.0);
+            assert!(node.wins == prior.wins || node.wins == prior.wins + 1.0);
+        }
+    }
+
+    /// White wins whatever either side plays in the last five empty squares, and nobody has
+    /// to pass on the way.
+    fn white_wins_last_five() -> Game {
+        let moves = "e6 f6 d3 c5 g7 f7 c6 e3 c3 c2 c1 d6 c4 b6 f8 b2 a2 b4 b3 h8 a6 b7 c8 a8 f2 \
+                     g8 d2 a1 a5 b1 h6 e2 f5 d7 f3 g5 a3 e7 f1 h7 c7 b5 g4 a4 e8 g3 f4 e1 g6 g1 \
+                     h2 h4 g2 d8 d1";
+        let mut game = Game::new();
+        for mv in moves.split_whitespace() {
+            game.play_next_turn(parse_move(mv).unwrap()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_backpropagation_alternates() {
+        let game = white_wins_last_five();
+        assert_eq!((game.to_move(), game.empty_squares()), (Color::White, 5));
+
+        let mut tree = Tree::new();
+        tree.search_n(game, 1000);
+        let mut level = vec![game];
+        let mut depth = 0;
+        while !level.is_empty() {
+            depth += 1;
+            let mut next = Vec::new();
+            for position in level {
+                for mv in position.legal_moves() {
+                    let mut child = position;
+                    child.play_next_turn(mv).unwrap();
+                    let node = tree.map[&child.zobrist_hash()];
+                    // white moves at odd depths and wins every playout through them
+                    let expected = if depth % 2 == 1 { 1.0 } else { 0.0 };
+                    assert_eq!(node.ratio(), expected, "depth {}", depth);
+                    next.push(child);
+                }
+            }
+            level = next;
+        }
+        assert_eq!(depth, 6);
+        let pv = tree.principal_variation(game);
+        assert_eq!(pv[0].win_ratio, 1.0);
+        assert_eq!(pv[1].win_ratio, 0.0);
+    }
+
+    #[test]
+    fn test_auto_pass_credits_the_mover() {
+        // find a move that forces the opponent to pass
+        let mut rng = RngBackend::ChaCha.stream(2, 0, 0);
+        let (parent, child) = 'search: loop {
+            let mut game = Game::new();
+            game.set_auto_pass(true);
+            while !game.is_over() {
+                for mv in game.legal_moves() {
+                    let mut child = game;
+                    child.play_next_turn(mv).unwrap();
+                    if !child.is_over() && child.to_move() == game.to_move() {
+                        break 'search (game, child);
+                    }
+                }
+                game.play_next_turn(game.random_move(&mut rng)).unwrap();
+            }
+        };
+        let mover = parent.to_move();
+        assert_eq!(
+            path(parent, &[], parent, child).collect::<Vec<_>>(),
+            [(child, mover)]
+        );
+
+        let mut tree = Tree::new();
+        tree.backpropagate(Some(mover), path(parent, &[], parent, child));
+        assert_eq!(tree.map[&child.zobrist_hash()].wins, 1.0);
+
+        // the child played out is also credited to the leaf's player when the leaf is deeper
+        let mut grandchild = child;
+        grandchild.play_next_turn(child.legal_moves()[0]).unwrap();
+        let nodes: Vec<_> = path(parent, &[parent], child, grandchild).collect();
+        assert_eq!(nodes, [(parent, mover.opponent()), (grandchild, mover)]);
+    }
+
+    #[test]
+    fn test_misere() {
+        // with fewer discs winning, black wins every playout of the same position
+        let mut game = white_wins_last_five();
+        game.set_variant(Variant::Misere);
+        let mut tree = Tree::new();
+        tree.search_n(game, 200);
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            assert_eq!(tree.map[&child.zobrist_hash()].ratio(), 0.0);
+        }
+
+        // holding a corner counts against the player
+        let corner: Game = "B7/8/8/3WB3/3BW3/8/8/8 W 1 -".parse().unwrap();
+        let mut misere = corner;
+        misere.set_variant(Variant::Misere);
+        let standard = Tree::heuristic_terms(corner);
+        assert!(standard.corners > 0.0);
+        assert_eq!(Tree::heuristic_terms(misere).corners, -standard.corners);
+        assert_eq!(Tree::heuristic_terms(misere).mobility, standard.mobility);
+    }
+
+    #[test]
+    fn test_expansion_order() {
+        // the player to move can take a corner
+        let game = crate::corpus::find("tactical-1").unwrap().game();
+        let tree = |expansion_order| {
+            Tree::from_config(MctsConfig {
+                expansion_order,
+                ..MctsConfig::default()
+            })
+        };
+
+        let board = tree(ExpansionOrder::Board).expansion_order(game);
+        assert_eq!(board, game.legal_moves());
+        let squares = tree(ExpansionOrder::Squares).expansion_order(game);
+        assert_ne!(
+            squares[0].get_position().unwrap() & game.size().corners(),
+            0
+        );
+        let edge = |mv: &Move| mv.get_position().unwrap() & game.size().edges() != 0;
+        let edges = squares.iter().take_while(|mv| edge(mv)).count();
+        assert_eq!(edges, squares.iter().filter(|mv| edge(mv)).count());
+
+        let mut prior = tree(ExpansionOrder::Prior);
+        let order = prior.expansion_order(game);
+        let values: Vec<f64> = order
+            .iter()
+            .map(|mv| {
+                let mut child = game;
+                child.play_next_turn(*mv).unwrap();
+                Tree::heuristic(child)
+            })
+            .collect();
+        assert!(values.windows(2).all(|pair| pair[0] >= pair[1]));
+        // the first expansion follows the order
+        prior.search(game);
+        let mut first = game;
+        first.play_next_turn(order[0]).unwrap();
+        assert!(prior.map.contains_key(&first.zobrist_hash()));
+    }
+
+    #[test]
+    fn test_pruned_moves() {
+        let game = crate::corpus::find("tactical-1").unwrap().game();
+        let mut tree = Tree::new();
+        tree.search_n(game, 300);
+        assert!(tree.pruned_moves(game).is_empty());
+
+        let mut tree = Tree::from_config(MctsConfig {
+            exploration_factor: 0.2,
+            prune_margin: Some(0.0),
+            ..MctsConfig::default()
+        });
+        tree.search_n(game, 300);
+        let pruned = tree.pruned_moves(game);
+        assert!(!pruned.is_empty());
+        assert!(pruned.len() < game.legal_moves().len());
+        let visits = |tree: &Tree, mv: Move| {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            tree.map[&child.zobrist_hash()].visits
+        };
+        // no iteration goes through a move pruned before it
+        for _ in 0..100 {
+            let pruned = tree.pruned_moves(game);
+            let before: Vec<f64> = pruned.iter().map(|mv| visits(&tree, *mv)).collect();
+            tree.search(game);
+            for (mv, visits_before) in pruned.iter().zip(before) {
+                assert_eq!(visits(&tree, *mv), visits_before);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vetoes() {
+        let game = Game::new();
+        let mut tree = Tree::new();
+        tree.search_n(game, 50);
+        let best = tree.best_move(game);
+
+        tree.set_vetoes(game, &[best]);
+        let vetoed_visits = |tree: &Tree| {
+            let mut child = game;
+            child.play_next_turn(best).unwrap();
+            tree.map[&child.zobrist_hash()].visits
+        };
+        let visits = vetoed_visits(&tree);
+        tree.search_n(game, 50);
+        assert_eq!(vetoed_visits(&tree), visits);
+        assert_ne!(tree.best_move(game), best);
+
+        // a veto of every move is ignored
+        tree.set_vetoes(game, &game.legal_moves());
+        assert_eq!(tree.best_move(game), best);
+        tree.set_vetoes(game, &[]);
+        assert_eq!(tree.best_move(game), best);
+    }
+
+    #[test]
+    fn test_search_timings() {
+        let game = Game::new();
+        let mut tree = Tree::new();
+        tree.search_n(game, 5);
+        assert!(tree.take_timings().is_none());
+
+        tree.enable_timings();
+        tree.search_n(game, 20);
+        let mut other = Tree::new();
+        other.enable_timings();
+        other.search_n(game, 10);
+        tree.merge(other);
+
+        let timings = tree.take_timings().unwrap();
+        for (_, histogram) in timings.phases() {
+            assert_eq!(histogram.count(), 30);
+        }
+        assert_eq!(tree.take_timings().unwrap().iteration.count(), 0);
+    }
+
+    #[test]
+    fn test_eval_cache() {
+        let game = Game::new();
+        let mut uncached = Tree::new();
+        uncached.search_n(game, 50);
+
+        let cache = Arc::new(EvalCache::new(1 << 12));
+        let mut cached = Tree::new();
+        cached.set_eval_cache(cache.clone());
+        cached.search_n(game, 50);
+
+        // the cache only saves work, the search grows the same way
+        assert_eq!(cached.map, uncached.map);
+        assert_eq!(
+            cached.best_move_with_value(game),
+            uncached.best_move_with_value(game)
+        );
+        assert!(cache.take_stats().hits > 0);
+    }
+
+    #[test]
+    fn test_outcome_table() {
+        let config = MctsConfig {
+            outcome_empties: 64,
+            ..MctsConfig::default()
+        };
+        let game = Game::new();
+        let mut tree = Tree::from_config(config.clone());
+        tree.search_n(game, 30);
+        let stats = tree.take_outcome_stats().unwrap();
+        assert!(stats.hits > 0);
+        assert!(Tree::new().take_outcome_stats().is_none());
+
+        // a stored outcome ends the playout right away
+        let mut tree = Tree::from_config(config);
+        let (winner, plies, _) = tree.simulate(game, false);
+        assert!(plies >= 50);
+        let outcomes = tree.outcomes.as_mut().unwrap();
+        assert_eq!(outcomes.get(game.zobrist_hash()), Some(winner));
+        let other = match winner {
+            Some(Color::Black) => Some(Color::White),
+            _ => Some(Color::Black),
+        };
+        outcomes.insert(game.zobrist_hash(), other);
+        assert_eq!(tree.simulate(game, false), (other, 0, 0));
+    }
+
+    #[test]
+    fn test_policy_target() {
+        let mut tree = Tree::new();
+        let game = Game::new();
+
+        let policy = tree.policy_target(game);
+        assert_eq!(policy.len(), Move::INDICES);
+        assert_eq!(policy.iter().filter(|p| **p == 0.25).count(), 4);
+
+        for _ in 0..20 {
+            tree.search(game);
+        }
+        let policy = tree.policy_target(game);
+        let total: f64 = policy.iter().sum();
+        assert!((total - 1.0).abs() < 1e-9);
+        for mv in game.legal_moves() {
+            assert!(policy[mv.to_index()] > 0.0);
+        }
+        assert_eq!(policy[Move::PASS_INDEX], 0.0);
+    }
+
+    #[test]
+    fn test_principal_variation() {
+        let mut tree = Tree::new();
+        let game = Game::new();
+        assert!(tree.principal_variation(game).is_empty());
+
+        for _ in 0..200 {
+            tree.search(game);
+        }
+        let pv = tree.principal_variation(game);
+        assert!(pv.len() > 1);
+        let share = tree.best_move_share(game);
+        assert!((0.25..=1.0).contains(&share));
+        assert_eq!(Tree::new().best_move_share(game), 0.0);
+        let mut position = game;
+        for step in &pv {
+            // the most visited child of every position on the line
+            let most_visits = position
+                .legal_moves()
+                .iter()
+                .filter_map(|mv| {
+                    let mut child = position;
+                    child.play_next_turn(*mv).unwrap();
+                    tree.map.get(&child.zobrist_hash()).map(|node| node.visits)
+                })
+                .fold(0.0, f64::max);
+            assert_eq!(step.visits, most_visits);
+            assert!((0.0..=1.0).contains(&step.win_ratio));
+            position.play_next_turn(step.mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_contributions() {
+        let mut tree = Tree::new();
+        let game = Game::new();
+        for _ in 0..50 {
+            tree.search(game);
+        }
+        for mv in game.legal_moves() {
+            let mut child = game;
+            child.play_next_turn(mv).unwrap();
+            let contributions = tree.contributions(child);
+            assert_eq!(contributions.total(), tree.evaluate(child));
+            assert_eq!(contributions.heuristic(), Tree::heuristic(child));
+            assert!(contributions.visits > 5.0);
+        }
+        let unvisited = Tree::new().contributions(game);
+        assert_eq!(unvisited.corners, 0.0);
+        assert!(unvisited
+            .to_string()
+            .starts_with("visits +7.31, wins +5.00, corners +0.00"));
+    }
+
     #[test]
     fn test_merge() {
         let mut t1 = Tree::new();
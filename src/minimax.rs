@@ -20,15 +20,14 @@ impl Minimax {
 
     fn max_value(&self, game_state: Game, ply: i32) -> (i32, Move) {
         if game_state.is_terminal() || ply >= MAX_DEPTH {
-            let legal_moves = game_state.legal_moves();
-            let last_move = *legal_moves.first().unwrap_or(&Pass);
+            let last_move = game_state.legal_moves_iter().next().unwrap_or(Pass);
             let score = self.evaluate_state(game_state);
             return (score, last_move);
         }
 
         let mut v = i32::MIN;
-        let actions = game_state.legal_moves();
-        let mut mv = *actions.first().unwrap_or(&Pass);
+        let actions = game_state.legal_moves_iter();
+        let mut mv = actions.clone().next().unwrap_or(Pass);
         for action in actions {
             let mut sim_game = game_state;
             sim_game.play_next_turn(action).unwrap();
@@ -44,15 +43,14 @@ impl Minimax {
 
     fn min_value(&self, game_state: Game, ply: i32) -> (i32, Move) {
         if game_state.is_over() || ply >= MAX_DEPTH {
-            let legal_moves = game_state.legal_moves();
-            let last_move = *legal_moves.first().unwrap_or(&Pass);
+            let last_move = game_state.legal_moves_iter().next().unwrap_or(Pass);
             let score = self.evaluate_state(game_state);
             return (score, last_move);
         }
 
         let mut v = i32::MAX;
-        let actions = game_state.legal_moves();
-        let mut mv = *actions.first().unwrap_or(&Pass);
+        let actions = game_state.legal_moves_iter();
+        let mut mv = actions.clone().next().unwrap_or(Pass);
         for action in actions {
             let mut sim_game = game_state;
             sim_game.play_next_turn(action).unwrap();
@@ -70,25 +68,19 @@ impl Minimax {
         let mut score: i32 = 0;
         if game_state.get_turn() < 30 {
             if game_state.to_move() == self.max_player {
-                score = game_state.legal_moves().len() as i32;
+                score = game_state.mobility() as i32;
             } else {
-                for mv in game_state.legal_moves() {
+                for mv in game_state.legal_moves_iter() {
                     let mut sim_game = game_state;
                     sim_game.play_next_turn(mv).unwrap();
-                    let sim_moves = sim_game.legal_moves();
-                    if score > sim_moves.len() as i32 {
-                        score = sim_moves.len() as i32;
+                    let sim_moves = sim_game.mobility() as i32;
+                    if score > sim_moves {
+                        score = sim_moves;
                     }
                 }
             }
         } else {
-            score = game_state.score();
-            match self.max_player {
-                Color::Black => {}
-                Color::White => {
-                    score *= -1;
-                }
-            }
+            score = game_state.score_for(self.max_player);
         }
         score
     }
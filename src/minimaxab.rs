@@ -2,7 +2,7 @@
 use crate::drmecref::DrMecRef;
 use crate::othello::Color::{Black, White};
 use crate::othello::Move::Pass;
-use crate::othello::{Color, Game, Move, CORNERS, EDGES};
+use crate::othello::{Color, Game, Move};
 use std::cmp::Ordering;
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
@@ -160,13 +160,13 @@ fn evaluate_state(game_state: Game, max_player: Color) -> i32 {
             for mv in &current_moves {
                 let mut sim_game = game_state;
                 sim_game.play_next_turn(*mv).unwrap();
-                let sim_moves = sim_game.legal_moves();
-                let mut move_score = sim_moves.len() as i32;
+                let mut move_score = sim_game.mobility() as i32;
 
-                if CORNERS.contains(mv) {
+                let square = mv.get_position().unwrap_or(0);
+                if square & game_state.size().corners() != 0 {
                     move_score *= CORNER_MULTIPLIER;
                 }
-                if EDGES.contains(mv) {
+                if square & game_state.size().edges() != 0 {
                     move_score *= EDGE_MULTIPLIER;
                 }
 
@@ -176,10 +176,7 @@ fn evaluate_state(game_state: Game, max_player: Color) -> i32 {
             }
         }
     } else {
-        score = game_state.score();
-        if max_player == White {
-            score *= -1;
-        }
+        score = game_state.score_for(max_player);
     }
     score
 }
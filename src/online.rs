@@ -0,0 +1,428 @@
+//! Playing on online Othello servers.
+//!
+//! An [`OnlineClient`] is a connection to a game server that can log in, offer or accept
+//! games, send moves and report what happens on the server as [`ServerEvent`]s. Given a
+//! client, [`play_online`] plays a number of games with any [`Player`] unattended: it offers
+//! a game, accepts the first suitable challenge, plays the game to the end and starts over.
+//!
+//! [`GgsClient`] is a backend for servers in the style of the Generic Game Server, which talk
+//! in lines of text over a plain TCP connection. The client logs in by sending its name and
+//! password on lines of their own and talks to the Othello service with `ts` commands:
+//! ```text
+//! ts seek 8 05:00          offer a game on an 8x8 board with 5 minutes each
+//! ts accept .12            accept challenge .12
+//! ts play .45 d3           play d3 in game .45, `pa` passes
+//! ```
+//! The service answers with lines starting with `/os:`, of which the client understands
+//! ```text
+//! /os: + .12 alice 8 05:00                 alice challenges us to game .12
+//! /os: join .45 alice herb 05:00           game .45 starts, alice black, herb white
+//! /os: update .45 B d3 04:52 05:00         black played d3, then both clocks
+//! /os: end .45 40-24                       game .45 is over, the final disc counts
+//! ```
+//! Every other line is passed on as [`ServerEvent::Other`].
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::driver::GameResult;
+use crate::othello::Color::{Black, White};
+use crate::othello::{Color, Game, Move, RulesMode};
+use crate::record::{format_move, parse_move};
+use crate::Player;
+
+/// Something that happened on the server.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ServerEvent {
+    /// Another player wants to play us.
+    Challenge {
+        id: String,
+        opponent: String,
+        clock: Duration,
+    },
+    /// A game of ours has started.
+    GameStart {
+        id: String,
+        color: Color,
+        opponent: String,
+        clock: Duration,
+    },
+    /// A move was played in one of our games, ours included, with the time left on each
+    /// clock after it.
+    Move {
+        id: String,
+        color: Color,
+        mv: Move,
+        black_clock: Duration,
+        white_clock: Duration,
+    },
+    /// One of our games is over.
+    GameEnd { id: String, black: u32, white: u32 },
+    /// Anything else the server said.
+    Other(String),
+}
+
+/// A connection to an online game server, see the [module](self) documentation.
+pub trait OnlineClient {
+    /// Log in with the given name and password.
+    fn login(&mut self, name: &str, password: &str) -> io::Result<()>;
+
+    /// Offer a game with the given time for each player.
+    fn seek(&mut self, clock: Duration) -> io::Result<()>;
+
+    /// Accept the challenge with the given id.
+    fn accept(&mut self, id: &str) -> io::Result<()>;
+
+    /// Play a move in the game with the given id.
+    fn send_move(&mut self, id: &str, mv: Move) -> io::Result<()>;
+
+    /// Wait for the next event on the server.
+    fn next_event(&mut self) -> io::Result<ServerEvent>;
+}
+
+/// Which games [`play_online`] plays.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OnlineSettings {
+    /// The time of the games offered.
+    pub clock: Duration,
+    /// Challenges with less time than this are declined.
+    pub min_clock: Duration,
+    /// Challenges with more time than this are declined.
+    pub max_clock: Duration,
+    /// The number of games to play before returning.
+    pub games: usize,
+}
+
+/// Play games on the server with the player, unattended, until the settings' number of games
+/// is done. Every game starts with an offer, and the first challenge whose clock is in range
+/// is accepted. The player is told its color in every game, and everything the server says
+/// besides is ignored.
+///
+/// Returns the results of the games, or an error if the connection fails or the server sends
+/// a move that is not legal even leniently.
+pub fn play_online(
+    client: &mut dyn OnlineClient,
+    player: &mut dyn Player,
+    settings: &OnlineSettings,
+) -> io::Result<Vec<GameResult>> {
+    let mut results = Vec::new();
+    while results.len() < settings.games {
+        client.seek(settings.clock)?;
+        let (id, color, clock) = loop {
+            match client.next_event()? {
+                ServerEvent::Challenge { id, clock, .. }
+                    if (settings.min_clock..=settings.max_clock).contains(&clock) =>
+                {
+                    client.accept(&id)?
+                }
+                ServerEvent::GameStart {
+                    id, color, clock, ..
+                } => break (id, color, clock),
+                _ => {}
+            }
+        };
+        results.push(play_game(client, player, &id, color, clock)?);
+    }
+    Ok(results)
+}
+
+/// Play the game with the given id to the end as `color`, both sides starting with `clock`.
+fn play_game(
+    client: &mut dyn OnlineClient,
+    player: &mut dyn Player,
+    id: &str,
+    color: Color,
+    clock: Duration,
+) -> io::Result<GameResult> {
+    let mut result = GameResult {
+        moves: Vec::new(),
+        game: Game::new(),
+        black_time: Duration::ZERO,
+        white_time: Duration::ZERO,
+    };
+    player.new_game(color);
+    loop {
+        let game = result.game;
+        if game.to_move() == color && !game.is_over() {
+            let mv = player.get_next_move(game);
+            client.send_move(id, mv)?;
+            result.game.play_next_turn(mv).map_err(invalid)?;
+            result.moves.push(mv);
+            player.move_played(&game, mv);
+            continue;
+        }
+        match client.next_event()? {
+            ServerEvent::Move {
+                id: game_id,
+                color: mover,
+                mv,
+                black_clock,
+                white_clock,
+            } if game_id == id => {
+                result.black_time = clock.saturating_sub(black_clock);
+                result.white_time = clock.saturating_sub(white_clock);
+                // our own moves come back too
+                if mover == color || mover != game.to_move() {
+                    continue;
+                }
+                result
+                    .game
+                    .play_with_rules(mv, RulesMode::Lenient)
+                    .map_err(invalid)?;
+                result.moves.push(mv);
+                player.opponent_moved(&game, mv);
+            }
+            ServerEvent::GameEnd { id: game_id, .. } if game_id == id => break,
+            _ => {}
+        }
+    }
+    player.game_over(&result);
+    Ok(result)
+}
+
+/// A client for a server in the style of the Generic Game Server, see the [module](self)
+/// documentation.
+pub struct GgsClient<R, W> {
+    reader: R,
+    writer: W,
+    name: String,
+}
+
+impl GgsClient<BufReader<TcpStream>, TcpStream> {
+    /// Connect to the server at the address.
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let stream = TcpStream::connect(address)?;
+        Ok(GgsClient::new(BufReader::new(stream.try_clone()?), stream))
+    }
+}
+
+impl<R: BufRead, W: Write> GgsClient<R, W> {
+    /// Talk to the server through the given reader and writer.
+    pub fn new(reader: R, writer: W) -> Self {
+        GgsClient {
+            reader,
+            writer,
+            name: String::new(),
+        }
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()
+    }
+
+    /// Interpret a line from the server.
+    fn parse(&self, line: &str) -> io::Result<ServerEvent> {
+        let other = || Ok(ServerEvent::Other(line.to_string()));
+        let Some(message) = line.strip_prefix("/os:") else {
+            return other();
+        };
+        let words: Vec<&str> = message.split_whitespace().collect();
+        match words.as_slice() {
+            ["+", id, opponent, "8", clock, ..] => Ok(ServerEvent::Challenge {
+                id: id.to_string(),
+                opponent: opponent.to_string(),
+                clock: parse_clock(clock)?,
+            }),
+            ["join", id, black, white, clock, ..] => {
+                let (color, opponent) = if *black == self.name {
+                    (Black, white)
+                } else if *white == self.name {
+                    (White, black)
+                } else {
+                    // someone else's game
+                    return other();
+                };
+                Ok(ServerEvent::GameStart {
+                    id: id.to_string(),
+                    color,
+                    opponent: opponent.to_string(),
+                    clock: parse_clock(clock)?,
+                })
+            }
+            ["update", id, color, mv, black_clock, white_clock, ..] => Ok(ServerEvent::Move {
+                id: id.to_string(),
+                color: match *color {
+                    "B" => Black,
+                    "W" => White,
+                    _ => return Err(invalid(format!("Bad color: {}", line))),
+                },
+                mv: match *mv {
+                    "pa" => Move::Pass,
+                    mv => parse_move(mv).map_err(invalid)?,
+                },
+                black_clock: parse_clock(black_clock)?,
+                white_clock: parse_clock(white_clock)?,
+            }),
+            ["end", id, score, ..] => {
+                let bad_score = || invalid(format!("Bad score: {}", line));
+                let (black, white) = score.split_once('-').ok_or_else(bad_score)?;
+                Ok(ServerEvent::GameEnd {
+                    id: id.to_string(),
+                    black: black.parse().map_err(|_| bad_score())?,
+                    white: white.parse().map_err(|_| bad_score())?,
+                })
+            }
+            _ => other(),
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> OnlineClient for GgsClient<R, W> {
+    fn login(&mut self, name: &str, password: &str) -> io::Result<()> {
+        self.name = name.to_string();
+        self.send(name)?;
+        self.send(password)
+    }
+
+    fn seek(&mut self, clock: Duration) -> io::Result<()> {
+        self.send(&format!("ts seek 8 {}", format_clock(clock)))
+    }
+
+    fn accept(&mut self, id: &str) -> io::Result<()> {
+        self.send(&format!("ts accept {}", id))
+    }
+
+    fn send_move(&mut self, id: &str, mv: Move) -> io::Result<()> {
+        let mv = match mv {
+            Move::Pass => "pa".to_string(),
+            mv => format_move(mv),
+        };
+        self.send(&format!("ts play {} {}", id, mv))
+    }
+
+    fn next_event(&mut self) -> io::Result<ServerEvent> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Server closed the connection",
+            ));
+        }
+        self.parse(line.trim())
+    }
+}
+
+/// Parse a clock as `minutes:seconds`.
+fn parse_clock(clock: &str) -> io::Result<Duration> {
+    let bad_clock = || invalid(format!("Bad clock: {}", clock));
+    let (minutes, seconds) = clock.split_once(':').ok_or_else(bad_clock)?;
+    let minutes: u64 = minutes.parse().map_err(|_| bad_clock())?;
+    let seconds: u64 = seconds.parse().map_err(|_| bad_clock())?;
+    Ok(Duration::from_secs(minutes * 60 + seconds))
+}
+
+fn format_clock(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn invalid(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+
+    /// Lines of a server that challenges us and plays the moves of a greedy black.
+    fn script(herb_moves: &mut dyn Player) -> (String, GameResult) {
+        let mut greedy = Opponent::Greedy;
+        let result = crate::tournament::play_game(&mut greedy, herb_moves).unwrap();
+        let mut lines = vec![
+            "Welcome".to_string(),
+            "/os: + .7 alice 8 01:00".to_string(),
+            "/os: + .8 bob 8 05:00".to_string(),
+            "/os: join .9 bob someone 05:00".to_string(),
+            "/os: join .8 bob herb 05:00".to_string(),
+        ];
+        for (i, mv) in result.moves.iter().enumerate() {
+            let (color, mv) = (if i % 2 == 0 { "B" } else { "W" }, format_move(*mv));
+            let mv = if mv == "pass" { "pa".to_string() } else { mv };
+            lines.push(format!(
+                "/os: update .8 {} {} 04:{:02} 05:00",
+                color,
+                mv,
+                59 - i % 60
+            ));
+        }
+        let (black, white) = result.game.disc_counts();
+        lines.push(format!("/os: end .8 {}-{}", black, white));
+        (lines.join("\n") + "\n", result)
+    }
+
+    #[test]
+    fn test_parse() {
+        let mut client = GgsClient::new(io::empty(), Vec::new());
+        client.login("herb", "secret").unwrap();
+        assert_eq!(
+            client.parse("/os: join .4 herb alice 10:30").unwrap(),
+            ServerEvent::GameStart {
+                id: ".4".to_string(),
+                color: Black,
+                opponent: "alice".to_string(),
+                clock: Duration::from_secs(630),
+            }
+        );
+        assert!(matches!(
+            client.parse("/os: update .4 W pa 01:00 00:59").unwrap(),
+            ServerEvent::Move {
+                mv: Move::Pass,
+                color: White,
+                ..
+            }
+        ));
+        assert_eq!(
+            client.parse("/os: end .4 33-31").unwrap(),
+            ServerEvent::GameEnd {
+                id: ".4".to_string(),
+                black: 33,
+                white: 31
+            }
+        );
+        assert_eq!(
+            client.parse("hello").unwrap(),
+            ServerEvent::Other("hello".to_string())
+        );
+        assert!(client.parse("/os: update .4 W z9 01:00 00:59").is_err());
+        assert!(client.parse("/os: + .5 bob 8 soon").is_err());
+        assert_eq!(client.writer, b"herb\nsecret\n");
+    }
+
+    #[test]
+    fn test_play_online() {
+        let (lines, expected) = script(&mut Opponent::Greedy);
+        let mut client = GgsClient::new(lines.as_bytes(), Vec::new());
+        client.login("herb", "secret").unwrap();
+        let settings = OnlineSettings {
+            clock: Duration::from_secs(300),
+            min_clock: Duration::from_secs(120),
+            max_clock: Duration::from_secs(600),
+            games: 1,
+        };
+        let results = play_online(&mut client, &mut Opponent::Greedy, &settings).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].moves, expected.moves);
+        assert!(results[0].game.is_over());
+        assert!(results[0].black_time > Duration::ZERO);
+
+        let sent = String::from_utf8(client.writer).unwrap();
+        let sent: Vec<&str> = sent.lines().collect();
+        assert_eq!(
+            sent[..4],
+            ["herb", "secret", "ts seek 8 05:00", "ts accept .8"]
+        );
+        // white's moves, and nothing else
+        let white_moves = expected.moves.len() / 2;
+        assert_eq!(sent.len(), 4 + white_moves);
+        assert_eq!(
+            sent[4],
+            format!("ts play .8 {}", format_move(expected.moves[1]))
+        );
+
+        // the server hanging up is an error
+        let mut client = GgsClient::new(&b"/os: + .8 bob 8 05:00\n"[..], Vec::new());
+        assert!(play_online(&mut client, &mut Opponent::Greedy, &settings).is_err());
+    }
+}
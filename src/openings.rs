@@ -0,0 +1,119 @@
+//! Names of standard Othello openings.
+//!
+//! Openings are written the way they are in the literature, from Black's first move on `f5`
+//! in the algebraic notation of [`record`](crate::record). The four first moves are
+//! equivalent, so an opening also covers its rotations and reflections: a game starting
+//! `d3 c5` is a perpendicular opening just like `f5 d6`. Positions are compared rather than
+//! moves, which also names transpositions into an opening.
+//!
+//! ```rust
+//! use herb::openings;
+//! use herb::record::parse_move;
+//!
+//! let moves: Vec<_> = ["c4", "e3", "f6", "e6", "f5"]
+//!     .iter()
+//!     .map(|token| parse_move(token).unwrap())
+//!     .collect();
+//! assert_eq!(openings::opening_name(&moves), Some("Tiger"));
+//! ```
+use crate::othello::{Game, Move, Symmetry};
+use crate::record::parse_move;
+
+/// Opening names and their moves from the starting position, shorter lines first.
+pub const OPENINGS: [(&str, &str); 10] = [
+    ("Diagonal opening", "f5 f6"),
+    ("Perpendicular opening", "f5 d6"),
+    ("Parallel opening", "f5 f4"),
+    ("Cow", "f5 d6 c5 f4 e3"),
+    ("Rabbit", "f5 f6 e6 f4 e3"),
+    ("Heath", "f5 f6 e6 f4 g5"),
+    ("Tiger", "f5 d6 c3 d3 c4"),
+    ("Rose-bill", "f5 d6 c3 d3 c4 f4 f6 g5"),
+    ("Aubrey", "f5 d6 c3 d3 c4 f4 c5 b3 c2"),
+    ("Rose", "f5 d6 c3 d3 c4 f4 f6 f3 e6 e7"),
+];
+
+/// Returns the name of the opening the position is, `None` if it is not a named position.
+pub fn name(game: &Game) -> Option<&'static str> {
+    OPENINGS
+        .iter()
+        .filter(|(_, moves)| moves.split_whitespace().count() == game.get_turn())
+        .find(|(_, moves)| {
+            let position = play(moves);
+            Symmetry::ALL.iter().any(|symmetry| {
+                let transformed = position.transform(*symmetry);
+                transformed.get_board() == game.get_board()
+                    && transformed.to_move() == game.to_move()
+            })
+        })
+        .map(|(name, _)| *name)
+}
+
+/// Returns the name of the last named position the moves from the starting position pass
+/// through, `None` if they never reach one or a move is illegal.
+pub fn opening_name(moves: &[Move]) -> Option<&'static str> {
+    let mut game = Game::new();
+    let mut opening = None;
+    for mv in moves.iter().take(longest()) {
+        game.play_next_turn(*mv).ok()?;
+        opening = name(&game).or(opening);
+    }
+    opening
+}
+
+/// Returns the number of moves in the longest opening.
+fn longest() -> usize {
+    OPENINGS
+        .iter()
+        .map(|(_, moves)| moves.split_whitespace().count())
+        .max()
+        .unwrap_or(0)
+}
+
+fn play(moves: &str) -> Game {
+    let mut game = Game::new();
+    for token in moves.split_whitespace() {
+        game.play_next_turn(parse_move(token).unwrap()).unwrap();
+    }
+    game
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(moves: &str) -> Vec<Move> {
+        moves
+            .split_whitespace()
+            .map(|token| parse_move(token).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_openings_are_legal_and_distinct() {
+        for (i, (name, moves)) in OPENINGS.iter().enumerate() {
+            let game = play(moves);
+            assert_eq!(super::name(&game), Some(*name));
+            assert!(OPENINGS[..i].iter().all(|(_, other)| other != moves));
+        }
+    }
+
+    #[test]
+    fn test_opening_name() {
+        assert_eq!(opening_name(&[]), None);
+        assert_eq!(opening_name(&parse("f5")), None);
+        assert_eq!(
+            opening_name(&parse("f5 d6 c3")),
+            Some("Perpendicular opening")
+        );
+        // the Tiger from the other three first moves
+        assert_eq!(opening_name(&parse("d3 c5 f6 f5 e6")), Some("Tiger"));
+        assert_eq!(opening_name(&parse("e6 f4 c3 c4 d3")), Some("Tiger"));
+        // leaving the book keeps the last name
+        assert_eq!(
+            opening_name(&parse("f5 d6 c3 d3 c4 f4 f6 f3 e6 e7 d7 g6")),
+            Some("Rose")
+        );
+        assert_eq!(opening_name(&parse("f5 a1")), None);
+    }
+}
@@ -0,0 +1,207 @@
+//! Scripted reference opponents.
+//!
+//! Each [`Opponent`] picks its move with a simple fixed rule and no search, so a match
+//! against the whole [`Opponent::LADDER`] is a quick smoke test of Herb's strength that
+//! needs no external engines. The ladder is ordered roughly from weakest to strongest.
+//! Ties between equally good moves go to the first legal move, so every opponent except
+//! [`Opponent::Random`] plays the same game every time. [`Opponent::with_rng`] gives an
+//! opponent a generator of its own, so a random one plays the same game every time too.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use crate::evaluation::{Evaluator, WeightTable};
+use crate::othello::Move::Pass;
+use crate::othello::{Color, Game, Move};
+use crate::random::StreamRng;
+use crate::Player;
+
+/// A scripted opponent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Opponent {
+    /// Plays a random legal move.
+    Random,
+    /// Plays the move that flips the most discs.
+    Greedy,
+    /// Plays the move that leaves the opponent the fewest legal moves.
+    Mobility,
+    /// Takes a corner when it can, avoids the squares next to the corners, and otherwise
+    /// plays like [`Opponent::Greedy`].
+    Corner,
+    /// Plays the move that maximizes the [`WeightTable::CLASSIC`] score of its discs less
+    /// the opponent's.
+    Positional,
+}
+
+impl Opponent {
+    /// Every opponent, from weakest to strongest.
+    pub const LADDER: [Opponent; 5] = [
+        Opponent::Random,
+        Opponent::Greedy,
+        Opponent::Mobility,
+        Opponent::Corner,
+        Opponent::Positional,
+    ];
+
+    /// Choose a move in the given position. [`Opponent::Random`] draws it from a generator
+    /// seeded by the operating system.
+    pub fn choose(&self, game: &Game) -> Move {
+        match self {
+            Opponent::Random => game.random_move(&mut StreamRng::from_entropy()),
+            Opponent::Greedy => best_by(game, |_, after| own_discs(game, after)),
+            Opponent::Mobility => game.move_with_lowest_opp_mobility(),
+            Opponent::Corner => best_by(game, |mv, after| {
+                let position = mv.get_position().unwrap_or(0);
+                // the squares diagonally next to a corner usually give the corner away
+                let class = if position & game.size().corners() != 0 {
+                    2
+                } else if position & game.size().x_squares() != 0 {
+                    0
+                } else {
+                    1
+                };
+                class * 64 + own_discs(game, after)
+            }),
+            Opponent::Positional => WeightTable::CLASSIC.choose(game),
+        }
+    }
+
+    /// Returns the opponent as a player that draws its random moves from the given generator,
+    /// e.g. the stream of a game from [`RngBackend::stream`](crate::random::RngBackend::stream).
+    pub fn with_rng(self, rng: StreamRng) -> SeededOpponent {
+        SeededOpponent {
+            opponent: self,
+            rng,
+        }
+    }
+}
+
+/// An [`Opponent`] with a random number generator of its own, see [`Opponent::with_rng`].
+#[derive(Clone, Debug)]
+pub struct SeededOpponent {
+    pub opponent: Opponent,
+    rng: StreamRng,
+}
+
+impl Player for SeededOpponent {
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        match self.opponent {
+            Opponent::Random => game_state.random_move(&mut self.rng),
+            opponent => opponent.choose(&game_state),
+        }
+    }
+}
+
+impl Display for Opponent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Opponent::Random => "random",
+            Opponent::Greedy => "greedy",
+            Opponent::Mobility => "mobility",
+            Opponent::Corner => "corner",
+            Opponent::Positional => "positional",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Player for Opponent {
+    fn get_next_move(&mut self, game_state: Game) -> Move {
+        self.choose(&game_state)
+    }
+}
+
+/// Returns the first legal move with the highest score, or [`Pass`] when there are none.
+/// The score is given the move and the position after it.
+fn best_by(game: &Game, score: impl Fn(Move, &Game) -> i32) -> Move {
+    let mut best_move = Pass;
+    let mut best_score = i32::MIN;
+    for mv in game.legal_moves() {
+        let mut sim_game = *game;
+        sim_game.play_next_turn(mv).unwrap();
+        let value = score(mv, &sim_game);
+        if value > best_score {
+            best_score = value;
+            best_move = mv;
+        }
+    }
+    best_move
+}
+
+/// Returns the discs held after a move by the player who made it.
+fn own_discs(before: &Game, after: &Game) -> i32 {
+    own_bits(before, after).count_ones() as i32
+}
+
+fn own_bits(before: &Game, after: &Game) -> u64 {
+    let board = after.get_board();
+    match before.to_move() {
+        Color::Black => board.get_black(),
+        Color::White => board.get_white(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::random::RngBackend;
+    use crate::tournament::play_game;
+
+    #[test]
+    fn test_opponents_play_legal_games() {
+        for black in Opponent::LADDER {
+            for white in Opponent::LADDER {
+                let result = play_game(&mut { black }, &mut { white }).unwrap();
+                assert!(result.game.is_over());
+            }
+        }
+    }
+
+    #[test]
+    fn test_seeded_random() {
+        let play = |seed| {
+            let black = Opponent::Random.with_rng(RngBackend::Philox.stream(seed, 0, 0));
+            let white = Opponent::Random.with_rng(RngBackend::Philox.stream(seed, 0, 1));
+            play_game(&mut { black }, &mut { white }).unwrap().moves
+        };
+        assert_eq!(play(1), play(1));
+        assert_ne!(play(1), play(2));
+
+        // the other opponents play as they always do
+        let mut greedy = Opponent::Greedy.with_rng(StreamRng::from_entropy());
+        let game = Game::new();
+        assert_eq!(greedy.get_next_move(game), Opponent::Greedy.choose(&game));
+    }
+
+    #[test]
+    fn test_corner_taken_when_available() {
+        // play until a corner is legal for someone
+        let mut game = Game::new();
+        while !game.is_over() {
+            let corner = game
+                .legal_moves()
+                .into_iter()
+                .find(|mv| mv.get_position().unwrap_or(0) & game.size().corners() != 0);
+            if let Some(corner) = corner {
+                assert_eq!(Opponent::Corner.choose(&game), corner);
+                return;
+            }
+            game.play_next_turn(Opponent::Greedy.choose(&game)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_greedy_flips_the_most() {
+        let mut game = Game::new();
+        for _ in 0..10 {
+            game.play_next_turn(game.legal_moves()[0]).unwrap();
+        }
+        let mut after = game;
+        after
+            .play_next_turn(Opponent::Greedy.choose(&game))
+            .unwrap();
+        for other in game.legal_moves() {
+            let mut sim_game = game;
+            sim_game.play_next_turn(other).unwrap();
+            assert!(own_discs(&game, &sim_game) <= own_discs(&game, &after));
+        }
+    }
+}
@@ -31,7 +31,8 @@
 //! a square on the board. The least significant bit represents the top left square, and the
 //! most significant bit represents the bottom right square. If a bit is set to 1, it means
 //! that square is occupied by a disc of that color. If a bit is set to 0, it means that
-//! square is empty.
+//! square is empty. The [`bits`] module has shifts, fills and masks for working with these
+//! bitboards directly.
 //!
 //!```text
 //!     0    1    2    3    4    5    6    7
@@ -153,15 +154,26 @@
 //! ```
 //!
 
+pub mod bits;
+pub mod corners;
+pub mod edges;
+pub mod history;
+pub mod position;
+pub mod regions;
+pub mod stability;
+pub mod zobrist;
+
 use rand::Rng;
 use std::error::Error;
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
+use crate::othello::bits::Direction;
+use crate::othello::corners::CornerThreat;
+use crate::othello::edges::{Edge, EdgeClass};
+use crate::othello::history::Turn;
+use crate::othello::regions::{EmptyRegion, RegionStats};
 use crate::othello::Color::{Black, White};
-use crate::othello::GameError::{GameOver, InvalidMove};
-use crate::othello::SearchDirection::{
-    DiagonalDownLeft, DiagonalDownRight, DiagonalUpLeft, DiagonalUpRight, Down, Left, Right, Up,
-};
+use crate::othello::GameError::{GameOver, InvalidMove, InvalidPosition};
 
 use crate::othello::Move::Pass;
 use serde::{Deserialize, Serialize};
@@ -169,71 +181,8 @@ use serde::{Deserialize, Serialize};
 const BLACK_INITIAL_POSITIONS: u64 = 1 << 28 | 1 << 35;
 const WHITE_INITIAL_POSITIONS: u64 = 1 << 27 | 1 << 36;
 
-const LEFT_EDGE_MASK: u64 = left_edge_mask();
-const RIGHT_EDGE_MASK: u64 = right_edge_mask();
-
-// Directions in this order: Up, Left, Diagonal Up-Left, Diagonal Down-Left,
-// Down, Right, Diagonal Down-Right, Diagonal Up-Right
-const DIRECTION_MASKS: [u64; 8] = [
-    0x7F7F7F7F7F7F7F7F,
-    0x00FFFFFFFFFFFFFF,
-    0x007F7F7F7F7F7F7F,
-    0x00FEFEFEFEFEFEFE,
-    0xFEFEFEFEFEFEFEFE,
-    0xFFFFFFFFFFFFFF00,
-    0xFEFEFEFEFEFEFE00,
-    0x7F7F7F7F7F7F7F00,
-];
-
-const DIRECTION_OFFSETS: [u64; 4] = [1, 8, 9, 7];
-
-pub const CORNERS: [Move; 4] = [Move::Move(0), Move::Move(7), Move::Move(56), Move::Move(63)];
-
-pub const X_MOVES: [Move; 12] = [
-    Move::Move(1),
-    Move::Move(6),
-    Move::Move(8),
-    Move::Move(9),
-    Move::Move(14),
-    Move::Move(15),
-    Move::Move(48),
-    Move::Move(49),
-    Move::Move(54),
-    Move::Move(55),
-    Move::Move(57),
-    Move::Move(62),
-];
-
-pub const EDGES: [Move; 28] = [
-    Move::Move(0),
-    Move::Move(1),
-    Move::Move(2),
-    Move::Move(3),
-    Move::Move(4),
-    Move::Move(5),
-    Move::Move(6),
-    Move::Move(7),
-    Move::Move(8),
-    Move::Move(16),
-    Move::Move(24),
-    Move::Move(32),
-    Move::Move(40),
-    Move::Move(48),
-    Move::Move(56),
-    Move::Move(15),
-    Move::Move(23),
-    Move::Move(31),
-    Move::Move(39),
-    Move::Move(47),
-    Move::Move(55),
-    Move::Move(57),
-    Move::Move(58),
-    Move::Move(59),
-    Move::Move(60),
-    Move::Move(61),
-    Move::Move(62),
-    Move::Move(63),
-];
+/// Number of planes produced by [`Game::feature_planes`].
+pub const FEATURE_PLANES: usize = 5;
 
 const DIAGONALS: [Move; 16] = [
     Move::Move(0),
@@ -280,26 +229,35 @@ const INNER_BOARD: [Move; 16] = [
     Move::Move(45),
 ];
 
-/// Builds a mask with bits set along the 'left' edge of the board.
-const fn left_edge_mask() -> u64 {
-    let mut result: u64 = 0;
-    let mut i: u8 = 0;
-    while i < 64 {
-        result |= 1 << i;
-        i += 8;
-    }
-    result
+/// Mirrors the board left to right, column `c` becomes column `7 - c`.
+const fn mirror_horizontal(bits: u64) -> u64 {
+    const K1: u64 = 0x5555555555555555;
+    const K2: u64 = 0x3333333333333333;
+    const K4: u64 = 0x0F0F0F0F0F0F0F0F;
+    let bits = ((bits >> 1) & K1) | ((bits & K1) << 1);
+    let bits = ((bits >> 2) & K2) | ((bits & K2) << 2);
+    ((bits >> 4) & K4) | ((bits & K4) << 4)
 }
 
-/// Builds a mask with bits set along the 'right' edge of the board
-const fn right_edge_mask() -> u64 {
-    let mut result: u64 = 0;
-    let mut i: u8 = 7;
-    while i < 64 {
-        result |= 1 << i;
-        i += 8;
-    }
-    result
+/// Flips the board top to bottom, row `r` becomes row `7 - r`.
+const fn flip_vertical(bits: u64) -> u64 {
+    bits.swap_bytes()
+}
+
+/// Transposes the board along the main (top-left to bottom-right) diagonal,
+/// the square at `(col, row)` moves to `(row, col)`.
+const fn transpose(bits: u64) -> u64 {
+    const K1: u64 = 0x5500550055005500;
+    const K2: u64 = 0x3333000033330000;
+    const K4: u64 = 0x0F0F0F0F00000000;
+    let mut bits = bits;
+    let mut t = K4 & (bits ^ (bits << 28));
+    bits ^= t ^ (t >> 28);
+    t = K2 & (bits ^ (bits << 14));
+    bits ^= t ^ (t >> 14);
+    t = K1 & (bits ^ (bits << 7));
+    bits ^= t ^ (t >> 7);
+    bits
 }
 
 /// Holds the state of a game of Othello.
@@ -307,21 +265,111 @@ const fn right_edge_mask() -> u64 {
 /// Some functions update the state and require Game variables
 /// to be declared as mut: `let mut game = Game::new();`.
 #[derive(PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, Debug)]
+#[serde(from = "GameState")]
 pub struct Game {
     current_board: Bitboard,
     current_player: Color,
     turn: i32,
+    #[serde(default)]
+    last_move: Option<Move>,
+    #[serde(default)]
+    auto_pass: bool,
+    #[serde(default, skip_serializing_if = "BoardSize::is_standard")]
+    size: BoardSize,
+    #[serde(default, skip_serializing_if = "Variant::is_standard")]
+    variant: Variant,
+    /// The [`zobrist`] hash of the position, kept up to date move by move.
+    #[serde(skip)]
+    hash: u64,
+}
+
+/// The serialized fields of a [`Game`], which has its hash computed when it is read back.
+#[derive(Deserialize)]
+struct GameState {
+    current_board: Bitboard,
+    current_player: Color,
+    turn: i32,
+    #[serde(default)]
+    last_move: Option<Move>,
+    #[serde(default)]
+    auto_pass: bool,
+    #[serde(default)]
+    size: BoardSize,
+    #[serde(default)]
+    variant: Variant,
+}
+
+impl From<GameState> for Game {
+    fn from(state: GameState) -> Self {
+        let mut game = Game {
+            current_board: state.current_board,
+            current_player: state.current_player,
+            turn: state.turn,
+            last_move: state.last_move,
+            auto_pass: state.auto_pass,
+            size: state.size,
+            variant: state.variant,
+            hash: 0,
+        };
+        game.rehash();
+        game
+    }
 }
 
 impl Game {
     /// Creates new Game state initializes the board with the pieces
     /// in their starting positions and sets the current player to black.
     pub fn new() -> Self {
-        Game {
+        Game::with_size(BoardSize::Eight)
+    }
+
+    /// Creates a new game on a board of the given size. The four starting discs are in the
+    /// middle of the board whatever its size.
+    ///
+    /// ```rust
+    /// use herb::othello::{BoardSize, Game};
+    ///
+    /// let game = Game::with_size(BoardSize::Six);
+    /// assert_eq!(game.empty_squares(), 32);
+    /// assert_eq!(game.legal_moves(), Game::new().legal_moves());
+    /// ```
+    pub fn with_size(size: BoardSize) -> Self {
+        let mut game = Game {
             turn: 0,
             current_player: Black,
             current_board: Bitboard::new(),
-        }
+            last_move: None,
+            auto_pass: false,
+            size,
+            variant: Variant::Standard,
+            hash: 0,
+        };
+        game.rehash();
+        game
+    }
+
+    /// Returns the size of the board the game is played on.
+    pub fn size(&self) -> BoardSize {
+        self.size
+    }
+
+    /// Set the rules the game is won by, see [`Variant`].
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    /// Returns the rules the game is won by.
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    /// Recompute the hash from scratch after the board or the player to move were set.
+    fn rehash(&mut self) {
+        self.hash = zobrist::hash(
+            self.current_board.black,
+            self.current_board.white,
+            self.current_player,
+        );
     }
 
     /// Progresses the game by one turn. If given a valid move the board will be updated
@@ -329,207 +377,194 @@ impl Game {
     /// it is taken as a pass by the current player and the game moves forward one turn
     /// switching to the other player, but it does not update the board.
     ///
-    /// If the given move is illegal by the rules of Othello, an [`InvalidMove`] error is returned
-    /// and the game state is unchanged.
+    /// The rules are applied in [`RulesMode::Strict`]: if the given move is illegal by the
+    /// rules of Othello, including passing when there are legal moves and any move other
+    /// than [`Pass`] when the current player [must pass](Game::must_pass), an [`InvalidMove`]
+    /// error is returned and the game state is unchanged. Use [`Game::play_with_rules`] for
+    /// moves from outside the engine.
+    ///
+    /// With [auto pass](Game::set_auto_pass) enabled, a forced pass by the next player is
+    /// played straight after the move, so the same player moves again.
     pub fn play_next_turn(&mut self, mv: Move) -> Result<(), GameError> {
+        self.play_with_rules(mv, RulesMode::Strict).map(|_| ())
+    }
+
+    /// Progresses the game by one turn like [`Game::play_next_turn`], applying the rules in
+    /// the given mode.
+    ///
+    /// In [`RulesMode::Lenient`] irregular moves that still have an obvious meaning are
+    /// accepted and described by the returned warning: a move after the game is over is
+    /// ignored, a move when the player must pass is played as a pass, and a pass when there
+    /// are legal moves is allowed. Moves to illegal squares are an error in both modes.
+    pub fn play_with_rules(
+        &mut self,
+        mv: Move,
+        mode: RulesMode,
+    ) -> Result<Option<RulesWarning>, GameError> {
+        let lenient = mode == RulesMode::Lenient;
         if self.is_over() {
-            return Err(GameOver);
+            return if lenient {
+                Ok(Some(RulesWarning::MoveAfterGameOver(mv)))
+            } else {
+                Err(GameOver)
+            };
         }
 
         let legal_moves = self.legal_moves();
-
-        if !legal_moves.is_empty() {
-            match mv {
-                Move::Move(_position) => {
-                    if legal_moves.contains(&mv) {
-                        self.apply_move(mv);
-                    } else {
-                        return Err(InvalidMove);
-                    }
-                }
-                Move::Pass => self.apply_move(mv),
+        let mut warning = None;
+        let mv = match mv {
+            Move::Move(_) if legal_moves.contains(&mv) => mv,
+            Move::Move(_) if legal_moves.is_empty() && lenient => {
+                warning = Some(RulesWarning::MoveWhenForcedToPass(mv));
+                Pass
             }
+            Move::Move(_) => return Err(InvalidMove),
+            Move::Pass if legal_moves.is_empty() => Pass,
+            Move::Pass if lenient => {
+                warning = Some(RulesWarning::VoluntaryPass);
+                Pass
+            }
+            Move::Pass => return Err(InvalidMove),
+        };
+
+        self.apply_move(mv);
+        self.switch_turn(mv);
+
+        if self.auto_pass && self.must_pass() {
+            self.switch_turn(Pass);
         }
 
+        Ok(warning)
+    }
+
+    /// Records the move just played and hands the turn to the other player.
+    fn switch_turn(&mut self, mv: Move) {
+        self.last_move = Some(mv);
         self.turn += 1;
+        self.current_player = self.current_player.opponent();
+        self.hash ^= zobrist::WHITE_TO_MOVE;
+    }
 
-        // switch players
-        self.current_player = match self.current_player {
-            Black => White,
-            White => Black,
-        };
+    /// Returns true if the current player has no legal moves but the game is not over, so
+    /// the only legal move is [`Pass`].
+    pub fn must_pass(&self) -> bool {
+        self.legal_moves().is_empty() && !self.is_over()
+    }
 
-        Ok(())
+    /// Enable or disable playing forced passes automatically.
+    ///
+    /// Referees count a forced pass as a turn without the player sending anything, so with
+    /// auto pass enabled the turn count matches the referee's even if the caller never plays
+    /// the passes itself. [`Game::last_move`] is then [`Pass`] after a move that forced one.
+    pub fn set_auto_pass(&mut self, auto_pass: bool) {
+        self.auto_pass = auto_pass;
+        if auto_pass && self.must_pass() {
+            self.switch_turn(Pass);
+        }
+    }
+
+    /// Returns true if forced passes are played automatically.
+    pub fn auto_pass(&self) -> bool {
+        self.auto_pass
+    }
+
+    /// Take back the turn, which must be the last turn played in this game, recorded as in
+    /// [`GameHistory`](history::GameHistory).
+    pub fn undo(&mut self, turn: &Turn) {
+        debug_assert_eq!(turn.player, self.current_player.opponent());
+        if let Some(position) = turn.mv.get_position() {
+            let board = &mut self.current_board;
+            let (own, opponent) = match turn.player {
+                Black => (&mut board.black, &mut board.white),
+                White => (&mut board.white, &mut board.black),
+            };
+            *own &= !(position | turn.flipped);
+            *opponent |= turn.flipped;
+            self.hash ^= zobrist::discs(position, turn.player) ^ zobrist::flips(turn.flipped);
+        }
+        self.last_move = turn.previous;
+        self.turn -= 1;
+        self.current_player = turn.player;
+        self.hash ^= zobrist::WHITE_TO_MOVE;
+    }
+
+    /// Play the turn again after it was taken back with [`Game::undo`]. Unlike
+    /// [`Game::play_next_turn`] it does not check the move or play a forced pass after it,
+    /// a pass played automatically is a turn of its own.
+    pub fn redo(&mut self, turn: &Turn) {
+        debug_assert_eq!(turn.player, self.current_player);
+        self.apply_move(turn.mv);
+        self.switch_turn(turn.mv);
     }
 
     /// Applies the given move to the internal board and flips appropriate pieces.
     /// DOES NOT CHECK FOR INVALID MOVES.
     /// If given an invalid move the behavior is undefined.
-    ///
-    /// Returns true if update was successful. It is all bit-wise operations from here,
-    /// so if something fails there is a bigger problem.
     fn apply_move(&mut self, mv: Move) {
-        let player = self.current_player;
-
-        // make copies of the board values to work on
-        let (mut new_black, mut new_white) = (self.current_board.black, self.current_board.white);
-
         if let Some(position) = mv.get_position() {
-            // depending on whose turn it is, set the position bit and flip the appropriate bits
-            match player {
-                Black => {
-                    new_black |= position;
-                    let directions = [
-                        Left,
-                        Right,
-                        Down,
-                        Up,
-                        DiagonalUpRight,
-                        DiagonalDownRight,
-                        DiagonalUpLeft,
-                        DiagonalDownLeft,
-                    ];
-                    // look in each direction for pieces that would be flipped
-                    for &dir in directions.iter() {
-                        self.flip(position, &mut new_black, &mut new_white, dir);
-                    }
-                }
-                White => {
-                    new_white |= position;
-                    let directions = [
-                        Left,
-                        Right,
-                        Down,
-                        Up,
-                        DiagonalUpRight,
-                        DiagonalDownRight,
-                        DiagonalUpLeft,
-                        DiagonalDownLeft,
-                    ];
-                    // look in each direction for pieces that would be flipped
-                    for &dir in directions.iter() {
-                        self.flip(position, &mut new_white, &mut new_black, dir);
-                    }
-                }
-            }
-
-            // replace the current_board values with the updated copies
-            self.current_board.black = new_black;
-            self.current_board.white = new_white;
+            let square = position.trailing_zeros() as usize;
+            let board = &mut self.current_board;
+            let (own, opponent) = match self.current_player {
+                Black => (&mut board.black, &mut board.white),
+                White => (&mut board.white, &mut board.black),
+            };
+            let flips = bits::flips(*own, *opponent, square);
+            *own |= position | flips;
+            *opponent &= !flips;
+            self.hash ^= zobrist::discs(position, self.current_player) ^ zobrist::flips(flips);
         }
     }
 
+    /// Returns the discs the move would flip for the current player, without playing it.
+    /// Returns 0 for [`Pass`] and illegal moves.
+    ///
+    /// ```rust
+    /// use herb::othello::{Game, Move};
+    ///
+    /// let game = Game::new();
+    /// let d3 = Move::from_col_row(3, 2).unwrap();
+    /// // d3 flips the white disc on d4
+    /// assert_eq!(game.would_flip(d3), 1 << 27);
+    /// ```
+    pub fn would_flip(&self, mv: Move) -> u64 {
+        let Some(position) = mv.get_position() else {
+            return 0;
+        };
+        let (own, opponent) = match self.current_player {
+            Black => (self.current_board.black, self.current_board.white),
+            White => (self.current_board.white, self.current_board.black),
+        };
+        bits::flips(own, opponent, position.trailing_zeros() as usize)
+    }
+
+    /// Returns the number of discs the move would flip for the current player, without
+    /// playing it. Returns 0 for [`Pass`] and illegal moves.
+    pub fn count_flips(&self, mv: Move) -> u32 {
+        self.would_flip(mv).count_ones()
+    }
+
     /// Returns a vector of all legal moves for the current player.
     ///
     /// If the returned vector is empty, there are no legal moves for the
     /// current player.
     pub fn legal_moves(&self) -> Vec<Move> {
-        let mut legal_moves = Vec::new();
+        self.legal_moves_iter().collect()
+    }
+
+    /// Returns the squares the current player can move to as a bitboard, without building a
+    /// vector of [`Move`]s.
+    pub fn legal_moves_mask(&self) -> u64 {
         let (player_pieces, opponent_pieces) = match self.current_player {
             Black => (self.current_board.black, self.current_board.white),
             White => (self.current_board.white, self.current_board.black),
         };
-
-        let empty_tiles = !(self.current_board.black | self.current_board.white);
-        let mut valid_moves: u64 = 0;
-
-        for i in 0..4 {
-            let mut neighbors =
-                ((player_pieces & DIRECTION_MASKS[i]) << DIRECTION_OFFSETS[i]) & opponent_pieces;
-            while neighbors != 0 {
-                let potential_flips = (neighbors & DIRECTION_MASKS[i]) << DIRECTION_OFFSETS[i];
-                valid_moves |= potential_flips & empty_tiles;
-                neighbors = potential_flips & opponent_pieces;
-            }
-
-            neighbors = ((player_pieces & DIRECTION_MASKS[i + 4]) >> DIRECTION_OFFSETS[i])
-                & opponent_pieces;
-            while neighbors != 0 {
-                let potential_flips = (neighbors & DIRECTION_MASKS[i + 4]) >> DIRECTION_OFFSETS[i];
-                valid_moves |= potential_flips & empty_tiles;
-                neighbors = potential_flips & opponent_pieces;
-            }
-        }
-
-        // Convert the bitboard of valid moves into a vector of Move
-        for row in 0..8 {
-            for col in 0..8 {
-                let shift = (row * 8) + col;
-                let position = 1u64 << shift;
-                if (valid_moves & position) != 0 {
-                    // This position is a valid move, so we add it to the list of legal moves.
-                    if let Ok(mv) = Move::new(position) {
-                        legal_moves.push(mv);
-                    }
-                }
-            }
-        }
-
-        legal_moves
+        bits::moves(player_pieces, opponent_pieces) & self.size.squares()
     }
 
-    /// Flips the opponent pieces that are captured between pos in the given direction.
-    /// Updates the internal state of the game.
-    fn flip(&mut self, pos: u64, own: &mut u64, opponent: &mut u64, direction: SearchDirection) {
-        let mut flip = 0u64;
-        let mut mask = pos;
-
-        loop {
-            // move in the given direction until we hit an edge by shifting the mask one bit at a time
-            mask = match direction {
-                Right => {
-                    if mask & RIGHT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask << 1
-                }
-                Left => {
-                    if mask & LEFT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask >> 1
-                }
-                Down => mask << 8,
-                Up => mask >> 8,
-                DiagonalUpRight => {
-                    if mask & RIGHT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask << 9
-                }
-                DiagonalDownRight => {
-                    if mask & LEFT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask >> 9
-                }
-                DiagonalDownLeft => {
-                    if mask & LEFT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask << 7
-                }
-                DiagonalUpLeft => {
-                    if mask & RIGHT_EDGE_MASK != 0 {
-                        break;
-                    }
-                    mask >> 7
-                }
-            };
-
-            // look if there is an opponent's piece under the mask
-            if mask & *opponent != 0 {
-                // there is a piece to flip
-                flip |= mask;
-            } else if mask & *own != 0 {
-                // we hit our own piece, set the appropriate bits
-                *own ^= flip;
-                *opponent ^= flip;
-                break;
-            } else {
-                break;
-            }
-        }
+    /// Returns an iterator over the legal moves for the current player in the same order as
+    /// [`Game::legal_moves`], without allocating.
+    pub fn legal_moves_iter(&self) -> Moves {
+        Moves::new(self.legal_moves_mask())
     }
 
     /// Returns the internal [`Bitboard`].
@@ -547,36 +582,23 @@ impl Game {
         self.turn as usize
     }
 
+    /// Returns the move played on the previous turn, or `None` at the start of the game.
+    pub fn last_move(&self) -> Option<Move> {
+        self.last_move
+    }
+
     /// Returns true if there are no legal moves left for either player.
     pub fn is_over(&self) -> bool {
-        // Check if the current player has legal moves
-        let current_legal_moves = self.legal_moves();
-
-        // If the current player has legal moves, the game isn't over
-        if !current_legal_moves.is_empty() {
-            return false;
-        }
-
-        // Simulate the next player's turn to check for legal moves
-        let mut sim_game = *self;
-        sim_game.turn += 1;
-        sim_game.current_player = match self.current_player {
-            Black => White,
-            White => Black,
-        };
-        let next_player_legal_moves = sim_game.legal_moves();
-
-        // If neither player has legal moves, the game is over
-        current_legal_moves.is_empty() && next_player_legal_moves.is_empty()
+        let (black, white) = (self.current_board.black, self.current_board.white);
+        (bits::moves(black, white) | bits::moves(white, black)) & self.size.squares() == 0
     }
 
     /// Returns true if the next call to [`play_next_move`] will end the game.
     pub fn is_terminal(&self) -> bool {
         let mut sim_game = *self;
         if !sim_game.is_over() {
-            let legal_moves = sim_game.legal_moves();
-            if let Some(next_move) = legal_moves.first() {
-                sim_game.play_next_turn(*next_move).unwrap();
+            if let Some(next_move) = sim_game.legal_moves_iter().next() {
+                sim_game.play_next_turn(next_move).unwrap();
                 if sim_game.is_over() {
                     return true;
                 }
@@ -590,15 +612,41 @@ impl Game {
     /// -  greater than `0` means black is ahead by the amount returned
     /// -  less than `0` means white is ahead by the amount returned
     pub fn score(&self) -> i32 {
-        self.current_board.black.count_ones() as i32 - self.current_board.white.count_ones() as i32
+        let (black, white) = self.disc_counts();
+        black as i32 - white as i32
+    }
+
+    /// Returns the number of black and white discs on the board.
+    pub fn disc_counts(&self) -> (u32, u32) {
+        (
+            self.current_board.black.count_ones(),
+            self.current_board.white.count_ones(),
+        )
+    }
+
+    /// Returns the number of discs the given player is ahead by, negative when it is behind.
+    pub fn score_for(&self, color: Color) -> i32 {
+        match color {
+            Black => self.score(),
+            White => -self.score(),
+        }
     }
 
-    /// Returns the player that is currently winning.
+    /// Returns the score from the perspective of the player to move, see [`Game::score_for`].
+    pub fn relative_score(&self) -> i32 {
+        self.score_for(self.to_move())
+    }
+
+    /// Returns the player that is currently winning, the one with more discs or, in
+    /// [`Variant::Misere`], the one with fewer.
     /// - `Some(Black)` Black is winning
     /// - `None` It is tied.
     /// - `Some(White)` White is winning.
     pub fn winner(&self) -> Option<Color> {
-        let score = self.score();
+        let score = match self.variant {
+            Variant::Standard => self.score(),
+            Variant::Misere => -self.score(),
+        };
         match score {
             score if score > 0 => Some(Black),
             0 => None,
@@ -608,12 +656,70 @@ impl Game {
 
     /// Returns the current number of empty squares on on the current board.
     pub fn empty_squares(&self) -> u64 {
-        (self.current_board.black | self.current_board.white).count_zeros() as u64
+        (self.size.squares() & !(self.current_board.black | self.current_board.white)).count_ones()
+            as u64
+    }
+
+    /// Returns the connected regions of empty squares with their sizes and parities, see
+    /// [`regions`].
+    pub fn empty_regions(&self) -> Vec<EmptyRegion> {
+        regions::empty_regions(
+            self.size.squares() & !(self.current_board.black | self.current_board.white),
+        )
+    }
+
+    /// Returns who can take each empty corner or be made to give it away over the next
+    /// turns, see [`corners`].
+    pub fn corner_threats(&self) -> Vec<CornerThreat> {
+        corners::corner_threats(self)
+    }
+
+    /// Returns how safe the discs of the color are on the edge, see [`edges`].
+    pub fn edge_class(&self, edge: Edge, color: Color) -> EdgeClass {
+        let (own, opponent) = match color {
+            Black => (self.current_board.black, self.current_board.white),
+            White => (self.current_board.white, self.current_board.black),
+        };
+        edge.classify(own, opponent)
+    }
+
+    /// Returns the class of every edge for the color, in the order of [`Edge::ALL`].
+    pub fn edge_classes(&self, color: Color) -> [EdgeClass; 4] {
+        Edge::ALL.map(|edge| self.edge_class(edge, color))
+    }
+
+    /// Returns the black and the white discs that can never be flipped, see [`stability`].
+    pub fn stable_discs(&self) -> (u64, u64) {
+        stability::stable_discs(
+            self.current_board.black,
+            self.current_board.white,
+            self.size.squares(),
+        )
     }
 
-    /// Return a random move from the list of legal moves available to the current player.
-    pub fn random_move(&self) -> Move {
-        let mut rng = rand::thread_rng();
+    /// Returns the legal moves of the current player that keep the tempo in their region of
+    /// empty squares as a bitboard, see [`regions::parity_moves`].
+    pub fn parity_moves(&self) -> u64 {
+        let (own, opponent) = match self.current_player {
+            Black => (self.current_board.black, self.current_board.white),
+            White => (self.current_board.white, self.current_board.black),
+        };
+        regions::parity_moves(own, opponent, self.size.squares())
+    }
+
+    /// Returns the discs in each quadrant and in the sweet sixteen along with the empty
+    /// regions, see [`RegionStats`].
+    pub fn region_stats(&self) -> RegionStats {
+        RegionStats::new(
+            self.current_board.black,
+            self.current_board.white,
+            self.size.squares(),
+        )
+    }
+
+    /// Return a random move from the list of legal moves available to the current player,
+    /// drawn from the given random number generator.
+    pub fn random_move(&self, rng: &mut impl Rng) -> Move {
         let legal_moves = self.legal_moves();
         if !legal_moves.is_empty() {
             return legal_moves[rng.gen::<usize>() % legal_moves.len()];
@@ -621,6 +727,34 @@ impl Game {
         Pass
     }
 
+    /// Return the legal move that takes a corner, or when there is none the one that flips the
+    /// most discs. In [`Variant::Misere`] it is the other way around, the move that flips the
+    /// fewest discs without taking a corner, if there is one. Ties go to the first legal move,
+    /// and the move is [`Pass`] without legal moves.
+    ///
+    /// This is the move played when the search has no move to offer, it is cheap and never
+    /// worse than an arbitrary legal move.
+    pub fn greedy_move(&self) -> Move {
+        let mut best_move = Pass;
+        let mut best_score = 0;
+        for mv in self.legal_moves() {
+            let takes_corner = mv
+                .get_position()
+                .is_some_and(|position| position & self.size.corners() != 0);
+            let corner = if takes_corner { 64 } else { 0 };
+            let score = match self.variant {
+                Variant::Standard => corner + self.count_flips(mv),
+                // a move flips fewer than 64 discs, so the score stays positive
+                Variant::Misere => 128 - corner - self.count_flips(mv),
+            };
+            if score > best_score {
+                best_score = score;
+                best_move = mv;
+            }
+        }
+        best_move
+    }
+
     /// Return the [`Move`] that leads to an opponent position with the fewest legal moves.
     /// The returned move will be from the list of legal moves available to the current player.
     pub fn move_with_lowest_opp_mobility(&self) -> Move {
@@ -641,11 +775,129 @@ impl Game {
 
     /// Return the number of legal moves available to the current player.
     pub fn mobility(&self) -> usize {
-        self.legal_moves().len()
+        self.legal_moves_mask().count_ones() as usize
     }
 
-    pub fn get_hash(&self) -> u64 {
-        self.current_board.black | self.current_board.white
+    /// Returns the moves that lead from this position to the given board, passes included, or
+    /// `None` if it can not be reached in at most `max_moves` moves.
+    ///
+    /// Discs are never taken off the board, so the squares that filled up are the moves that
+    /// were played and only their order is searched. When several orders reach the board the
+    /// first one found is returned.
+    pub fn infer_moves(&self, target: &Bitboard, max_moves: usize) -> Option<Vec<Move>> {
+        let occupied = self.current_board.black | self.current_board.white;
+        let target_occupied = target.black | target.white;
+        let placed = target_occupied & !occupied;
+        if occupied & !target_occupied != 0 || placed.count_ones() as usize > max_moves {
+            return None;
+        }
+        let mut moves = Vec::new();
+        self.infer_from(target, placed, &mut moves).then_some(moves)
+    }
+
+    /// Extend the moves with an order of the placed squares that reaches the target.
+    fn infer_from(&self, target: &Bitboard, placed: u64, moves: &mut Vec<Move>) -> bool {
+        if placed == 0 {
+            return self.current_board == *target;
+        }
+        if self.is_over() {
+            return false;
+        }
+        let mut candidates = self.legal_moves();
+        candidates.retain(|mv| mv.get_position().is_some_and(|bit| placed & bit != 0));
+        if self.legal_moves().is_empty() {
+            candidates.push(Pass);
+        }
+        for mv in candidates {
+            let mut next = *self;
+            next.play_next_turn(mv).unwrap();
+            moves.push(mv);
+            let left = placed & !mv.get_position().unwrap_or(0);
+            if next.infer_from(target, left, moves) {
+                return true;
+            }
+            moves.pop();
+        }
+        false
+    }
+
+    /// Returns the [`zobrist`] hash of the discs on the board and the player to move.
+    ///
+    /// It is updated incrementally as moves are played, so it is free to call. Positions
+    /// reached by different move orders share their hash, and positions with the same
+    /// occupied squares but other colors or another player to move almost surely do not, so
+    /// it keys the search tree, caches and transposition tables.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns a copy of this game with the board rotated or reflected by the given
+    /// [`Symmetry`]. The player to move and turn number are unchanged.
+    pub fn transform(&self, symmetry: Symmetry) -> Game {
+        let mut game = Game {
+            current_board: Bitboard {
+                black: symmetry.apply(self.current_board.black),
+                white: symmetry.apply(self.current_board.white),
+            },
+            last_move: self.last_move.map(|mv| mv.transform(symmetry)),
+            auto_pass: self.auto_pass,
+            ..*self
+        };
+        game.rehash();
+        game
+    }
+
+    /// Returns the representative of this position among its 8 symmetric equivalents along
+    /// with the [`Symmetry`] that maps this game onto it.
+    ///
+    /// The representative is the transformed board with the smallest `(black, white)` pair,
+    /// so any two positions that are rotations or reflections of each other share the same
+    /// canonical form. The player to move and turn number are unchanged.
+    pub fn canonical(&self) -> (Game, Symmetry) {
+        let mut best = (*self, Symmetry::Identity);
+        for symmetry in Symmetry::ALL {
+            let game = self.transform(symmetry);
+            let key = (game.current_board.black, game.current_board.white);
+            if key < (best.0.current_board.black, best.0.current_board.white) {
+                best = (game, symmetry);
+            }
+        }
+        best
+    }
+
+    /// Encodes the position as stacked binary feature planes for machine learning models.
+    ///
+    /// The returned vector has `8 * 8 * FEATURE_PLANES` entries in row-major `8x8xN` layout,
+    /// the value of plane `p` on the square at `(col, row)` is at index
+    /// `(row * 8 + col) * FEATURE_PLANES + p`. The planes are, in order:
+    /// 0. discs of the player to move
+    /// 1. discs of the opponent
+    /// 2. legal moves for the player to move
+    /// 3. the square of the last move, empty after a pass or at the start of the game
+    /// 4. side to move, all ones if black is to move, all zeros otherwise
+    pub fn feature_planes(&self) -> Vec<f32> {
+        let (own, opponent) = match self.current_player {
+            Black => (self.current_board.black, self.current_board.white),
+            White => (self.current_board.white, self.current_board.black),
+        };
+        let legal = self
+            .legal_moves()
+            .iter()
+            .filter_map(|mv| mv.get_position())
+            .fold(0, |acc, position| acc | position);
+        let last = self.last_move.and_then(|mv| mv.get_position()).unwrap_or(0);
+        let side = if self.current_player == Black { !0 } else { 0 };
+
+        let planes = [own, opponent, legal, last, side];
+        let mut features = vec![0.0; 64 * FEATURE_PLANES];
+        for square in 0..64 {
+            for (plane, bits) in planes.iter().enumerate() {
+                if bits & (1 << square) != 0 {
+                    features[square * FEATURE_PLANES + plane] = 1.0;
+                }
+            }
+        }
+        features
     }
 
     /// Return the number of corner pieces by each player.
@@ -657,8 +909,8 @@ impl Game {
         let black_pieces = self.current_board.black;
         let white_pieces = self.current_board.white;
 
-        for corner in CORNERS {
-            let position = corner.get_position().unwrap();
+        for corner in bits::squares(self.size.corners()) {
+            let position = 1 << corner;
             if position & black_pieces > 0 {
                 count_black += 1;
             }
@@ -679,8 +931,8 @@ impl Game {
         let black_pieces = self.current_board.black;
         let white_pieces = self.current_board.white;
 
-        for edge in EDGES {
-            let position = edge.get_position().unwrap();
+        for edge in bits::squares(self.size.edges()) {
+            let position = 1 << edge;
             if position & black_pieces > 0 {
                 count_black += 1;
             }
@@ -702,8 +954,8 @@ impl Game {
         let black_pieces = self.current_board.black;
         let white_pieces = self.current_board.white;
 
-        for xmove in X_MOVES {
-            let position = xmove.get_position().unwrap();
+        for xmove in bits::squares(self.size.x_squares() | self.size.c_squares()) {
+            let position = 1 << xmove;
             if position & black_pieces > 0 {
                 count_black += 1;
             }
@@ -788,15 +1040,389 @@ impl Default for Game {
     }
 }
 
+impl Game {
+    /// Creates a game in the given position, e.g. a mid-game position to analyze or solve.
+    /// Use [`GameBuilder`] to also set the last move or disc by disc.
+    ///
+    /// Returns an [`InvalidPosition`] error if a square holds a disc of both colors.
+    ///
+    /// ```rust
+    /// use herb::othello::{Color, Game};
+    ///
+    /// let game = Game::from_position(1 << 28 | 1 << 35, 1 << 27 | 1 << 36, Color::Black, 0)
+    ///     .unwrap();
+    /// assert_eq!(game, Game::new());
+    /// ```
+    pub fn from_position(
+        black: u64,
+        white: u64,
+        to_move: Color,
+        turn: usize,
+    ) -> Result<Self, GameError> {
+        GameBuilder::new()
+            .black(black)
+            .white(white)
+            .to_move(to_move)
+            .turn(turn)
+            .build()
+    }
+}
+
+/// Sets up a [`Game`] in an arbitrary position.
+///
+/// Starts out with an empty board, black to move, and the turn counted from the discs on the
+/// board as if nobody passed.
+///
+/// ```rust
+/// use herb::othello::{Color, GameBuilder, Move};
+///
+/// let a1 = Move::from_col_row(0, 0).unwrap();
+/// let b1 = Move::from_col_row(1, 0).unwrap();
+/// let game = GameBuilder::new()
+///     .disc(a1, Color::White)
+///     .disc(b1, Color::Black)
+///     .to_move(Color::White)
+///     .build()
+///     .unwrap();
+/// assert_eq!(game.disc_counts(), (1, 1));
+/// assert_eq!(game.get_turn(), 0);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GameBuilder {
+    black: u64,
+    white: u64,
+    to_move: Option<Color>,
+    turn: Option<usize>,
+    last_move: Option<Move>,
+    auto_pass: bool,
+    size: BoardSize,
+    variant: Variant,
+}
+
+impl GameBuilder {
+    /// Creates a builder of an empty board.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the black discs.
+    pub fn black(mut self, black: u64) -> Self {
+        self.black = black;
+        self
+    }
+
+    /// Sets the white discs.
+    pub fn white(mut self, white: u64) -> Self {
+        self.white = white;
+        self
+    }
+
+    /// Adds a disc of the color on the square of the move. [`Pass`] adds nothing.
+    pub fn disc(mut self, square: Move, color: Color) -> Self {
+        let bit = square.get_position().unwrap_or(0);
+        match color {
+            Black => self.black |= bit,
+            White => self.white |= bit,
+        }
+        self
+    }
+
+    /// Sets the player to move.
+    pub fn to_move(mut self, to_move: Color) -> Self {
+        self.to_move = Some(to_move);
+        self
+    }
+
+    /// Sets the turn number.
+    pub fn turn(mut self, turn: usize) -> Self {
+        self.turn = Some(turn);
+        self
+    }
+
+    /// Sets the move played on the previous turn.
+    pub fn last_move(mut self, last_move: Move) -> Self {
+        self.last_move = Some(last_move);
+        self
+    }
+
+    /// Plays forced passes automatically, see [`Game::set_auto_pass`].
+    pub fn auto_pass(mut self, auto_pass: bool) -> Self {
+        self.auto_pass = auto_pass;
+        self
+    }
+
+    /// Sets the size of the board.
+    pub fn size(mut self, size: BoardSize) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// Sets the rules the game is won by.
+    pub fn variant(mut self, variant: Variant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Creates the game.
+    ///
+    /// Returns an [`InvalidPosition`] error if a square holds a disc of both colors or a disc
+    /// is off the board.
+    pub fn build(self) -> Result<Game, GameError> {
+        if self.black & self.white != 0 || (self.black | self.white) & !self.size.squares() != 0 {
+            return Err(InvalidPosition);
+        }
+        let discs = (self.black | self.white).count_ones() as usize;
+        let turn = self.turn.unwrap_or(discs.saturating_sub(4));
+        let mut game = Game {
+            current_board: Bitboard::from_discs(self.black, self.white),
+            current_player: self.to_move.unwrap_or(Black),
+            turn: turn as i32,
+            last_move: self.last_move,
+            auto_pass: false,
+            size: self.size,
+            variant: self.variant,
+            hash: 0,
+        };
+        game.rehash();
+        game.set_auto_pass(self.auto_pass);
+        Ok(game)
+    }
+}
+
+/// The black and white disc counts after every turn of a game.
+///
+/// [`Game`] is `Copy` and only knows the current position, so the history is kept alongside
+/// it by whoever wants to see how material evolved.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MaterialHistory {
+    counts: Vec<(u32, u32)>,
+}
+
+impl MaterialHistory {
+    /// Start a history at the given position.
+    pub fn new(game: &Game) -> Self {
+        MaterialHistory {
+            counts: vec![game.disc_counts()],
+        }
+    }
+
+    /// Replay the moves from the starting position, recording the counts after each.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn from_moves(moves: &[Move]) -> Result<Self, GameError> {
+        let mut game = Game::new();
+        let mut history = MaterialHistory::new(&game);
+        for mv in moves {
+            game.play_next_turn(*mv)?;
+            history.push(&game);
+        }
+        Ok(history)
+    }
+
+    /// Record the counts of the position after the next turn.
+    pub fn push(&mut self, game: &Game) {
+        self.counts.push(game.disc_counts());
+    }
+
+    /// Returns the black and white disc counts, starting with the first position.
+    pub fn counts(&self) -> &[(u32, u32)] {
+        &self.counts
+    }
+}
+
+impl Display for MaterialHistory {
+    /// Writes the counts as `black-white` pairs separated by spaces.
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let counts: Vec<String> = self
+            .counts
+            .iter()
+            .map(|(black, white)| format!("{}-{}", black, white))
+            .collect();
+        write!(f, "{}", counts.join(" "))
+    }
+}
+
+/// The size of the board a [`Game`] is played on.
+///
+/// Boards are [`Bitboard`]s of 8 by 8 squares, a smaller board is the squares in the middle
+/// of it, so every square outside stays empty and no line of discs runs across it. The
+/// corners, X-squares and edges the engine values are those of the board, see
+/// [`BoardSize::corners`].
+///
+/// Boards larger than 8 by 8, such as 10 by 10, are not supported: their squares do not fit
+/// the 64 bits of the bitboards every move generator works on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardSize {
+    /// 6 by 6 squares, b2 to g7.
+    Six,
+    /// The standard 8 by 8 board.
+    #[default]
+    Eight,
+}
+
+impl BoardSize {
+    /// Returns the number of squares along a side.
+    pub fn side(self) -> usize {
+        match self {
+            BoardSize::Six => 6,
+            BoardSize::Eight => 8,
+        }
+    }
+
+    /// Returns the squares of the board as a bitboard.
+    pub fn squares(self) -> u64 {
+        match self {
+            BoardSize::Six => 0x007E_7E7E_7E7E_7E00,
+            BoardSize::Eight => u64::MAX,
+        }
+    }
+
+    /// Returns true for the standard 8 by 8 board.
+    pub fn is_standard(&self) -> bool {
+        *self == BoardSize::Eight
+    }
+
+    /// Returns the squares on the edge of the board, those with a side off the board.
+    pub fn edges(self) -> u64 {
+        let squares = self.squares();
+        let inner = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+        .iter()
+        .fold(squares, |inner, &direction| {
+            inner & bits::shift(squares, direction)
+        });
+        squares & !inner
+    }
+
+    /// Returns the four corners of the board, a1, h1, a8 and h8 on the standard board.
+    pub fn corners(self) -> u64 {
+        let squares = self.squares();
+        let ends = |forward, backward| {
+            squares & !(bits::shift(squares, forward) & bits::shift(squares, backward))
+        };
+        ends(Direction::North, Direction::South) & ends(Direction::East, Direction::West)
+    }
+
+    /// Returns the squares diagonally next to a corner, which give the corner away.
+    pub fn x_squares(self) -> u64 {
+        bits::neighbours(self.corners()) & self.squares() & !self.edges()
+    }
+
+    /// Returns the squares on an edge next to a corner.
+    pub fn c_squares(self) -> u64 {
+        bits::neighbours(self.corners()) & self.edges() & !self.corners()
+    }
+}
+
+/// The rules a [`Game`] is won by. The moves are the same in every variant.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Variant {
+    /// The player with more discs at the end wins.
+    #[default]
+    Standard,
+    /// Anti-Othello: the player with fewer discs at the end wins.
+    Misere,
+}
+
+impl Variant {
+    /// Returns true for the standard rules.
+    pub fn is_standard(&self) -> bool {
+        *self == Variant::Standard
+    }
+}
+
+/// How strictly [`Game::play_with_rules`] and the protocol layer treat irregular input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RulesMode {
+    /// Any irregularity is an error, for moves made inside the engine where one is a bug.
+    #[default]
+    Strict,
+    /// Irregular input is interpreted as well as possible and reported as a warning, for
+    /// moves and messages that come from outside.
+    Lenient,
+}
+
+/// An irregular move accepted in [`RulesMode::Lenient`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RulesWarning {
+    /// The game was already over, the move was ignored.
+    MoveAfterGameOver(Move),
+    /// The player had no legal moves, the move was played as a pass.
+    MoveWhenForcedToPass(Move),
+    /// The player passed although it had legal moves.
+    VoluntaryPass,
+}
+
+impl Display for RulesWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            RulesWarning::MoveAfterGameOver(mv) => {
+                write!(f, "Ignored move {} after the game was over", mv)
+            }
+            RulesWarning::MoveWhenForcedToPass(mv) => {
+                write!(f, "Played move {} as a pass, there were no legal moves", mv)
+            }
+            RulesWarning::VoluntaryPass => write!(f, "Passed with legal moves available"),
+        }
+    }
+}
+
+/// An iterator over the squares of a bitboard as [`Move`]s, lowest square first, e.g. the
+/// legal moves of [`Game::legal_moves_iter`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Moves(u64);
+
+impl Moves {
+    /// Iterate over the set bits of the given bitboard.
+    pub fn new(mask: u64) -> Self {
+        Moves(mask)
+    }
+}
+
+impl Iterator for Moves {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.0 == 0 {
+            return None;
+        }
+        let square = self.0 & self.0.wrapping_neg();
+        self.0 ^= square;
+        Some(Move::Move(square))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.0.count_ones() as usize;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Moves {}
+
 /// Holds the position on the board as a [`u64`] with a single bit set
 /// in the position it would occupy in a [`Bitboard`].
+///
+/// With the `algebraic` feature a move serializes as its algebraic coordinates, see
+/// [`Move::from_algebraic`], instead of as the enum.
 #[derive(Hash, Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "algebraic", serde(into = "String", try_from = "String"))]
 pub enum Move {
     Move(u64),
     Pass,
 }
 
 impl Move {
+    /// The number of move indices, one per square and one for [`Pass`].
+    pub const INDICES: usize = 65;
+    /// The index of [`Pass`], after the 64 squares.
+    pub const PASS_INDEX: usize = 64;
+
     /// Creates a new Move with the given position.
     ///
     /// Returns Ok(Move) if the position is valid. `InvalidMove` otherwise.
@@ -823,6 +1449,33 @@ impl Move {
         }
     }
 
+    /// Creates a new Move from algebraic coordinates, the file `a` to `h` followed by the rank
+    /// `1` to `8`, or from `pass`. Upper case files are accepted too.
+    ///
+    /// Returns `InvalidMove` if the coordinates are not on the board.
+    ///
+    /// ```rust
+    /// use herb::othello::Move;
+    ///
+    /// let mv = Move::from_algebraic("d3").unwrap();
+    /// assert_eq!(mv, Move::from_col_row(3, 2).unwrap());
+    /// assert_eq!(mv.to_string(), "d3");
+    /// assert_eq!(Move::from_algebraic("pass").unwrap(), Move::Pass);
+    /// assert!(Move::from_algebraic("i9").is_err());
+    /// ```
+    pub fn from_algebraic(text: &str) -> Result<Self, GameError> {
+        if text.eq_ignore_ascii_case("pass") {
+            return Ok(Move::Pass);
+        }
+        match *text.as_bytes() {
+            [file @ (b'a'..=b'h' | b'A'..=b'H'), rank @ b'1'..=b'8'] => Move::from_col_row(
+                (file.to_ascii_lowercase() - b'a') as u64,
+                (rank - b'1') as u64,
+            ),
+            _ => Err(InvalidMove),
+        }
+    }
+
     /// Returns the column value, 0 indexed
     pub fn get_col(&self) -> Option<u8> {
         match *self {
@@ -846,16 +1499,134 @@ impl Move {
             Move::Pass => None,
         }
     }
+
+    /// Returns the index of the move, `row * 8 + col` for a square and [`Move::PASS_INDEX`]
+    /// for a [`Pass`]. Policy vectors and statistics indexed by move use this order.
+    ///
+    /// ```rust
+    /// use herb::othello::Move;
+    ///
+    /// assert_eq!(Move::from_col_row(3, 2).unwrap().to_index(), 19);
+    /// assert_eq!(Move::Pass.to_index(), Move::PASS_INDEX);
+    /// ```
+    pub fn to_index(&self) -> usize {
+        match *self {
+            Move::Move(position) => position.trailing_zeros() as usize,
+            Move::Pass => Move::PASS_INDEX,
+        }
+    }
+
+    /// Returns the move with the given index, see [`Move::to_index`].
+    ///
+    /// Returns `InvalidMove` if the index is past [`Move::PASS_INDEX`].
+    pub fn from_index(index: usize) -> Result<Self, GameError> {
+        match index {
+            0..=63 => Ok(Move::Move(1 << index)),
+            Move::PASS_INDEX => Ok(Move::Pass),
+            _ => Err(InvalidMove),
+        }
+    }
+
+    /// Returns the square this move lands on after the board is rotated or reflected by the
+    /// given [`Symmetry`]. A [`Pass`] is unaffected.
+    pub fn transform(&self, symmetry: Symmetry) -> Move {
+        match *self {
+            Move::Move(position) => Move::Move(symmetry.apply(position)),
+            Move::Pass => Move::Pass,
+        }
+    }
 }
 
+/// Writes the move in algebraic notation, e.g. `d3`, or `pass`.
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        if let Some(col) = self.get_col() {
-            if let Some(row) = self.get_row() {
-                return write!(f, "{:?} {:?}", col, row);
-            }
+        match (self.get_col(), self.get_row()) {
+            (Some(col), Some(row)) => write!(f, "{}{}", (b'a' + col) as char, row + 1),
+            _ => write!(f, "pass"),
+        }
+    }
+}
+
+impl From<Move> for String {
+    fn from(mv: Move) -> Self {
+        mv.to_string()
+    }
+}
+
+impl TryFrom<String> for Move {
+    type Error = GameError;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        Move::from_algebraic(&text)
+    }
+}
+
+/// Enumerates the 8 symmetries of the board, the rotations and reflections
+/// that map a legal Othello position onto another legal position.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Symmetry {
+    Identity,
+    /// Rotate 90 degrees clockwise.
+    Rotate90,
+    Rotate180,
+    /// Rotate 270 degrees clockwise.
+    Rotate270,
+    /// Mirror left to right.
+    FlipHorizontal,
+    /// Mirror top to bottom.
+    FlipVertical,
+    /// Reflect along the a1-h8 diagonal.
+    FlipDiagonal,
+    /// Reflect along the h1-a8 diagonal.
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    /// All 8 symmetries, starting with [`Symmetry::Identity`].
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// Returns the symmetry that undoes this one.
+    pub fn inverse(&self) -> Symmetry {
+        match *self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            other => other,
+        }
+    }
+
+    /// Applies this symmetry to a set of squares in [`Bitboard`] layout.
+    pub fn apply(&self, bits: u64) -> u64 {
+        match *self {
+            Symmetry::Identity => bits,
+            Symmetry::Rotate90 => mirror_horizontal(transpose(bits)),
+            Symmetry::Rotate180 => bits.reverse_bits(),
+            Symmetry::Rotate270 => flip_vertical(transpose(bits)),
+            Symmetry::FlipHorizontal => mirror_horizontal(bits),
+            Symmetry::FlipVertical => flip_vertical(bits),
+            Symmetry::FlipDiagonal => transpose(bits),
+            Symmetry::FlipAntiDiagonal => transpose(bits).reverse_bits(),
         }
-        write!(f, "Invalid move")
+    }
+
+    /// Remaps a policy vector indexed by [`Move::to_index`] so it lines up with a board
+    /// transformed by this symmetry. Entries past the 64 squares, such as a trailing pass
+    /// probability, are copied unchanged. The policy must cover all 64 squares.
+    pub fn transform_policy(&self, policy: &[f64]) -> Vec<f64> {
+        let mut transformed = policy.to_vec();
+        for (index, value) in policy.iter().enumerate().take(64) {
+            let target = Move::Move(1 << index).transform(*self).to_index();
+            transformed[target] = *value;
+        }
+        transformed
     }
 }
 
@@ -876,6 +1647,12 @@ impl Bitboard {
         }
     }
 
+    /// Creates a board with the given discs, e.g. one read from a board dump. A square with
+    /// a disc of both colors is not checked for.
+    pub fn from_discs(black: u64, white: u64) -> Self {
+        Self { black, white }
+    }
+
     /// Returns the value of the u64 representing the black pieces
     pub fn get_black(&self) -> u64 {
         self.black
@@ -919,6 +1696,16 @@ pub enum Color {
     Black,
 }
 
+impl Color {
+    /// Returns the other color.
+    pub fn opponent(&self) -> Color {
+        match *self {
+            White => Black,
+            Black => White,
+        }
+    }
+}
+
 impl Display for Color {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match *self {
@@ -933,6 +1720,8 @@ impl Display for Color {
 pub enum GameError {
     InvalidMove,
     GameOver,
+    /// A position set up with a square holding a disc of both colors.
+    InvalidPosition,
 }
 
 impl Display for GameError {
@@ -944,28 +1733,20 @@ impl Display for GameError {
             GameOver => {
                 write!(f, "Game Over.")
             }
+            InvalidPosition => {
+                write!(f, "Invalid position!")
+            }
         }
     }
 }
 
 impl Error for GameError {}
 
-/// Enumerates search directions for finding moves and flips.
-#[derive(Clone, Copy)]
-enum SearchDirection {
-    Up,
-    Down,
-    Left,
-    Right,
-    DiagonalDownRight,
-    DiagonalDownLeft,
-    DiagonalUpLeft,
-    DiagonalUpRight,
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
     use std::hash::{Hash, Hasher};
 
     #[test]
@@ -1000,7 +1781,7 @@ mod tests {
     fn test_game_over_after_terminal_move() {
         let mut game = Game::new();
         // set bottom-right corner for white
-        let white: u64 = 0 | (1 << 63);
+        let white: u64 = 1 << 63;
         // set the whole board, unset the top-left and bottom-right corners
         let black: u64 = (!0 ^ 1) ^ (1 << 63);
         game.current_board.white = white;
@@ -1027,7 +1808,7 @@ mod tests {
     fn test_legal_moves_terminal_move() {
         let mut game = Game::new();
         // set bottom-right corner for white
-        let white: u64 = 0 | (1 << 63);
+        let white: u64 = 1 << 63;
         // set the whole board, unset the top-left and bottom-right corners
         let black: u64 = (!0 ^ 1) ^ (1 << 63);
         game.current_board.white = white;
@@ -1086,11 +1867,11 @@ mod tests {
         let mut mid_game_white = 0;
 
         for pos in black_positions {
-            mid_game_black |= 1 << (pos.1 * 8) + pos.0;
+            mid_game_black |= 1 << ((pos.1 * 8) + pos.0);
         }
 
         for pos in white_positions {
-            mid_game_white |= 1 << (pos.1 * 8) + pos.0;
+            mid_game_white |= 1 << ((pos.1 * 8) + pos.0);
         }
 
         let mut game = Game::new();
@@ -1142,11 +1923,11 @@ mod tests {
         let mut new_black = 0;
         let mut new_white = 0;
         for pos in new_black_positions {
-            new_black |= 1 << (pos.1 * 8) + pos.0;
+            new_black |= 1 << ((pos.1 * 8) + pos.0);
         }
 
         for pos in new_white_positions {
-            new_white |= 1 << (pos.1 * 8) + pos.0;
+            new_white |= 1 << ((pos.1 * 8) + pos.0);
         }
 
         assert_eq!(game.current_player, White);
@@ -1210,6 +1991,109 @@ mod tests {
         assert_eq!(board.white, 1 << 36);
     }
 
+    #[test]
+    fn test_greedy_move() {
+        let mut game = Game::new();
+        // all four first moves flip one disc, the first one wins the tie
+        assert_eq!(game.greedy_move(), game.legal_moves()[0]);
+
+        // a1 flips one disc, a3 flips two
+        let a1 = Move::from_col_row(0, 0).unwrap();
+        let a3 = Move::from_col_row(0, 2).unwrap();
+        game.current_board.black = 1 << 2 | 1 << 19;
+        game.current_board.white = 1 << 1 | 1 << 17 | 1 << 18;
+        game.current_player = Black;
+        assert_eq!(game.count_flips(a1), 1);
+        assert_eq!(game.count_flips(a3), 2);
+        assert_eq!(game.greedy_move(), a1);
+
+        // without the corner the most flips win
+        game.current_board.black = 1 << 19;
+        game.current_board.white = 1 << 17 | 1 << 18;
+        assert_eq!(game.greedy_move(), a3);
+
+        // in misère the fewest flips win, d5 flips only d4
+        let d5 = Move::from_col_row(3, 4).unwrap();
+        game.current_board.white = 1 << 17 | 1 << 18 | 1 << 27;
+        assert_eq!(game.greedy_move(), a3);
+        game.set_variant(Variant::Misere);
+        assert_eq!(game.greedy_move(), d5);
+        // and the corner is avoided even though it flips fewer discs than a3
+        game.current_board.black = 1 << 2 | 1 << 19;
+        game.current_board.white = 1 << 1 | 1 << 17 | 1 << 18;
+        assert_eq!(game.greedy_move(), a3);
+
+        game.current_board.white = 0;
+        assert_eq!(game.greedy_move(), Pass);
+    }
+
+    #[test]
+    fn test_count_flips() {
+        let mut game = Game::new();
+        let d3 = Move::from_col_row(3, 2).unwrap();
+        assert_eq!(game.count_flips(d3), 1);
+        assert_eq!(game.count_flips(Pass), 0);
+        // a1 is empty but flanks nothing
+        assert_eq!(game.count_flips(Move::from_col_row(0, 0).unwrap()), 0);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        for _ in 0..40 {
+            let mv = game.random_move(&mut rng);
+            let (black, white) = game.disc_counts();
+            let before = if game.to_move() == Black {
+                black
+            } else {
+                white
+            };
+            let flips = game.count_flips(mv);
+            let player = game.to_move();
+            game.play_next_turn(mv).unwrap();
+            let (black, white) = game.disc_counts();
+            let after = if player == Black { black } else { white };
+            if mv != Pass {
+                assert_eq!(after, before + 1 + flips);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_mask() {
+        let mut game = Game::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(6);
+        while !game.is_over() {
+            let mask = game.legal_moves_mask();
+            let moves = game.legal_moves_iter();
+            assert_eq!(moves.len(), mask.count_ones() as usize);
+            assert_eq!(moves.collect::<Vec<_>>(), game.legal_moves());
+            assert_eq!(
+                game.legal_moves()
+                    .iter()
+                    .fold(0, |bits, mv| bits | mv.get_position().unwrap()),
+                mask
+            );
+            let mv = game.random_move(&mut rng);
+            game.play_next_turn(mv).unwrap();
+        }
+        assert_eq!(game.legal_moves_mask(), 0);
+        assert_eq!(Moves::new(0).next(), None);
+    }
+
+    #[test]
+    fn test_would_flip() {
+        let mut game = Game::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for _ in 0..40 {
+            let mv = game.random_move(&mut rng);
+            let flips = game.would_flip(mv);
+            let before = game;
+            game.play_next_turn(mv).unwrap();
+            let changed = before.get_board().get_black() ^ game.get_board().get_black();
+            let placed = mv.get_position().unwrap_or(0);
+            assert_eq!(changed & !placed, flips);
+        }
+        assert_eq!(Game::new().would_flip(Move::from_col_row(0, 0).unwrap()), 0);
+    }
+
     #[test]
     fn test_score_initial_game_setup() {
         let mut game = Game::new();
@@ -1219,6 +2103,281 @@ mod tests {
         assert_eq!(game.score(), 3);
     }
 
+    #[test]
+    fn test_score_perspectives() {
+        let mut game = Game::new();
+        game.play_next_turn(Move::from_col_row(3, 2).unwrap())
+            .unwrap();
+        assert_eq!(game.score_for(Black), 3);
+        assert_eq!(game.score_for(White), -3);
+        // white to move and behind
+        assert_eq!(game.relative_score(), -3);
+    }
+
+    #[test]
+    fn test_variant() {
+        let mut game = Game::new();
+        game.play_next_turn(Move::from_col_row(3, 2).unwrap())
+            .unwrap();
+        assert_eq!(game.winner(), Some(Black));
+        game.set_variant(Variant::Misere);
+        assert_eq!(game.winner(), Some(White));
+        // the discs are counted the same way whoever they win for
+        assert_eq!(game.score(), 3);
+
+        let json = serde_json::to_string(&game).unwrap();
+        assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+        assert!(!serde_json::to_string(&Game::new())
+            .unwrap()
+            .contains("variant"));
+        let built = GameBuilder::new()
+            .black(game.get_board().get_black())
+            .white(game.get_board().get_white())
+            .variant(Variant::Misere)
+            .build()
+            .unwrap();
+        assert_eq!(built.variant(), Variant::Misere);
+    }
+
+    /// Black can capture on c1 and c3, white has no moves.
+    fn white_must_pass_soon() -> Game {
+        Game::from_position(1 << 0 | 1 << 16, 1 << 1 | 1 << 17, Black, 0).unwrap()
+    }
+
+    #[test]
+    fn test_game_builder() {
+        assert_eq!(
+            Game::from_position(BLACK_INITIAL_POSITIONS, WHITE_INITIAL_POSITIONS, Black, 0)
+                .unwrap(),
+            Game::new()
+        );
+        assert!(matches!(
+            Game::from_position(1, 1, Black, 0),
+            Err(InvalidPosition)
+        ));
+
+        let mut played = Game::new();
+        let d3 = Move::from_col_row(3, 2).unwrap();
+        played.play_next_turn(d3).unwrap();
+        let board = played.get_board();
+        let built = GameBuilder::new()
+            .black(board.get_black())
+            .white(board.get_white())
+            .to_move(White)
+            .last_move(d3)
+            .build()
+            .unwrap();
+        assert_eq!(built, played);
+        assert_eq!(built.zobrist_hash(), played.zobrist_hash());
+
+        // a forced pass is played straight away with auto pass
+        let game = GameBuilder::new()
+            .disc(Move::from_col_row(0, 0).unwrap(), Black)
+            .disc(Move::from_col_row(1, 0).unwrap(), White)
+            .to_move(White)
+            .auto_pass(true)
+            .build()
+            .unwrap();
+        assert_eq!(game.to_move(), Black);
+        assert_eq!(game.last_move(), Some(Pass));
+    }
+
+    #[test]
+    fn test_must_pass() {
+        let mut game = white_must_pass_soon();
+        assert!(!game.must_pass());
+        let c1 = Move::from_col_row(2, 0).unwrap();
+        game.play_next_turn(c1).unwrap();
+        assert!(game.must_pass());
+
+        // only a pass is legal, and a rejected move leaves the game unchanged
+        let mut lenient = game;
+        assert_eq!(
+            lenient
+                .play_with_rules(Move::from_col_row(2, 2).unwrap(), RulesMode::Lenient)
+                .unwrap(),
+            Some(RulesWarning::MoveWhenForcedToPass(
+                Move::from_col_row(2, 2).unwrap()
+            ))
+        );
+        assert_eq!(lenient.to_move(), Black);
+        let before = game;
+        let c3 = Move::from_col_row(2, 2).unwrap();
+        assert!(matches!(game.play_next_turn(c3), Err(InvalidMove)));
+        assert_eq!(game, before);
+        game.play_next_turn(Pass).unwrap();
+        assert_eq!(game.to_move(), Black);
+        assert_eq!(game.get_turn(), 2);
+
+        // after black's last capture nobody can move
+        game.play_next_turn(c3).unwrap();
+        assert!(game.is_over());
+        assert!(!game.must_pass());
+    }
+
+    #[test]
+    fn test_board_size_squares() {
+        let eight = BoardSize::Eight;
+        assert_eq!(eight.corners(), bits::CORNERS);
+        assert_eq!(eight.edges(), bits::EDGES);
+        assert_eq!(eight.x_squares(), 0x0042_0000_0000_4200);
+        assert_eq!(eight.c_squares(), 0x4281_0000_0000_8142);
+
+        // b2, g2, b7 and g7 are the corners of the small board, its a1, f1, a6 and f6
+        let six = BoardSize::Six;
+        let square = |name| Move::from_algebraic(name).unwrap().get_position().unwrap();
+        assert_eq!(
+            six.corners(),
+            square("b2") | square("g2") | square("b7") | square("g7")
+        );
+        assert_eq!(six.edges().count_ones(), 20);
+        assert_eq!(
+            six.x_squares(),
+            square("c3") | square("f3") | square("c6") | square("f6")
+        );
+        assert_eq!(six.c_squares().count_ones(), 8);
+        assert_eq!(six.edges() & !six.squares(), 0);
+    }
+
+    #[test]
+    fn test_greedy_move_small_board() {
+        // whenever a corner of the small board is legal the greedy move takes one
+        let six = BoardSize::Six;
+        let mut rng = ChaCha8Rng::seed_from_u64(8);
+        let mut taken = 0;
+        for _ in 0..20 {
+            let mut game = Game::with_size(six);
+            while !game.is_over() {
+                if game.legal_moves_mask() & six.corners() != 0 {
+                    let greedy = game.greedy_move().get_position().unwrap();
+                    assert_ne!(greedy & six.corners(), 0);
+                    taken += 1;
+                }
+                game.play_next_turn(game.random_move(&mut rng)).unwrap();
+            }
+        }
+        assert!(taken > 0);
+    }
+
+    #[test]
+    fn test_board_size() {
+        let six = BoardSize::Six;
+        assert_eq!(six.squares().count_ones() as usize, six.side() * six.side());
+        let a1 = Move::from_col_row(0, 0).unwrap();
+        let off_board = GameBuilder::new().disc(a1, Black).size(six).build();
+        assert!(matches!(off_board, Err(InvalidPosition)));
+
+        let mut rng = ChaCha8Rng::seed_from_u64(6);
+        for _ in 0..20 {
+            let mut game = Game::with_size(six);
+            while !game.is_over() {
+                assert_eq!(game.legal_moves_mask() & !six.squares(), 0);
+                assert_eq!(game.parity_moves() & !game.legal_moves_mask(), 0);
+                game.play_next_turn(game.random_move(&mut rng)).unwrap();
+            }
+            let board = game.get_board();
+            assert_eq!((board.get_black() | board.get_white()) & !six.squares(), 0);
+            assert_eq!(game.size(), six);
+
+            let json = serde_json::to_string(&game).unwrap();
+            assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+        }
+        // only the squares of the board are empty
+        let stats = Game::with_size(six).region_stats();
+        assert_eq!(stats.empty_regions.len(), 1);
+        assert_eq!(stats.empty_regions[0].size(), 32);
+        assert_eq!(stats.quadrants[0].empty, 8);
+
+        // the standard board serializes as it always did
+        assert!(!serde_json::to_string(&Game::new())
+            .unwrap()
+            .contains("size"));
+    }
+
+    #[test]
+    fn test_rules_modes() {
+        let mut game = Game::new();
+        assert!(matches!(game.play_next_turn(Pass), Err(InvalidMove)));
+        assert_eq!(
+            game.play_with_rules(Pass, RulesMode::Lenient).unwrap(),
+            Some(RulesWarning::VoluntaryPass)
+        );
+        assert_eq!(game.to_move(), White);
+
+        // illegal squares are an error in both modes
+        let a1 = Move::from_col_row(0, 0).unwrap();
+        assert!(game.play_with_rules(a1, RulesMode::Lenient).is_err());
+
+        let mut game = white_must_pass_soon();
+        game.current_board.white = 0;
+        assert!(game.is_over());
+        assert!(matches!(game.play_next_turn(Pass), Err(GameOver)));
+        assert_eq!(
+            game.play_with_rules(a1, RulesMode::Lenient).unwrap(),
+            Some(RulesWarning::MoveAfterGameOver(a1))
+        );
+    }
+
+    #[test]
+    fn test_auto_pass() {
+        let mut game = white_must_pass_soon();
+        game.set_auto_pass(true);
+        game.play_next_turn(Move::from_col_row(2, 0).unwrap())
+            .unwrap();
+        assert_eq!(game.to_move(), Black);
+        assert_eq!(game.get_turn(), 2);
+        assert_eq!(game.last_move(), Some(Pass));
+
+        // enabling auto pass plays a pending forced pass
+        let mut game = white_must_pass_soon();
+        game.play_next_turn(Move::from_col_row(2, 0).unwrap())
+            .unwrap();
+        game.set_auto_pass(true);
+        assert_eq!(game.to_move(), Black);
+        assert!(game.auto_pass());
+    }
+
+    #[test]
+    fn test_infer_moves() {
+        let start = Game::new();
+        let mut game = start;
+        let mut played = Vec::new();
+        for _ in 0..5 {
+            let mv = game.greedy_move();
+            game.play_next_turn(mv).unwrap();
+            played.push(mv);
+        }
+        let target = game.get_board();
+        assert_eq!(start.infer_moves(&start.get_board(), 0), Some(vec![]));
+        let inferred = start.infer_moves(&target, 8).unwrap();
+        assert_eq!(inferred.len(), 5);
+        let mut replayed = start;
+        for mv in inferred {
+            replayed.play_next_turn(mv).unwrap();
+        }
+        assert_eq!(replayed.get_board(), target);
+
+        // too many missed moves, or discs that vanished
+        assert_eq!(start.infer_moves(&target, 4), None);
+        assert_eq!(game.infer_moves(&start.get_board(), 8), None);
+        let impossible = Bitboard::from_discs(target.get_white(), target.get_black());
+        assert_eq!(start.infer_moves(&impossible, 8), None);
+    }
+
+    #[test]
+    fn test_material_history() {
+        let first = Move::from_col_row(3, 2).unwrap();
+        let mut game = Game::new();
+        game.play_next_turn(first).unwrap();
+        assert_eq!(game.disc_counts(), (4, 1));
+
+        let reply = game.legal_moves()[0];
+        let history = MaterialHistory::from_moves(&[first, reply]).unwrap();
+        assert_eq!(history.counts()[..2], [(2, 2), (4, 1)]);
+        assert_eq!(history.to_string(), "2-2 4-1 3-3");
+        assert!(MaterialHistory::from_moves(&[Move::from_col_row(0, 0).unwrap()]).is_err());
+    }
+
     #[test]
     fn test_move_new() {
         let position = 1 << 27;
@@ -1242,11 +2401,54 @@ mod tests {
         assert_eq!(mv.get_position().unwrap(), 1 << 27);
     }
 
+    #[test]
+    fn test_move_index() {
+        for index in 0..Move::INDICES {
+            assert_eq!(Move::from_index(index).unwrap().to_index(), index);
+        }
+        let mv = Move::from_col_row(3, 3).unwrap();
+        assert_eq!(Move::from_index(27).unwrap(), mv);
+        assert_eq!(Move::from_index(Move::PASS_INDEX).unwrap(), Move::Pass);
+        assert!(Move::from_index(Move::INDICES).is_err());
+    }
+
+    #[test]
+    fn test_move_algebraic() {
+        for index in 0..Move::INDICES {
+            let mv = Move::from_index(index).unwrap();
+            assert_eq!(Move::from_algebraic(&mv.to_string()).unwrap(), mv);
+        }
+        assert_eq!(Move::from_col_row(0, 0).unwrap().to_string(), "a1");
+        assert_eq!(Move::from_col_row(7, 7).unwrap().to_string(), "h8");
+        assert_eq!(Move::Pass.to_string(), "pass");
+        assert_eq!(
+            Move::from_algebraic("F5").unwrap(),
+            Move::from_col_row(5, 4).unwrap()
+        );
+        for bad in ["", "d", "d0", "d9", "i3", "3d", "d33", "pas"] {
+            assert!(
+                matches!(Move::from_algebraic(bad), Err(InvalidMove)),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[cfg(feature = "algebraic")]
+    #[test]
+    fn test_move_serde_algebraic() {
+        let moves = [Move::from_col_row(3, 2).unwrap(), Move::Pass];
+        let json = serde_json::to_string(&moves).unwrap();
+        assert_eq!(json, r#"["d3","pass"]"#);
+        assert_eq!(serde_json::from_str::<[Move; 2]>(&json).unwrap(), moves);
+        assert!(serde_json::from_str::<Move>(r#""z9""#).is_err());
+    }
+
     #[test]
     fn test_is_terminal() {
         let mut game = Game::new();
         // set bottom-right corner for white
-        let white: u64 = 0 | (1 << 63);
+        let white: u64 = 1 << 63;
         // set the whole board, unset the top-left and bottom-right corners
         let black: u64 = (!0 ^ 1) ^ (1 << 63);
         game.current_board.white = white;
@@ -1284,10 +2486,123 @@ mod tests {
 
         assert_eq!(legal_moves.len(), 0);
         for mv in &legal_moves {
-            assert!(legal_moves.contains(&mv));
+            assert!(legal_moves.contains(mv));
+        }
+    }
+
+    #[test]
+    fn test_symmetry_square_mapping() {
+        // top-left corner (a1) under each symmetry
+        let a1 = 1u64;
+        assert_eq!(Symmetry::Rotate90.apply(a1), 1 << 7);
+        assert_eq!(Symmetry::Rotate180.apply(a1), 1 << 63);
+        assert_eq!(Symmetry::Rotate270.apply(a1), 1 << 56);
+        assert_eq!(Symmetry::FlipHorizontal.apply(a1), 1 << 7);
+        assert_eq!(Symmetry::FlipVertical.apply(a1), 1 << 56);
+        assert_eq!(Symmetry::FlipDiagonal.apply(a1), a1);
+        assert_eq!(Symmetry::FlipAntiDiagonal.apply(a1), 1 << 63);
+
+        // (col 1, row 0) goes to (col 0, row 1) on the diagonal
+        assert_eq!(Symmetry::FlipDiagonal.apply(1 << 1), 1 << 8);
+        // and to (col 7, row 1) when rotated clockwise
+        assert_eq!(Symmetry::Rotate90.apply(1 << 1), 1 << 15);
+    }
+
+    #[test]
+    fn test_symmetry_inverse() {
+        let bits: u64 = 0x0123456789ABCDEF;
+        for symmetry in Symmetry::ALL {
+            assert_eq!(symmetry.inverse().apply(symmetry.apply(bits)), bits);
+            assert_eq!(symmetry.apply(bits).count_ones(), bits.count_ones());
         }
     }
 
+    #[test]
+    fn test_transform_preserves_legal_moves() {
+        let mut game = Game::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        for _ in 0..12 {
+            let mv = game.random_move(&mut rng);
+            game.play_next_turn(mv).unwrap();
+        }
+
+        for symmetry in Symmetry::ALL {
+            let transformed = game.transform(symmetry);
+            let mut expected: Vec<Move> = game
+                .legal_moves()
+                .iter()
+                .map(|mv| mv.transform(symmetry))
+                .collect();
+            let mut actual = transformed.legal_moves();
+            expected.sort_by_key(|mv| mv.get_position());
+            actual.sort_by_key(|mv| mv.get_position());
+            assert_eq!(actual, expected);
+            assert_eq!(transformed.score(), game.score());
+        }
+    }
+
+    #[test]
+    fn test_feature_planes() {
+        let mut game = Game::new();
+        let features = game.feature_planes();
+        assert_eq!(features.len(), 64 * FEATURE_PLANES);
+
+        let plane_count = |features: &[f32], plane: usize| {
+            features
+                .iter()
+                .skip(plane)
+                .step_by(FEATURE_PLANES)
+                .sum::<f32>()
+        };
+        assert_eq!(plane_count(&features, 0), 2.0);
+        assert_eq!(plane_count(&features, 1), 2.0);
+        assert_eq!(plane_count(&features, 2), 4.0);
+        assert_eq!(plane_count(&features, 3), 0.0);
+        assert_eq!(plane_count(&features, 4), 64.0);
+        // black to move owns d5 (col 3, row 4)
+        assert_eq!(features[35 * FEATURE_PLANES], 1.0);
+
+        let mv = Move::from_col_row(3, 2).unwrap();
+        game.play_next_turn(mv).unwrap();
+        let features = game.feature_planes();
+        assert_eq!(plane_count(&features, 0), 1.0);
+        assert_eq!(plane_count(&features, 1), 4.0);
+        assert_eq!(plane_count(&features, 3), 1.0);
+        assert_eq!(features[19 * FEATURE_PLANES + 3], 1.0);
+        assert_eq!(plane_count(&features, 4), 0.0);
+    }
+
+    #[test]
+    fn test_transform_policy() {
+        let mut policy = vec![0.0; 65];
+        policy[1] = 0.75;
+        policy[64] = 0.25;
+        let transformed = Symmetry::FlipDiagonal.transform_policy(&policy);
+        assert_eq!(transformed[8], 0.75);
+        assert_eq!(transformed[1], 0.0);
+        assert_eq!(transformed[64], 0.25);
+    }
+
+    #[test]
+    fn test_canonical_equivalent_openings() {
+        // the four opening moves are all symmetric to each other
+        let game = Game::new();
+        let canonical_forms: Vec<Game> = game
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut sim_game = game;
+                sim_game.play_next_turn(mv).unwrap();
+                sim_game.canonical().0
+            })
+            .collect();
+        assert!(canonical_forms.iter().all(|g| *g == canonical_forms[0]));
+
+        let (canonical, symmetry) = game.canonical();
+        assert_eq!(canonical, game);
+        assert_eq!(symmetry, Symmetry::Identity);
+    }
+
     #[test]
     fn test_game_hash() {
         let game1 = Game::new();
@@ -1309,8 +2624,9 @@ mod tests {
         let mut game1 = Game::new();
         let mut game2 = Game::new();
 
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
         for _ in 0..30 {
-            let mv = game1.random_move();
+            let mv = game1.random_move(&mut rng);
             game1.play_next_turn(mv).unwrap();
             game2.play_next_turn(mv).unwrap();
         }
@@ -1325,4 +2641,53 @@ mod tests {
         let game2_hash_value = hasher2.finish();
         assert_eq!(game1_hash_value, game2_hash_value);
     }
+
+    #[test]
+    fn test_zobrist_hash() {
+        let play = |moves: &[(u64, u64)]| {
+            let mut game = Game::new();
+            for (col, row) in moves {
+                game.play_next_turn(Move::from_col_row(*col, *row).unwrap())
+                    .unwrap();
+            }
+            game
+        };
+        // d3 c3 c4 and c4 c3 d3 transpose into the same position
+        let game1 = play(&[(3, 2), (2, 2), (2, 3)]);
+        let game2 = play(&[(2, 3), (2, 2), (3, 2)]);
+        assert_ne!(game1, game2);
+        assert_eq!(game1.get_board(), game2.get_board());
+        assert_eq!(game1.zobrist_hash(), game2.zobrist_hash());
+
+        // the same squares with different colors or another player to move differ
+        let mut passed = game1;
+        passed.current_player = passed.current_player.opponent();
+        passed.rehash();
+        assert_ne!(passed.zobrist_hash(), game1.zobrist_hash());
+        let mut flipped = game1;
+        flipped.current_board = Bitboard {
+            black: game1.current_board.white,
+            white: game1.current_board.black,
+        };
+        flipped.rehash();
+        assert_ne!(flipped.zobrist_hash(), game1.zobrist_hash());
+
+        // the incremental hash matches the hash from scratch, also after passes and through
+        // serialization and symmetries
+        let mut game = Game::new();
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        while !game.is_over() {
+            let mv = game.random_move(&mut rng);
+            game.play_next_turn(mv).unwrap();
+            let mut fresh = game;
+            fresh.rehash();
+            assert_eq!(game.zobrist_hash(), fresh.zobrist_hash());
+            let json = serde_json::to_string(&game).unwrap();
+            assert_eq!(serde_json::from_str::<Game>(&json).unwrap(), game);
+            let rotated = game.transform(Symmetry::Rotate90);
+            let mut fresh = rotated;
+            fresh.rehash();
+            assert_eq!(rotated.zobrist_hash(), fresh.zobrist_hash());
+        }
+    }
 }
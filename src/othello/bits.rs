@@ -0,0 +1,442 @@
+//! Low-level bitboard helpers.
+//!
+//! A bitboard is a `u64` with a bit for every square, bit `row * 8 + col`, so bit 0 is a1 in
+//! the top left corner and bit 63 is h8 in the bottom right, see the [`othello`](super)
+//! module. Shifting a bitboard moves every disc on it one square, but a plain shift wraps
+//! discs on the a- and h-files around to the other side of the board. [`shift`] masks the
+//! wrapped discs off, and everything else here is built on top of it, so solvers and
+//! evaluations written against this module do not have to get the masks right themselves.
+//!
+//! Runs of discs are found with a Kogge-Stone parallel prefix fill, three shifts per direction
+//! rather than a step per square, so [`moves`] and [`flips`] work on every square at once
+//! without loops or branches over the board, which makes them cheap enough to score moves
+//! without playing them. [`ray_flips`] finds the same flips by looking them up in the
+//! precomputed [`RAYS`] instead. Games play their moves with [`flips`], `herb-bench` measures
+//! both, see [`bench`](crate::bench), so the faster one can be told from the numbers.
+//!
+//! ```rust
+//! use herb::othello::bits::{self, Direction};
+//! use herb::othello::Game;
+//!
+//! let board = Game::new().get_board();
+//! let moves = bits::moves(board.get_black(), board.get_white());
+//! assert_eq!(moves.count_ones(), 4);
+//! // a disc on h1 has nowhere to go east
+//! assert_eq!(bits::shift(1 << 7, Direction::East), 0);
+//! ```
+
+/// The a-file, the leftmost column.
+pub const FILE_A: u64 = 0x0101_0101_0101_0101;
+/// The h-file, the rightmost column.
+pub const FILE_H: u64 = 0x8080_8080_8080_8080;
+/// The first, top, row.
+pub const RANK_1: u64 = 0x0000_0000_0000_00FF;
+/// The eighth, bottom, row.
+pub const RANK_8: u64 = 0xFF00_0000_0000_0000;
+/// The 28 squares on the edge of the board.
+pub const EDGES: u64 = FILE_A | FILE_H | RANK_1 | RANK_8;
+/// The four corners.
+pub const CORNERS: u64 = 0x8100_0000_0000_0081;
+
+/// A direction on the board. North is towards the first row, east towards the h-file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Every direction, clockwise from north.
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Returns true if stepping in the direction goes to a higher square index.
+    pub const fn is_increasing(self) -> bool {
+        matches!(
+            self,
+            Direction::East | Direction::SouthEast | Direction::South | Direction::SouthWest
+        )
+    }
+
+    /// Returns the direction pointing the other way.
+    pub const fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::NorthEast => Direction::SouthWest,
+            Direction::East => Direction::West,
+            Direction::SouthEast => Direction::NorthWest,
+            Direction::South => Direction::North,
+            Direction::SouthWest => Direction::NorthEast,
+            Direction::West => Direction::East,
+            Direction::NorthWest => Direction::SouthEast,
+        }
+    }
+}
+
+/// The squares from each square to the edge of the board in each direction, not including the
+/// square itself, indexed by square and then by direction in the order of [`Direction::ALL`].
+pub const RAYS: [[u64; 8]; 64] = rays();
+
+const fn rays() -> [[u64; 8]; 64] {
+    let mut rays = [[0; 8]; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            rays[square][direction] = ray(square, Direction::ALL[direction]);
+            direction += 1;
+        }
+        square += 1;
+    }
+    rays
+}
+
+/// Returns the bitboard with every disc moved one square in the direction. Discs that would
+/// leave the board are dropped.
+pub const fn shift(bits: u64, direction: Direction) -> u64 {
+    match direction {
+        Direction::North => bits >> 8,
+        Direction::NorthEast => (bits >> 7) & !FILE_A,
+        Direction::East => (bits << 1) & !FILE_A,
+        Direction::SouthEast => (bits << 9) & !FILE_A,
+        Direction::South => bits << 8,
+        Direction::SouthWest => (bits << 7) & !FILE_H,
+        Direction::West => (bits >> 1) & !FILE_H,
+        Direction::NorthWest => (bits >> 9) & !FILE_H,
+    }
+}
+
+/// Returns the squares next to any of the given squares in any direction, which may include
+/// some of the given squares themselves.
+pub fn neighbours(bits: u64) -> u64 {
+    Direction::ALL.iter().fold(0, |neighbours, &direction| {
+        neighbours | shift(bits, direction)
+    })
+}
+
+/// Returns the bitboard with every disc moved `steps` squares in the direction. Discs on the
+/// a- and h-files wrap around, callers mask them off.
+const fn shift_unmasked(bits: u64, direction: Direction, steps: u32) -> u64 {
+    match direction {
+        Direction::North => bits >> (8 * steps),
+        Direction::NorthEast => bits >> (7 * steps),
+        Direction::East => bits << steps,
+        Direction::SouthEast => bits << (9 * steps),
+        Direction::South => bits << (8 * steps),
+        Direction::SouthWest => bits << (7 * steps),
+        Direction::West => bits >> steps,
+        Direction::NorthWest => bits >> (9 * steps),
+    }
+}
+
+/// Returns the squares a step in the direction can land on without wrapping around the board.
+const fn landing(direction: Direction) -> u64 {
+    match direction {
+        Direction::NorthEast | Direction::East | Direction::SouthEast => !FILE_A,
+        Direction::SouthWest | Direction::West | Direction::NorthWest => !FILE_H,
+        Direction::North | Direction::South => !0,
+    }
+}
+
+/// Returns the given squares and every square reached by stepping from them in the direction
+/// for as long as the steps stay on `through`, e.g. the discs of a player that are connected
+/// to a corner along an edge.
+///
+/// The fill is a Kogge-Stone parallel prefix: after the first step the squares of `through`
+/// that continue a run are doubled up, so runs of up to seven squares take three shifts.
+pub const fn fill(bits: u64, through: u64, direction: Direction) -> u64 {
+    let mut filled = bits;
+    let mut through = through & landing(direction);
+    filled |= through & shift_unmasked(filled, direction, 1);
+    through &= shift_unmasked(through, direction, 1);
+    filled |= through & shift_unmasked(filled, direction, 2);
+    through &= shift_unmasked(through, direction, 2);
+    filled |= through & shift_unmasked(filled, direction, 4);
+    filled
+}
+
+/// Returns the squares where the player with the `own` discs can move against the `opponent`
+/// discs.
+pub fn moves(own: u64, opponent: u64) -> u64 {
+    let empty = !(own | opponent);
+    Direction::ALL.iter().fold(0, |moves, &direction| {
+        let captured = fill(shift(own, direction) & opponent, opponent, direction);
+        moves | (shift(captured, direction) & empty)
+    })
+}
+
+/// Returns the `opponent` discs flipped by the player with the `own` discs moving on the
+/// square, `0` if the move is not legal.
+///
+/// In each direction the run of opponent discs next to the square is filled in, and it is
+/// flipped if the square after the run holds an own disc.
+pub fn flips(own: u64, opponent: u64, square: usize) -> u64 {
+    let mv = 1 << square;
+    if (own | opponent) & mv != 0 {
+        return 0;
+    }
+    Direction::ALL.iter().fold(0, |flips, &direction| {
+        let run = fill(shift(mv, direction) & opponent, opponent, direction);
+        let bracketed = shift(run, direction) & own != 0;
+        flips | (run & (bracketed as u64).wrapping_neg())
+    })
+}
+
+/// Returns the same flips as [`flips`], looked up in [`RAYS`].
+///
+/// In each direction the first square along the ray that does not hold an opponent disc
+/// decides: if it holds an own disc, every square before it on the ray is flipped.
+pub fn ray_flips(own: u64, opponent: u64, square: usize) -> u64 {
+    if (own | opponent) & (1 << square) != 0 {
+        return 0;
+    }
+    let mut flips = 0;
+    for (index, direction) in Direction::ALL.iter().enumerate() {
+        let ray = RAYS[square][index];
+        let stops = ray & !opponent;
+        if stops == 0 {
+            continue;
+        }
+        let stop = if direction.is_increasing() {
+            stops.trailing_zeros()
+        } else {
+            63 - stops.leading_zeros()
+        } as usize;
+        if own & (1 << stop) != 0 {
+            flips |= ray & !RAYS[stop][index] & !(1 << stop);
+        }
+    }
+    flips
+}
+
+/// Returns the squares strictly between two squares on the same row, column or diagonal, `0`
+/// if they are not on a line or are next to each other.
+pub fn between(from: usize, to: usize) -> u64 {
+    let target = 1u64 << to;
+    for direction in Direction::ALL {
+        let mut line = 0;
+        let mut square = shift(1 << from, direction);
+        while square != 0 {
+            if square == target {
+                return line;
+            }
+            line |= square;
+            square = shift(square, direction);
+        }
+    }
+    0
+}
+
+/// Returns the squares from the square to the edge of the board in the direction, not
+/// including the square itself.
+pub const fn ray(square: usize, direction: Direction) -> u64 {
+    fill(shift(1 << square, direction), !0, direction)
+}
+
+/// Returns the index of every set bit, lowest first.
+pub fn squares(bits: u64) -> impl Iterator<Item = usize> {
+    let mut remaining = bits;
+    std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let square = remaining.trailing_zeros() as usize;
+        remaining &= remaining - 1;
+        Some(square)
+    })
+}
+
+/// Returns the groups of connected squares among the given squares, squares touching in any
+/// direction belonging to the same group, the group of the lowest square first.
+pub fn regions(bits: u64) -> impl Iterator<Item = u64> {
+    let mut remaining = bits;
+    std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let region = region(remaining, remaining.trailing_zeros() as usize);
+        remaining &= !region;
+        Some(region)
+    })
+}
+
+/// Returns the group of connected squares among the given squares that the square belongs
+/// to, see [`regions`]. The square must be one of the given squares.
+pub fn region(bits: u64, square: usize) -> u64 {
+    let mut region = 1 << square;
+    loop {
+        let grown = (region | neighbours(region)) & bits;
+        if grown == region {
+            return region;
+        }
+        region = grown;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::{Color, Game};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_shift() {
+        // d4 moves to every neighbour
+        let d4 = 1u64 << 27;
+        let steps: Vec<usize> = Direction::ALL
+            .iter()
+            .map(|&direction| shift(d4, direction).trailing_zeros() as usize)
+            .collect();
+        assert_eq!(steps, vec![19, 20, 28, 36, 35, 34, 26, 18]);
+        for direction in Direction::ALL {
+            assert_eq!(shift(shift(d4, direction), direction.opposite()), d4);
+        }
+
+        // nothing wraps around the edges
+        assert_eq!(shift(FILE_H, Direction::East), 0);
+        assert_eq!(shift(FILE_A, Direction::West), 0);
+        assert_eq!(shift(FILE_H, Direction::NorthEast), 0);
+        assert_eq!(shift(FILE_A, Direction::SouthWest), 0);
+        assert_eq!(shift(RANK_1, Direction::North), 0);
+        assert_eq!(shift(RANK_8, Direction::South), 0);
+        assert_eq!(neighbours(1).count_ones(), 3);
+        assert_eq!(EDGES.count_ones(), 28);
+    }
+
+    #[test]
+    fn test_region() {
+        // the first rank and the h-file are connected through h1, a8 is on its own
+        let squares = RANK_1 | FILE_H | 1 << 56;
+        assert_eq!(region(squares, 0), RANK_1 | FILE_H);
+        assert_eq!(region(squares, 63), RANK_1 | FILE_H);
+        assert_eq!(region(squares, 56), 1 << 56);
+        assert_eq!(
+            regions(squares).collect::<Vec<_>>(),
+            [RANK_1 | FILE_H, 1 << 56]
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        // a1 to h8 along the main diagonal
+        assert_eq!(between(0, 63).count_ones(), 6);
+        assert_eq!(between(63, 0), between(0, 63));
+        assert_eq!(between(0, 7), RANK_1 & !CORNERS);
+        assert_eq!(between(0, 1), 0);
+        // b1 and a3 are not on a line
+        assert_eq!(between(1, 16), 0);
+        assert_eq!(ray(0, Direction::South), FILE_A & !1);
+        assert_eq!(ray(0, Direction::North), 0);
+        for (square, rays) in RAYS.iter().enumerate() {
+            for (&ray, direction) in rays.iter().zip(&Direction::ALL) {
+                assert_eq!(ray, fill(shift(1 << square, *direction), !0, *direction));
+                if ray != 0 {
+                    let next = shift(1 << square, *direction).trailing_zeros() as usize;
+                    assert_eq!(direction.is_increasing(), next > square);
+                }
+            }
+        }
+        assert_eq!(fill(1, RANK_1, Direction::East), RANK_1);
+        assert_eq!(fill(1, 0b1010, Direction::East), 0b11);
+        assert_eq!(squares(CORNERS).collect::<Vec<_>>(), vec![0, 7, 56, 63]);
+    }
+
+    /// Flips found by stepping from square to square.
+    fn stepped_flips(own: u64, opponent: u64, square: usize) -> u64 {
+        if (own | opponent) & (1 << square) != 0 {
+            return 0;
+        }
+        let mut flips = 0;
+        for direction in Direction::ALL {
+            let mut run = 0;
+            let mut step = shift(1 << square, direction);
+            while step & opponent != 0 {
+                run |= step;
+                step = shift(step, direction);
+            }
+            if step & own != 0 {
+                flips |= run;
+            }
+        }
+        flips
+    }
+
+    #[test]
+    fn test_flips_match_stepping() {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for _ in 0..500 {
+            let occupied: u64 = rng.gen::<u64>() | rng.gen::<u64>();
+            let own = occupied & rng.gen::<u64>();
+            let opponent = occupied & !own;
+            for square in 0..64 {
+                let stepped = stepped_flips(own, opponent, square);
+                assert_eq!(
+                    flips(own, opponent, square),
+                    stepped,
+                    "{:#x} {:#x} {}",
+                    own,
+                    opponent,
+                    square
+                );
+                assert_eq!(ray_flips(own, opponent, square), stepped);
+            }
+            for direction in Direction::ALL {
+                let mut stepped = own;
+                for _ in 0..7 {
+                    stepped |= shift(stepped, direction) & opponent;
+                }
+                assert_eq!(fill(own, opponent, direction), stepped);
+            }
+        }
+    }
+
+    #[test]
+    fn test_moves_and_flips_match_game() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        for _ in 0..20 {
+            let mut game = Game::new();
+            while !game.is_over() {
+                let board = game.get_board();
+                let (own, opponent) = match game.to_move() {
+                    Color::Black => (board.get_black(), board.get_white()),
+                    Color::White => (board.get_white(), board.get_black()),
+                };
+                let legal: u64 = game
+                    .legal_moves()
+                    .iter()
+                    .fold(0, |bits, mv| bits | mv.get_position().unwrap());
+                assert_eq!(moves(own, opponent), legal);
+
+                let mv = game.random_move(&mut rng);
+                if let Some(position) = mv.get_position() {
+                    let flipped = flips(own, opponent, position.trailing_zeros() as usize);
+                    let mut after = game;
+                    after.play_next_turn(mv).unwrap();
+                    let after = after.get_board();
+                    let opponent_after = match game.to_move() {
+                        Color::Black => after.get_white(),
+                        Color::White => after.get_black(),
+                    };
+                    assert_eq!(opponent & !opponent_after, flipped);
+                    assert_ne!(flipped, 0);
+                }
+                game.play_next_turn(mv).unwrap();
+            }
+        }
+    }
+}
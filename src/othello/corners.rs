@@ -0,0 +1,200 @@
+//! Threats against the corners.
+//!
+//! A disc on a corner can never be flipped, so taking a corner, or being made to give one
+//! away, usually decides more than anything else about a move. [`Game::corner_threats`] looks
+//! at each empty corner a turn ahead: who could take it now, and whether either player is
+//! already forced to hand it over on the next turn whatever they play.
+//!
+//! ```rust
+//! use herb::othello::Game;
+//!
+//! // nobody is anywhere near a corner at the start
+//! let threats = Game::new().corner_threats();
+//! assert_eq!(threats.len(), 4);
+//! assert!(threats.iter().all(|threat| threat.is_quiet()));
+//! ```
+use serde::Serialize;
+
+use super::bits;
+use super::{Color, Game, Move};
+
+/// What the next turns hold for an empty corner. "The player" is the player to move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct CornerThreat {
+    pub corner: Move,
+    /// The player can take the corner this turn.
+    pub can_take: bool,
+    /// The opponent could take the corner if it were its turn.
+    pub opponent_can_take: bool,
+    /// Every move of the player lets the opponent take the corner next turn.
+    pub must_concede: bool,
+    /// The player has a move after which every reply of the opponent lets the player take
+    /// the corner on the turn after.
+    pub can_force: bool,
+}
+
+impl CornerThreat {
+    /// Returns true if neither player can take the corner or be made to give it away.
+    pub fn is_quiet(&self) -> bool {
+        !(self.can_take || self.opponent_can_take || self.must_concede || self.can_force)
+    }
+}
+
+/// Returns the threats against each empty corner of the game, a1, h1, a8 and h8 in that
+/// order on the standard board, the corners of the board on a smaller one. A finished game
+/// has none.
+pub fn corner_threats(game: &Game) -> Vec<CornerThreat> {
+    if game.is_over() {
+        return Vec::new();
+    }
+    let board = game.get_board();
+    let (own, opponent) = match game.to_move() {
+        Color::Black => (board.get_black(), board.get_white()),
+        Color::White => (board.get_white(), board.get_black()),
+    };
+    let squares = game.size().squares();
+    let empty = squares & !(own | opponent);
+    let moves = |own, opponent| bits::moves(own, opponent) & squares;
+    let own_moves = moves(own, opponent);
+    let opponent_moves = moves(opponent, own);
+    // the opponent's moves after each move of the player, a pass if there is none
+    let replies: Vec<(u64, u64, u64)> = if own_moves == 0 {
+        vec![(own, opponent, opponent_moves)]
+    } else {
+        bits::squares(own_moves)
+            .map(|square| {
+                let (own, opponent) = play(own, opponent, square);
+                (own, opponent, moves(opponent, own))
+            })
+            .collect()
+    };
+
+    bits::squares(game.size().corners() & empty)
+        .map(|square| {
+            let corner = 1u64 << square;
+            let must_concede = replies.iter().all(|&(_, _, moves)| moves & corner != 0);
+            let can_force = own_moves != 0
+                && replies
+                    .iter()
+                    .any(|&(own, opponent, moves)| forces(own, opponent, moves, corner));
+            CornerThreat {
+                corner: Move::Move(corner),
+                can_take: own_moves & corner != 0,
+                opponent_can_take: opponent_moves & corner != 0,
+                must_concede,
+                can_force,
+            }
+        })
+        .collect()
+}
+
+/// Returns true if every reply of the opponent, with `moves` to choose from, lets the player
+/// take the corner.
+fn forces(own: u64, opponent: u64, moves: u64, corner: u64) -> bool {
+    if moves & corner != 0 {
+        return false;
+    }
+    if moves == 0 {
+        return bits::moves(own, opponent) & corner != 0;
+    }
+    bits::squares(moves).all(|square| {
+        let (opponent, own) = play(opponent, own, square);
+        bits::moves(own, opponent) & corner != 0
+    })
+}
+
+/// Returns the discs of both players after the player with the `own` discs moves on the
+/// square.
+fn play(own: u64, opponent: u64, square: usize) -> (u64, u64) {
+    let flipped = bits::flips(own, opponent, square);
+    (own | flipped | 1 << square, opponent & !flipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::BoardSize;
+    use crate::othello::Move::Pass;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Returns the moves of the player to move, a pass if there are none.
+    fn moves(game: &Game) -> Vec<Move> {
+        match game.legal_moves() {
+            moves if moves.is_empty() => vec![Pass],
+            moves => moves,
+        }
+    }
+
+    /// Returns the game after the move, `None` if the game is over.
+    fn after(game: &Game, mv: Move) -> Option<Game> {
+        let mut game = *game;
+        game.play_next_turn(mv).ok()?;
+        Some(game)
+    }
+
+    #[test]
+    fn test_corner_threats() {
+        // black takes a1 over b1, white could take h1 over g1
+        let game: Game = "1WB2WB1/8/8/8/8/8/8/8 B 10 -".parse().unwrap();
+        let threats = game.corner_threats();
+        assert_eq!(threats.len(), 4);
+        assert!(threats[0].can_take && !threats[0].opponent_can_take);
+        assert!(!threats[1].can_take && threats[1].opponent_can_take);
+        assert!(threats[2].is_quiet() && threats[3].is_quiet());
+        assert_eq!(threats[3].corner, Move::from_algebraic("h8").unwrap());
+
+        let mut over = Game::new();
+        while !over.is_over() {
+            over.play_next_turn(over.greedy_move()).unwrap();
+        }
+        assert!(over.corner_threats().is_empty());
+    }
+
+    #[test]
+    fn test_corner_threats_match_playing_them_out() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut seen = [0; 4];
+        for i in 0..40 {
+            // the small board has corners of its own
+            let size = if i % 2 == 0 {
+                BoardSize::Eight
+            } else {
+                BoardSize::Six
+            };
+            let mut game = Game::with_size(size);
+            while !game.is_over() {
+                for threat in game.corner_threats() {
+                    let corner = threat.corner;
+                    assert_ne!(corner.get_position().unwrap() & size.corners(), 0);
+                    let gives = |game: Option<Game>| {
+                        game.is_some_and(|g| g.legal_moves().contains(&corner))
+                    };
+                    assert_eq!(threat.can_take, gives(Some(game)));
+                    let mut swapped = game;
+                    swapped.current_player = game.to_move().opponent();
+                    assert_eq!(threat.opponent_can_take, gives(Some(swapped)));
+                    let concede = moves(&game).iter().all(|&mv| gives(after(&game, mv)));
+                    assert_eq!(threat.must_concede, concede);
+                    let force = !game.legal_moves().is_empty()
+                        && game.legal_moves().iter().any(|&mv| {
+                            let reply = after(&game, mv).unwrap();
+                            !gives(Some(reply))
+                                && moves(&reply).iter().all(|&r| gives(after(&reply, r)))
+                        });
+                    assert_eq!(threat.can_force, force);
+                    for (count, flag) in seen.iter_mut().zip([
+                        threat.can_take,
+                        threat.opponent_can_take,
+                        threat.must_concede,
+                        threat.can_force,
+                    ]) {
+                        *count += flag as usize;
+                    }
+                }
+                game.play_next_turn(game.random_move(&mut rng)).unwrap();
+            }
+        }
+        assert!(seen.iter().all(|&count| count > 0), "{:?}", seen);
+    }
+}
@@ -0,0 +1,272 @@
+//! Safe, unsettled and poisoned edges.
+//!
+//! A disc on an edge can only be flipped along that edge, every other line through it ends
+//! on it, so whether the discs of an edge can ever be taken is a question about its eight
+//! squares alone. With each square empty, own or the opponent's there are 3^8 = 6561 edges,
+//! few enough to settle every one of them once, playing out every sequence of moves on the
+//! edge, and keep the answers in a table:
+//! - [`EdgeClass::Safe`]: no sequence of moves on the edge ever flips one of the player's
+//!   discs on it,
+//! - [`EdgeClass::Poisoned`]: the opponent can wedge in, there is a single empty square
+//!   between two of the player's discs where a disc of the opponent's could never be flipped
+//!   back along the edge,
+//! - [`EdgeClass::Unsettled`]: anything else, some of the player's discs can still be lost.
+//!
+//! Moves on the edge are counted whether or not they would be legal on the full board, since
+//! that depends on the rest of it, so a safe edge is safe whatever happens elsewhere.
+//!
+//! ```rust
+//! use herb::othello::edges::{Edge, EdgeClass};
+//! use herb::othello::{Color, Game};
+//!
+//! // nobody has a disc on an edge yet
+//! let game = Game::new();
+//! assert_eq!(game.edge_class(Edge::Rank1, Color::Black), EdgeClass::Safe);
+//! ```
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::sync::OnceLock;
+
+use serde::Serialize;
+
+/// The number of different edges, three states for each of the eight squares.
+pub const EDGE_CONFIGURATIONS: usize = 6561;
+
+/// An edge of the board.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Edge {
+    /// a1 to h1.
+    Rank1,
+    /// a8 to h8.
+    Rank8,
+    /// a1 to a8.
+    FileA,
+    /// h1 to h8.
+    FileH,
+}
+
+impl Edge {
+    /// Every edge.
+    pub const ALL: [Edge; 4] = [Edge::Rank1, Edge::Rank8, Edge::FileA, Edge::FileH];
+
+    /// Returns the squares of the edge, from the a-file or the first rank on.
+    pub const fn squares(self) -> [usize; 8] {
+        let (start, step) = match self {
+            Edge::Rank1 => (0, 1),
+            Edge::Rank8 => (56, 1),
+            Edge::FileA => (0, 8),
+            Edge::FileH => (7, 8),
+        };
+        let mut squares = [0; 8];
+        let mut i = 0;
+        while i < 8 {
+            squares[i] = start + i * step;
+            i += 1;
+        }
+        squares
+    }
+
+    /// Returns the squares of the edge as a bitboard.
+    pub fn mask(self) -> u64 {
+        self.squares()
+            .iter()
+            .fold(0, |mask, &square| mask | 1 << square)
+    }
+
+    /// Returns the index of the edge into the table, the sum of the state of each square
+    /// times `3^i`: 0 for empty, 1 for an `own` disc and 2 for an `opponent` disc.
+    pub fn index(self, own: u64, opponent: u64) -> usize {
+        self.squares().iter().rev().fold(0, |index, &square| {
+            let state = if own >> square & 1 == 1 {
+                1
+            } else if opponent >> square & 1 == 1 {
+                2
+            } else {
+                0
+            };
+            index * 3 + state
+        })
+    }
+
+    /// Returns the class of the edge for the player with the `own` discs.
+    pub fn classify(self, own: u64, opponent: u64) -> EdgeClass {
+        edge_table()[self.index(own, opponent)]
+    }
+}
+
+impl Display for Edge {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Edge::Rank1 => "1st rank",
+            Edge::Rank8 => "8th rank",
+            Edge::FileA => "a-file",
+            Edge::FileH => "h-file",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How safe a player's discs on an edge are, see the [module](self) documentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EdgeClass {
+    Safe,
+    Unsettled,
+    Poisoned,
+}
+
+/// Returns the class of every edge for the player with the discs in state 1, see
+/// [`Edge::index`].
+pub fn edge_table() -> &'static [EdgeClass; EDGE_CONFIGURATIONS] {
+    static TABLE: OnceLock<Box<[EdgeClass; EDGE_CONFIGURATIONS]>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let unstable = unstable_discs();
+        let mut table = Box::new([EdgeClass::Safe; EDGE_CONFIGURATIONS]);
+        for (index, class) in table.iter_mut().enumerate() {
+            let cells = decode(index);
+            let own = mask(&cells, 1);
+            let wedge = (1..7).any(|i| cells[i] == 0 && cells[i - 1] == 1 && cells[i + 1] == 1);
+            *class = if wedge {
+                EdgeClass::Poisoned
+            } else if own & unstable[index] != 0 {
+                EdgeClass::Unsettled
+            } else {
+                EdgeClass::Safe
+            };
+        }
+        table
+    })
+}
+
+/// Returns for every edge the discs of either player that some sequence of moves on the edge
+/// flips, a bit per square.
+///
+/// A move fills a square, so the edges are settled from the full ones up: a disc is unstable
+/// if a move flips it right away, or if it is unstable in the edge after some move.
+fn unstable_discs() -> Vec<u8> {
+    let mut unstable = vec![0u8; EDGE_CONFIGURATIONS];
+    let mut by_empties: Vec<usize> = (0..EDGE_CONFIGURATIONS).collect();
+    by_empties.sort_by_key(|&index| mask(&decode(index), 0).count_ones());
+    for index in by_empties {
+        let cells = decode(index);
+        let occupied = !mask(&cells, 0);
+        let mut flippable = 0;
+        for square in 0..8 {
+            if cells[square] != 0 {
+                continue;
+            }
+            for player in [1, 2] {
+                let (after, flipped) = place(cells, square, player);
+                flippable |= flipped | (unstable[encode(&after)] & occupied);
+            }
+        }
+        unstable[index] = flippable;
+    }
+    unstable
+}
+
+/// Returns the edge after `player` moves on the empty square and the discs the move flips
+/// along the edge.
+fn place(mut cells: [u8; 8], square: usize, player: u8) -> ([u8; 8], u8) {
+    let mut flipped = 0u8;
+    for step in [-1isize, 1] {
+        let mut run = 0u8;
+        let mut i = square as isize + step;
+        while (0..8).contains(&i) && cells[i as usize] == 3 - player {
+            run |= 1 << i;
+            i += step;
+        }
+        if (0..8).contains(&i) && cells[i as usize] == player {
+            flipped |= run;
+        }
+    }
+    cells[square] = player;
+    for (i, cell) in cells.iter_mut().enumerate() {
+        if flipped >> i & 1 == 1 {
+            *cell = player;
+        }
+    }
+    (cells, flipped)
+}
+
+fn decode(mut index: usize) -> [u8; 8] {
+    let mut cells = [0; 8];
+    for cell in cells.iter_mut() {
+        *cell = (index % 3) as u8;
+        index /= 3;
+    }
+    cells
+}
+
+fn encode(cells: &[u8; 8]) -> usize {
+    cells
+        .iter()
+        .rev()
+        .fold(0, |index, &cell| index * 3 + cell as usize)
+}
+
+/// Returns a bit for each square in the given state.
+fn mask(cells: &[u8; 8], state: u8) -> u8 {
+    (0..8)
+        .filter(|&i| cells[i] == state)
+        .fold(0, |mask, i| mask | 1 << i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::{Color, Game};
+
+    /// Returns the class of the first rank given as 8 characters, `O` for own discs, `X` for
+    /// the opponent's and `-` for empty squares.
+    fn rank(edge: &str) -> EdgeClass {
+        let square = |c| {
+            edge.chars()
+                .enumerate()
+                .filter(|&(_, s)| s == c)
+                .fold(0, |bits, (i, _)| bits | 1 << i)
+        };
+        Edge::Rank1.classify(square('O'), square('X'))
+    }
+
+    #[test]
+    fn test_edge_classes() {
+        assert_eq!(rank("--------"), EdgeClass::Safe);
+        // a corner and the discs next to it along the edge can never be flipped
+        assert_eq!(rank("OOO-----"), EdgeClass::Safe);
+        assert_eq!(rank("OOOOOOOO"), EdgeClass::Safe);
+        assert_eq!(rank("OOOXXXXX"), EdgeClass::Safe);
+        // the opponent can take a1 and flip b1 later
+        assert_eq!(rank("-OO-----"), EdgeClass::Unsettled);
+        assert_eq!(rank("--OOOO--"), EdgeClass::Unsettled);
+        // b1 is flipped as soon as the opponent moves on c1
+        assert_eq!(rank("XO------"), EdgeClass::Unsettled);
+        // a disc of the opponent's on e1 can not be flipped back
+        assert_eq!(rank("-OOO-OO-"), EdgeClass::Poisoned);
+        assert_eq!(rank("OOOO-OOO"), EdgeClass::Poisoned);
+    }
+
+    #[test]
+    fn test_edge_index() {
+        for index in [0, 1, 3280, EDGE_CONFIGURATIONS - 1] {
+            assert_eq!(encode(&decode(index)), index);
+        }
+        // every edge of the table can be reached from the squares of each edge
+        for edge in Edge::ALL {
+            let squares = edge.squares();
+            let own = 1u64 << squares[0] | 1 << squares[7];
+            let opponent = 1u64 << squares[1];
+            assert_eq!(edge.index(own, opponent), 1 + 2 * 3 + 3usize.pow(7));
+        }
+        assert_eq!(Edge::FileH.squares()[7], 63);
+
+        // d3 leaves every edge empty
+        let mut game = Game::new();
+        game.play_next_turn(game.legal_moves()[0]).unwrap();
+        for color in [Color::Black, Color::White] {
+            assert!(game
+                .edge_classes(color)
+                .iter()
+                .all(|&class| class == EdgeClass::Safe));
+        }
+    }
+}
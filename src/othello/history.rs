@@ -0,0 +1,173 @@
+//! Stepping backward and forward through a game.
+//!
+//! [`Game`] is `Copy` and only knows the current position, which keeps it cheap to copy
+//! around the search tree. To take moves back without replaying the game from the start, a
+//! [`Turn`] records what a turn changed: the move, the discs it flipped and who played it.
+//! [`Game::undo`] and [`Game::redo`] take a turn back and play it again from that record, and
+//! a [`GameHistory`] keeps the records of a game so front-ends and analysis tools can step
+//! through it.
+//!
+//! ```rust
+//! use herb::othello::history::GameHistory;
+//! use herb::othello::{Game, Move};
+//!
+//! let mut history = GameHistory::new(Game::new());
+//! history.play(Move::from_col_row(3, 2).unwrap()).unwrap();
+//! assert_eq!(history.undo(), Some(Move::from_col_row(3, 2).unwrap()));
+//! assert_eq!(*history.game(), Game::new());
+//! history.redo();
+//! assert_eq!(history.game().get_turn(), 1);
+//! ```
+use serde::{Deserialize, Serialize};
+
+use super::Move::Pass;
+use super::{Color, Game, GameError, Move};
+
+/// What a single turn of a game changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Turn {
+    /// The move played, [`Pass`] for a pass.
+    pub mv: Move,
+    /// The discs the move flipped.
+    pub flipped: u64,
+    /// The player who made the move.
+    pub player: Color,
+    /// The move of the turn before, the last move of the game before this turn.
+    pub previous: Option<Move>,
+}
+
+/// A game with the turns played so far and the turns taken back.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameHistory {
+    game: Game,
+    past: Vec<Turn>,
+    // taken back turns, the next one to redo last
+    future: Vec<Turn>,
+}
+
+impl GameHistory {
+    /// Start a history at the given position.
+    pub fn new(game: Game) -> Self {
+        GameHistory {
+            game,
+            past: Vec::new(),
+            future: Vec::new(),
+        }
+    }
+
+    /// Returns the current position.
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    /// Returns the turns played to reach the current position, the first turn first.
+    pub fn history(&self) -> &[Turn] {
+        &self.past
+    }
+
+    /// Play the move like [`Game::play_next_turn`] and forget the turns taken back. A forced
+    /// pass played automatically after the move is a turn of its own.
+    ///
+    /// Returns an error, leaving the history unchanged, if the move is illegal.
+    pub fn play(&mut self, mv: Move) -> Result<(), GameError> {
+        let before = self.game;
+        self.game.play_next_turn(mv)?;
+        self.past.push(Turn {
+            mv,
+            flipped: before.would_flip(mv),
+            player: before.to_move(),
+            previous: before.last_move(),
+        });
+        if self.game.get_turn() > before.get_turn() + 1 {
+            self.past.push(Turn {
+                mv: Pass,
+                flipped: 0,
+                player: before.to_move().opponent(),
+                previous: Some(mv),
+            });
+        }
+        self.future.clear();
+        Ok(())
+    }
+
+    /// Take the last turn back. Returns its move, `None` at the start of the history.
+    pub fn undo(&mut self) -> Option<Move> {
+        let turn = self.past.pop()?;
+        self.game.undo(&turn);
+        self.future.push(turn);
+        Some(turn.mv)
+    }
+
+    /// Play the last turn taken back again. Returns its move, `None` if no turn was taken
+    /// back since the last move played.
+    pub fn redo(&mut self) -> Option<Move> {
+        let turn = self.future.pop()?;
+        self.game.redo(&turn);
+        self.past.push(turn);
+        Some(turn.mv)
+    }
+
+    /// Returns true if there is a turn to take back.
+    pub fn can_undo(&self) -> bool {
+        !self.past.is_empty()
+    }
+
+    /// Returns true if there is a turn to play again.
+    pub fn can_redo(&self) -> bool {
+        !self.future.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_undo_redo() {
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut game = Game::new();
+        game.set_auto_pass(true);
+        let mut history = GameHistory::new(game);
+        let mut positions = vec![game];
+        while !history.game().is_over() {
+            let mv = history.game().random_move(&mut rng);
+            history.play(mv).unwrap();
+            positions.push(*history.game());
+        }
+        assert!(history.play(Pass).is_err());
+        assert!(!history.can_redo());
+
+        // every position comes back, hash and last move included
+        let turns = history.history().len();
+        let mut undone = Vec::new();
+        while let Some(mv) = history.undo() {
+            undone.push(mv);
+            let game = history.game();
+            assert_eq!(game.get_turn(), history.history().len());
+            if let Some(position) = positions.iter().find(|p| p.get_turn() == game.get_turn()) {
+                assert_eq!(game, position);
+            }
+            let mut fresh = *game;
+            fresh.rehash();
+            assert_eq!(game.zobrist_hash(), fresh.zobrist_hash());
+        }
+        assert_eq!(undone.len(), turns);
+        assert_eq!(*history.game(), positions[0]);
+
+        for _ in 0..turns {
+            history.redo().unwrap();
+        }
+        assert_eq!(history.redo(), None);
+        assert_eq!(history.game(), positions.last().unwrap());
+
+        // a new move forgets the turns taken back
+        history.undo();
+        history.undo();
+        assert!(history.can_redo());
+        let mv = history.game().legal_moves()[0];
+        history.play(mv).unwrap();
+        assert!(!history.can_redo());
+    }
+}
@@ -0,0 +1,174 @@
+//! A compact text format of a [`Game`], like FEN for chess.
+//!
+//! The position is written as four fields separated by spaces:
+//! - the board, row 1 first with the rows separated by `/`, each row from the a- to the
+//!   h-file with `B` for a black disc, `W` for a white disc and a digit for that many empty
+//!   squares,
+//! - the player to move, `B` or `W`,
+//! - the turn number,
+//! - the last move in algebraic notation, `pass`, or `-` at the start of a game.
+//!
+//! ```rust
+//! use herb::othello::Game;
+//!
+//! let game = Game::new();
+//! assert_eq!(game.to_string(), "8/8/8/3WB3/3BW3/8/8/8 B 0 -");
+//! assert_eq!("8/8/8/3WB3/3BW3/8/8/8 B 0 -".parse::<Game>().unwrap(), game);
+//! ```
+//!
+//! Parsing and displaying round-trip, so positions can be pasted between CLI tools, bug
+//! reports and tests. Whether the game [passes automatically](Game::set_auto_pass) is a
+//! setting rather than part of the position and is not written, a parsed game does not.
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::str::FromStr;
+
+use super::{Color, Game, GameBuilder};
+use crate::record::{format_move, parse_move};
+
+/// An error parsing a position string.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsePositionError(pub String);
+
+impl Display for ParsePositionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(f, "Bad position: {}", self.0)
+    }
+}
+
+impl Error for ParsePositionError {}
+
+impl Display for Game {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let board = self.get_board();
+        for row in 0..8 {
+            if row > 0 {
+                write!(f, "/")?;
+            }
+            let mut empty = 0;
+            for col in 0..8 {
+                let square = 1u64 << (row * 8 + col);
+                let disc = if board.get_black() & square != 0 {
+                    'B'
+                } else if board.get_white() & square != 0 {
+                    'W'
+                } else {
+                    empty += 1;
+                    continue;
+                };
+                if empty > 0 {
+                    write!(f, "{}", empty)?;
+                    empty = 0;
+                }
+                write!(f, "{}", disc)?;
+            }
+            if empty > 0 {
+                write!(f, "{}", empty)?;
+            }
+        }
+        let last_move = self.last_move().map_or("-".to_string(), format_move);
+        write!(f, " {} {} {}", self.to_move(), self.get_turn(), last_move)
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParsePositionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = |reason: &str| ParsePositionError(format!("{}: {}", reason, s));
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let [board, to_move, turn, last_move] = fields[..] else {
+            return Err(bad("expected 4 fields"));
+        };
+
+        let rows: Vec<&str> = board.split('/').collect();
+        if rows.len() != 8 {
+            return Err(bad("expected 8 rows"));
+        }
+        let (mut black, mut white) = (0, 0);
+        for (row, squares) in rows.iter().enumerate() {
+            let mut col = 0;
+            for c in squares.chars() {
+                match c {
+                    'B' | 'W' if col < 8 => {
+                        let square = 1u64 << (row * 8 + col);
+                        if c == 'B' {
+                            black |= square;
+                        } else {
+                            white |= square;
+                        }
+                        col += 1;
+                    }
+                    '1'..='8' => col += c as usize - '0' as usize,
+                    _ => return Err(bad("bad row")),
+                }
+            }
+            if col != 8 {
+                return Err(bad("rows need 8 squares"));
+            }
+        }
+
+        let to_move = match to_move {
+            "B" => Color::Black,
+            "W" => Color::White,
+            _ => return Err(bad("bad player to move")),
+        };
+        let turn = turn.parse().map_err(|_| bad("bad turn"))?;
+        let mut builder = GameBuilder::new()
+            .black(black)
+            .white(white)
+            .to_move(to_move)
+            .turn(turn);
+        if last_move != "-" {
+            let last_move = parse_move(last_move).map_err(|_| bad("bad last move"))?;
+            builder = builder.last_move(last_move);
+        }
+        builder.build().map_err(|e| bad(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::Move::Pass;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = ChaCha8Rng::seed_from_u64(11);
+        let mut game = Game::new();
+        loop {
+            let text = game.to_string();
+            let parsed: Game = text.parse().unwrap();
+            assert_eq!(parsed, game);
+            assert_eq!(parsed.to_string(), text);
+            if game.is_over() {
+                break;
+            }
+            game.play_next_turn(game.random_move(&mut rng)).unwrap();
+        }
+
+        let mut passed = "BW6/8/8/8/8/8/8/8 W 3 -".parse::<Game>().unwrap();
+        passed.play_next_turn(Pass).unwrap();
+        assert_eq!(passed.to_string(), "BW6/8/8/8/8/8/8/8 B 4 pass");
+    }
+
+    #[test]
+    fn test_bad_positions() {
+        for bad in [
+            "",
+            "8/8/8/3WB3/3BW3/8/8/8 B 0",
+            "8/8/8/3WB3/3BW3/8/8 B 0 -",
+            "8/8/8/3WB4/3BW3/8/8/8 B 0 -",
+            "8/8/8/3WB2/3BW3/8/8/8 B 0 -",
+            "8/8/8/3WX3/3BW3/8/8/8 B 0 -",
+            "8/8/8/3WB3/3BW3/8/8/8 X 0 -",
+            "8/8/8/3WB3/3BW3/8/8/8 B -1 -",
+            "8/8/8/3WB3/3BW3/8/8/8 B 0 z9",
+            "8/8/8/3WB3/3BW3/8/8/8 B 0 - extra",
+        ] {
+            assert!(bad.parse::<Game>().is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Regions of the board.
+//!
+//! Late in the game the empty squares break up into separate regions. Who moves last in a
+//! region tends to gain the discs there, and with an odd number of empty squares in a region
+//! that is whoever moves into it first, all else being equal. This region parity is one of
+//! the strongest endgame heuristics, the [`solver`](crate::solver) tries moves into odd
+//! regions first.
+//!
+//! ```rust
+//! use herb::othello::Game;
+//!
+//! // at the start every empty square is connected to every other one
+//! let regions = Game::new().empty_regions();
+//! assert_eq!(regions.len(), 1);
+//! assert_eq!(regions[0].size(), 60);
+//! assert!(!regions[0].is_odd());
+//! ```
+//!
+//! [`RegionStats`] gathers the regions together with the discs in each quadrant and in the
+//! sweet sixteen, the sixteen squares from c3 to f6. Early in the game the side with fewer
+//! discs outside the sweet sixteen tends to have more moves, and which quadrants are still
+//! open decides where the endgame is played.
+use super::bits;
+use super::Color;
+
+/// The four quadrants, a1-d4, e1-h4, a5-d8 and e5-h8.
+pub const QUADRANTS: [u64; 4] = [
+    0x0000_0000_0F0F_0F0F,
+    0x0000_0000_F0F0_F0F0,
+    0x0F0F_0F0F_0000_0000,
+    0xF0F0_F0F0_0000_0000,
+];
+
+/// The sixteen central squares from c3 to f6.
+pub const SWEET_SIXTEEN: u64 = 0x0000_3C3C_3C3C_0000;
+
+/// The number of discs of each color and of empty squares in an area of the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DiscCounts {
+    pub black: u32,
+    pub white: u32,
+    pub empty: u32,
+}
+
+impl DiscCounts {
+    /// Count the discs on the given squares.
+    pub fn new(area: u64, black: u64, white: u64) -> Self {
+        DiscCounts {
+            black: (area & black).count_ones(),
+            white: (area & white).count_ones(),
+            empty: (area & !(black | white)).count_ones(),
+        }
+    }
+
+    /// Returns the number of discs of the color.
+    pub fn discs(&self, color: Color) -> u32 {
+        match color {
+            Color::Black => self.black,
+            Color::White => self.white,
+        }
+    }
+}
+
+/// Statistics of the areas of the board in one place.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegionStats {
+    /// The discs in each quadrant, in the order of [`QUADRANTS`].
+    pub quadrants: [DiscCounts; 4],
+    /// The discs in the [`SWEET_SIXTEEN`].
+    pub sweet_sixteen: DiscCounts,
+    /// The regions of the empty squares, see [`empty_regions`].
+    pub empty_regions: Vec<EmptyRegion>,
+}
+
+impl RegionStats {
+    /// Gather the statistics of the position with the given discs on a board of the given
+    /// `squares`, all of them for the standard board.
+    pub fn new(black: u64, white: u64, squares: u64) -> Self {
+        let area = |area: u64| DiscCounts::new(area & squares, black, white);
+        RegionStats {
+            quadrants: QUADRANTS.map(area),
+            sweet_sixteen: area(SWEET_SIXTEEN),
+            empty_regions: empty_regions(squares & !(black | white)),
+        }
+    }
+
+    /// Returns the number of empty regions with an odd number of squares.
+    pub fn odd_regions(&self) -> usize {
+        self.empty_regions.iter().filter(|r| r.is_odd()).count()
+    }
+
+    /// Returns the indices into [`QUADRANTS`] of the quadrants with an odd number of empty
+    /// squares.
+    pub fn odd_quadrants(&self) -> Vec<usize> {
+        (0..4)
+            .filter(|&i| self.quadrants[i].empty % 2 == 1)
+            .collect()
+    }
+}
+
+/// A group of connected empty squares, squares touching in any direction belonging to the
+/// same region.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmptyRegion {
+    /// The squares of the region.
+    pub squares: u64,
+}
+
+impl EmptyRegion {
+    /// Returns the number of squares in the region.
+    pub fn size(&self) -> u32 {
+        self.squares.count_ones()
+    }
+
+    /// Returns true if the region has an odd number of squares.
+    pub fn is_odd(&self) -> bool {
+        self.size() % 2 == 1
+    }
+}
+
+/// Returns the regions of the empty squares, the region of the lowest square first.
+pub fn empty_regions(empty: u64) -> Vec<EmptyRegion> {
+    bits::regions(empty)
+        .map(|squares| EmptyRegion { squares })
+        .collect()
+}
+
+/// Returns the empty squares that lie in a region with an odd number of squares.
+pub fn odd_squares(empty: u64) -> u64 {
+    bits::regions(empty)
+        .filter(|region| region.count_ones() % 2 == 1)
+        .fold(0, |odd, region| odd | region)
+}
+
+/// Returns the moves of the player with the `own` discs that keep the tempo in their region:
+/// moves into a region with an odd number of empty squares, and among those the moves after
+/// which the opponent has no reply in the same region, so the player gets its last move.
+/// Each preference is dropped when none of the moves meet it, the result is only empty
+/// without legal moves. Only the given `squares` are on the board.
+pub fn parity_moves(own: u64, opponent: u64, squares: u64) -> u64 {
+    let legal = bits::moves(own, opponent) & squares;
+    let empty = squares & !(own | opponent);
+    let odd = odd_squares(empty) & legal;
+    let candidates = if odd != 0 { odd } else { legal };
+    let quiet = bits::squares(candidates)
+        .filter(|&square| {
+            let region = bits::region(empty, square);
+            let flipped = bits::flips(own, opponent, square);
+            let replies = bits::moves(opponent & !flipped, own | flipped | 1 << square);
+            replies & region == 0
+        })
+        .fold(0, |quiet, square| quiet | 1 << square);
+    if quiet != 0 {
+        quiet
+    } else {
+        candidates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::bits::{CORNERS, FILE_A, FILE_H};
+    use crate::othello::Game;
+
+    #[test]
+    fn test_empty_regions() {
+        assert!(empty_regions(0).is_empty());
+        // the four corners are separate regions of one square
+        let regions = empty_regions(CORNERS);
+        assert_eq!(regions.len(), 4);
+        assert!(regions
+            .iter()
+            .all(|region| region.size() == 1 && region.is_odd()));
+        assert_eq!(regions[0].squares, 1);
+        assert_eq!(odd_squares(CORNERS), CORNERS);
+
+        // a1 and b2 touch diagonally, the a- and h-files do not wrap into each other
+        assert_eq!(empty_regions(1 | 1 << 9).len(), 1);
+        let files = empty_regions(FILE_A | FILE_H);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].squares, FILE_A);
+        assert_eq!(files[0].size(), 8);
+        assert_eq!(odd_squares(FILE_A | FILE_H | 1 << 3), 1 << 3);
+    }
+
+    #[test]
+    fn test_region_stats() {
+        assert_eq!(QUADRANTS.iter().fold(0, |all, q| all | q), u64::MAX);
+        assert_eq!(SWEET_SIXTEEN.count_ones(), 16);
+
+        let stats = Game::new().region_stats();
+        assert_eq!(stats.sweet_sixteen.black, 2);
+        assert_eq!(stats.sweet_sixteen.white, 2);
+        assert_eq!(stats.sweet_sixteen.empty, 12);
+        // d4 is the only disc of the first quadrant, a white one
+        assert_eq!(
+            stats.quadrants[0],
+            DiscCounts {
+                black: 0,
+                white: 1,
+                empty: 15
+            }
+        );
+        assert_eq!(stats.quadrants[3].discs(Color::White), 1);
+        assert_eq!(stats.odd_quadrants(), vec![0, 1, 2, 3]);
+        assert_eq!(stats.odd_regions(), 0);
+
+        let corners = RegionStats::new(!CORNERS & !(1 << 27), 1 << 27, !0);
+        assert_eq!(corners.empty_regions.len(), 4);
+        assert_eq!(corners.odd_regions(), 4);
+        assert_eq!(corners.sweet_sixteen.black, 15);
+        assert_eq!(corners.quadrants[0].empty, 1);
+    }
+
+    #[test]
+    fn test_parity_moves() {
+        // white on b1 and g1, black on c1 and h1 and the rest of the board: black can move on
+        // a1, a region of its own, or on f1, in the region of d1 to f1 where white answers d1
+        let black = 1 << 2 | 1 << 7;
+        let white = 1 << 1 | 1 << 6;
+        let (a1, f1) = (1 << 0, 1 << 5);
+        assert_eq!(bits::moves(black, white) & (a1 | f1), a1 | f1);
+        let full = !(0xFF | black | white);
+        assert_eq!(parity_moves(black | full, white, !0) & (a1 | f1), a1);
+        assert_eq!(parity_moves(0, 0, !0), 0);
+        // without a region of odd size every legal move stays a candidate
+        let game = Game::new();
+        let (black, white) = (game.get_board().get_black(), game.get_board().get_white());
+        assert_eq!(parity_moves(black, white, !0), bits::moves(black, white));
+    }
+}
@@ -0,0 +1,138 @@
+//! Stable discs.
+//!
+//! A disc is stable if no sequence of moves can ever flip it. Finding every one of them
+//! takes a search, but most of them are found by propagating from the corners: a disc can
+//! only be flipped along one of the four lines through it, and it can't be along a line that
+//! is full, or where it has a stable disc of its own color or the edge of the board next to
+//! it on either side. A disc that is safe on all four lines is stable, which may make its
+//! neighbours stable in turn. Discs on an edge that is [`EdgeClass::Safe`] for their color
+//! are stable to begin with, the other three lines through them end on the edge.
+//!
+//! The discs found are a lower bound: every one of them is stable, but not every stable disc
+//! is found.
+//!
+//! ```rust
+//! use herb::othello::{Color, Game, GameBuilder, Move};
+//!
+//! // nothing is stable at the start
+//! assert_eq!(Game::new().stable_discs(), (0, 0));
+//!
+//! // a corner is always stable, the disc next to it isn't yet
+//! let a1 = Move::from_col_row(0, 0).unwrap();
+//! let b2 = Move::from_col_row(1, 1).unwrap();
+//! let game = GameBuilder::new()
+//!     .disc(a1, Color::Black)
+//!     .disc(b2, Color::White)
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(game.stable_discs(), (1 << 0, 0));
+//! ```
+use super::bits::{self, Direction};
+use super::edges::{Edge, EdgeClass};
+
+/// The four lines through a square, each given by one of its two directions.
+const LINES: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::NorthEast,
+    Direction::SouthEast,
+];
+
+/// Returns the stable `black` and `white` discs on a board of the given `squares`, the empty
+/// squares outside of it counting as the edge of the board.
+pub fn stable_discs(black: u64, white: u64, squares: u64) -> (u64, u64) {
+    let walls = !squares;
+    let occupied = black | white | walls;
+    let full = LINES.map(|direction| full_lines(occupied, direction));
+    // the edge tables only know the edges of the full board
+    let standard = squares == !0;
+    let stable = |own: u64, opponent: u64| {
+        let own = own & squares;
+        let mut stable = 0;
+        if standard {
+            for edge in Edge::ALL {
+                if edge.classify(own, opponent) == EdgeClass::Safe {
+                    stable |= own & edge.mask();
+                }
+            }
+        }
+        loop {
+            let anchors = stable | walls;
+            let mut safe = own & !stable;
+            for (line, &direction) in LINES.iter().enumerate() {
+                let opposite = direction.opposite();
+                safe &= full[line]
+                    | bits::shift(anchors, direction)
+                    | bits::shift(anchors, opposite)
+                    | !bits::shift(!0, direction)
+                    | !bits::shift(!0, opposite);
+            }
+            if safe == 0 {
+                return stable;
+            }
+            stable |= safe;
+        }
+    };
+    (stable(black, white), stable(white, black))
+}
+
+/// Returns the squares whose line in the direction and its opposite is full.
+fn full_lines(occupied: u64, direction: Direction) -> u64 {
+    bits::squares(occupied)
+        .filter(|&square| {
+            let line = bits::ray(square, direction) | bits::ray(square, direction.opposite());
+            line & !occupied == 0
+        })
+        .fold(0, |full, square| full | 1 << square)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::bits::{EDGES, RANK_1};
+
+    #[test]
+    fn test_corners_propagate() {
+        // black holds a1 to c1 and a2 to b2, white has a disc in the middle of the board
+        let black = 0b111 | 0b11 << 8;
+        let white = 1 << 27;
+        let (stable_black, stable_white) = stable_discs(black, white, !0);
+        assert_eq!(stable_black, black);
+        assert_eq!(stable_white, 0);
+
+        // without c1 the diagonal from a3 to c1 through b2 is open
+        let black = 0b11 | 0b11 << 8;
+        assert_eq!(stable_discs(black, white, !0).0, black & !(1 << 9));
+    }
+
+    #[test]
+    fn test_full_board() {
+        // every disc of a full board is stable
+        let black = 0x0F0F_0F0F_0F0F_0F0F;
+        assert_eq!(stable_discs(black, !black, !0), (black, !black));
+    }
+
+    #[test]
+    fn test_safe_edges() {
+        // white on a1, c1, e1 and g1 can never flip b1, any black discs next to it are
+        // flanked by white already
+        let black = 1 << 1;
+        let white = 0b0101_0101;
+        assert_eq!(stable_discs(black, white, !0), (black, 1 << 0));
+        // with c1 empty white can flip it from there
+        let white = 0b0101_0001;
+        assert_eq!(stable_discs(black, white, !0).0 & RANK_1, 0);
+        // discs off the edges are not stable just for being alone
+        let (stable_black, _) = stable_discs(1 << 27, 1 << 28, !0);
+        assert_eq!(stable_black & !EDGES, 0);
+    }
+
+    #[test]
+    fn test_small_board() {
+        // b2 is the corner of the middle six by six squares
+        let squares = 0x007E_7E7E_7E7E_7E00;
+        assert_eq!(stable_discs(1 << 9, 1 << 27, squares), (1 << 9, 0));
+        // a1 is outside of the board
+        assert_eq!(stable_discs(1 << 9 | 1 << 0, 1 << 27, squares), (1 << 9, 0));
+    }
+}
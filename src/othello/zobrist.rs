@@ -0,0 +1,120 @@
+//! Zobrist hashing of positions.
+//!
+//! Every square has a random key for a black disc and one for a white disc, and there is a
+//! key for white to move. The hash of a position is the xor of the keys of its discs and,
+//! when white is to move, the side-to-move key. Because xor is its own inverse a move only
+//! has to toggle the keys of the squares it changes: the placed disc, both keys of every
+//! flipped disc and the side-to-move key. [`Game`](super::Game) keeps its hash up to date
+//! this way in [`play_next_turn`](super::Game::play_next_turn), so
+//! [`zobrist_hash`](super::Game::zobrist_hash) costs nothing, and it is the key of the nodes
+//! of the search tree and of the caches in [`cache`](crate::cache).
+//!
+//! The keys are generated at compile time from fixed seeds, so hashes are the same in every
+//! build and on every machine.
+//!
+//! ```rust
+//! use herb::othello::{zobrist, Color, Game, Move};
+//!
+//! let mut game = Game::new();
+//! game.play_next_turn(Move::from_col_row(3, 2).unwrap()).unwrap();
+//! let board = game.get_board();
+//! let hash = zobrist::hash(board.get_black(), board.get_white(), Color::White);
+//! assert_eq!(game.zobrist_hash(), hash);
+//! ```
+use super::Color;
+
+/// Random keys for a black disc on every square, then for a white disc on every square.
+pub const KEYS: [[u64; 64]; 2] = keys();
+/// Both keys of every square, which toggle a disc from one color to the other.
+pub const FLIP_KEYS: [u64; 64] = flip_keys();
+/// Key of the positions where white is to move.
+pub const WHITE_TO_MOVE: u64 = splitmix64(128);
+
+/// The SplitMix64 generator, seeded with the index of a key so the keys never change.
+const fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+const fn keys() -> [[u64; 64]; 2] {
+    let mut keys = [[0; 64]; 2];
+    let mut i = 0;
+    while i < 128 {
+        keys[i / 64][i % 64] = splitmix64(i as u64);
+        i += 1;
+    }
+    keys
+}
+
+const fn flip_keys() -> [u64; 64] {
+    let keys = keys();
+    let mut flip_keys = [0; 64];
+    let mut i = 0;
+    while i < 64 {
+        flip_keys[i] = keys[0][i] ^ keys[1][i];
+        i += 1;
+    }
+    flip_keys
+}
+
+/// Returns the xor of the keys of the squares of the bitboard.
+fn fold(keys: &[u64; 64], mut bits: u64) -> u64 {
+    let mut hash = 0;
+    while bits != 0 {
+        hash ^= keys[bits.trailing_zeros() as usize];
+        bits &= bits - 1;
+    }
+    hash
+}
+
+/// Returns the xor of the keys of discs of the color on the squares of the bitboard.
+pub fn discs(bits: u64, color: Color) -> u64 {
+    match color {
+        Color::Black => fold(&KEYS[0], bits),
+        Color::White => fold(&KEYS[1], bits),
+    }
+}
+
+/// Returns the change of the hash when the discs on the squares of the bitboard are flipped.
+pub fn flips(bits: u64) -> u64 {
+    fold(&FLIP_KEYS, bits)
+}
+
+/// Returns the key of the player to move.
+pub fn to_move(color: Color) -> u64 {
+    match color {
+        Color::Black => 0,
+        Color::White => WHITE_TO_MOVE,
+    }
+}
+
+/// Returns the hash of the position, computed from scratch.
+pub fn hash(black: u64, white: u64, to_move: Color) -> u64 {
+    discs(black, Color::Black) ^ discs(white, Color::White) ^ self::to_move(to_move)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keys() {
+        // every key differs, so no single disc or the side to move cancels another
+        let mut all: Vec<u64> = KEYS.iter().flatten().copied().collect();
+        all.push(WHITE_TO_MOVE);
+        all.sort_unstable();
+        all.dedup();
+        assert_eq!(all.len(), 129);
+
+        // flipping a disc swaps its key for the key of the other color
+        let square = 1 << 27;
+        assert_eq!(
+            discs(square, Color::Black) ^ flips(square),
+            discs(square, Color::White)
+        );
+        assert_eq!(hash(0, 0, Color::Black), 0);
+        assert_eq!(hash(0, 0, Color::White), WHITE_TO_MOVE);
+    }
+}
@@ -0,0 +1,261 @@
+//! Random number streams for games played in parallel.
+//!
+//! Self-play runs many games on many workers at once, and every one of them needs random
+//! numbers that are the same on every run, however the games are spread over the workers.
+//! Drawing them from one generator makes them depend on the order the games happen to ask
+//! for them, so instead every game and worker gets a stream of its own, derived from the
+//! `(seed, game_id, worker_id)` alone, without any coordination between the workers.
+//!
+//! Two backends derive the streams, chosen with [`RngBackend`]:
+//! - [`RngBackend::ChaCha`]: ChaCha8 keyed by the seed, with the game as the stream and the
+//!   worker as the position in it, `2^64` words apart. Worker 0 of game 0 draws the same
+//!   numbers as `ChaCha8Rng::seed_from_u64(seed)`, so seeded games stay as they were.
+//! - [`RngBackend::Philox`]: the counter-based Philox4x32-10 generator, see [`Philox`]. The
+//!   `n`-th block of a stream is a function of the key and `n` only, a few multiplications,
+//!   so streams are cheap to create and to jump around in.
+//!
+//! ```rust
+//! use herb::random::RngBackend;
+//! use rand::Rng;
+//!
+//! let mut game_3 = RngBackend::Philox.stream(7, 3, 0);
+//! let mut again = RngBackend::Philox.stream(7, 3, 0);
+//! let mut game_4 = RngBackend::Philox.stream(7, 4, 0);
+//! let draw: u64 = game_3.gen();
+//! assert_eq!(draw, again.gen::<u64>());
+//! assert_ne!(draw, game_4.gen::<u64>());
+//! ```
+use rand::{Error, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+/// The generator behind the random number streams.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RngBackend {
+    /// ChaCha8, the generator Herb has always used.
+    #[default]
+    ChaCha,
+    /// The counter-based Philox4x32-10.
+    Philox,
+}
+
+impl RngBackend {
+    /// Returns the stream of the game and worker for the seed.
+    pub fn stream(self, seed: u64, game_id: u64, worker_id: u32) -> StreamRng {
+        self.stream_from_key(
+            ChaCha8Rng::seed_from_u64(seed).get_seed(),
+            game_id,
+            worker_id,
+        )
+    }
+
+    /// Returns the stream of the game and worker for a key of 32 random bytes, such as
+    /// a seed already expanded by `ChaCha8Rng::seed_from_u64`. Philox only uses the first 8.
+    pub fn stream_from_key(self, key: [u8; 32], game_id: u64, worker_id: u32) -> StreamRng {
+        match self {
+            RngBackend::ChaCha => {
+                let mut rng = ChaCha8Rng::from_seed(key);
+                rng.set_stream(game_id);
+                rng.set_word_pos(u128::from(worker_id) << 64);
+                StreamRng::ChaCha(Box::new(rng))
+            }
+            RngBackend::Philox => {
+                let mut seed = [0; 8];
+                seed.copy_from_slice(&key[..8]);
+                StreamRng::Philox(Philox::new(u64::from_le_bytes(seed), game_id, worker_id))
+            }
+        }
+    }
+}
+
+/// A random number stream of either backend.
+#[derive(Clone, Debug)]
+pub enum StreamRng {
+    ChaCha(Box<ChaCha8Rng>),
+    Philox(Philox),
+}
+
+impl StreamRng {
+    /// Returns a ChaCha8 stream seeded from the operating system, different on every run.
+    pub fn from_entropy() -> Self {
+        StreamRng::ChaCha(Box::new(ChaCha8Rng::from_entropy()))
+    }
+}
+
+impl RngCore for StreamRng {
+    fn next_u32(&mut self) -> u32 {
+        match self {
+            StreamRng::ChaCha(rng) => rng.next_u32(),
+            StreamRng::Philox(rng) => rng.next_u32(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            StreamRng::ChaCha(rng) => rng.next_u64(),
+            StreamRng::Philox(rng) => rng.next_u64(),
+        }
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        match self {
+            StreamRng::ChaCha(rng) => rng.fill_bytes(dest),
+            StreamRng::Philox(rng) => rng.fill_bytes(dest),
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+const MULTIPLIERS: [u32; 2] = [0xD251_1F53, 0xCD9E_8D57];
+/// Added to the key after every round, the golden ratio and `sqrt(3) - 1`.
+const WEYL: [u32; 2] = [0x9E37_79B9, 0xBB67_AE85];
+const ROUNDS: usize = 10;
+
+/// The Philox4x32-10 counter-based generator of Salmon et al., "Parallel random numbers: as
+/// easy as 1, 2, 3".
+///
+/// Every block of four words is ten rounds of multiplications and xors of a 128-bit counter
+/// under a 64-bit key, the seed. The counter is made of the block's index in the stream, the
+/// worker and the game, so every game and worker has `2^32` blocks, 64 GiB, of its own
+/// before the stream repeats.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Philox {
+    key: [u32; 2],
+    counter: [u32; 4],
+    block: [u32; 4],
+    /// The next word of the block, 4 when it is used up.
+    index: usize,
+}
+
+impl Philox {
+    /// Create the stream of the game and worker for the seed.
+    pub fn new(seed: u64, game_id: u64, worker_id: u32) -> Self {
+        Philox {
+            key: [seed as u32, (seed >> 32) as u32],
+            counter: [0, worker_id, game_id as u32, (game_id >> 32) as u32],
+            block: [0; 4],
+            index: 4,
+        }
+    }
+
+    /// Returns the block of the counter under the key.
+    pub fn block(counter: [u32; 4], key: [u32; 2]) -> [u32; 4] {
+        let mut x = counter;
+        let mut key = key;
+        for round in 0..ROUNDS {
+            if round > 0 {
+                key[0] = key[0].wrapping_add(WEYL[0]);
+                key[1] = key[1].wrapping_add(WEYL[1]);
+            }
+            let product_0 = u64::from(MULTIPLIERS[0]) * u64::from(x[0]);
+            let product_1 = u64::from(MULTIPLIERS[1]) * u64::from(x[2]);
+            x = [
+                (product_1 >> 32) as u32 ^ x[1] ^ key[0],
+                product_1 as u32,
+                (product_0 >> 32) as u32 ^ x[3] ^ key[1],
+                product_0 as u32,
+            ];
+        }
+        x
+    }
+}
+
+impl RngCore for Philox {
+    fn next_u32(&mut self) -> u32 {
+        if self.index == 4 {
+            self.block = Philox::block(self.counter, self.key);
+            self.counter[0] = self.counter[0].wrapping_add(1);
+            self.index = 0;
+        }
+        self.index += 1;
+        self.block[self.index - 1]
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let low = self.next_u32();
+        u64::from(low) | u64::from(self.next_u32()) << 32
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_philox_known_answers() {
+        // the known answers of the Random123 reference implementation
+        assert_eq!(
+            Philox::block([0; 4], [0; 2]),
+            [0x6627_E8D5, 0xE169_C58D, 0xBC57_AC4C, 0x9B00_DBD8]
+        );
+        assert_eq!(
+            Philox::block([u32::MAX; 4], [u32::MAX; 2]),
+            [0x408F_276D, 0x41C8_3B0E, 0xA20B_C7C6, 0x6D54_51FD]
+        );
+        assert_eq!(
+            Philox::block(
+                [0x243F_6A88, 0x85A3_08D3, 0x1319_8A2E, 0x0370_7344],
+                [0xA409_3822, 0x299F_31D0]
+            ),
+            [0xD16C_FE09, 0x94FD_CCEB, 0x5001_E420, 0x2412_6EA1]
+        );
+    }
+
+    #[test]
+    fn test_streams() {
+        for backend in [RngBackend::ChaCha, RngBackend::Philox] {
+            let draws = |game_id, worker_id| -> Vec<u64> {
+                let mut rng = backend.stream(9, game_id, worker_id);
+                (0..8).map(|_| rng.gen()).collect()
+            };
+            assert_eq!(draws(1, 2), draws(1, 2));
+            assert_ne!(draws(1, 2), draws(2, 2));
+            assert_ne!(draws(1, 2), draws(1, 3));
+            assert_ne!(
+                draws(1, 2),
+                backend.stream(10, 1, 2).gen::<[u64; 8]>().to_vec()
+            );
+        }
+
+        // worker 0 of game 0 is the generator seeded games always used
+        let mut chacha = ChaCha8Rng::seed_from_u64(9);
+        let mut stream = RngBackend::ChaCha.stream(9, 0, 0);
+        assert_eq!(chacha.gen::<[u64; 4]>(), stream.gen::<[u64; 4]>());
+    }
+
+    #[test]
+    fn test_philox_bytes() {
+        let mut words = Philox::new(5, 0, 0);
+        let mut bytes = Philox::new(5, 0, 0);
+        let mut dest = [0; 6];
+        bytes.fill_bytes(&mut dest);
+        let first = words.next_u32().to_le_bytes();
+        let second = words.next_u32().to_le_bytes();
+        assert_eq!(dest[..4], first);
+        assert_eq!(dest[4..], second[..2]);
+
+        // the blocks go on past the first
+        let mut rng = Philox::new(5, 0, 0);
+        let draws: Vec<u32> = (0..12).map(|_| rng.next_u32()).collect();
+        assert_eq!(draws[..4], Philox::block([0, 0, 0, 0], rng.key));
+        assert_eq!(draws[8..], Philox::block([2, 0, 0, 0], rng.key));
+    }
+}
@@ -0,0 +1,399 @@
+//! Game records and human readable transcripts.
+//!
+//! A [`GameRecord`] holds every move of a game along with optional clock and evaluation
+//! annotations. Records can be written as a plain text transcript that reads well in a
+//! terminal or a forum post and parsed back into an identical record:
+//! ```text
+//! [Black "Herb"]
+//! [White "Random"]
+//! [Result "40-24"]
+//! [Opening "Tiger"]
+//!
+//! 1. d3 {1:58.2 +0.53} c5 {1:59.0}
+//! 2. c4 {1:55.7 +0.61} e3 {1:57.4}
+//! ...
+//! Black wins 40-24
+//! ```
+//! Moves are numbered in pairs, black first, using algebraic coordinates with columns `a`
+//! through `h` from left to right and rows `1` through `8` from top to bottom. The annotation
+//! after a move holds the mover's remaining clock as `minutes:seconds` and the engine's
+//! evaluation of the move, either may be left out. The `Opening` tag names the
+//! [opening](crate::openings) the game started with, if it is a known one.
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+
+use serde::{Deserialize, Serialize};
+
+use crate::openings;
+use crate::othello::Color::{Black, White};
+use crate::othello::{Game, GameError, MaterialHistory, Move, Symmetry};
+
+/// A move in a [`GameRecord`] with its annotations.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub mv: Move,
+    /// Seconds left on the mover's clock after the move.
+    pub clock: Option<f64>,
+    /// The mover's evaluation of the move.
+    pub eval: Option<f64>,
+}
+
+impl RecordedMove {
+    /// Create a move with no annotations.
+    pub fn new(mv: Move) -> Self {
+        RecordedMove {
+            mv,
+            clock: None,
+            eval: None,
+        }
+    }
+}
+
+/// A complete or in-progress game.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    /// Free form tags such as player names, written in sorted order.
+    pub tags: BTreeMap<String, String>,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl GameRecord {
+    /// Create an empty record.
+    pub fn new() -> Self {
+        GameRecord::default()
+    }
+
+    /// Create a record of the given moves without annotations.
+    pub fn from_moves(moves: &[Move]) -> Self {
+        GameRecord {
+            tags: BTreeMap::new(),
+            moves: moves.iter().map(|mv| RecordedMove::new(*mv)).collect(),
+        }
+    }
+
+    /// Add a move to the end of the record.
+    pub fn push(&mut self, mv: RecordedMove) {
+        self.moves.push(mv);
+    }
+
+    /// Replay the moves from the starting position.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn game(&self) -> Result<Game, GameError> {
+        let mut game = Game::new();
+        for recorded in &self.moves {
+            game.play_next_turn(recorded.mv)?;
+        }
+        Ok(game)
+    }
+
+    /// Returns the disc counts after every move of the game.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn material_history(&self) -> Result<MaterialHistory, GameError> {
+        let moves: Vec<Move> = self.moves.iter().map(|recorded| recorded.mv).collect();
+        MaterialHistory::from_moves(&moves)
+    }
+
+    /// Returns the name of the opening the game started with, see
+    /// [`openings::opening_name`].
+    pub fn opening_name(&self) -> Option<&'static str> {
+        let moves: Vec<Move> = self.moves.iter().map(|recorded| recorded.mv).collect();
+        openings::opening_name(&moves)
+    }
+
+    /// Write the record as a text transcript.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn to_transcript(&self) -> Result<String, GameError> {
+        let mut transcript = String::new();
+        for (name, value) in &self.tags {
+            transcript += &format!("[{} \"{}\"]\n", name, value);
+        }
+
+        let game = self.game()?;
+        let (black, white) = game.disc_counts();
+        if game.is_over() {
+            transcript += &format!("[Result \"{}-{}\"]\n", black, white);
+        }
+        if let Some(opening) = self.opening_name() {
+            transcript += &format!("[Opening \"{}\"]\n", opening);
+        }
+        if !transcript.is_empty() {
+            transcript += "\n";
+        }
+
+        for (i, pair) in self.moves.chunks(2).enumerate() {
+            let moves: Vec<String> = pair.iter().map(format_recorded_move).collect();
+            transcript += &format!("{}. {}\n", i + 1, moves.join(" "));
+        }
+
+        if game.is_over() {
+            let outcome = match game.winner() {
+                Some(Black) => "Black wins",
+                Some(White) => "White wins",
+                None => "Draw",
+            };
+            transcript += &format!("{} {}-{}\n", outcome, black, white);
+        }
+        Ok(transcript)
+    }
+
+    /// Parse a text transcript written by [`GameRecord::to_transcript`].
+    ///
+    /// The `Result` and `Opening` tags and the final result line are recomputed from the moves,
+    /// so they are not stored in the record.
+    pub fn from_transcript(transcript: &str) -> Result<Self, RecordError> {
+        let mut record = GameRecord::new();
+
+        for line in transcript.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(tag) = line.strip_prefix('[') {
+                let (name, value) = parse_tag(tag)?;
+                if name != "Result" && name != "Opening" {
+                    record.tags.insert(name, value);
+                }
+                continue;
+            }
+            let Some((number, moves)) = line.split_once(". ") else {
+                // the result line
+                continue;
+            };
+            if number.parse::<usize>().is_err() {
+                return Err(RecordError::Parse(format!("Bad move number: {}", line)));
+            }
+            parse_move_line(moves, &mut record.moves)?;
+        }
+
+        record.game()?;
+        Ok(record)
+    }
+}
+
+/// Formats a move as algebraic coordinates, e.g. `d3`, or `pass`.
+pub fn format_move(mv: Move) -> String {
+    mv.to_string()
+}
+
+/// Parses algebraic coordinates, e.g. `d3`, or `pass`.
+pub fn parse_move(token: &str) -> Result<Move, RecordError> {
+    Move::from_algebraic(token).map_err(|_| RecordError::Parse(format!("Bad move: {}", token)))
+}
+
+/// When two records count as the same game for [`dedup`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Equivalence {
+    /// The games have the same moves.
+    Identical,
+    /// The games have the same moves once the board is rotated or reflected by one of the
+    /// symmetries of the starting position, as happens when archives record a game from
+    /// different sides of the board.
+    Symmetric,
+}
+
+/// Drop the records that repeat an earlier game, keeping the first of each, and return the
+/// kept records along with the number dropped. Tags and annotations are not compared.
+pub fn dedup(records: Vec<GameRecord>, equivalence: Equivalence) -> (Vec<GameRecord>, usize) {
+    let symmetries: Vec<Symmetry> = match equivalence {
+        Equivalence::Identical => vec![Symmetry::Identity],
+        Equivalence::Symmetric => {
+            let start = Game::new();
+            Symmetry::ALL
+                .into_iter()
+                .filter(|symmetry| start.transform(*symmetry).get_board() == start.get_board())
+                .collect()
+        }
+    };
+    let mut seen = HashSet::new();
+    let total = records.len();
+    let kept: Vec<GameRecord> = records
+        .into_iter()
+        .filter(|record| {
+            // the same key for every symmetric copy of the game
+            let key = symmetries
+                .iter()
+                .map(|symmetry| {
+                    record
+                        .moves
+                        .iter()
+                        .map(|recorded| recorded.mv.transform(*symmetry).to_index())
+                        .collect::<Vec<usize>>()
+                })
+                .min()
+                .unwrap_or_default();
+            seen.insert(key)
+        })
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+fn format_recorded_move(recorded: &RecordedMove) -> String {
+    let mut annotations = Vec::new();
+    if let Some(clock) = recorded.clock {
+        let minutes = (clock / 60.0).floor();
+        annotations.push(format!("{}:{:04.1}", minutes, clock - minutes * 60.0));
+    }
+    if let Some(eval) = recorded.eval {
+        annotations.push(format!("{:+.2}", eval));
+    }
+    if annotations.is_empty() {
+        format_move(recorded.mv)
+    } else {
+        format!("{} {{{}}}", format_move(recorded.mv), annotations.join(" "))
+    }
+}
+
+fn parse_tag(tag: &str) -> Result<(String, String), RecordError> {
+    let bad_tag = || RecordError::Parse(format!("Bad tag: [{}", tag));
+    let tag = tag.strip_suffix(']').ok_or_else(bad_tag)?;
+    let (name, value) = tag.split_once(' ').ok_or_else(bad_tag)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(bad_tag)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+/// Parses the moves after the move number, e.g. `d3 {1:58.2 +0.53} c5`.
+fn parse_move_line(line: &str, moves: &mut Vec<RecordedMove>) -> Result<(), RecordError> {
+    let mut rest = line.trim();
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let mut recorded = RecordedMove::new(parse_move(&rest[..end])?);
+        rest = rest[end..].trim_start();
+
+        if let Some(annotation) = rest.strip_prefix('{') {
+            let close = annotation
+                .find('}')
+                .ok_or_else(|| RecordError::Parse(format!("Unclosed annotation: {}", line)))?;
+            for token in annotation[..close].split_whitespace() {
+                if let Some((minutes, seconds)) = token.split_once(':') {
+                    let clock = minutes.parse::<f64>().ok().zip(seconds.parse::<f64>().ok());
+                    let (minutes, seconds) =
+                        clock.ok_or_else(|| RecordError::Parse(format!("Bad clock: {}", token)))?;
+                    recorded.clock = Some(minutes * 60.0 + seconds);
+                } else {
+                    let eval = token
+                        .parse::<f64>()
+                        .map_err(|_| RecordError::Parse(format!("Bad evaluation: {}", token)))?;
+                    recorded.eval = Some(eval);
+                }
+            }
+            rest = annotation[close + 1..].trim_start();
+        }
+        moves.push(recorded);
+    }
+    Ok(())
+}
+
+/// Enumerates errors reading a game record.
+#[derive(Debug)]
+pub enum RecordError {
+    /// The text could not be parsed.
+    Parse(String),
+    /// The moves do not make a legal game.
+    Game(GameError),
+}
+
+impl From<GameError> for RecordError {
+    fn from(e: GameError) -> Self {
+        RecordError::Game(e)
+    }
+}
+
+impl Display for RecordError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            RecordError::Parse(message) => write!(f, "Parse error: {}", message),
+            RecordError::Game(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for RecordError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_move_game() -> Vec<Move> {
+        let mut game = Game::new();
+        let mut moves = Vec::new();
+        while !game.is_over() {
+            let mv = *game.legal_moves().first().unwrap_or(&Move::Pass);
+            game.play_next_turn(mv).unwrap();
+            moves.push(mv);
+        }
+        moves
+    }
+
+    #[test]
+    fn test_dedup() {
+        let moves = first_move_game();
+        let game = GameRecord::from_moves(&moves);
+        let mut annotated = game.clone();
+        annotated.moves[0].eval = Some(1.0);
+        annotated
+            .tags
+            .insert("Event".to_string(), "Archive".to_string());
+        let rotated = GameRecord::from_moves(
+            &moves
+                .iter()
+                .map(|mv| mv.transform(Symmetry::Rotate180))
+                .collect::<Vec<Move>>(),
+        );
+        rotated.game().unwrap();
+        let other = GameRecord::from_moves(&moves[..moves.len() - 1]);
+
+        let records = vec![game.clone(), annotated, rotated.clone(), other.clone()];
+        let (kept, dropped) = dedup(records.clone(), Equivalence::Identical);
+        assert_eq!(kept, vec![game.clone(), rotated, other.clone()]);
+        assert_eq!(dropped, 1);
+        let (kept, dropped) = dedup(records, Equivalence::Symmetric);
+        assert_eq!(kept, vec![game, other]);
+        assert_eq!(dropped, 2);
+    }
+
+    #[test]
+    fn test_transcript_round_trip() {
+        let mut record = GameRecord::from_moves(&first_move_game());
+        record.tags.insert("Black".to_string(), "Herb".to_string());
+        record
+            .tags
+            .insert("White".to_string(), "Random".to_string());
+        record.moves[0].clock = Some(118.25);
+        record.moves[0].eval = Some(0.5);
+        record.moves[1].eval = Some(-1.25);
+        record.moves[2].clock = Some(59.5);
+
+        let transcript = record.to_transcript().unwrap();
+        assert!(transcript.starts_with("[Black \"Herb\"]\n[White \"Random\"]\n[Result "));
+        assert!(transcript.contains("[Opening \"Diagonal opening\"]\n\n1. d3 {1:58.2 +0.50} c3"));
+        assert!(transcript.contains("1. d3 {1:58.2 +0.50} c3 {-1.25}\n2. b3 {0:59.5} b2\n"));
+
+        let parsed = GameRecord::from_transcript(&transcript).unwrap();
+        assert_eq!(parsed.tags, record.tags);
+        assert_eq!(parsed.moves.len(), record.moves.len());
+        for (a, b) in parsed.moves.iter().zip(&record.moves) {
+            assert_eq!(a.mv, b.mv);
+            assert_eq!(a.eval, b.eval);
+            match (a.clock, b.clock) {
+                (Some(x), Some(y)) => assert!((x - y).abs() < 0.1),
+                (x, y) => assert_eq!(x, y),
+            }
+        }
+    }
+
+    #[test]
+    fn test_transcript_rejects_illegal_moves() {
+        assert!(GameRecord::from_transcript("1. a1").is_err());
+        assert!(GameRecord::from_transcript("1. z9").is_err());
+        assert!(GameRecord::from_transcript("1. d3 {bogus}").is_err());
+        assert!(GameRecord::from_transcript("1. d3 c5\n").is_ok());
+    }
+}
@@ -0,0 +1,263 @@
+//! SVG diagrams of board positions.
+//!
+//! Enabled with the `render` feature. [`render_svg`] draws a position as a standalone SVG
+//! document that can be opened in a browser or embedded in a report. The move that was
+//! chosen can be highlighted and a principal variation drawn as numbered arrows.
+//! [`render_heatmap`] draws per-square statistics in the same layout.
+use std::fmt::Write;
+
+use crate::heatmap::Heatmap;
+use crate::othello::{Game, Move};
+
+const SQUARE: u32 = 48;
+const MARGIN: u32 = 24;
+const BOARD_COLOR: &str = "#2e7d32";
+const LINE_COLOR: &str = "#1b5e20";
+const HIGHLIGHT_COLOR: &str = "#fdd835";
+const ARROW_COLOR: &str = "#e53935";
+
+/// Extra markings drawn on top of the position.
+#[derive(Clone, Debug, Default)]
+pub struct RenderOptions {
+    /// The move to highlight, usually the one chosen by the engine.
+    pub chosen: Option<Move>,
+    /// A sequence of moves starting from the position, drawn as numbered arrows.
+    pub pv: Vec<Move>,
+    /// Mark the legal moves of the player to move with small dots.
+    pub show_legal_moves: bool,
+}
+
+/// Returns the center of a square in pixels.
+fn center(mv: Move) -> Option<(u32, u32)> {
+    let (col, row) = (mv.get_col()? as u32, mv.get_row()? as u32);
+    Some((
+        MARGIN + col * SQUARE + SQUARE / 2,
+        MARGIN + row * SQUARE + SQUARE / 2,
+    ))
+}
+
+/// Draw the given game as an SVG document.
+pub fn render_svg(game: &Game, options: &RenderOptions) -> String {
+    let size = 2 * MARGIN + 8 * SQUARE;
+    let mut svg = String::new();
+
+    // writing to a String can not fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#
+    );
+    let _ = writeln!(
+        svg,
+        r##"<defs><marker id="arrow" markerWidth="6" markerHeight="6" refX="3" refY="3" orient="auto"><path d="M0,0 L6,3 L0,6 z" fill="{ARROW_COLOR}"/></marker></defs>"##
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{size}" height="{size}" fill="white"/>"#
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{MARGIN}" y="{MARGIN}" width="{}" height="{}" fill="{BOARD_COLOR}"/>"#,
+        8 * SQUARE,
+        8 * SQUARE
+    );
+
+    if let Some((x, y)) = options.chosen.and_then(center) {
+        let _ = writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{SQUARE}" height="{SQUARE}" fill="{HIGHLIGHT_COLOR}" opacity="0.6"/>"#,
+            x - SQUARE / 2,
+            y - SQUARE / 2
+        );
+    }
+
+    // grid lines and coordinates
+    for i in 0..=8 {
+        let offset = MARGIN + i * SQUARE;
+        let end = MARGIN + 8 * SQUARE;
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{offset}" y1="{MARGIN}" x2="{offset}" y2="{end}" stroke="{LINE_COLOR}"/>"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<line x1="{MARGIN}" y1="{offset}" x2="{end}" y2="{offset}" stroke="{LINE_COLOR}"/>"#
+        );
+        if i < 8 {
+            let label = offset + SQUARE / 2;
+            let _ = writeln!(
+                svg,
+                r#"<text x="{label}" y="{}" font-size="14" text-anchor="middle">{}</text>"#,
+                MARGIN - 8,
+                (b'a' + i as u8) as char
+            );
+            let _ = writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="14" text-anchor="middle">{}</text>"#,
+                MARGIN / 2,
+                label + 5,
+                i + 1
+            );
+        }
+    }
+
+    // discs
+    let board = game.get_board();
+    for square in 0..64u64 {
+        let position = 1u64 << square;
+        let fill = if board.get_black() & position != 0 {
+            "black"
+        } else if board.get_white() & position != 0 {
+            "white"
+        } else {
+            continue;
+        };
+        if let Some((x, y)) = Move::new(position).ok().and_then(center) {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{x}" cy="{y}" r="{}" fill="{fill}" stroke="black"/>"#,
+                SQUARE * 2 / 5
+            );
+        }
+    }
+
+    if options.show_legal_moves {
+        for (x, y) in game.legal_moves().into_iter().filter_map(center) {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{x}" cy="{y}" r="4" fill="{LINE_COLOR}"/>"#
+            );
+        }
+    }
+
+    // principal variation, each arrow points from the previous move to the next
+    let mut previous = None;
+    for (i, (x, y)) in options.pv.iter().filter_map(|mv| center(*mv)).enumerate() {
+        if let Some((px, py)) = previous {
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{px}" y1="{py}" x2="{x}" y2="{y}" stroke="{ARROW_COLOR}" stroke-width="3" marker-end="url(#arrow)"/>"#
+            );
+        }
+        let _ = writeln!(
+            svg,
+            r#"<text x="{x}" y="{}" font-size="16" font-weight="bold" fill="{ARROW_COLOR}" text-anchor="middle">{}</text>"#,
+            y + 6,
+            i + 1
+        );
+        previous = Some((x, y));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Draw the heatmap as an SVG document, shading each square from white for zero to red for
+/// the largest value and labelling it with its value as a percentage.
+pub fn render_heatmap(heatmap: &Heatmap, title: &str) -> String {
+    let size = 2 * MARGIN + 8 * SQUARE;
+    let max = heatmap.max();
+    let mut svg = String::new();
+
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{size}" height="{}" viewBox="0 0 {size} {}">"#,
+        size + MARGIN,
+        size + MARGIN
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect width="{size}" height="{}" fill="white"/>"#,
+        size + MARGIN
+    );
+    for (square, value) in heatmap.values.iter().enumerate() {
+        let (col, row) = (square as u32 % 8, square as u32 / 8);
+        let (x, y) = (MARGIN + col * SQUARE, MARGIN + row * SQUARE);
+        let intensity = if max > 0.0 { value / max } else { 0.0 };
+        // fade green and blue out of white to get to red
+        let shade = (255.0 * (1.0 - intensity)).round() as u8;
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{x}" y="{y}" width="{SQUARE}" height="{SQUARE}" fill="#ff{shade:02x}{shade:02x}" stroke="{LINE_COLOR}"/>"##
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="12" text-anchor="middle">{:.0}</text>"#,
+            x + SQUARE / 2,
+            y + SQUARE / 2 + 4,
+            value * 100.0
+        );
+    }
+    for i in 0..8 {
+        let label = MARGIN + i * SQUARE + SQUARE / 2;
+        let _ = writeln!(
+            svg,
+            r#"<text x="{label}" y="{}" font-size="14" text-anchor="middle">{}</text>"#,
+            MARGIN - 8,
+            (b'a' + i as u8) as char
+        );
+        let _ = writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="14" text-anchor="middle">{}</text>"#,
+            MARGIN / 2,
+            label + 5,
+            i + 1
+        );
+    }
+    let _ = writeln!(
+        svg,
+        r#"<text x="{}" y="{}" font-size="14" text-anchor="middle">{}</text>"#,
+        size / 2,
+        size + MARGIN / 2,
+        title
+    );
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_initial_position() {
+        let game = Game::new();
+        let svg = render_svg(&game, &RenderOptions::default());
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches(r#"fill="black" stroke"#).count(), 2);
+        assert_eq!(svg.matches(r#"fill="white" stroke"#).count(), 2);
+    }
+
+    #[test]
+    fn test_render_options() {
+        let game = Game::new();
+        let legal_moves = game.legal_moves();
+        let mut sim_game = game;
+        sim_game.play_next_turn(legal_moves[0]).unwrap();
+        let reply = sim_game.legal_moves()[0];
+
+        let options = RenderOptions {
+            chosen: Some(legal_moves[0]),
+            pv: vec![legal_moves[0], reply],
+            show_legal_moves: true,
+        };
+        let svg = render_svg(&game, &options);
+        assert_eq!(svg.matches(HIGHLIGHT_COLOR).count(), 1);
+        assert_eq!(svg.matches("marker-end").count(), 1);
+        assert_eq!(svg.matches(r#"r="4""#).count(), legal_moves.len());
+    }
+
+    #[test]
+    fn test_render_heatmap() {
+        let mut values = [0.0; 64];
+        values[0] = 0.5;
+        values[63] = 0.25;
+        let svg = render_heatmap(&Heatmap { values }, "corners");
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<rect x=").count(), 64);
+        assert_eq!(svg.matches(r##"fill="#ff0000""##).count(), 1);
+        assert_eq!(svg.matches(r##"fill="#ffffff""##).count(), 62);
+        assert!(svg.contains(">50</text>") && svg.contains(">corners</text>"));
+    }
+}
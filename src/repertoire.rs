@@ -0,0 +1,230 @@
+//! Restricting Herb to a repertoire of opening lines.
+//!
+//! For training variety or themed exhibition matches Herb can be told to play only certain
+//! openings, e.g. only the diagonal opening. The [`Repertoire`] is part of the
+//! [`Config`](crate::config::Config):
+//! ```json
+//! "repertoire": {
+//!     "plies": 6,
+//!     "lines": [
+//!         { "moves": "d3 c3 c4 e3", "weight": 2.0 },
+//!         { "moves": "d3 c5 d6 e3" }
+//!     ]
+//! }
+//! ```
+//! Moves use the algebraic notation of [`record`](crate::record). A line also covers its 7
+//! rotations and reflections, so `d3 c3` stands for every diagonal opening whatever Black's
+//! first move. For the first `plies` plies, whenever the position is on one or more lines
+//! Herb plays the next move of one of them, chosen at random in proportion to the line
+//! weights. Once the opponent leaves every line, or the lines run out, Herb searches as usual.
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::othello::{Game, Move, Symmetry};
+use crate::record::parse_move;
+
+/// An opening line and how often to choose it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RepertoireLine {
+    /// Moves from the starting position separated by spaces, e.g. `d3 c3 c4`.
+    pub moves: String,
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl RepertoireLine {
+    /// Returns the moves of the line, `None` if any of them can not be parsed.
+    pub fn parsed_moves(&self) -> Option<Vec<Move>> {
+        self.moves
+            .split_whitespace()
+            .map(|token| parse_move(token).ok())
+            .collect()
+    }
+}
+
+/// Opening lines Herb is restricted to for the first plies of a game.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Repertoire {
+    /// Number of plies from the start the repertoire is enforced for.
+    #[serde(default)]
+    pub plies: usize,
+    #[serde(default)]
+    pub lines: Vec<RepertoireLine>,
+}
+
+impl Repertoire {
+    /// Returns every line that can not be played from the starting position.
+    pub fn invalid_lines(&self) -> Vec<&RepertoireLine> {
+        self.lines
+            .iter()
+            .filter(|line| {
+                let Some(moves) = line.parsed_moves() else {
+                    return true;
+                };
+                let mut game = Game::new();
+                moves.iter().any(|mv| game.play_next_turn(*mv).is_err())
+            })
+            .collect()
+    }
+
+    /// Returns the number of plies from the start the repertoire can cover, the enforced plies
+    /// or the longest valid line if that is shorter. Time is not planned for these plies.
+    pub fn book_plies(&self) -> usize {
+        let invalid = self.invalid_lines();
+        self.lines
+            .iter()
+            .filter(|line| !invalid.contains(line))
+            .filter_map(|line| line.parsed_moves())
+            .map(|moves| moves.len())
+            .max()
+            .unwrap_or(0)
+            .min(self.plies)
+    }
+
+    /// Returns the moves the repertoire allows in the given position with their weights,
+    /// empty if the repertoire does not apply.
+    pub fn candidates(&self, game: &Game) -> Vec<(Move, f64)> {
+        let ply = game.get_turn();
+        if ply >= self.plies {
+            return Vec::new();
+        }
+        let mut candidates: Vec<(Move, f64)> = Vec::new();
+        for line in &self.lines {
+            let Some(moves) = line.parsed_moves() else {
+                continue;
+            };
+            if moves.len() <= ply || line.weight <= 0.0 {
+                continue;
+            }
+            let mut position = Game::new();
+            if moves[..ply]
+                .iter()
+                .any(|mv| position.play_next_turn(*mv).is_err())
+            {
+                continue;
+            }
+            // at most one symmetry of a line can add a given move
+            let mut added: Vec<Move> = Vec::new();
+            for symmetry in Symmetry::ALL {
+                let transformed = position.transform(symmetry);
+                let mv = moves[ply].transform(symmetry);
+                let same = transformed.get_board() == game.get_board()
+                    && transformed.to_move() == game.to_move();
+                if !same || added.contains(&mv) {
+                    continue;
+                }
+                added.push(mv);
+                match candidates
+                    .iter_mut()
+                    .find(|(candidate, _)| *candidate == mv)
+                {
+                    Some((_, weight)) => *weight += line.weight,
+                    None => candidates.push((mv, line.weight)),
+                }
+            }
+        }
+        candidates
+    }
+
+    /// Choose a move from the repertoire for the given position with the given random number
+    /// generator, `None` if the repertoire does not apply and the move should be searched for.
+    pub fn choose(&self, game: &Game, rng: &mut impl Rng) -> Option<Move> {
+        let candidates = self.candidates(game);
+        let legal_moves = game.legal_moves();
+        let candidates: Vec<_> = candidates
+            .into_iter()
+            .filter(|(mv, _)| legal_moves.contains(mv))
+            .collect();
+        let index = WeightedIndex::new(candidates.iter().map(|(_, weight)| *weight)).ok()?;
+        Some(candidates[index.sample(rng)].0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn diagonal() -> Repertoire {
+        Repertoire {
+            plies: 4,
+            lines: vec![
+                RepertoireLine {
+                    moves: "d3 c3 c4".to_string(),
+                    weight: 1.0,
+                },
+                RepertoireLine {
+                    moves: "not a line".to_string(),
+                    weight: 1.0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_candidates() {
+        let repertoire = diagonal();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        assert_eq!(repertoire.invalid_lines().len(), 1);
+
+        // black may open on any of the four symmetric squares
+        let game = Game::new();
+        let candidates = repertoire.candidates(&game);
+        assert_eq!(candidates.len(), 4);
+        let mut legal_moves = game.legal_moves();
+        legal_moves.sort_by_key(|mv| mv.get_position());
+        let mut moves: Vec<_> = candidates.iter().map(|(mv, _)| *mv).collect();
+        moves.sort_by_key(|mv| mv.get_position());
+        assert_eq!(moves, legal_moves);
+
+        // white answers diagonally whichever square black opened on
+        for opening in game.legal_moves() {
+            let mut game = game;
+            game.play_next_turn(opening).unwrap();
+            let candidates = repertoire.candidates(&game);
+            assert_eq!(candidates.len(), 1);
+            let reply = repertoire.choose(&game, &mut rng).unwrap();
+            game.play_next_turn(reply).unwrap();
+            assert_eq!(repertoire.candidates(&game).len(), 1);
+        }
+    }
+
+    #[test]
+    fn test_off_book_and_out_of_plies() {
+        let repertoire = diagonal();
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut game = Game::new();
+        game.play_next_turn(parse_move("d3").unwrap()).unwrap();
+        // the perpendicular reply leaves the line
+        game.play_next_turn(parse_move("e3").unwrap()).unwrap();
+        assert_eq!(repertoire.choose(&game, &mut rng), None);
+
+        let short = Repertoire {
+            plies: 1,
+            ..diagonal()
+        };
+        let mut game = Game::new();
+        game.play_next_turn(parse_move("d3").unwrap()).unwrap();
+        assert_eq!(short.choose(&game, &mut rng), None);
+        assert!(short.choose(&Game::new(), &mut rng).is_some());
+    }
+
+    #[test]
+    fn test_book_plies() {
+        // the invalid line does not count, the valid one is shorter than the plies
+        assert_eq!(diagonal().book_plies(), 3);
+        let short = Repertoire {
+            plies: 2,
+            ..diagonal()
+        };
+        assert_eq!(short.book_plies(), 2);
+        assert_eq!(Repertoire::default().book_plies(), 0);
+    }
+}
@@ -0,0 +1,206 @@
+//! How the search scales with the number of threads.
+//!
+//! Herb searches a move on several threads by root parallelization: every thread grows a
+//! tree of its own from the same position and the trees are merged when time is up. Whether
+//! more threads pay off depends on the machine, so [`report`] measures it: the same position
+//! is searched for the same time on 1, 2, 4, ... threads with each [`Strategy`], and every
+//! [`ScalingRow`] gives the iterations per second relative to a single thread, the effective
+//! speedup, and the visits to the root moves of the merged tree, a proxy for the strength of
+//! the move chosen from it.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::cache::EvalCache;
+use crate::config::MctsConfig;
+use crate::mcts::Tree;
+use crate::othello::Game;
+use crate::random::RngBackend;
+
+/// Static evaluations cached by [`Strategy::SharedCache`], as many as Herb's default.
+const CACHE_SIZE: usize = 1 << 20;
+
+/// How the threads of a search share their work.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    /// Every thread has a tree of its own and nothing else is shared until the merge.
+    Independent,
+    /// Every thread has a tree of its own, and all of them share one cache of static
+    /// evaluations, as Herb does with `eval_cache` set.
+    SharedCache,
+}
+
+impl Strategy {
+    /// Every strategy.
+    pub const ALL: [Strategy; 2] = [Strategy::Independent, Strategy::SharedCache];
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        let name = match self {
+            Strategy::Independent => "independent",
+            Strategy::SharedCache => "shared-cache",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The numbers of threads a report measures: the powers of two up to `max`, and `max`.
+pub fn thread_counts(max: usize) -> Vec<usize> {
+    let mut counts: Vec<usize> = (0..usize::BITS)
+        .map(|power| 1 << power)
+        .take_while(|&count| count <= max)
+        .collect();
+    if counts.last() != Some(&max) && max > 0 {
+        counts.push(max);
+    }
+    counts
+}
+
+/// The search of a position on a number of threads.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalingRow {
+    pub strategy: Strategy,
+    pub threads: usize,
+    /// Search iterations of all threads together.
+    pub iterations: u64,
+    pub elapsed: Duration,
+    /// The visits to the root moves of the merged tree.
+    pub root_visits: f64,
+    /// Iterations per second relative to the same strategy on a single thread.
+    pub speedup: f64,
+}
+
+impl ScalingRow {
+    /// Returns the iterations per second of all threads together.
+    pub fn iterations_per_second(&self) -> f64 {
+        self.iterations as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl Display for ScalingRow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "{:<12} {:>3} threads {:>10.0} iterations/s {:>6.2}x speedup {:>10.0} root visits",
+            self.strategy.to_string(),
+            self.threads,
+            self.iterations_per_second(),
+            self.speedup,
+            self.root_visits
+        )
+    }
+}
+
+/// Search the game on `threads` threads for `duration`, with a tree for each thread, and
+/// return the merged tree.
+pub fn search(
+    game: Game,
+    config: &MctsConfig,
+    strategy: Strategy,
+    threads: usize,
+    duration: Duration,
+) -> Tree {
+    let cache = (strategy == Strategy::SharedCache).then(|| Arc::new(EvalCache::new(CACHE_SIZE)));
+    let start = Instant::now();
+    let trees: Vec<Tree> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.max(1))
+            .map(|index| {
+                let cache = cache.clone();
+                scope.spawn(move || {
+                    let mut tree = Tree::from_config(config.clone());
+                    tree.set_rng(RngBackend::ChaCha.stream(0, 0, index as u32));
+                    if let Some(cache) = cache {
+                        tree.set_eval_cache(cache);
+                    }
+                    while start.elapsed() < duration {
+                        tree.search(game);
+                    }
+                    tree
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("search threads do not panic"))
+            .collect()
+    });
+    let mut merged = Tree::from_config(config.clone());
+    for tree in trees {
+        merged.merge(tree);
+    }
+    merged
+}
+
+/// Returns the visits to the root moves of the tree.
+pub fn root_visits(tree: &Tree, game: Game) -> f64 {
+    game.legal_moves_iter()
+        .filter_map(|mv| {
+            let mut child = game;
+            child.play_next_turn(mv).ok()?;
+            tree.map.get(&child.zobrist_hash()).map(|node| node.visits)
+        })
+        .sum()
+}
+
+/// Search the game for `duration` with every strategy on each of the [`thread_counts`] up to
+/// `max_threads`.
+pub fn report(
+    game: Game,
+    config: &MctsConfig,
+    max_threads: usize,
+    duration: Duration,
+) -> Vec<ScalingRow> {
+    let mut rows = Vec::new();
+    for strategy in Strategy::ALL {
+        let mut single = None;
+        for threads in thread_counts(max_threads) {
+            let start = Instant::now();
+            let tree = search(game, config, strategy, threads, duration);
+            let elapsed = start.elapsed();
+            let rate = tree.search_iterations as f64 / elapsed.as_secs_f64();
+            let single = *single.get_or_insert(rate);
+            rows.push(ScalingRow {
+                strategy,
+                threads,
+                iterations: tree.search_iterations,
+                elapsed,
+                root_visits: root_visits(&tree, game),
+                speedup: rate / single,
+            });
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_counts() {
+        assert_eq!(thread_counts(1), vec![1]);
+        assert_eq!(thread_counts(4), vec![1, 2, 4]);
+        assert_eq!(thread_counts(6), vec![1, 2, 4, 6]);
+        assert!(thread_counts(0).is_empty());
+    }
+
+    #[test]
+    fn test_report() {
+        let game = Game::new();
+        let rows = report(game, &MctsConfig::default(), 2, Duration::from_millis(20));
+        assert_eq!(rows.len(), 2 * Strategy::ALL.len());
+        for row in &rows {
+            assert!(row.iterations > 0);
+            // every iteration visits one root move, or adds one as a node of its own
+            assert!(row.root_visits > 0.0 && row.root_visits <= row.iterations as f64 + 4.0);
+        }
+        assert_eq!(rows[0].speedup, 1.0);
+        assert_eq!(
+            (rows[1].strategy, rows[1].threads),
+            (Strategy::Independent, 2)
+        );
+        assert_eq!(rows[2].strategy, Strategy::SharedCache);
+    }
+}
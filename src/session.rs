@@ -0,0 +1,225 @@
+//! Recordings of the raw referee protocol.
+//!
+//! When `session` is set in the [`Config`](crate::config::Config), every line sent to and
+//! received from the referee is appended to the session file as it happens, exactly as it was
+//! written or read, along with the seconds since the session started:
+//! ```text
+//! {"elapsed":0.0,"direction":"received","data":"I B\n"}
+//! {"elapsed":0.001,"direction":"sent","data":"R B\n"}
+//! {"elapsed":1.942,"direction":"sent","data":"B d 3\n"}
+//! ```
+//! A session settles what was actually said when a game goes wrong, and [`replay`] feeds the
+//! referee's side of it back into an engine to reproduce the game, see `herb-replay`.
+use std::fs::{self, File};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+/// Which way a line of the protocol went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// From Herb to the referee.
+    Sent,
+    /// From the referee to Herb.
+    Received,
+}
+
+/// One line of a session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionEntry {
+    /// Seconds since the session started.
+    pub elapsed: f64,
+    pub direction: Direction,
+    /// The line including its line ending, if it had one.
+    pub data: String,
+}
+
+/// Appends the lines of a session to a file.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    start: Instant,
+    file: Mutex<File>,
+}
+
+impl SessionRecorder {
+    /// Create the session file, replacing any existing one.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(SessionRecorder {
+            start: Instant::now(),
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+
+    /// Append a line to the session. Every line is written straight away so nothing is lost
+    /// if Herb is killed by the referee.
+    pub fn record(&self, direction: Direction, data: &str) -> io::Result<()> {
+        let entry = SessionEntry {
+            elapsed: self.start.elapsed().as_secs_f64(),
+            direction,
+            data: data.to_string(),
+        };
+        let line = serde_json::to_string(&entry)? + "\n";
+        self.file.lock().unwrap().write_all(line.as_bytes())
+    }
+}
+
+static RECORDER: OnceLock<SessionRecorder> = OnceLock::new();
+
+/// Start recording the referee protocol of this process to the given file.
+///
+/// Returns an error if the file can not be created or a session is already being recorded.
+pub fn start(path: &Path) -> io::Result<()> {
+    RECORDER
+        .set(SessionRecorder::create(path)?)
+        .map_err(|_| io::Error::new(io::ErrorKind::AlreadyExists, "Session already started"))
+}
+
+/// Record a line of the referee protocol if a session was started. Failing to record never
+/// interrupts a game.
+pub(crate) fn record(direction: Direction, data: &str) {
+    if let Some(recorder) = RECORDER.get() {
+        let _ = recorder.record(direction, data);
+    }
+}
+
+/// Read a session file.
+pub fn load(path: &Path) -> io::Result<Vec<SessionEntry>> {
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// A reply from the engine that differs from the one in the session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// The index of the recorded reply in the session.
+    pub index: usize,
+    /// The reply in the session, without its line ending.
+    pub expected: String,
+    /// The engine's reply, `None` if it stopped replying.
+    pub actual: Option<String>,
+}
+
+/// Feed the referee's lines of a session to an engine and compare its replies with the
+/// recorded ones. Comments are free text, so they are neither compared nor waited for.
+///
+/// The engine is sent every received line in order, and before each recorded reply the next
+/// reply is read from the engine's output. Returns every reply that differs, stopping at the
+/// first one missing because the engine stopped writing.
+pub fn replay(
+    entries: &[SessionEntry],
+    mut engine_input: impl Write,
+    mut engine_output: impl BufRead,
+) -> io::Result<Vec<Divergence>> {
+    let mut divergences = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        match entry.direction {
+            Direction::Received => {
+                // an engine that has exited can not be told anything more
+                if engine_input.write_all(entry.data.as_bytes()).is_err() {
+                    break;
+                }
+                engine_input.flush()?;
+            }
+            Direction::Sent if is_comment(&entry.data) => {}
+            Direction::Sent => {
+                let expected = entry.data.trim_end_matches(['\r', '\n']).to_string();
+                let actual = read_reply(&mut engine_output)?;
+                if actual.as_deref() != Some(expected.as_str()) {
+                    let stopped = actual.is_none();
+                    divergences.push(Divergence {
+                        index,
+                        expected,
+                        actual,
+                    });
+                    if stopped {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    Ok(divergences)
+}
+
+fn is_comment(line: &str) -> bool {
+    line.trim().is_empty() || line.starts_with('C')
+}
+
+/// Read the engine's next line that is not a comment, `None` at the end of its output.
+fn read_reply(output: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if output.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !is_comment(&line) {
+            return Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(direction: Direction, data: &str) -> SessionEntry {
+        SessionEntry {
+            elapsed: 0.0,
+            direction,
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session.jsonl");
+        let recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(Direction::Received, "I B\r\n").unwrap();
+        recorder.record(Direction::Sent, "R B\n").unwrap();
+
+        let entries = load(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].direction, Direction::Received);
+        assert_eq!(entries[0].data, "I B\r\n");
+        assert_eq!(entries[1].data, "R B\n");
+        assert!(entries[1].elapsed >= entries[0].elapsed);
+    }
+
+    #[test]
+    fn test_replay() {
+        let entries = vec![
+            entry(Direction::Received, "I B\n"),
+            entry(Direction::Sent, "R B\n"),
+            entry(Direction::Sent, "C thinking\n"),
+            entry(Direction::Sent, "B d 3\n"),
+            entry(Direction::Received, "W c 3\n"),
+            entry(Direction::Sent, "B c 4\n"),
+        ];
+        let mut input = Vec::new();
+        let output = "R B\nC different thoughts\nB d 3\nB e 6\n".as_bytes();
+        let divergences = replay(&entries, &mut input, output).unwrap();
+        assert_eq!(String::from_utf8(input).unwrap(), "I B\nW c 3\n");
+        assert_eq!(
+            divergences,
+            vec![Divergence {
+                index: 5,
+                expected: "B c 4".to_string(),
+                actual: Some("B e 6".to_string()),
+            }]
+        );
+
+        // an engine that stops replying
+        let divergences = replay(&entries, Vec::new(), "R B\n".as_bytes()).unwrap();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].actual, None);
+    }
+}
@@ -0,0 +1,247 @@
+//! Snapshots of the root of a search tree, and the differences between two of them.
+//!
+//! A [`TreeSnapshot`] records the visits, wins and evaluation of every move at the root of a
+//! searched position. Capturing the same position with the same budget at two points, e.g.
+//! before and after a change, or from two versions of Herb, and diffing the snapshots shows
+//! which statistics moved, which answers questions like "why did the new version stop
+//! playing c4 here?". Snapshots are stored as JSON so they can be captured by one build and
+//! compared by another.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::formats::{self, Header, VersionedReader};
+use crate::mcts::Tree;
+use crate::othello::Game;
+use crate::record::format_move;
+
+/// The format of snapshot files.
+pub const FORMAT: Header = Header::new("snapshot", 1);
+
+/// The statistics of a move at the root of a tree.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveStats {
+    /// The move in algebraic notation, e.g. `d3`.
+    pub mv: String,
+    pub visits: f64,
+    pub wins: f64,
+    /// The evaluation the move is chosen by.
+    pub value: f64,
+}
+
+/// The root statistics of a searched position.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TreeSnapshot {
+    /// What the snapshot was taken of, e.g. a version or branch name.
+    pub label: String,
+    pub position: Game,
+    /// Search iterations in the tree.
+    pub iterations: u64,
+    /// The move the tree would play.
+    pub best: String,
+    pub moves: Vec<MoveStats>,
+}
+
+impl TreeSnapshot {
+    /// Record the statistics of every legal move in the position.
+    pub fn capture(tree: &Tree, game: Game, label: &str) -> Self {
+        let moves = game
+            .legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let mut child = game;
+                child.play_next_turn(mv).unwrap();
+                let (visits, wins) = tree
+                    .map
+                    .get(&child.zobrist_hash())
+                    .map_or((0.0, 0.0), |node| (node.visits, node.wins));
+                MoveStats {
+                    mv: format_move(mv),
+                    visits,
+                    wins,
+                    value: tree.evaluate(child),
+                }
+            })
+            .collect();
+        TreeSnapshot {
+            label: label.to_string(),
+            position: game,
+            iterations: tree.search_iterations,
+            best: format_move(tree.best_move(game)),
+            moves,
+        }
+    }
+
+    /// Read a snapshot written by [`TreeSnapshot::save`], also by an older build.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        VersionedReader::new(FORMAT)
+            .migration(0, Ok)
+            .read_file(path)
+    }
+
+    /// Write the snapshot as JSON, with a [`formats`] header.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        formats::save(path, FORMAT, self)
+    }
+
+    /// Returns the statistics of the move, if it is legal.
+    pub fn get(&self, mv: &str) -> Option<&MoveStats> {
+        self.moves.iter().find(|stats| stats.mv == mv)
+    }
+
+    /// Returns the fraction of the visits to the root's children that went to the move.
+    pub fn visit_share(&self, mv: &str) -> f64 {
+        let total: f64 = self.moves.iter().map(|stats| stats.visits).sum();
+        match self.get(mv) {
+            Some(stats) if total > 0.0 => stats.visits / total,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Errors from diffing snapshots.
+#[derive(Debug, PartialEq)]
+pub enum SnapshotError {
+    /// The snapshots are of different positions.
+    DifferentPositions,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            SnapshotError::DifferentPositions => {
+                write!(f, "The snapshots are of different positions")
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// The differences between two snapshots of the same position.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SnapshotDiff<'a> {
+    pub before: &'a TreeSnapshot,
+    pub after: &'a TreeSnapshot,
+}
+
+impl<'a> SnapshotDiff<'a> {
+    /// Compare two snapshots, returns an error if they are of different positions.
+    pub fn new(before: &'a TreeSnapshot, after: &'a TreeSnapshot) -> Result<Self, SnapshotError> {
+        if before.position.get_board() != after.position.get_board()
+            || before.position.to_move() != after.position.to_move()
+        {
+            return Err(SnapshotError::DifferentPositions);
+        }
+        Ok(SnapshotDiff { before, after })
+    }
+
+    /// Returns the moves ordered by how much their share of the visits changed, largest
+    /// change first.
+    pub fn moves_by_change(&self) -> Vec<&str> {
+        let mut moves: Vec<&str> = self.after.moves.iter().map(|s| s.mv.as_str()).collect();
+        let change = |mv: &str| (self.after.visit_share(mv) - self.before.visit_share(mv)).abs();
+        moves.sort_by(|a, b| change(b).total_cmp(&change(a)));
+        moves
+    }
+}
+
+impl Display for SnapshotDiff<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        writeln!(
+            f,
+            "before: {} ({} iterations, plays {})",
+            self.before.label, self.before.iterations, self.before.best
+        )?;
+        writeln!(
+            f,
+            "after:  {} ({} iterations, plays {})",
+            self.after.label, self.after.iterations, self.after.best
+        )?;
+        writeln!(
+            f,
+            "{:<5} {:>15} {:>17} {:>17}",
+            "move", "visit share", "win rate", "value"
+        )?;
+        for mv in self.moves_by_change() {
+            let before = self.before.get(mv);
+            let after = self.after.get(mv);
+            let win_rate = |stats: Option<&MoveStats>| {
+                stats.map_or(0.0, |s| {
+                    if s.visits > 0.0 {
+                        s.wins / s.visits
+                    } else {
+                        0.0
+                    }
+                })
+            };
+            let value = |stats: Option<&MoveStats>| stats.map_or(0.0, |s| s.value);
+            let marker = match (mv == self.before.best, mv == self.after.best) {
+                (true, true) => "",
+                (true, false) => " -best",
+                (false, true) => " +best",
+                (false, false) => "",
+            };
+            writeln!(
+                f,
+                "{:<5} {:>6.1}% {:>6.1}% {:>7.3} {:>7.3}  {:>7.2} {:>7.2}{}",
+                mv,
+                self.before.visit_share(mv) * 100.0,
+                self.after.visit_share(mv) * 100.0,
+                win_rate(before),
+                win_rate(after),
+                value(before),
+                value(after),
+                marker
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let game = Game::new();
+        let mut tree = Tree::new();
+        tree.search_n(game, 20);
+        let before = TreeSnapshot::capture(&tree, game, "20");
+        tree.search_n(game, 200);
+        let after = TreeSnapshot::capture(&tree, game, "220");
+
+        assert_eq!(before.moves.len(), 4);
+        assert_eq!(after.iterations, 220);
+        let shares: f64 = after.moves.iter().map(|s| after.visit_share(&s.mv)).sum();
+        assert!((shares - 1.0).abs() < 1e-9);
+        assert_eq!(after.visit_share("a1"), 0.0);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.json");
+        after.save(&path).unwrap();
+        let loaded = TreeSnapshot::load(&path).unwrap();
+        assert_eq!(loaded.position, after.position);
+        for (loaded, saved) in loaded.moves.iter().zip(&after.moves) {
+            assert_eq!((&loaded.mv, loaded.visits), (&saved.mv, saved.visits));
+            assert!((loaded.value - saved.value).abs() < 1e-9);
+        }
+
+        let diff = SnapshotDiff::new(&before, &after).unwrap();
+        assert_eq!(diff.moves_by_change().len(), 4);
+        let report = diff.to_string();
+        assert!(report.starts_with("before: 20 (20 iterations"));
+        assert_eq!(report.lines().count(), 7);
+
+        let mut moved = game;
+        moved.play_next_turn(game.legal_moves()[0]).unwrap();
+        let other = TreeSnapshot::capture(&tree, moved, "other");
+        assert_eq!(
+            SnapshotDiff::new(&before, &other),
+            Err(SnapshotError::DifferentPositions)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+//! Read-only live feed of games in progress.
+//!
+//! A [`SpectatorFeed`] serves [server-sent events] over plain HTTP so a browser or `curl`
+//! can watch games as they are played without being able to interfere with the engines:
+//! ```text
+//! curl -N http://localhost:8080/
+//! ```
+//! Every update is sent as one json encoded [`SpectatorEvent`]. Clients that connect in the
+//! middle of a game are sent the latest update straight away.
+//!
+//! [server-sent events]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::openings;
+use crate::othello::{Color, Game, Move};
+
+/// One update of a game in progress.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpectatorEvent {
+    /// Identifies the game when several are played at once.
+    pub game_id: usize,
+    pub black: u64,
+    pub white: u64,
+    pub to_move: Color,
+    pub turn: usize,
+    pub last_move: Option<Move>,
+    /// Seconds left on black's clock, if known.
+    pub black_clock: Option<f64>,
+    /// Seconds left on white's clock, if known.
+    pub white_clock: Option<f64>,
+    /// The last mover's evaluation of its move, if known.
+    pub eval: Option<f64>,
+    /// The name of the [opening](crate::openings) the game started with, if known.
+    #[serde(default)]
+    pub opening: Option<String>,
+    pub game_over: bool,
+}
+
+impl SpectatorEvent {
+    /// Create an event for the given position without clocks or evaluation. The opening is
+    /// only named while the position is a named one.
+    pub fn new(game_id: usize, game: &Game) -> Self {
+        let board = game.get_board();
+        SpectatorEvent {
+            game_id,
+            black: board.get_black(),
+            white: board.get_white(),
+            to_move: game.to_move(),
+            turn: game.get_turn(),
+            last_move: game.last_move(),
+            black_clock: None,
+            white_clock: None,
+            eval: None,
+            opening: openings::name(game).map(str::to_string),
+            game_over: game.is_over(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Clients {
+    streams: Vec<TcpStream>,
+    latest: Option<String>,
+}
+
+/// Broadcasts [`SpectatorEvent`]s to every connected client.
+#[derive(Clone)]
+pub struct SpectatorFeed {
+    clients: Arc<Mutex<Clients>>,
+    address: SocketAddr,
+}
+
+impl SpectatorFeed {
+    /// Start listening on the given address. Clients are accepted on a background thread
+    /// for as long as the program runs.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let feed = SpectatorFeed {
+            clients: Arc::new(Mutex::new(Clients::default())),
+            address: listener.local_addr()?,
+        };
+
+        let clients = Arc::clone(&feed.clients);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let _ = accept(stream, &clients);
+            }
+        });
+        Ok(feed)
+    }
+
+    /// Returns the address the feed is listening on.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Returns the number of connected clients.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().streams.len()
+    }
+
+    /// Send an event to every client, dropping the clients that have disconnected.
+    pub fn broadcast(&self, event: &SpectatorEvent) {
+        let Ok(data) = serde_json::to_string(event) else {
+            return;
+        };
+        let message = format!("data: {}\n\n", data);
+        let mut clients = self.clients.lock().unwrap();
+        clients
+            .streams
+            .retain_mut(|stream| stream.write_all(message.as_bytes()).is_ok());
+        clients.latest = Some(message);
+    }
+}
+
+/// Read the client's request and answer with the event stream headers.
+fn accept(stream: TcpStream, clients: &Mutex<Clients>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    // skip the request, spectators have nothing to say
+    while reader.read_line(&mut line)? > 0 && line != "\r\n" && line != "\n" {
+        line.clear();
+    }
+
+    let mut stream = stream;
+    stream.write_all(
+        b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\nAccess-Control-Allow-Origin: *\r\n\r\n",
+    )?;
+
+    let mut clients = clients.lock().unwrap();
+    if let Some(latest) = &clients.latest {
+        stream.write_all(latest.as_bytes())?;
+    }
+    clients.streams.push(stream);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_broadcast_to_client() {
+        let feed = SpectatorFeed::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(feed.local_addr()).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .unwrap();
+        while feed.clients() == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+
+        let game = Game::new();
+        feed.broadcast(&SpectatorEvent::new(3, &game));
+
+        let mut reader = BufReader::new(client);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            if line.starts_with("data: ") {
+                break;
+            }
+        }
+        let event: SpectatorEvent = serde_json::from_str(line["data: ".len()..].trim()).unwrap();
+        assert_eq!(event, SpectatorEvent::new(3, &game));
+    }
+}
@@ -0,0 +1,166 @@
+//! Strength estimates against fixed reference configurations.
+//!
+//! During a training run the current configuration is checked now and then with a quick
+//! match against each of a few frozen reference snapshots. Every match is turned into an Elo
+//! difference with a 95% error margin and appended to a CSV log, one row per reference:
+//! ```text
+//! time,label,reference,games,wins,losses,draws,score,elo,margin
+//! 1760486400,v12,baseline,20,13,6,1,0.675,127.0,163.2
+//! ```
+//! Plotting `elo` against `time` for each reference shows a regression in the training loop
+//! within a few checks, while the margin says how much of a change is just noise.
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::tournament::MatchScore;
+
+/// The CSV header of a strength log.
+pub const HEADER: &str = "time,label,reference,games,wins,losses,draws,score,elo,margin";
+
+/// Returns the Elo difference that the given score corresponds to.
+///
+/// A perfect or zero score would be infinitely far apart, so the score is kept half a game
+/// away from either end.
+pub fn elo_difference(score: &MatchScore) -> f64 {
+    let games = score.games().max(1) as f64;
+    let margin = 0.5 / games;
+    let s = score.score().clamp(margin, 1.0 - margin);
+    400.0 * (s / (1.0 - s)).log10()
+}
+
+/// Returns the half width of the 95% confidence interval of [`elo_difference`].
+pub fn elo_margin(score: &MatchScore) -> f64 {
+    let games = score.games().max(1) as f64;
+    let margin = 0.5 / games;
+    let s = score.score().clamp(margin, 1.0 - margin);
+    let standard_error = (s * (1.0 - s) / games).sqrt();
+    // slope of the Elo curve at s
+    let slope = 400.0 / (std::f64::consts::LN_10 * s * (1.0 - s));
+    1.96 * standard_error * slope
+}
+
+/// The estimated strength of a configuration relative to one reference.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StrengthEstimate {
+    /// Seconds since the Unix epoch when the match finished.
+    pub time: u64,
+    /// Name of the configuration being measured, e.g. its version.
+    pub label: String,
+    /// Name of the reference snapshot.
+    pub reference: String,
+    /// Score from the measured configuration's perspective.
+    pub score: MatchScore,
+}
+
+impl StrengthEstimate {
+    /// Create an estimate from a match that just finished.
+    pub fn new(label: &str, reference: &str, score: MatchScore) -> Self {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_secs());
+        StrengthEstimate {
+            time,
+            label: label.to_string(),
+            reference: reference.to_string(),
+            score,
+        }
+    }
+
+    /// Returns the Elo difference to the reference, positive if the configuration is
+    /// stronger.
+    pub fn elo(&self) -> f64 {
+        elo_difference(&self.score)
+    }
+
+    /// Returns the estimate as a row of the strength log.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{:.3},{:.1},{:.1}",
+            self.time,
+            self.label,
+            self.reference,
+            self.score.games(),
+            self.score.wins,
+            self.score.losses,
+            self.score.draws,
+            self.score.score(),
+            self.elo(),
+            elo_margin(&self.score)
+        )
+    }
+}
+
+/// Append the estimates to the strength log at the given path, writing the header first if
+/// the log is new.
+pub fn append_log(path: &Path, estimates: &[StrengthEstimate]) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    if file.metadata()?.len() == 0 {
+        writeln!(file, "{}", HEADER)?;
+    }
+    for estimate in estimates {
+        writeln!(file, "{}", estimate.to_csv())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_elo_difference() {
+        let even = MatchScore {
+            wins: 5,
+            losses: 5,
+            draws: 0,
+        };
+        assert_eq!(elo_difference(&even), 0.0);
+
+        let three_to_one = MatchScore {
+            wins: 3,
+            losses: 1,
+            draws: 0,
+        };
+        assert!((elo_difference(&three_to_one) - 190.8).abs() < 0.1);
+        assert!(
+            elo_margin(&three_to_one)
+                > elo_margin(&MatchScore {
+                    wins: 30,
+                    losses: 10,
+                    draws: 0
+                })
+        );
+
+        let sweep = MatchScore {
+            wins: 10,
+            losses: 0,
+            draws: 0,
+        };
+        assert!(elo_difference(&sweep).is_finite());
+        assert!(elo_difference(&sweep) > elo_difference(&three_to_one));
+    }
+
+    #[test]
+    fn test_append_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("strength.csv");
+        let score = MatchScore {
+            wins: 1,
+            losses: 1,
+            draws: 0,
+        };
+        append_log(&path, &[StrengthEstimate::new("v1", "base", score)]).unwrap();
+        append_log(&path, &[StrengthEstimate::new("v2", "base", score)]).unwrap();
+
+        let log = fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], HEADER);
+        assert!(lines[2].contains(",v2,base,2,1,1,0,0.500,0.0,"));
+    }
+}
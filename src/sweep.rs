@@ -0,0 +1,178 @@
+//! Strength against the time budget.
+//!
+//! A change to the search can help at blitz speeds and hurt in long games, or the other way
+//! around, so a single match at one time control says little about it. A sweep plays a
+//! version against a reference at each of a grid of time budgets per move, from 10ms to 10s
+//! by default, both sides with the same budget, and turns every match into an Elo difference.
+//! Every match of a sweep is a [`Tournament`] with the same seed, so every version plays the
+//! same openings at every budget and the points of the curves differ by the engines alone.
+//! The engines of every game are seeded with the game's random stream of that seed.
+//!
+//! The curves are written as CSV, one row per version and budget:
+//! ```text
+//! label,budget,games,wins,losses,draws,score,elo,margin
+//! v12,0.010,20,13,6,1,0.675,127.0,163.2
+//! ```
+use std::io::{self, Write};
+
+use crate::config::{Config, Verbosity};
+use crate::strength::{elo_difference, elo_margin};
+use crate::tournament::{MatchScore, Tournament};
+use crate::Herb;
+
+/// The CSV header of a sweep.
+pub const HEADER: &str = "label,budget,games,wins,losses,draws,score,elo,margin";
+
+/// Seconds per move of a default sweep, from 10ms to 10s.
+pub const DEFAULT_BUDGETS: [f64; 7] = [0.01, 0.03, 0.1, 0.3, 1.0, 3.0, 10.0];
+
+/// Returns the configuration with every move searching for `seconds`, no warm-up and no
+/// comments. The game's clock is long enough that it never cuts a move short.
+pub fn per_move(config: &Config, seconds: f64) -> Config {
+    let mut config = config.clone();
+    config.max_time = 64.0 * seconds + 60.0;
+    config.warm_up = 0.0;
+    config.verbosity = Some(Verbosity::Silent);
+    config.time.controller.min_move_time = seconds;
+    config.time.controller.max_move_time = Some(seconds);
+    config
+}
+
+/// The result of a version against the reference at one budget.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepPoint {
+    /// Name of the version, e.g. its config file.
+    pub label: String,
+    /// Seconds per move.
+    pub budget: f64,
+    /// Score from the version's perspective.
+    pub score: MatchScore,
+}
+
+impl SweepPoint {
+    /// Returns the Elo difference to the reference, positive if the version is stronger.
+    pub fn elo(&self) -> f64 {
+        elo_difference(&self.score)
+    }
+
+    /// Returns the point as a row of the CSV.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{:.3},{},{},{},{},{:.3},{:.1},{:.1}",
+            self.label,
+            self.budget,
+            self.score.games(),
+            self.score.wins,
+            self.score.losses,
+            self.score.draws,
+            self.score.score(),
+            self.elo(),
+            elo_margin(&self.score)
+        )
+    }
+}
+
+/// The grid of a sweep.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sweep {
+    /// Seconds per move.
+    pub budgets: Vec<f64>,
+    /// Games of every match.
+    pub games: u32,
+    /// Random moves every game starts with.
+    pub opening_plies: usize,
+    pub seed: u64,
+}
+
+impl Default for Sweep {
+    fn default() -> Self {
+        Sweep {
+            budgets: DEFAULT_BUDGETS.to_vec(),
+            games: 10,
+            opening_plies: 4,
+            seed: 1,
+        }
+    }
+}
+
+impl Sweep {
+    /// Play every version against the reference at every budget, calling `on_point` with each
+    /// point as soon as its match is over.
+    pub fn run(
+        &self,
+        reference: &Config,
+        versions: &[(String, Config)],
+        mut on_point: impl FnMut(&SweepPoint),
+    ) -> io::Result<Vec<SweepPoint>> {
+        let mut points = Vec::new();
+        for (label, version) in versions {
+            for &budget in &self.budgets {
+                let version = per_move(version, budget);
+                let reference = per_move(reference, budget);
+                let mut tournament = Tournament::new(self.games, self.opening_plies, self.seed);
+                let score = tournament.run(
+                    |index| Herb::new(version.clone()).with_stream(self.seed, index as u64, 0),
+                    |index| Herb::new(reference.clone()).with_stream(self.seed, index as u64, 0),
+                    None,
+                )?;
+                let point = SweepPoint {
+                    label: label.clone(),
+                    budget,
+                    score,
+                };
+                on_point(&point);
+                points.push(point);
+            }
+        }
+        Ok(points)
+    }
+}
+
+/// Write the points as CSV, the header first.
+pub fn write_csv(mut out: impl Write, points: &[SweepPoint]) -> io::Result<()> {
+    writeln!(out, "{}", HEADER)?;
+    for point in points {
+        writeln!(out, "{}", point.to_csv())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_move() {
+        let config = per_move(&Config::default(), 0.5);
+        assert_eq!(config.time.controller.min_move_time, 0.5);
+        assert_eq!(config.time.controller.max_move_time, Some(0.5));
+        assert_eq!(config.verbosity(), Verbosity::Silent);
+        // a whole game at the budget fits on the clock
+        assert!(config.max_time > 30.0 * 0.5);
+    }
+
+    #[test]
+    fn test_sweep() {
+        let sweep = Sweep {
+            budgets: vec![0.002, 0.004],
+            games: 2,
+            opening_plies: 2,
+            seed: 3,
+        };
+        let versions = vec![("same".to_string(), Config::default())];
+        let mut seen = 0;
+        let points = sweep
+            .run(&Config::default(), &versions, |_| seen += 1)
+            .unwrap();
+        assert_eq!(seen, 2);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].budget, 0.004);
+        assert!(points.iter().all(|point| point.score.games() == 2));
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &points).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with(HEADER));
+        assert!(csv.lines().nth(1).unwrap().starts_with("same,0.002,2,"));
+    }
+}
@@ -0,0 +1,359 @@
+//! Latency histograms of the search.
+//!
+//! When `telemetry` is enabled in the [`Config`](crate::config::Config), each search thread
+//! times every MCTS iteration and each of its four phases. The timings are merged and
+//! reported after every move, which shows whether a slow turn comes from deep selects, long
+//! simulations or something else.
+//!
+//! The hit rate of the [`EvalCache`](crate::cache::EvalCache) is reported with them.
+//!
+//! Independent of the timings, every search thread counts its iterations, the nodes it
+//! created, the plies of its playouts, the mean and deepest depth of its selects and the
+//! playout moves it had to fall back on a greedy move for in a [`WorkerStats`]. The counters
+//! are cheap enough to always be on, and are added up into [`SearchStats`] once per move.
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Number of buckets, enough for about 18 minutes.
+const BUCKETS: usize = 40;
+
+/// A histogram of durations with power of two buckets in nanoseconds.
+///
+/// Bucket `i` counts durations of at least `2^(i-1)` and less than `2^i` nanoseconds, so
+/// percentiles are only accurate to within a factor of two, which is plenty to tell a
+/// microsecond select from a millisecond simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Histogram {
+            buckets: [0; BUCKETS],
+            count: 0,
+            total: Duration::ZERO,
+            max: Duration::ZERO,
+        }
+    }
+}
+
+impl Histogram {
+    /// Add a duration to the histogram.
+    pub fn record(&mut self, duration: Duration) {
+        let nanos = duration.as_nanos().min(u64::MAX as u128) as u64;
+        let bucket = (u64::BITS - nanos.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total += duration;
+        self.max = self.max.max(duration);
+    }
+
+    /// Add the counts of another histogram to this one.
+    pub fn merge(&mut self, other: &Histogram) {
+        for (bucket, count) in self.buckets.iter_mut().zip(other.buckets) {
+            *bucket += count;
+        }
+        self.count += other.count;
+        self.total += other.total;
+        self.max = self.max.max(other.max);
+    }
+
+    /// Returns the number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of the recorded durations.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            Duration::ZERO
+        } else {
+            self.total / self.count as u32
+        }
+    }
+
+    /// Returns the longest recorded duration.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns an upper bound of the given quantile, between 0 and 1, of the recorded
+    /// durations.
+    pub fn quantile(&self, quantile: f64) -> Duration {
+        let target = (quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                return Duration::from_nanos(1u64 << bucket).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+impl Display for Histogram {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "n={} mean={:?} p50<={:?} p90<={:?} p99<={:?} max={:?}",
+            self.count,
+            self.mean(),
+            self.quantile(0.5),
+            self.quantile(0.9),
+            self.quantile(0.99),
+            self.max
+        )
+    }
+}
+
+/// Latency histograms for whole MCTS iterations and each of their phases.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SearchTimings {
+    pub iteration: Histogram,
+    pub select: Histogram,
+    pub expand: Histogram,
+    pub simulate: Histogram,
+    pub backpropagate: Histogram,
+}
+
+impl SearchTimings {
+    /// Add the counts of another set of timings to this one.
+    pub fn merge(&mut self, other: &SearchTimings) {
+        self.iteration.merge(&other.iteration);
+        self.select.merge(&other.select);
+        self.expand.merge(&other.expand);
+        self.simulate.merge(&other.simulate);
+        self.backpropagate.merge(&other.backpropagate);
+    }
+
+    /// Returns each histogram with its name, whole iterations first.
+    pub fn phases(&self) -> [(&'static str, &Histogram); 5] {
+        [
+            ("iteration", &self.iteration),
+            ("select", &self.select),
+            ("expand", &self.expand),
+            ("simulate", &self.simulate),
+            ("backpropagate", &self.backpropagate),
+        ]
+    }
+}
+
+/// What a single MCTS iteration did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct IterationStats {
+    /// Nodes walked through to reach the selected leaf.
+    pub select_depth: u64,
+    /// Plies played in the playout.
+    pub playout_plies: u64,
+    /// Nodes added to the tree.
+    pub nodes_created: u64,
+    /// Playout moves the tree had no choice for, see [`Game::greedy_move`].
+    ///
+    /// [`Game::greedy_move`]: crate::othello::Game::greedy_move
+    pub fallbacks: u64,
+}
+
+/// The counters of one search thread.
+///
+/// Only the thread that owns it writes to it, so relaxed atomics are enough, and each worker's
+/// counters sit on their own cache line so the threads never contend for one.
+#[derive(Debug, Default)]
+#[repr(align(64))]
+pub struct WorkerStats {
+    iterations: AtomicU64,
+    select_depth: AtomicU64,
+    playout_plies: AtomicU64,
+    nodes_created: AtomicU64,
+    fallbacks: AtomicU64,
+    max_select_depth: AtomicU64,
+}
+
+impl WorkerStats {
+    /// Count a search iteration.
+    pub fn record(&self, iteration: &IterationStats) {
+        self.iterations.fetch_add(1, Ordering::Relaxed);
+        self.select_depth
+            .fetch_add(iteration.select_depth, Ordering::Relaxed);
+        self.playout_plies
+            .fetch_add(iteration.playout_plies, Ordering::Relaxed);
+        self.nodes_created
+            .fetch_add(iteration.nodes_created, Ordering::Relaxed);
+        self.fallbacks
+            .fetch_add(iteration.fallbacks, Ordering::Relaxed);
+        self.max_select_depth
+            .fetch_max(iteration.select_depth, Ordering::Relaxed);
+    }
+
+    /// Returns the counts so far.
+    pub fn snapshot(&self) -> SearchStats {
+        SearchStats {
+            iterations: self.iterations.load(Ordering::Relaxed),
+            select_depth: self.select_depth.load(Ordering::Relaxed),
+            playout_plies: self.playout_plies.load(Ordering::Relaxed),
+            nodes_created: self.nodes_created.load(Ordering::Relaxed),
+            fallbacks: self.fallbacks.load(Ordering::Relaxed),
+            max_select_depth: self.max_select_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Totals of search iterations and what they did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    pub iterations: u64,
+    pub select_depth: u64,
+    pub playout_plies: u64,
+    pub nodes_created: u64,
+    pub fallbacks: u64,
+    /// The deepest select of any iteration.
+    pub max_select_depth: u64,
+}
+
+impl SearchStats {
+    /// Add the counts of another set of statistics to this one.
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.iterations += other.iterations;
+        self.select_depth += other.select_depth;
+        self.playout_plies += other.playout_plies;
+        self.nodes_created += other.nodes_created;
+        self.fallbacks += other.fallbacks;
+        self.max_select_depth = self.max_select_depth.max(other.max_select_depth);
+    }
+
+    /// Returns the mean depth of the selects, 0 without iterations.
+    pub fn mean_select_depth(&self) -> f64 {
+        self.select_depth as f64 / self.iterations.max(1) as f64
+    }
+
+    /// Returns the mean number of plies of the playouts, 0 without iterations.
+    pub fn mean_playout_plies(&self) -> f64 {
+        self.playout_plies as f64 / self.iterations.max(1) as f64
+    }
+}
+
+impl Display for SearchStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "iterations={} nodes created={} mean select depth={:.1} max select depth={} \
+             mean playout plies={:.1} fallbacks={}",
+            self.iterations,
+            self.nodes_created,
+            self.mean_select_depth(),
+            self.max_select_depth,
+            self.mean_playout_plies(),
+            self.fallbacks
+        )
+    }
+}
+
+/// Lookups of a cache.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// Add the lookups of another set of statistics to this one.
+    pub fn merge(&mut self, other: &CacheStats) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+    }
+
+    /// Returns the fraction of lookups that were hits, 0 if there were none.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / lookups as f64
+        }
+    }
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        write!(
+            f,
+            "hits={} misses={} hit rate={:.1}%",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.quantile(0.5), Duration::ZERO);
+        for micros in 1..=100 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.count(), 100);
+        assert_eq!(histogram.max(), Duration::from_micros(100));
+        assert_eq!(histogram.mean(), Duration::from_nanos(50_500));
+
+        // quantiles are upper bounds within a factor of two
+        let median = histogram.quantile(0.5);
+        assert!(median >= Duration::from_micros(50) && median <= Duration::from_micros(100));
+        assert_eq!(histogram.quantile(1.0), Duration::from_micros(100));
+
+        let mut merged = Histogram::default();
+        merged.merge(&histogram);
+        merged.merge(&histogram);
+        assert_eq!(merged.count(), 200);
+        assert_eq!(merged.quantile(0.5), median);
+    }
+
+    #[test]
+    fn test_cache_stats() {
+        assert_eq!(CacheStats::default().hit_rate(), 0.0);
+        let stats = CacheStats { hits: 3, misses: 1 };
+        assert_eq!(stats.hit_rate(), 0.75);
+        assert_eq!(stats.to_string(), "hits=3 misses=1 hit rate=75.0%");
+    }
+
+    #[test]
+    fn test_worker_stats() {
+        assert_eq!(std::mem::align_of::<WorkerStats>(), 64);
+        let worker = WorkerStats::default();
+        worker.record(&IterationStats {
+            select_depth: 3,
+            playout_plies: 40,
+            nodes_created: 1,
+            fallbacks: 1,
+        });
+        worker.record(&IterationStats {
+            select_depth: 5,
+            playout_plies: 20,
+            nodes_created: 0,
+            fallbacks: 0,
+        });
+        let mut stats = worker.snapshot();
+        assert_eq!(stats.iterations, 2);
+        assert_eq!(stats.mean_select_depth(), 4.0);
+        assert_eq!(stats.mean_playout_plies(), 30.0);
+        stats.merge(&worker.snapshot());
+        assert_eq!(stats.nodes_created, 2);
+        assert_eq!(stats.fallbacks, 2);
+        assert_eq!(stats.max_select_depth, 5);
+        assert_eq!(
+            stats.to_string(),
+            "iterations=4 nodes created=2 mean select depth=4.0 max select depth=5 \
+             mean playout plies=30.0 fallbacks=2"
+        );
+        assert_eq!(SearchStats::default().mean_select_depth(), 0.0);
+    }
+}
@@ -0,0 +1,531 @@
+//! Time management in two levels, a plan for the game and a controller for each move.
+//!
+//! The [`GamePlan`] decides before a search how long the move should take. It splits the
+//! time left between the opening, the midgame and the endgame by their configured shares,
+//! counting only the part of each phase still ahead, and gives each of Herb's moves in the
+//! current phase an equal part of its phase's time. Plies expected from the
+//! [`Repertoire`](crate::repertoire::Repertoire) cost nothing, so they are left out.
+//!
+//! The [`MoveController`] then runs the search of that one move. It stops early when the
+//! best move is clear, keeps searching past the target while it is not, up to a limit, and
+//! in panic mode, with little time left, holds every move to a small fraction of it. While a
+//! corner is at stake it does not stop early, see [`MoveController::guard_corners`]. Whatever
+//! the plan asks for, no move searches for less than `min_move_time` or longer than
+//! `max_move_time`, unless the clock itself can not afford the minimum.
+//!
+//! Both are part of the [`Config`](crate::config::Config), every option can be left out. The
+//! shares, fractions, the reserve and the move times must be finite and not negative, and the
+//! minimum move time no more than the maximum, see [`TimeConfig::validate`]:
+//! ```json
+//! "time": {
+//!     "plan": { "opening": 0.2, "midgame": 0.55, "endgame": 0.25 },
+//!     "controller": { "max_extension": 2.0, "panic_time": 5.0, "max_move_time": 20.0 }
+//! }
+//! ```
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::ConfigError;
+use crate::othello::Game;
+
+/// Options of both levels of time management.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeConfig {
+    pub plan: PlanConfig,
+    pub controller: ControllerConfig,
+}
+
+/// Options of the [`GamePlan`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlanConfig {
+    /// Share of the game's time for the opening.
+    pub opening: f64,
+    /// Share of the game's time for the midgame.
+    pub midgame: f64,
+    /// Share of the game's time for the endgame.
+    pub endgame: f64,
+    /// The midgame starts with this many empty squares.
+    pub midgame_empties: u64,
+    /// The endgame starts with this many empty squares.
+    pub endgame_empties: u64,
+    /// Fraction of the game's time that is never planned for, a margin against overruns.
+    pub reserve: f64,
+}
+
+impl TimeConfig {
+    /// Returns an error if a setting is out of its range. The plan and the controller turn
+    /// them into durations, which can not be negative.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let plan = &self.plan;
+        let controller = &self.controller;
+        non_negative("time.plan.opening", plan.opening)?;
+        non_negative("time.plan.midgame", plan.midgame)?;
+        non_negative("time.plan.endgame", plan.endgame)?;
+        non_negative("time.plan.reserve", plan.reserve)?;
+        non_negative("time.controller.max_share", controller.max_share)?;
+        non_negative("time.controller.panic_fraction", controller.panic_fraction)?;
+        non_negative("time.controller.min_move_time", controller.min_move_time)?;
+        if let Some(max_move_time) = controller.max_move_time {
+            non_negative("time.controller.max_move_time", max_move_time)?;
+            if controller.min_move_time > max_move_time {
+                return Err(ConfigError::Exceeds {
+                    setting: "time.controller.min_move_time",
+                    value: controller.min_move_time,
+                    limit: "time.controller.max_move_time",
+                    limit_value: max_move_time,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns an error unless the value of the setting is finite and not negative.
+fn non_negative(setting: &'static str, value: f64) -> Result<(), ConfigError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(ConfigError::OutOfRange {
+            setting,
+            value,
+            range: "finite and at least 0",
+        })
+    }
+}
+
+impl Default for PlanConfig {
+    fn default() -> Self {
+        PlanConfig {
+            opening: 0.2,
+            midgame: 0.55,
+            endgame: 0.25,
+            midgame_empties: 44,
+            endgame_empties: 20,
+            reserve: 0.05,
+        }
+    }
+}
+
+/// Options of the [`MoveController`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControllerConfig {
+    /// The search may stop early once this fraction of the target has passed...
+    pub early_stop_after: f64,
+    /// ...if the best move has at least this share of the root visits. Above 1 never stops
+    /// early.
+    pub early_stop_share: f64,
+    /// Never stop early while a corner can be taken or given away over the next turns.
+    pub corner_safety: bool,
+    /// At the target the search goes on while the best move has less than this share of the
+    /// root visits...
+    pub stable_share: f64,
+    /// ...up to this many times the target.
+    pub max_extension: f64,
+    /// A move never takes more than this fraction of the time left.
+    pub max_share: f64,
+    /// With fewer seconds than this left, every move takes at most `panic_fraction` of them.
+    pub panic_time: f64,
+    pub panic_fraction: f64,
+    /// Seconds every move searches for at least, unless the clock can not afford it.
+    pub min_move_time: f64,
+    /// Seconds no move searches for longer than, extensions included. Left out, only the
+    /// clock limits a move.
+    pub max_move_time: Option<f64>,
+}
+
+impl Default for ControllerConfig {
+    fn default() -> Self {
+        ControllerConfig {
+            early_stop_after: 0.5,
+            early_stop_share: 0.8,
+            corner_safety: true,
+            stable_share: 0.5,
+            max_extension: 2.0,
+            max_share: 0.25,
+            panic_time: 5.0,
+            panic_fraction: 0.05,
+            min_move_time: 0.0,
+            max_move_time: None,
+        }
+    }
+}
+
+/// How a game's time is split between its moves, see the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GamePlan {
+    config: PlanConfig,
+    max_time: f64,
+    book_plies: usize,
+}
+
+impl GamePlan {
+    /// Plan a game of `max_time` seconds in which the first `book_plies` plies come from the
+    /// book.
+    pub fn new(config: PlanConfig, max_time: f64, book_plies: usize) -> Self {
+        GamePlan {
+            config,
+            max_time,
+            book_plies,
+        }
+    }
+
+    /// Returns how long the move in the position should take with `time_remaining` seconds
+    /// left on the clock.
+    pub fn target(&self, game: &Game, time_remaining: f64) -> Duration {
+        let usable = (time_remaining - self.config.reserve * self.max_time).max(0.0);
+        let empties = game.empty_squares();
+        // the squares empty before the first move, all but the four in the middle
+        let start_empties = game.size().squares().count_ones() as u64 - 4;
+        // plies still to be searched in each phase, book plies are free
+        let book = self.book_plies.saturating_sub(game.get_turn()) as u64;
+        let phases = [
+            (
+                self.config.opening,
+                start_empties.saturating_sub(self.config.midgame_empties),
+                empties
+                    .saturating_sub(self.config.midgame_empties)
+                    .saturating_sub(book),
+            ),
+            (
+                self.config.midgame,
+                self.config
+                    .midgame_empties
+                    .saturating_sub(self.config.endgame_empties),
+                empties
+                    .min(self.config.midgame_empties)
+                    .saturating_sub(self.config.endgame_empties),
+            ),
+            (
+                self.config.endgame,
+                self.config.endgame_empties,
+                empties.min(self.config.endgame_empties),
+            ),
+        ];
+        let weight = |(share, length, left): (f64, u64, u64)| {
+            if length == 0 {
+                0.0
+            } else {
+                share.max(0.0) * left as f64 / length as f64
+            }
+        };
+        let total: f64 = phases.iter().map(|phase| weight(*phase)).sum();
+        let current = phases.iter().find(|(_, _, left)| *left > 0);
+        let seconds = match current {
+            Some(&phase) if total > 0.0 => {
+                usable * weight(phase) / total / own_moves(phase.2) as f64
+            }
+            _ => usable / own_moves(empties) as f64,
+        };
+        Duration::from_secs_f64(seconds)
+    }
+}
+
+/// Returns the moves of the player to move in the next `plies` plies, at least one.
+fn own_moves(plies: u64) -> u64 {
+    plies.div_ceil(2).max(1)
+}
+
+/// Decides when the search of a single move stops, see the [module](self) documentation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MoveController {
+    config: ControllerConfig,
+    target: Duration,
+    limit: Duration,
+    min: Duration,
+    panicking: bool,
+    guarded: bool,
+}
+
+impl MoveController {
+    /// Control the search of a move the plan gave `target` for, with `time_remaining`
+    /// seconds left on the clock.
+    ///
+    /// The target is clamped to the minimum and maximum move time whatever the plan says, and
+    /// then held to `max_share` of the clock, which wins over the minimum. The config must
+    /// pass [`TimeConfig::validate`].
+    pub fn new(config: &ControllerConfig, target: Duration, time_remaining: f64) -> Self {
+        let time_remaining = time_remaining.max(0.0);
+        let panicking = time_remaining < config.panic_time;
+        let mut seconds = target.as_secs_f64();
+        if panicking {
+            seconds = seconds.min(time_remaining * config.panic_fraction);
+        }
+        let max = config.max_move_time.unwrap_or(f64::INFINITY);
+        let cap = time_remaining * config.max_share;
+        let min = config.min_move_time.min(cap);
+        seconds = seconds.max(min).min(max).min(cap);
+        let limit = (seconds * config.max_extension.max(1.0)).min(max).min(cap);
+        MoveController {
+            config: config.clone(),
+            target: Duration::from_secs_f64(seconds),
+            limit: Duration::from_secs_f64(limit),
+            min: Duration::from_secs_f64(min),
+            panicking,
+            guarded: false,
+        }
+    }
+
+    /// Keep the search from stopping early if a corner of the game is at stake, see
+    /// [`Game::corner_threats`], and the config asks for corner safety. The best move's share
+    /// of the visits can settle before the search has seen the corner it gives away.
+    pub fn guard_corners(mut self, game: &Game) -> Self {
+        self.guarded = self.config.corner_safety
+            && game
+                .corner_threats()
+                .iter()
+                .any(|threat| !threat.is_quiet());
+        self
+    }
+
+    /// Returns true if the search may not stop early because a corner is at stake.
+    pub fn is_guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Returns the time the move is expected to take.
+    pub fn target(&self) -> Duration {
+        self.target
+    }
+
+    /// Returns the time after which the search stops whatever the position.
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    /// Returns true if the clock is so low that the move was held to panic time.
+    pub fn is_panicking(&self) -> bool {
+        self.panicking
+    }
+
+    /// Returns true if the search should stop after `elapsed`. `best_share` returns the share
+    /// of the root visits the best move has, it is only called when it matters. The search
+    /// never stops early before the minimum move time.
+    pub fn should_stop(&self, elapsed: Duration, best_share: impl FnOnce() -> f64) -> bool {
+        if elapsed >= self.limit {
+            return true;
+        }
+        if elapsed < self.min {
+            return false;
+        }
+        let progress = elapsed.as_secs_f64() / self.target.as_secs_f64().max(f64::EPSILON);
+        if progress >= 1.0 {
+            return best_share() >= self.config.stable_share;
+        }
+        !self.guarded
+            && progress >= self.config.early_stop_after
+            && best_share() >= self.config.early_stop_share
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::BoardSize;
+    use crate::othello::Color::Black;
+
+    /// Play the first legal move until the position has `empties` empty squares.
+    fn with_empties(empties: u64) -> Game {
+        let mut game = Game::new();
+        while game.empty_squares() > empties {
+            game.play_next_turn(game.greedy_move()).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn test_plan_phases() {
+        let plan = GamePlan::new(PlanConfig::default(), 100.0, 0);
+        let opening = plan.target(&Game::new(), 100.0);
+        let midgame = plan.target(&with_empties(40), 80.0);
+        let endgame = plan.target(&with_empties(10), 25.0);
+        assert!(opening > Duration::ZERO);
+        // the midgame gets the largest share per move
+        assert!(midgame > opening);
+        assert!(midgame > endgame);
+
+        // following the plan for Black's moves uses all of the time but the reserve
+        let mut game = Game::new();
+        let mut time_remaining = 100.0;
+        while !game.is_over() {
+            if game.to_move() == Black {
+                time_remaining -= plan.target(&game, time_remaining).as_secs_f64();
+            }
+            game.play_next_turn(game.greedy_move()).unwrap();
+        }
+        assert!((time_remaining - 5.0).abs() < 1e-9);
+
+        // and so does a game on the small board that fills it, which the greedy one doesn't
+        let mut game = Game::with_size(BoardSize::Six);
+        let mut time_remaining = 100.0;
+        while !game.is_over() {
+            if game.to_move() == Black {
+                time_remaining -= plan.target(&game, time_remaining).as_secs_f64();
+            }
+            game.play_next_turn(game.legal_moves()[0]).unwrap();
+        }
+        assert_eq!(game.empty_squares(), 0);
+        assert!((time_remaining - 5.0).abs() < 1e-9);
+
+        // nothing left to plan with
+        assert_eq!(plan.target(&Game::new(), 5.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_plan_book() {
+        let config = PlanConfig::default();
+        let without = GamePlan::new(config.clone(), 100.0, 0).target(&Game::new(), 100.0);
+        let with = GamePlan::new(config.clone(), 100.0, 8).target(&Game::new(), 100.0);
+        // the opening's time is spread over fewer searched moves
+        assert!(with > without);
+
+        // a book covering the whole opening moves its time to the midgame
+        let plan = GamePlan::new(config, 100.0, 16);
+        let target = plan.target(&Game::new(), 100.0);
+        assert!(target > with);
+    }
+
+    #[test]
+    fn test_controller() {
+        let config = ControllerConfig::default();
+        let target = Duration::from_secs(2);
+        let controller = MoveController::new(&config, target, 60.0);
+        assert_eq!(controller.target(), target);
+        assert_eq!(controller.limit(), Duration::from_secs(4));
+        assert!(!controller.is_panicking());
+
+        let second = Duration::from_secs(1);
+        let unreachable = || panic!("the share does not matter yet");
+        assert!(!controller.should_stop(Duration::from_millis(500), unreachable));
+        // early stop once the best move is clear
+        assert!(!controller.should_stop(second, || 0.6));
+        assert!(controller.should_stop(second, || 0.9));
+        // extension while it is not
+        assert!(!controller.should_stop(Duration::from_secs(3), || 0.3));
+        assert!(controller.should_stop(Duration::from_secs(3), || 0.6));
+        assert!(controller.should_stop(Duration::from_secs(4), unreachable));
+
+        // never more than a quarter of the clock
+        let controller = MoveController::new(&config, target, 6.0);
+        assert_eq!(controller.limit(), Duration::from_secs_f64(1.5));
+
+        // panic mode
+        let controller = MoveController::new(&config, target, 4.0);
+        assert!(controller.is_panicking());
+        assert_eq!(controller.target(), Duration::from_secs_f64(0.2));
+        assert_eq!(controller.limit(), Duration::from_secs_f64(0.4));
+
+        let controller = MoveController::new(&config, target, -1.0);
+        assert!(controller.should_stop(Duration::ZERO, || 0.0));
+    }
+
+    #[test]
+    fn test_guard_corners() {
+        let config = ControllerConfig::default();
+        let target = Duration::from_secs(2);
+        let second = Duration::from_secs(1);
+        let quiet = MoveController::new(&config, target, 60.0).guard_corners(&Game::new());
+        assert!(!quiet.is_guarded());
+        assert!(quiet.should_stop(second, || 0.9));
+
+        // black can take a1
+        let game: Game = "1WB5/8/8/3WB3/3BW3/8/8/8 B 10 -".parse().unwrap();
+        let guarded = MoveController::new(&config, target, 60.0).guard_corners(&game);
+        assert!(guarded.is_guarded());
+        assert!(!guarded.should_stop(second, || 0.9));
+        // the target and the extension still hold
+        assert!(guarded.should_stop(target, || 0.9));
+
+        let config = ControllerConfig {
+            corner_safety: false,
+            ..config
+        };
+        assert!(!MoveController::new(&config, target, 60.0)
+            .guard_corners(&game)
+            .is_guarded());
+    }
+
+    #[test]
+    fn test_move_time_clamps() {
+        let config = ControllerConfig {
+            min_move_time: 0.5,
+            max_move_time: Some(3.0),
+            ..ControllerConfig::default()
+        };
+        // a plan asking for too much
+        let controller = MoveController::new(&config, Duration::from_secs(40), 600.0);
+        assert_eq!(controller.target(), Duration::from_secs(3));
+        assert_eq!(controller.limit(), Duration::from_secs(3));
+
+        // and for too little, the search does not stop early before the minimum
+        let controller = MoveController::new(&config, Duration::from_millis(5), 600.0);
+        assert_eq!(controller.target(), Duration::from_millis(500));
+        assert_eq!(controller.limit(), Duration::from_secs(1));
+        assert!(!controller.should_stop(Duration::from_millis(400), || 1.0));
+        assert!(controller.should_stop(Duration::from_millis(500), || 1.0));
+
+        // panic mode is held to the minimum too
+        let controller = MoveController::new(&config, Duration::from_secs(1), 4.0);
+        assert_eq!(controller.target(), Duration::from_millis(500));
+
+        // but the clock comes first
+        let controller = MoveController::new(&config, Duration::from_secs(1), 1.0);
+        assert_eq!(controller.target(), Duration::from_millis(250));
+        assert_eq!(controller.limit(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(TimeConfig::default().validate(), Ok(()));
+        let mut config = TimeConfig::default();
+        config.controller.max_share = -0.25;
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::OutOfRange {
+                setting: "time.controller.max_share",
+                value: -0.25,
+                range: "finite and at least 0",
+            })
+        );
+        let mut config = TimeConfig::default();
+        config.plan.midgame = f64::INFINITY;
+        assert!(config.validate().is_err());
+        let mut config = TimeConfig::default();
+        config.controller.panic_fraction = f64::NAN;
+        assert!(config.validate().is_err());
+
+        // a minimum move time above the maximum is rejected rather than lowered to it
+        let mut config = TimeConfig::default();
+        config.controller.min_move_time = 5.0;
+        config.controller.max_move_time = Some(2.0);
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::Exceeds {
+                setting: "time.controller.min_move_time",
+                value: 5.0,
+                limit: "time.controller.max_move_time",
+                limit_value: 2.0,
+            })
+        );
+        config.controller.max_move_time = Some(5.0);
+        assert_eq!(config.validate(), Ok(()));
+        config.controller.max_move_time = None;
+        assert_eq!(config.validate(), Ok(()));
+        config.controller.min_move_time = -1.0;
+        assert!(config.validate().is_err());
+        config.controller.min_move_time = 0.0;
+        config.controller.max_move_time = Some(-1.0);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let config: TimeConfig = serde_json::from_str(r#"{ "plan": { "opening": 0.3 } }"#).unwrap();
+        assert_eq!(config.plan.opening, 0.3);
+        assert_eq!(config.plan.midgame, PlanConfig::default().midgame);
+        assert_eq!(config.controller, ControllerConfig::default());
+    }
+}
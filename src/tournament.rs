@@ -0,0 +1,773 @@
+//! Plays games and matches between [`Player`]s.
+//!
+//! Both players run in the same process and see the same [`Game`], so any two
+//! [`Player`] implementations can be paired up, e.g. two [`Herb`](crate::Herb)s with
+//! different configurations.
+//!
+//! Long matches are run with a [`Tournament`], which keeps the schedule, the results of
+//! completed games and the state of its random number generator together so they can be
+//! checkpointed to disk and resumed after an interruption.
+//!
+//! Colors are assigned by a [`ColorScheme`], alternating by default as in most engine
+//! matches, or randomly or by letting the loser of each game pick as in club tournaments.
+//!
+//! The random numbers come from the tournament's seed by way of a [`RngBackend`], ChaCha8
+//! unless [`Tournament::with_rng`] picks another.
+//!
+//! A tournament can give the players different amounts of time with a [`TimeControl`], e.g.
+//! 30 seconds against 120, to measure strength differences in time rather than Elo.
+use std::io;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::driver::{Match, MatchError, Seat};
+use crate::formats::{self, Header, VersionedReader};
+use crate::othello::Color::{Black, White};
+use crate::othello::{Color, Game, GameError, Move};
+use crate::random::RngBackend;
+use crate::record::GameRecord;
+use crate::Player;
+
+pub use crate::driver::GameResult;
+
+/// Play a full game from the starting position.
+///
+/// Returns an error if either player makes an illegal move.
+pub fn play_game(black: &mut dyn Player, white: &mut dyn Player) -> Result<GameResult, GameError> {
+    play_game_from(Game::new(), black, white)
+}
+
+/// Play a game to the end starting from the given position.
+///
+/// Returns an error if either player makes an illegal move.
+pub fn play_game_from(
+    game: Game,
+    black: &mut dyn Player,
+    white: &mut dyn Player,
+) -> Result<GameResult, GameError> {
+    Match::new(Seat::Local(black), Seat::Local(white))
+        .from_position(game)
+        .play()
+        .map_err(|e| match e {
+            MatchError::Game(e) => e,
+            MatchError::Interface(_) => unreachable!("local players have no interface"),
+        })
+}
+
+/// Wins, losses and draws from one player's perspective.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+impl MatchScore {
+    /// Record the result of a game played as `color`.
+    pub fn record(&mut self, winner: Option<Color>, color: Color) {
+        match winner {
+            None => self.draws += 1,
+            Some(winner) if winner == color => self.wins += 1,
+            _ => self.losses += 1,
+        }
+    }
+
+    /// Returns the number of games played.
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Returns the fraction of points scored, counting a draw as half a win.
+    /// A score with no games is `0.5`.
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+}
+
+/// Play `games` games between two players, alternating colors with the first player
+/// taking black in the first game. Fresh players are created for every game, by calling
+/// `first` and `second` with the game's number, e.g. to give them the game's random stream
+/// with [`RngBackend::stream`].
+///
+/// Returns the score from the first player's perspective.
+pub fn play_match<A, B>(
+    games: u32,
+    mut first: impl FnMut(u32) -> A,
+    mut second: impl FnMut(u32) -> B,
+) -> Result<MatchScore, GameError>
+where
+    A: Player,
+    B: Player,
+{
+    let mut score = MatchScore::default();
+    for i in 0..games {
+        let mut a = first(i);
+        let mut b = second(i);
+        if i % 2 == 0 {
+            let result = play_game(&mut a, &mut b)?;
+            score.record(result.winner(), Black);
+        } else {
+            let result = play_game(&mut b, &mut a)?;
+            score.record(result.winner(), White);
+        }
+    }
+    Ok(score)
+}
+
+/// Called with a game's index in the schedule and its current position.
+pub type Observer<'a> = dyn Fn(usize, &Game) + Sync + 'a;
+
+/// Extra time a player may use beyond its budget before it loses on time, covering the
+/// overhead outside of its own search.
+const TIME_GRACE: Duration = Duration::from_secs(1);
+
+/// The format of tournament checkpoint files.
+pub const CHECKPOINT_FORMAT: Header = Header::new("tournament", 1);
+
+/// Time budgets per game, in seconds, for the players of a [`Tournament`].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeControl {
+    pub first: f64,
+    pub second: f64,
+}
+
+impl TimeControl {
+    /// Returns the budget of the player with the given color.
+    pub fn for_color(&self, color: Color, first_color: Color) -> f64 {
+        if color == first_color {
+            self.first
+        } else {
+            self.second
+        }
+    }
+}
+
+/// How the players' colors are assigned in the games of a [`Tournament`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ColorScheme {
+    /// The first player takes black in even games and white in odd ones.
+    #[default]
+    Alternate,
+    /// Every game's colors are drawn from the tournament's seeded random number generator.
+    Random,
+    /// The first player takes black in the first game, after that the loser of a game takes
+    /// the given color in the next one. Colors alternate after a draw.
+    ///
+    /// The colors of a game depend on the game before it, so these tournaments are always
+    /// played one game at a time.
+    LoserPicks(Color),
+}
+
+/// A game in a [`Tournament`] schedule.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScheduledGame {
+    /// The color played by the first player. With [`ColorScheme::LoserPicks`] this is only
+    /// settled when the game before it has finished, see [`CompletedGame::first_color`].
+    pub first_color: Color,
+}
+
+/// A finished game in a [`Tournament`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompletedGame {
+    /// Position of the game in the schedule.
+    pub index: usize,
+    /// The color played by the first player.
+    pub first_color: Color,
+    /// Every move played including the random opening, passes included.
+    pub moves: Vec<Move>,
+    pub winner: Option<Color>,
+    /// The color that lost by making an illegal move, crashing or running out of time, if
+    /// any.
+    #[serde(default)]
+    pub forfeit: Option<Color>,
+    /// The time budgets the game was played with, if the players were timed.
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    /// True if the forfeit was for running out of time.
+    #[serde(default)]
+    pub on_time: bool,
+    /// The build that played the game, `None` in checkpoints from before builds were
+    /// recorded.
+    #[serde(default)]
+    pub build: Option<BuildInfo>,
+}
+
+impl CompletedGame {
+    /// Returns the game as a [`GameRecord`] tagged with the players' colors.
+    pub fn record(&self, first_name: &str, second_name: &str) -> GameRecord {
+        let mut record = GameRecord::from_moves(&self.moves);
+        let (black, white) = match self.first_color {
+            Black => (first_name, second_name),
+            White => (second_name, first_name),
+        };
+        record.tags.insert("Black".to_string(), black.to_string());
+        record.tags.insert("White".to_string(), white.to_string());
+        record
+            .tags
+            .insert("Game".to_string(), (self.index + 1).to_string());
+        if let Some(time_control) = self.time_control {
+            for (tag, color) in [("BlackTime", Black), ("WhiteTime", White)] {
+                let seconds = time_control.for_color(color, self.first_color);
+                record.tags.insert(tag.to_string(), seconds.to_string());
+            }
+        }
+        if self.on_time {
+            record
+                .tags
+                .insert("Termination".to_string(), "time forfeit".to_string());
+        }
+        if let Some(build) = &self.build {
+            build.stamp(&mut record);
+        }
+        record
+    }
+}
+
+/// A resumable match between two players.
+///
+/// Each game starts with `opening_plies` random moves. Game `i` draws its opening from stream
+/// `i` of the tournament's seeded random number generator, so games differ from each other
+/// but the openings do not depend on the order games are played in, a resumed or parallel
+/// tournament plays exactly the openings a sequential uninterrupted one would have.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Tournament {
+    schedule: Vec<ScheduledGame>,
+    results: Vec<CompletedGame>,
+    opening_plies: usize,
+    seed: [u8; 32],
+    #[serde(default)]
+    time_control: Option<TimeControl>,
+    #[serde(default)]
+    colors: ColorScheme,
+    #[serde(default)]
+    rng: RngBackend,
+}
+
+impl Tournament {
+    /// Create a tournament of `games` games with alternating colors, the first player
+    /// taking black in the first game.
+    pub fn new(games: u32, opening_plies: usize, seed: u64) -> Self {
+        let schedule = (0..games)
+            .map(|i| ScheduledGame {
+                first_color: if i % 2 == 0 { Black } else { White },
+            })
+            .collect();
+        Tournament {
+            schedule,
+            results: Vec::new(),
+            opening_plies,
+            seed: ChaCha8Rng::seed_from_u64(seed).get_seed(),
+            time_control: None,
+            colors: ColorScheme::Alternate,
+            rng: RngBackend::ChaCha,
+        }
+    }
+
+    /// Assign the players' colors with the given scheme instead of alternating them.
+    pub fn with_color_scheme(mut self, colors: ColorScheme) -> Self {
+        // a stream of its own, so the colors do not change the openings
+        let mut rng = self.rng.stream_from_key(self.seed, u64::MAX, 0);
+        for (i, game) in self.schedule.iter_mut().enumerate() {
+            game.first_color = match colors {
+                ColorScheme::Random if rng.gen_bool(0.5) => White,
+                ColorScheme::Random => Black,
+                _ if i % 2 == 0 => Black,
+                _ => White,
+            };
+        }
+        self.colors = colors;
+        self
+    }
+
+    /// Draw the openings and random colors from the given backend's streams instead of
+    /// ChaCha8's, see [`random`](crate::random).
+    pub fn with_rng(mut self, rng: RngBackend) -> Self {
+        self.rng = rng;
+        let colors = self.colors;
+        self.with_color_scheme(colors)
+    }
+
+    /// Returns the backend the openings and random colors are drawn from.
+    pub fn rng(&self) -> RngBackend {
+        self.rng
+    }
+
+    /// Returns how the players' colors are assigned.
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.colors
+    }
+
+    /// Returns the first player's color in the game at the given index, given the games
+    /// finished so far.
+    fn first_color(&self, index: usize, finished: &[CompletedGame]) -> Color {
+        let ColorScheme::LoserPicks(pick) = self.colors else {
+            return self.schedule[index].first_color;
+        };
+        let previous = index
+            .checked_sub(1)
+            .and_then(|previous| finished.iter().find(|r| r.index == previous));
+        match previous {
+            None => Black,
+            Some(previous) => match previous.winner {
+                None => previous.first_color.opponent(),
+                // the first player lost
+                Some(winner) if winner != previous.first_color => pick,
+                Some(_) => pick.opponent(),
+            },
+        }
+    }
+
+    /// Time the players with the given budgets. A player that uses more than its budget in a
+    /// game, plus a second of grace, loses that game on time.
+    pub fn with_time_control(mut self, time_control: TimeControl) -> Self {
+        self.time_control = Some(time_control);
+        self
+    }
+
+    /// Returns the time budgets of the players, if they are timed.
+    pub fn time_control(&self) -> Option<TimeControl> {
+        self.time_control
+    }
+
+    /// Load a tournament from a checkpoint file. Checkpoints from before the format was
+    /// versioned are upgraded.
+    pub fn resume(path: &Path) -> io::Result<Self> {
+        VersionedReader::new(CHECKPOINT_FORMAT)
+            .migration(0, Ok)
+            .read_file(path)
+    }
+
+    /// Write the tournament to a checkpoint file. The file is replaced atomically so an
+    /// interruption while writing leaves the previous checkpoint intact.
+    pub fn checkpoint(&self, path: &Path) -> io::Result<()> {
+        formats::save(path, CHECKPOINT_FORMAT, self)
+    }
+
+    /// Returns the schedule of all games, played or not.
+    pub fn schedule(&self) -> &[ScheduledGame] {
+        &self.schedule
+    }
+
+    /// Returns the completed games in the order they finished.
+    pub fn results(&self) -> &[CompletedGame] {
+        &self.results
+    }
+
+    /// Returns true if every scheduled game has been played.
+    pub fn is_finished(&self) -> bool {
+        self.results.len() >= self.schedule.len()
+    }
+
+    /// Returns the score so far from the first player's perspective.
+    pub fn score(&self) -> MatchScore {
+        let mut score = MatchScore::default();
+        for result in &self.results {
+            score.record(result.winner, result.first_color);
+        }
+        score
+    }
+
+    /// Returns the indexes of the scheduled games that have not been played yet.
+    fn pending(&self) -> Vec<usize> {
+        (0..self.schedule.len())
+            .filter(|i| !self.results.iter().any(|r| r.index == *i))
+            .collect()
+    }
+
+    /// Returns the random opening for the game at the given index.
+    fn opening(&self, index: usize) -> Vec<Move> {
+        let mut rng = self.rng.stream_from_key(self.seed, index as u64, 0);
+
+        let mut game = Game::new();
+        let mut opening = Vec::new();
+        for _ in 0..self.opening_plies {
+            if game.is_over() {
+                break;
+            }
+            let legal_moves = game.legal_moves();
+            let mv = if legal_moves.is_empty() {
+                Move::Pass
+            } else {
+                legal_moves[rng.gen_range(0..legal_moves.len())]
+            };
+            game.play_next_turn(mv).unwrap();
+            opening.push(mv);
+        }
+        opening
+    }
+
+    /// Play the game at the given index. An illegal move, a panic while a player is
+    /// choosing its move or running out of time forfeits the game for that player.
+    ///
+    /// The observer is called with the game's index and position after the opening and
+    /// after every move.
+    fn play(
+        &self,
+        index: usize,
+        first_color: Color,
+        first: &mut dyn Player,
+        second: &mut dyn Player,
+        observer: &Observer<'_>,
+    ) -> CompletedGame {
+        let mut moves = self.opening(index);
+        let mut game = Game::new();
+        for mv in &moves {
+            game.play_next_turn(*mv).unwrap();
+        }
+        observer(index, &game);
+        first.new_game(first_color);
+        second.new_game(first_color.opponent());
+
+        let mut forfeit = None;
+        let mut on_time = false;
+        // time used by the first and second player
+        let mut used = [Duration::ZERO; 2];
+        while !game.is_over() {
+            let (player, used): (&mut dyn Player, _) = if game.to_move() == first_color {
+                (first, &mut used[0])
+            } else {
+                (second, &mut used[1])
+            };
+            let start = Instant::now();
+            let mv = panic::catch_unwind(AssertUnwindSafe(|| player.get_next_move(game)));
+            *used += start.elapsed();
+            if let Some(time_control) = self.time_control {
+                let budget = time_control.for_color(game.to_move(), first_color);
+                if *used > Duration::from_secs_f64(budget.max(0.0)) + TIME_GRACE {
+                    forfeit = Some(game.to_move());
+                    on_time = true;
+                    break;
+                }
+            }
+            match mv.map(|mv| (mv, game.play_next_turn(mv))) {
+                Ok((mv, Ok(()))) => {
+                    moves.push(mv);
+                    observer(index, &game);
+                }
+                _ => {
+                    forfeit = Some(game.to_move());
+                    break;
+                }
+            }
+        }
+
+        let (black_time, white_time) = match first_color {
+            Black => (used[0], used[1]),
+            White => (used[1], used[0]),
+        };
+        let result = GameResult {
+            moves: moves.clone(),
+            game,
+            black_time,
+            white_time,
+        };
+        first.game_over(&result);
+        second.game_over(&result);
+
+        let winner = match forfeit {
+            Some(color) => Some(color.opponent()),
+            None => game.winner(),
+        };
+        CompletedGame {
+            index,
+            first_color,
+            moves,
+            winner,
+            forfeit,
+            time_control: self.time_control,
+            on_time,
+            build: Some(BuildInfo::current()),
+        }
+    }
+
+    /// Play the next scheduled game, returning `None` if the tournament is finished.
+    pub fn play_next<A, B>(&mut self, first: &mut A, second: &mut B) -> Option<&CompletedGame>
+    where
+        A: Player,
+        B: Player,
+    {
+        let index = *self.pending().first()?;
+        let first_color = self.first_color(index, &self.results);
+        let result = self.play(index, first_color, first, second, &|_, _| {});
+        self.results.push(result);
+        self.results.last()
+    }
+
+    /// Play all remaining games, one at a time, creating fresh players for every game by
+    /// calling `first` and `second` with the game's index in the schedule. Seeding the players
+    /// with the index, e.g. by [`Herb::with_stream`](crate::Herb::with_stream), makes every
+    /// game play the same on every run and resumed after a checkpoint.
+    ///
+    /// If a checkpoint `(path, interval)` is given, the tournament is checkpointed to `path`
+    /// after every `interval` games and once more when it finishes.
+    pub fn run<A, B>(
+        &mut self,
+        first: impl Fn(usize) -> A + Sync,
+        second: impl Fn(usize) -> B + Sync,
+        checkpoint: Option<(&Path, usize)>,
+    ) -> io::Result<MatchScore>
+    where
+        A: Player,
+        B: Player,
+    {
+        self.run_parallel(1, first, second, checkpoint)
+    }
+
+    /// Play all remaining games on `workers` threads, each worker creating a fresh pair of
+    /// players for every game it plays from the game's index, as in [`Tournament::run`]. The
+    /// games do not depend on which worker plays them. A game whose player panics is forfeited by that
+    /// player without affecting the other games. With [`ColorScheme::LoserPicks`] a single
+    /// worker is used.
+    ///
+    /// If a checkpoint `(path, interval)` is given, the tournament is checkpointed to `path`
+    /// after every `interval` games and once more when it finishes.
+    pub fn run_parallel<A, B>(
+        &mut self,
+        workers: usize,
+        first: impl Fn(usize) -> A + Sync,
+        second: impl Fn(usize) -> B + Sync,
+        checkpoint: Option<(&Path, usize)>,
+    ) -> io::Result<MatchScore>
+    where
+        A: Player,
+        B: Player,
+    {
+        self.run_observed(workers, first, second, checkpoint, &|_, _| {})
+    }
+
+    /// Like [`Tournament::run_parallel`], calling the observer with the game's index and
+    /// position after every move of every game, e.g. to feed a
+    /// [`SpectatorFeed`](crate::spectator::SpectatorFeed).
+    pub fn run_observed<A, B>(
+        &mut self,
+        workers: usize,
+        first: impl Fn(usize) -> A + Sync,
+        second: impl Fn(usize) -> B + Sync,
+        checkpoint: Option<(&Path, usize)>,
+        observer: &Observer<'_>,
+    ) -> io::Result<MatchScore>
+    where
+        A: Player,
+        B: Player,
+    {
+        let pending = Mutex::new(self.pending().into_iter());
+        let state = Mutex::new((Vec::new(), Ok(())));
+        let this = &*self;
+        let workers = match self.colors {
+            ColorScheme::LoserPicks(_) => 1,
+            _ => workers.max(1),
+        };
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(|| loop {
+                    let next = pending.lock().unwrap().next();
+                    let Some(index) = next else { break };
+                    let first_color = {
+                        let state = state.lock().unwrap();
+                        let finished: Vec<_> =
+                            this.results.iter().chain(&state.0).cloned().collect();
+                        this.first_color(index, &finished)
+                    };
+                    let mut a = first(index);
+                    let mut b = second(index);
+                    let result = this.play(index, first_color, &mut a, &mut b, observer);
+
+                    let mut state = state.lock().unwrap();
+                    state.0.push(result);
+                    if let Some((path, interval)) = checkpoint {
+                        let done = this.results.len() + state.0.len();
+                        if done == this.schedule.len() || done.is_multiple_of(interval.max(1)) {
+                            let mut snapshot = this.clone();
+                            snapshot.results.extend(state.0.iter().cloned());
+                            if let Err(e) = snapshot.checkpoint(path) {
+                                state.1 = Err(e);
+                            }
+                        }
+                    }
+                });
+            }
+        });
+
+        let (results, checkpointed) = state.into_inner().unwrap();
+        self.results.extend(results);
+        checkpointed?;
+        Ok(self.score())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opponents::Opponent;
+
+    /// Plays the first legal move.
+    struct FirstMove;
+
+    impl Player for FirstMove {
+        fn get_next_move(&mut self, game_state: Game) -> Move {
+            *game_state.legal_moves().first().unwrap_or(&Move::Pass)
+        }
+    }
+
+    /// Panics instead of moving.
+    struct Crashes;
+
+    impl Player for Crashes {
+        fn get_next_move(&mut self, _game_state: Game) -> Move {
+            panic!("crashed")
+        }
+    }
+
+    #[test]
+    fn test_resume_matches_uninterrupted_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tournament.json");
+
+        let mut uninterrupted = Tournament::new(6, 4, 42);
+        uninterrupted
+            .run(|_| FirstMove, |_| FirstMove, None)
+            .unwrap();
+
+        let mut interrupted = Tournament::new(6, 4, 42);
+        for _ in 0..3 {
+            interrupted
+                .play_next(&mut FirstMove, &mut FirstMove)
+                .unwrap();
+        }
+        interrupted.checkpoint(&path).unwrap();
+
+        let mut resumed = Tournament::resume(&path).unwrap();
+        assert_eq!(resumed, interrupted);
+        resumed
+            .run(|_| FirstMove, |_| FirstMove, Some((&path, 2)))
+            .unwrap();
+
+        assert!(resumed.is_finished());
+        assert_eq!(resumed, uninterrupted);
+        assert_eq!(Tournament::resume(&path).unwrap(), uninterrupted);
+        assert_eq!(resumed.score().games(), 6);
+    }
+
+    #[test]
+    fn test_parallel_run_matches_sequential_run() {
+        let mut sequential = Tournament::new(8, 4, 7);
+        sequential.run(|_| FirstMove, |_| FirstMove, None).unwrap();
+
+        let mut parallel = Tournament::new(8, 4, 7);
+        parallel
+            .run_parallel(4, |_| FirstMove, |_| FirstMove, None)
+            .unwrap();
+
+        let mut results = parallel.results().to_vec();
+        results.sort_by_key(|r| r.index);
+        assert_eq!(results, sequential.results());
+
+        // random players seeded with the game play the same whichever worker gets it
+        let random =
+            |index: usize| Opponent::Random.with_rng(RngBackend::Philox.stream(3, index as u64, 0));
+        let mut sequential = Tournament::new(8, 0, 7);
+        sequential.run(random, random, None).unwrap();
+        let mut parallel = Tournament::new(8, 0, 7);
+        parallel.run_parallel(4, random, random, None).unwrap();
+        let mut results = parallel.results().to_vec();
+        results.sort_by_key(|r| r.index);
+        assert_eq!(results, sequential.results());
+    }
+
+    /// Takes its time over every move.
+    struct Slow;
+
+    impl Player for Slow {
+        fn get_next_move(&mut self, game_state: Game) -> Move {
+            thread::sleep(Duration::from_millis(300));
+            FirstMove.get_next_move(game_state)
+        }
+    }
+
+    #[test]
+    fn test_time_odds() {
+        let time_control = TimeControl {
+            first: 60.0,
+            second: 0.0,
+        };
+        let mut tournament = Tournament::new(2, 0, 0).with_time_control(time_control);
+        let score = tournament
+            .run_parallel(2, |_| FirstMove, |_| Slow, None)
+            .unwrap();
+        assert_eq!(score.wins, 2);
+        for result in tournament.results() {
+            assert!(result.on_time);
+            assert_eq!(result.forfeit, Some(result.first_color.opponent()));
+            let record = result.record("Fast", "Slow");
+            let slow_tag = match result.first_color {
+                Black => "WhiteTime",
+                White => "BlackTime",
+            };
+            assert_eq!(record.tags[slow_tag], "0");
+            assert_eq!(record.tags["Termination"], "time forfeit");
+            assert_eq!(record.tags["Build"], BuildInfo::current().to_string());
+        }
+    }
+
+    #[test]
+    fn test_color_schemes() {
+        let alternate = Tournament::new(8, 4, 3);
+        let random = alternate.clone().with_color_scheme(ColorScheme::Random);
+        assert_eq!(random.color_scheme(), ColorScheme::Random);
+        assert_ne!(random.schedule(), alternate.schedule());
+        assert_eq!(
+            random.schedule(),
+            Tournament::new(8, 4, 3)
+                .with_color_scheme(ColorScheme::Random)
+                .schedule()
+        );
+        assert_eq!(random.opening(5), alternate.opening(5));
+
+        // another backend draws other openings, and the same ones every time
+        let philox = Tournament::new(8, 4, 3).with_rng(RngBackend::Philox);
+        assert_eq!(philox.rng(), RngBackend::Philox);
+        assert_ne!(philox.opening(5), alternate.opening(5));
+        assert_eq!(philox.opening(5), philox.clone().opening(5));
+        assert_eq!(philox.opening(5).len(), 4);
+
+        // the crashing second player loses every game, so it always picks white
+        let mut loser_picks =
+            Tournament::new(4, 0, 0).with_color_scheme(ColorScheme::LoserPicks(White));
+        loser_picks
+            .run_parallel(4, |_| FirstMove, |_| Crashes, None)
+            .unwrap();
+        let colors: Vec<_> = loser_picks
+            .results()
+            .iter()
+            .map(|r| r.first_color)
+            .collect();
+        assert_eq!(colors, [Black, Black, Black, Black]);
+    }
+
+    #[test]
+    fn test_crash_is_forfeited() {
+        let mut tournament = Tournament::new(4, 0, 0);
+        let score = tournament
+            .run_parallel(2, |_| FirstMove, |_| Crashes, None)
+            .unwrap();
+        assert_eq!(score.wins, 4);
+        assert!(tournament
+            .results()
+            .iter()
+            .all(|r| r.forfeit == Some(r.first_color.opponent())));
+    }
+}
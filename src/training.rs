@@ -0,0 +1,249 @@
+//! Training data export.
+//!
+//! Positions reached during play are turned into [`TrainingSample`]s labelled with the
+//! eventual result of the game and written out as json lines, one sample per line:
+//! ```json
+//! {"game":{"current_board":{"black":34628173824,"white":68853694464},"current_player":"Black","turn":0,"last_move":null},"value":0.5,"policy":[],"weight":1.0}
+//! ```
+//!
+//! A sample may carry a policy target, a probability for each move indexed by
+//! [`Move::to_index`](crate::othello::Move::to_index), the 64 squares followed by pass. Whenever a sample's position is rotated or reflected
+//! its policy is remapped with it, so [`augment`] can safely produce the 8 symmetric copies
+//! of a sample.
+//!
+//! The same position shows up many times across games, often as a rotation or reflection
+//! of a position seen before. When exporting with deduplication enabled, every position is
+//! first mapped to its canonical form under the 8 board [`Symmetry`]s and duplicates are merged
+//! into a single sample whose value is the weighted average of the merged values and whose
+//! weight is their sum.
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::build_info::BuildInfo;
+use crate::othello::{Bitboard, Color, Game, Symmetry};
+
+/// A single position labelled with a training target.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TrainingSample {
+    pub game: Game,
+    /// Expected result from the perspective of the player to move in `game`:
+    /// `1.0` is a win, `0.5` a draw and `0.0` a loss.
+    pub value: f64,
+    /// Move probabilities indexed by square with pass last, empty if the sample has no
+    /// policy target.
+    #[serde(default)]
+    pub policy: Vec<f64>,
+    /// How many samples this one stands for.
+    pub weight: f64,
+}
+
+impl TrainingSample {
+    /// Create a new sample with a weight of `1.0`.
+    pub fn new(game: Game, value: f64) -> Self {
+        TrainingSample {
+            game,
+            value,
+            policy: Vec::new(),
+            weight: 1.0,
+        }
+    }
+
+    /// Create a new sample with a policy target and a weight of `1.0`.
+    pub fn with_policy(game: Game, value: f64, policy: Vec<f64>) -> Self {
+        TrainingSample {
+            policy,
+            ..TrainingSample::new(game, value)
+        }
+    }
+
+    /// Returns this sample with its position and policy rotated or reflected by the given
+    /// [`Symmetry`].
+    pub fn transform(&self, symmetry: Symmetry) -> TrainingSample {
+        let policy = if self.policy.is_empty() {
+            Vec::new()
+        } else {
+            symmetry.transform_policy(&self.policy)
+        };
+        TrainingSample {
+            game: self.game.transform(symmetry),
+            value: self.value,
+            policy,
+            weight: self.weight,
+        }
+    }
+
+    /// Returns this sample with its position mapped to its canonical form.
+    pub fn canonical(&self) -> (TrainingSample, Symmetry) {
+        let (_game, symmetry) = self.game.canonical();
+        (self.transform(symmetry), symmetry)
+    }
+}
+
+/// Label every position of a finished game with the game's result.
+pub fn samples_from_game(positions: &[Game], winner: Option<Color>) -> Vec<TrainingSample> {
+    positions
+        .iter()
+        .map(|game| {
+            let value = match winner {
+                None => 0.5,
+                Some(winner) if winner == game.to_move() => 1.0,
+                _ => 0.0,
+            };
+            TrainingSample::new(*game, value)
+        })
+        .collect()
+}
+
+/// Expand each sample into its 8 symmetric copies.
+pub fn augment(samples: &[TrainingSample]) -> Vec<TrainingSample> {
+    samples
+        .iter()
+        .flat_map(|sample| {
+            Symmetry::ALL
+                .iter()
+                .map(move |symmetry| sample.transform(*symmetry))
+        })
+        .collect()
+}
+
+/// Canonicalize the given samples and merge the ones that share a position.
+///
+/// Positions are compared by board and player to move, the turn number is ignored.
+/// Policies of merged samples are averaged the same way as their values.
+/// The order of first appearance is preserved.
+pub fn deduplicate(samples: Vec<TrainingSample>) -> Vec<TrainingSample> {
+    let mut merged: Vec<TrainingSample> = Vec::new();
+    let mut index: HashMap<(Bitboard, Color), usize> = HashMap::new();
+
+    for sample in samples {
+        let (sample, _symmetry) = sample.canonical();
+        let key = (sample.game.get_board(), sample.game.to_move());
+        match index.get(&key) {
+            Some(&i) => {
+                let existing = &mut merged[i];
+                let total_weight = existing.weight + sample.weight;
+                if total_weight > 0.0 {
+                    existing.value = (existing.value * existing.weight
+                        + sample.value * sample.weight)
+                        / total_weight;
+                    if existing.policy.len() == sample.policy.len() {
+                        for (p, q) in existing.policy.iter_mut().zip(&sample.policy) {
+                            *p = (*p * existing.weight + q * sample.weight) / total_weight;
+                        }
+                    }
+                }
+                existing.weight = total_weight;
+            }
+            None => {
+                index.insert(key, merged.len());
+                merged.push(sample);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Write the samples to the given path as json lines, merging symmetric duplicates first if
+/// `dedup` is set. The [`BuildInfo`] of the running binary is written next to them.
+///
+/// Returns the number of samples written.
+pub fn export(samples: Vec<TrainingSample>, path: &Path, dedup: bool) -> io::Result<usize> {
+    let samples = if dedup { deduplicate(samples) } else { samples };
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    for sample in &samples {
+        serde_json::to_writer(&mut writer, sample)?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    BuildInfo::current().save_alongside(path)?;
+
+    Ok(samples.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::othello::Move;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn test_deduplicate_symmetric_openings() {
+        let game = Game::new();
+        let samples: Vec<TrainingSample> = game
+            .legal_moves()
+            .into_iter()
+            .enumerate()
+            .map(|(i, mv)| {
+                let mut sim_game = game;
+                sim_game.play_next_turn(mv).unwrap();
+                TrainingSample::new(sim_game, if i % 2 == 0 { 1.0 } else { 0.0 })
+            })
+            .collect();
+
+        let merged = deduplicate(samples);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].weight, 4.0);
+        assert_eq!(merged[0].value, 0.5);
+    }
+
+    #[test]
+    fn test_augment_remaps_policy() {
+        let game = Game::new();
+        let mut policy = vec![0.0; Move::INDICES];
+        let mv = game.legal_moves()[0];
+        policy[mv.to_index()] = 1.0;
+
+        let augmented = augment(&[TrainingSample::with_policy(game, 1.0, policy)]);
+        assert_eq!(augmented.len(), 8);
+        for sample in augmented {
+            let index = sample.policy.iter().position(|p| *p == 1.0).unwrap();
+            let target = Move::from_index(index).unwrap();
+            assert!(sample.game.legal_moves().contains(&target));
+        }
+    }
+
+    #[test]
+    fn test_samples_from_game_values() {
+        let mut game = Game::new();
+        let mut positions = vec![game];
+        game.play_next_turn(game.legal_moves()[0]).unwrap();
+        positions.push(game);
+
+        let samples = samples_from_game(&positions, Some(Color::Black));
+        assert_eq!(samples[0].value, 1.0);
+        assert_eq!(samples[1].value, 0.0);
+
+        let samples = samples_from_game(&positions, None);
+        assert!(samples.iter().all(|s| s.value == 0.5));
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("samples.jsonl");
+        let samples = vec![
+            TrainingSample::new(Game::new(), 1.0),
+            TrainingSample::new(Game::new(), 0.0),
+        ];
+
+        let written = export(samples, &path, true).unwrap();
+        assert_eq!(written, 1);
+
+        let lines: Vec<String> = BufReader::new(File::open(&path).unwrap())
+            .lines()
+            .map(|l| l.unwrap())
+            .collect();
+        assert_eq!(lines.len(), 1);
+        let sample: TrainingSample = serde_json::from_str(&lines[0]).unwrap();
+        assert_eq!(sample.weight, 2.0);
+        assert_eq!(sample.value, 0.5);
+        assert!(dir.path().join("samples.build.json").exists());
+    }
+}
@@ -0,0 +1,469 @@
+//! The WTHOR database format of the French Othello federation.
+//!
+//! The federation publishes every rated game played since 1977 in WTHOR files, millions of
+//! games by human players. A database is three kinds of file, each a 16 byte [`Header`]
+//! followed by fixed size records:
+//! - `.wtb` files hold the games, 68 bytes each: the tournament, the black and the white
+//!   player as numbers into the other two files, black's disc count at the end, black's
+//!   theoretical score, and the 60 moves as `10 * rank + file`, so 11 is a1 and 88 is h8,
+//!   padded with 0. Passes are not written.
+//! - `.jou` files hold the names of the players, 20 bytes each.
+//! - `.trn` files hold the names of the tournaments, 26 bytes each.
+//!
+//! Numbers are little endian and names are Latin-1, padded with zeros. [`read_games`] reads a
+//! `.wtb` file lazily, so databases of any size can be streamed into the evaluation tuner, and
+//! [`WthorGame::record`] turns each game into a [`GameRecord`] with the passes put back in.
+//!
+//! ```rust
+//! use herb::othello::{Game, Move};
+//! use herb::record::GameRecord;
+//! use herb::wthor::{self, WthorGame};
+//!
+//! let record = GameRecord::from_moves(&[Move::from_algebraic("f5").unwrap()]);
+//! let mut file = Vec::new();
+//! wthor::write_games(&mut file, 2024, &[WthorGame::from_record(&record).unwrap()]).unwrap();
+//! let (header, games) = wthor::read_games(file.as_slice()).unwrap();
+//! assert_eq!(header.records, 1);
+//! for game in games {
+//!     assert_eq!(game.unwrap().record(&[], &[]).unwrap().moves, record.moves);
+//! }
+//! ```
+use std::io::{self, ErrorKind, Read, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::othello::{Game, Move};
+use crate::record::{GameRecord, RecordError, RecordedMove};
+
+/// The size of the header of every file.
+pub const HEADER_LEN: usize = 16;
+/// The size of a game in a `.wtb` file.
+pub const GAME_LEN: usize = 68;
+/// The size of a name in a `.jou` file.
+pub const PLAYER_LEN: usize = 20;
+/// The size of a name in a `.trn` file.
+pub const TOURNAMENT_LEN: usize = 26;
+
+/// The header at the start of every WTHOR file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The date the file was created, year, month and day.
+    pub created: (u16, u8, u8),
+    /// The number of games of a `.wtb` file.
+    pub records: u32,
+    /// The number of names of a `.jou` or `.trn` file.
+    pub names: u16,
+    /// The year the games were played in.
+    pub year: u16,
+    /// 8 for the usual board, 0 in older files means the same.
+    pub board_size: u8,
+    /// True for a file of solitaires rather than games.
+    pub solitaire: bool,
+    /// The number of empty squares the theoretical scores were solved from.
+    pub depth: u8,
+}
+
+impl Header {
+    /// The header of a file of games or names created today.
+    pub fn new(records: u32, names: u16, year: u16) -> Self {
+        Header {
+            created: today(),
+            records,
+            names,
+            year,
+            board_size: 8,
+            solitaire: false,
+            depth: 0,
+        }
+    }
+
+    /// Read the header from the start of a file.
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut bytes = [0; HEADER_LEN];
+        reader.read_exact(&mut bytes)?;
+        let mut fields = &bytes[4..];
+        let header = Header {
+            created: (bytes[0] as u16 * 100 + bytes[1] as u16, bytes[2], bytes[3]),
+            records: fields.read_u32::<LittleEndian>()?,
+            names: fields.read_u16::<LittleEndian>()?,
+            year: fields.read_u16::<LittleEndian>()?,
+            board_size: bytes[12],
+            solitaire: bytes[13] == 1,
+            depth: bytes[14],
+        };
+        if header.board_size != 0 && header.board_size != 8 {
+            return Err(invalid(format!(
+                "Unsupported board size {}",
+                header.board_size
+            )));
+        }
+        Ok(header)
+    }
+
+    /// Write the header to the start of a file.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        let (year, month, day) = self.created;
+        writer.write_all(&[(year / 100) as u8, (year % 100) as u8, month, day])?;
+        writer.write_u32::<LittleEndian>(self.records)?;
+        writer.write_u16::<LittleEndian>(self.names)?;
+        writer.write_u16::<LittleEndian>(self.year)?;
+        writer.write_all(&[self.board_size, self.solitaire as u8, self.depth, 0])
+    }
+}
+
+/// A game of a `.wtb` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WthorGame {
+    /// The index of the tournament in the `.trn` file.
+    pub tournament: u16,
+    /// The index of the black player in the `.jou` file.
+    pub black: u16,
+    /// The index of the white player in the `.jou` file.
+    pub white: u16,
+    /// Black's discs at the end of the game, the empty squares counting for the winner.
+    pub black_score: u8,
+    /// Black's score with perfect play from the depth of the [`Header`].
+    pub theoretical_score: u8,
+    /// The moves without passes.
+    pub moves: Vec<Move>,
+}
+
+impl WthorGame {
+    /// Read a game, `None` at the end of the file.
+    pub fn read(reader: &mut impl Read) -> io::Result<Option<Self>> {
+        let mut bytes = [0; GAME_LEN];
+        match reader.read_exact(&mut bytes) {
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        let mut fields = &bytes[..];
+        let tournament = fields.read_u16::<LittleEndian>()?;
+        let black = fields.read_u16::<LittleEndian>()?;
+        let white = fields.read_u16::<LittleEndian>()?;
+        let moves = bytes[8..]
+            .iter()
+            .take_while(|&&square| square != 0)
+            .map(|&square| {
+                let (rank, file) = (square / 10, square % 10);
+                if !(1..=8).contains(&rank) || !(1..=8).contains(&file) {
+                    return Err(invalid(format!("Bad square {}", square)));
+                }
+                Move::from_col_row(file as u64 - 1, rank as u64 - 1)
+                    .map_err(|e| invalid(e.to_string()))
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Some(WthorGame {
+            tournament,
+            black,
+            white,
+            black_score: bytes[6],
+            theoretical_score: bytes[7],
+            moves,
+        }))
+    }
+
+    /// Write the game.
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        if self.moves.len() > 60 {
+            return Err(invalid("More than 60 moves".to_string()));
+        }
+        writer.write_u16::<LittleEndian>(self.tournament)?;
+        writer.write_u16::<LittleEndian>(self.black)?;
+        writer.write_u16::<LittleEndian>(self.white)?;
+        writer.write_all(&[self.black_score, self.theoretical_score])?;
+        let mut squares = [0; 60];
+        for (square, mv) in squares.iter_mut().zip(&self.moves) {
+            if let (Some(col), Some(row)) = (mv.get_col(), mv.get_row()) {
+                *square = 10 * (row + 1) + col + 1;
+            }
+        }
+        writer.write_all(&squares)
+    }
+
+    /// Make a game of the record, without players or tournament, scored as it ended. Passes
+    /// are left out.
+    ///
+    /// Returns an error if any move of the record is illegal.
+    pub fn from_record(record: &GameRecord) -> Result<Self, RecordError> {
+        let game = record.game()?;
+        let score = black_score(&game);
+        Ok(WthorGame {
+            tournament: 0,
+            black: 0,
+            white: 0,
+            black_score: score,
+            theoretical_score: score,
+            moves: record
+                .moves
+                .iter()
+                .map(|recorded| recorded.mv)
+                .filter(|mv| *mv != Move::Pass)
+                .collect(),
+        })
+    }
+
+    /// Replay the game from the starting position, passing whenever the player to move has
+    /// no legal move.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn game(&self) -> Result<Game, RecordError> {
+        Ok(self.record(&[], &[])?.game()?)
+    }
+
+    /// Turn the game into a record with the passes put back in, tagged with the names of the
+    /// players and the tournament found in the given names.
+    ///
+    /// Returns an error if any move is illegal.
+    pub fn record(
+        &self,
+        players: &[String],
+        tournaments: &[String],
+    ) -> Result<GameRecord, RecordError> {
+        let mut record = GameRecord::new();
+        let mut game = Game::new();
+        for &mv in &self.moves {
+            if game.must_pass() {
+                game.play_next_turn(Move::Pass)?;
+                record.push(RecordedMove::new(Move::Pass));
+            }
+            game.play_next_turn(mv)?;
+            record.push(RecordedMove::new(mv));
+        }
+        let names = [
+            ("Black", players.get(self.black as usize)),
+            ("White", players.get(self.white as usize)),
+            ("Event", tournaments.get(self.tournament as usize)),
+        ];
+        for (tag, name) in names {
+            if let Some(name) = name {
+                record.tags.insert(tag.to_string(), name.clone());
+            }
+        }
+        Ok(record)
+    }
+}
+
+/// The games of a `.wtb` file, read one at a time.
+pub struct Games<R> {
+    reader: R,
+    remaining: u32,
+}
+
+impl<R: Read> Iterator for Games<R> {
+    type Item = io::Result<WthorGame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        match WthorGame::read(&mut self.reader) {
+            Ok(Some(game)) => Some(Ok(game)),
+            Ok(None) => {
+                self.remaining = 0;
+                Some(Err(invalid(
+                    "The file ends before its last game".to_string(),
+                )))
+            }
+            Err(e) => Some(Err(e)),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining as usize))
+    }
+}
+
+/// Read the header of a `.wtb` file and return it with an iterator over the games.
+///
+/// Returns an error if the header can not be read.
+pub fn read_games<R: Read>(mut reader: R) -> io::Result<(Header, Games<R>)> {
+    let header = Header::read(&mut reader)?;
+    let games = Games {
+        reader,
+        remaining: header.records,
+    };
+    Ok((header, games))
+}
+
+/// Write a `.wtb` file of the games, played in the given year.
+pub fn write_games(writer: &mut impl Write, year: u16, games: &[WthorGame]) -> io::Result<()> {
+    let records = u32::try_from(games.len()).map_err(|_| invalid("Too many games".to_string()))?;
+    Header::new(records, 0, year).write(writer)?;
+    games.iter().try_for_each(|game| game.write(writer))
+}
+
+/// Read the names of a `.jou` file, with `len` [`PLAYER_LEN`], or of a `.trn` file, with
+/// `len` [`TOURNAMENT_LEN`].
+///
+/// Returns an error if the file ends before its last name.
+pub fn read_names(reader: &mut impl Read, len: usize) -> io::Result<Vec<String>> {
+    let header = Header::read(reader)?;
+    let mut bytes = vec![0; len];
+    (0..header.names)
+        .map(|_| {
+            reader.read_exact(&mut bytes)?;
+            // Latin-1 maps each byte to the code point of the same value
+            Ok(bytes
+                .iter()
+                .take_while(|&&byte| byte != 0)
+                .map(|&byte| byte as char)
+                .collect())
+        })
+        .collect()
+}
+
+/// Write a `.jou` or `.trn` file of the names, `len` bytes each, see [`read_names`]. Names are
+/// cut short to fit with a terminating zero, and characters outside Latin-1 become `?`.
+pub fn write_names(writer: &mut impl Write, names: &[String], len: usize) -> io::Result<()> {
+    let count = u16::try_from(names.len()).map_err(|_| invalid("Too many names".to_string()))?;
+    Header::new(0, count, 0).write(writer)?;
+    for name in names {
+        let mut bytes = vec![0; len];
+        for (byte, c) in bytes.iter_mut().take(len - 1).zip(name.chars()) {
+            *byte = u8::try_from(c as u32).unwrap_or(b'?');
+        }
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Returns black's score by the WTHOR rule, the empty squares counting for the winner and
+/// split on a draw.
+fn black_score(game: &Game) -> u8 {
+    let (black, white) = game.disc_counts();
+    let empty = 64 - black - white;
+    let score = match black.cmp(&white) {
+        std::cmp::Ordering::Greater => black + empty,
+        std::cmp::Ordering::Less => black,
+        std::cmp::Ordering::Equal => black + empty / 2,
+    };
+    score as u8
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+/// Returns today's date in UTC, year, month and day.
+fn today() -> (u16, u8, u8) {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86400) as i64;
+    // the civil calendar from days since 1970-01-01, in eras of 400 years from 0000-03-01
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn random_record(seed: u64) -> GameRecord {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut game = Game::new();
+        let mut moves = Vec::new();
+        while !game.is_over() {
+            let mv = game.random_move(&mut rng);
+            game.play_next_turn(mv).unwrap();
+            moves.push(mv);
+        }
+        GameRecord::from_moves(&moves)
+    }
+
+    #[test]
+    fn test_games_round_trip() {
+        let records: Vec<GameRecord> = (0..20).map(random_record).collect();
+        let mut games: Vec<WthorGame> = records
+            .iter()
+            .map(|record| WthorGame::from_record(record).unwrap())
+            .collect();
+        games[1].black = 1;
+        games[1].tournament = 0;
+
+        let mut file = Vec::new();
+        write_games(&mut file, 1999, &games).unwrap();
+        assert_eq!(file.len(), HEADER_LEN + GAME_LEN * games.len());
+        let (header, read) = read_games(file.as_slice()).unwrap();
+        assert_eq!(header.records, 20);
+        assert_eq!(header.year, 1999);
+        assert!(header.created.0 >= 2024);
+        let read: Vec<WthorGame> = read.map(Result::unwrap).collect();
+        assert_eq!(read, games);
+
+        let players = ["Alice".to_string(), "Bob".to_string()];
+        let tournaments = ["Open".to_string()];
+        for (game, record) in read.iter().zip(&records) {
+            // passes come back from replaying the moves
+            let replayed = game.record(&players, &tournaments).unwrap();
+            assert_eq!(replayed.moves, record.moves);
+            assert_eq!(replayed.tags["Event"], "Open");
+            let final_position = game.game().unwrap();
+            assert_eq!(black_score(&final_position), game.black_score);
+        }
+        assert_eq!(read[1].record(&players, &[]).unwrap().tags["Black"], "Bob");
+
+        // a file cut short ends in an error
+        let (_, mut short) = read_games(&file[..file.len() - 10]).unwrap();
+        assert_eq!(short.nth(18).map(|game| game.is_ok()), Some(true));
+        assert!(short.next().unwrap().is_err());
+        assert!(short.next().is_none());
+    }
+
+    #[test]
+    fn test_wthor_squares() {
+        let mut bytes = vec![0; GAME_LEN];
+        bytes[6] = 33;
+        bytes[8] = 56; // f5
+        let game = WthorGame::read(&mut bytes.as_slice()).unwrap().unwrap();
+        assert_eq!(game.moves, vec![Move::from_algebraic("f5").unwrap()]);
+        assert_eq!(game.black_score, 33);
+        bytes[8] = 59;
+        assert!(WthorGame::read(&mut bytes.as_slice()).is_err());
+        assert!(WthorGame::read(&mut [].as_slice()).unwrap().is_none());
+
+        // an illegal move is an error when replayed
+        bytes[8] = 11;
+        let game = WthorGame::read(&mut bytes.as_slice()).unwrap().unwrap();
+        assert!(game.record(&[], &[]).is_err());
+    }
+
+    #[test]
+    fn test_names_round_trip() {
+        let names = vec![
+            "Tamenori Hiroshi".to_string(),
+            "Müller".to_string(),
+            "A name much too long for a player".to_string(),
+        ];
+        let mut file = Vec::new();
+        write_names(&mut file, &names, PLAYER_LEN).unwrap();
+        assert_eq!(file.len(), HEADER_LEN + PLAYER_LEN * 3);
+        let read = read_names(&mut file.as_slice(), PLAYER_LEN).unwrap();
+        assert_eq!(read[..2], names[..2]);
+        assert_eq!(read[2], "A name much too lon");
+        assert!(read_names(&mut &file[..file.len() - 1], PLAYER_LEN).is_err());
+
+        let mut header = Header::new(0, 0, 0);
+        header.board_size = 10;
+        let mut file = Vec::new();
+        header.write(&mut file).unwrap();
+        assert_eq!(file.len(), HEADER_LEN);
+        assert!(Header::read(&mut file.as_slice()).is_err());
+    }
+}